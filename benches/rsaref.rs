@@ -0,0 +1,155 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_integer::Integer;
+use rand::rngs::OsRng;
+use rsa::{BigUint, PaddingScheme, PublicKey, PublicKeyParts, RsaPrivateKey};
+use rsaref_rs::{
+    generate_pem_keys_with_random, RSAPrivateKey, RSAProtoKey, RSAPublicKey, RandomStruct,
+};
+
+const KEY_BITS: [u32; 3] = [512, 1024, 2048];
+
+fn seeded_random_struct() -> RandomStruct {
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(&[0x42u8; 256]);
+    random_struct
+}
+
+/* The same key in both representations, so the rsa crate serves as the
+   baseline for every block operation. Keys beyond MAX_RSA_MODULUS_BITS
+   cannot come from generate_pem_keys, so all of them are built from the
+   rsa crate's primes.
+*/
+fn key_pair(bits: u32) -> (RSAPublicKey, RSAPrivateKey, RsaPrivateKey) {
+    let baseline = RsaPrivateKey::new(&mut OsRng, bits as usize).unwrap();
+    let (p, q) = (&baseline.primes()[0], &baseline.primes()[1]);
+    let one = BigUint::from(1u32);
+    let d = baseline.d();
+    let private_key = RSAPrivateKey::from_components(
+        bits,
+        baseline.n().clone(),
+        baseline.e().clone(),
+        d.clone(),
+        [p.clone(), q.clone()],
+        [d.mod_floor(&(p - &one)), d.mod_floor(&(q - &one))],
+        q.modpow(&(p - BigUint::from(2u32)), p),
+    );
+    (private_key.public_key(), private_key, baseline)
+}
+
+fn bench_bignum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bignum");
+    let mut random_struct = seeded_random_struct();
+    let a = BigUint::from_bytes_be(&random_struct.generate_bytes(128).unwrap());
+    let b = BigUint::from_bytes_be(&random_struct.generate_bytes(128).unwrap());
+    let m =
+        BigUint::from_bytes_be(&random_struct.generate_bytes(128).unwrap()) | BigUint::from(1u32);
+    let product = &a * &b;
+
+    group.bench_function("mul/1024", |bench| bench.iter(|| &a * &b));
+    group.bench_function("div_rem/2048by1024", |bench| {
+        bench.iter(|| product.div_rem(&m))
+    });
+    group.bench_function("modpow/1024", |bench| bench.iter(|| a.modpow(&b, &m)));
+    group.finish();
+}
+
+fn bench_rsa_blocks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rsa");
+    let input = [0x5au8; 36];
+
+    for bits in KEY_BITS {
+        let (public_key, private_key, baseline) = key_pair(bits);
+        let mut random_struct = seeded_random_struct();
+        let ciphertext = public_key.encrypt(&input, &mut random_struct).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::new("public_encrypt", bits),
+            &input,
+            |bench, input| bench.iter(|| public_key.encrypt(input, &mut random_struct).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("public_encrypt/rsa-crate", bits),
+            &input,
+            |bench, input| {
+                bench.iter(|| {
+                    baseline
+                        .encrypt(&mut OsRng, PaddingScheme::new_pkcs1v15_encrypt(), input)
+                        .unwrap()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("private_decrypt", bits),
+            &ciphertext,
+            |bench, ciphertext| bench.iter(|| private_key.rsa_private_decrypt(ciphertext).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("private_decrypt/rsa-crate", bits),
+            &ciphertext,
+            |bench, ciphertext| {
+                bench.iter(|| {
+                    baseline
+                        .decrypt(PaddingScheme::new_pkcs1v15_encrypt(), ciphertext)
+                        .unwrap()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("private_encrypt", bits),
+            &input,
+            |bench, input| bench.iter(|| private_key.rsa_private_encrypt(input).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("private_encrypt/rsa-crate", bits),
+            &input,
+            |bench, input| {
+                bench.iter(|| {
+                    baseline
+                        .sign(PaddingScheme::new_pkcs1v15_sign(None), input)
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_random(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random");
+    let mut random_struct = seeded_random_struct();
+    group.throughput(Throughput::Bytes(4096));
+    group.bench_function("generate_bytes/4096", |bench| {
+        bench.iter(|| random_struct.generate_bytes(4096).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keygen");
+    group.sample_size(10);
+    for bits in [512u32, 1024] {
+        let proto_key = RSAProtoKey {
+            bits,
+            use_fermat4: true,
+        };
+        /* One structure for every iteration, so each draws new primes. */
+        let mut random_struct = seeded_random_struct();
+        group.bench_with_input(
+            BenchmarkId::new("generate_pem_keys", bits),
+            &proto_key,
+            |bench, proto_key| {
+                bench.iter(|| generate_pem_keys_with_random(proto_key, &mut random_struct).unwrap())
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_bignum,
+    bench_rsa_blocks,
+    bench_random,
+    bench_keygen
+);
+criterion_main!(benches);
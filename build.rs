@@ -0,0 +1,35 @@
+/* With the c-header feature, writes rsaref.h for the FFI layer to OUT_DIR,
+   and also to RSAREF_INCLUDE_DIR when that is set. include/rsaref.h in the
+   repository is this output.
+*/
+fn main() {
+    #[cfg(feature = "c-header")]
+    generate_header();
+}
+
+#[cfg(feature = "c-header")]
+fn generate_header() {
+    use std::env;
+    use std::path::PathBuf;
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=RSAREF_INCLUDE_DIR");
+
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    /* Only ffi.rs is parsed, so the header holds nothing but the C
+       interface.
+    */
+    let config = cbindgen::Config::from_file(PathBuf::from(&crate_dir).join("cbindgen.toml"))
+        .expect("cbindgen.toml is unreadable");
+    let bindings = cbindgen::Builder::new()
+        .with_src(PathBuf::from(&crate_dir).join("src/ffi.rs"))
+        .with_config(config)
+        .generate()
+        .expect("rsaref.h could not be generated");
+
+    bindings.write_to_file(PathBuf::from(env::var("OUT_DIR").unwrap()).join("rsaref.h"));
+    if let Ok(dir) = env::var("RSAREF_INCLUDE_DIR") {
+        bindings.write_to_file(PathBuf::from(dir).join("rsaref.h"));
+    }
+}
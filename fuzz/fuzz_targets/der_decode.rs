@@ -0,0 +1,27 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsaref_rs::{
+    decode_digest_info, CertificationRequest, DetachedSignature, EnvelopedData, SignedData,
+};
+
+/* The DER and BER parsers behind the CMS, PKCS #10 and detached
+   signature formats.
+*/
+fuzz_target!(|data: &[u8]| {
+    if let Ok(enveloped) = EnvelopedData::decode(data) {
+        let _ = EnvelopedData::decode(&enveloped.encode()).unwrap();
+    }
+    let _ = EnvelopedData::decode_ber(data);
+    if let Ok(signed) = SignedData::decode(data) {
+        let _ = SignedData::decode(&signed.encode()).unwrap();
+    }
+    let _ = SignedData::decode_ber(data);
+    if let Ok(request) = CertificationRequest::decode(data) {
+        let _ = request.verify();
+    }
+    if let Ok(signature) = DetachedSignature::decode(data) {
+        let _ = DetachedSignature::decode(&signature.encode()).unwrap();
+    }
+    let _ = decode_digest_info(data);
+});
@@ -0,0 +1,37 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsaref_rs::{DHParams, RandomStruct};
+
+/* Agreement under arbitrary parameters. The input is the private value
+   length (1 byte), the length of the encoded parameters (2 bytes, little
+   endian), the parameters, and then the other party's public value
+   (primeLen bytes) followed by its private value. Parameters that
+   decode must be usable without panicking, validated or not.
+*/
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 3 {
+        return;
+    }
+    let private_value_len = data[0] as usize;
+    let params_len = u16::from_le_bytes([data[1], data[2]]) as usize;
+    let Some((params, rest)) = data[3..].split_at_checked(params_len) else {
+        return;
+    };
+    let Ok(params) = DHParams::decode(params) else {
+        return;
+    };
+    let (other_public_value, private_value) = rest.split_at(rest.len().min(params.prime_len()));
+
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(&[0x5au8; 256]);
+    if let Ok((public_value, own_private_value)) =
+        params.setup_agreement(private_value_len, &mut random_struct)
+    {
+        let _ = params.compute_agreed_key(&public_value, &own_private_value);
+        let _ = params.compute_agreed_key(other_public_value, &own_private_value);
+    }
+    let _ = params.compute_agreed_key(other_public_value, private_value);
+    let _ = params.validate_public_value(other_public_value);
+    let _ = params.validate_subgroup_public_value(other_public_value);
+});
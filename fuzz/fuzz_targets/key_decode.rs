@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsaref_rs::{DHParams, RSAPrivateKey, RSAPublicKey, RandomStruct, SecretShare};
+
+/* Every fixed-layout decoder. Whatever decodes must encode back to the
+   bytes it was decoded from.
+*/
+fuzz_target!(|data: &[u8]| {
+    if let Ok(key) = RSAPublicKey::decode(data) {
+        assert_eq!(key.encode(), data[..260]);
+    }
+    if let Ok(key) = RSAPrivateKey::decode(data) {
+        assert_eq!(key.encode(), data[..708]);
+    }
+    if let Ok(params) = DHParams::decode(data) {
+        let _ = params.validate();
+    }
+    if let Ok(random_struct) = RandomStruct::decode(data) {
+        assert_eq!(random_struct.encode(), data);
+    }
+    if let Ok(share) = SecretShare::decode(data) {
+        assert_eq!(share.encode(), data);
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsaref_rs::{
+    decode_pem_block, decode_pem_message, ArmoredBlock, CertificationRequest, DetachedSignature,
+};
+
+/* The text decoders: base64 bodies, PEM messages and OpenPGP armor. */
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = core::str::from_utf8(data) else {
+        return;
+    };
+    let _ = decode_pem_block(text);
+    let _ = decode_pem_message(text, None);
+    if let Ok(block) = ArmoredBlock::decode(text) {
+        let _ = ArmoredBlock::decode(&block.encode()).unwrap();
+    }
+    let _ = CertificationRequest::decode_pem(text);
+    let _ = DetachedSignature::decode_pem(text);
+});
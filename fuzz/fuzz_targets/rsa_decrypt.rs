@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsaref_rs::{RSAPrivateKey, RSAPublicKey};
+
+/* Decryption of arbitrary input under arbitrary keys. The input is a
+   public key (260 bytes), a private key (708 bytes) and the ciphertext.
+   Keys whose bits disagree with their modulus must be rejected, not
+   panic.
+*/
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 260 + 708 {
+        return;
+    }
+    let (public_key, rest) = data.split_at(260);
+    let (private_key, ciphertext) = rest.split_at(708);
+
+    if let Ok(public_key) = RSAPublicKey::decode(public_key) {
+        let _ = public_key.decrypt(ciphertext);
+    }
+    if let Ok(private_key) = RSAPrivateKey::decode(private_key) {
+        let _ = private_key.rsa_private_decrypt(ciphertext);
+        let _ = private_key.decrypt(ciphertext);
+        let _ = private_key.rsa_private_encrypt(ciphertext);
+    }
+});
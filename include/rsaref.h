@@ -0,0 +1,135 @@
+#ifndef _RSAREF_H_
+#define _RSAREF_H_
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit. */
+
+
+#define MIN_RSA_MODULUS_BITS 508
+#define MAX_RSA_MODULUS_BITS 1024
+#define MAX_RSA_MODULUS_LEN ((MAX_RSA_MODULUS_BITS + 7) / 8)
+#define MAX_RSA_PRIME_BITS ((MAX_RSA_MODULUS_BITS + 1) / 2)
+#define MAX_RSA_PRIME_LEN ((MAX_RSA_PRIME_BITS + 7) / 8)
+
+#define RE_CONTENT_ENCODING 1024
+
+#define RE_DATA 1025
+
+#define RE_DIGEST_ALGORITHM 1026
+
+#define RE_ENCODING 1027
+
+#define RE_KEY 1028
+
+#define RE_KEY_ENCODING 1029
+
+#define RE_LEN 1030
+
+#define RE_MODULUS_LEN 1031
+
+#define RE_NEED_RANDOM 1032
+
+#define RE_PRIVATE_KEY 1033
+
+#define RE_PUBLIC_KEY 1034
+
+#define RE_SIGNATURE 1035
+
+#define RE_SIGNATURE_ENCODING 1036
+
+#define RE_ENCRYPTION_ALGORITHM 1037
+
+typedef struct {
+  unsigned int bytesNeeded;
+  unsigned char state[16];
+  unsigned int outputAvailable;
+  unsigned char output[16];
+} R_RANDOM_STRUCT;
+
+typedef struct {
+  unsigned int bits;
+  unsigned char modulus[MAX_RSA_MODULUS_LEN];
+  unsigned char exponent[MAX_RSA_MODULUS_LEN];
+} R_RSA_PUBLIC_KEY;
+
+typedef struct {
+  unsigned int bits;
+  unsigned char modulus[MAX_RSA_MODULUS_LEN];
+  unsigned char publicExponent[MAX_RSA_MODULUS_LEN];
+  unsigned char exponent[MAX_RSA_MODULUS_LEN];
+  unsigned char prime[2][MAX_RSA_PRIME_LEN];
+  unsigned char primeExponent[2][MAX_RSA_PRIME_LEN];
+  unsigned char coefficient[MAX_RSA_PRIME_LEN];
+} R_RSA_PRIVATE_KEY;
+
+typedef struct {
+  unsigned int bits;
+  int useFermat4;
+} R_RSA_PROTO_KEY;
+
+typedef struct {
+  unsigned char *prime;
+  unsigned int primeLen;
+  unsigned char *generator;
+  unsigned int generatorLen;
+} R_DH_PARAMS;
+
+int R_RandomInit(R_RANDOM_STRUCT *random_struct);
+
+int R_RandomUpdate(R_RANDOM_STRUCT *random_struct,
+                   const unsigned char *block,
+                   unsigned int block_len);
+
+int R_GetRandomBytesNeeded(unsigned int *bytes_needed, R_RANDOM_STRUCT *random_struct);
+
+int R_GenerateBytes(unsigned char *block, unsigned int block_len, R_RANDOM_STRUCT *random_struct);
+
+void R_RandomFinal(R_RANDOM_STRUCT *random_struct);
+
+int RSAPublicEncrypt(unsigned char *output,
+                     unsigned int *output_len,
+                     const unsigned char *input,
+                     unsigned int input_len,
+                     const R_RSA_PUBLIC_KEY *public_key,
+                     R_RANDOM_STRUCT *random_struct);
+
+int RSAPublicDecrypt(unsigned char *output,
+                     unsigned int *output_len,
+                     const unsigned char *input,
+                     unsigned int input_len,
+                     const R_RSA_PUBLIC_KEY *public_key);
+
+int RSAPrivateEncrypt(unsigned char *output,
+                      unsigned int *output_len,
+                      const unsigned char *input,
+                      unsigned int input_len,
+                      const R_RSA_PRIVATE_KEY *private_key);
+
+int RSAPrivateDecrypt(unsigned char *output,
+                      unsigned int *output_len,
+                      const unsigned char *input,
+                      unsigned int input_len,
+                      const R_RSA_PRIVATE_KEY *private_key);
+
+int R_GeneratePEMKeys(R_RSA_PUBLIC_KEY *public_key,
+                      R_RSA_PRIVATE_KEY *private_key,
+                      const R_RSA_PROTO_KEY *proto_key,
+                      R_RANDOM_STRUCT *random_struct);
+
+int R_GenerateDHParams(R_DH_PARAMS *params,
+                       unsigned int prime_bits,
+                       unsigned int subprime_bits,
+                       R_RANDOM_STRUCT *random_struct);
+
+int R_SetupDHAgreement(unsigned char *public_value,
+                       unsigned char *private_value,
+                       unsigned int private_value_len,
+                       const R_DH_PARAMS *params,
+                       R_RANDOM_STRUCT *random_struct);
+
+int R_ComputeDHAgreedKey(unsigned char *agreed_key,
+                         const unsigned char *other_public_value,
+                         const unsigned char *private_value,
+                         unsigned int private_value_len,
+                         const R_DH_PARAMS *params);
+
+#endif  /* _RSAREF_H_ */
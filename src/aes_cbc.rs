@@ -0,0 +1,132 @@
+use crate::cbc::{cbc_update, BlockCipher};
+use crate::RSAError;
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes256};
+use alloc::{boxed::Box, vec::Vec};
+
+pub const AES_BLOCK_LEN: usize = 16;
+
+enum AESCore {
+    Aes128(Box<Aes128>),
+    Aes256(Box<Aes256>),
+}
+
+impl BlockCipher<AES_BLOCK_LEN> for AESCore {
+    fn encrypt_block(&self, block: &mut [u8; AES_BLOCK_LEN]) {
+        match self {
+            AESCore::Aes128(aes) => aes.encrypt_block(GenericArray::from_mut_slice(block)),
+            AESCore::Aes256(aes) => aes.encrypt_block(GenericArray::from_mut_slice(block)),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8; AES_BLOCK_LEN]) {
+        match self {
+            AESCore::Aes128(aes) => aes.decrypt_block(GenericArray::from_mut_slice(block)),
+            AESCore::Aes256(aes) => aes.decrypt_block(GenericArray::from_mut_slice(block)),
+        }
+    }
+}
+
+/* AES in CBC mode with the same interface as DESCBCContext. */
+pub struct AESCBCContext {
+    core: AESCore,
+    iv: [u8; AES_BLOCK_LEN],
+    original_iv: [u8; AES_BLOCK_LEN],
+    encrypt: bool,
+}
+
+impl AESCBCContext {
+    pub fn aes128(key: &[u8; 16], iv: &[u8; AES_BLOCK_LEN], encrypt: bool) -> Self {
+        Self::with_core(
+            AESCore::Aes128(Box::new(Aes128::new(key.into()))),
+            iv,
+            encrypt,
+        )
+    }
+
+    pub fn aes256(key: &[u8; 32], iv: &[u8; AES_BLOCK_LEN], encrypt: bool) -> Self {
+        Self::with_core(
+            AESCore::Aes256(Box::new(Aes256::new(key.into()))),
+            iv,
+            encrypt,
+        )
+    }
+
+    fn with_core(core: AESCore, iv: &[u8; AES_BLOCK_LEN], encrypt: bool) -> Self {
+        Self {
+            core,
+            iv: *iv,
+            original_iv: *iv,
+            encrypt,
+        }
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        cbc_update(&self.core, &mut self.iv, self.encrypt, input)
+    }
+
+    pub fn restart(&mut self) {
+        self.iv = self.original_iv;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 32] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17,
+        0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf,
+        0x8e, 0x51,
+    ];
+
+    #[test]
+    fn test_aes128_cbc_vector() {
+        // NIST SP 800-38A, F.2.1 and F.2.2
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let ciphertext = [
+            0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9,
+            0x19, 0x7d, 0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a,
+            0x91, 0x76, 0x78, 0xb2,
+        ];
+
+        let mut context = AESCBCContext::aes128(&key, &IV, true);
+        assert_eq!(context.update(&PLAINTEXT).unwrap(), ciphertext);
+
+        let mut context = AESCBCContext::aes128(&key, &IV, false);
+        let mut decrypted = context.update(&ciphertext[..16]).unwrap();
+        decrypted.extend(context.update(&ciphertext[16..]).unwrap());
+        assert_eq!(decrypted, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_aes256_cbc_vector() {
+        // NIST SP 800-38A, F.2.5 and F.2.6
+        let key = [
+            0x60, 0x3d, 0xeb, 0x10, 0x15, 0xca, 0x71, 0xbe, 0x2b, 0x73, 0xae, 0xf0, 0x85, 0x7d,
+            0x77, 0x81, 0x1f, 0x35, 0x2c, 0x07, 0x3b, 0x61, 0x08, 0xd7, 0x2d, 0x98, 0x10, 0xa3,
+            0x09, 0x14, 0xdf, 0xf4,
+        ];
+        let ciphertext = [
+            0xf5, 0x8c, 0x4c, 0x04, 0xd6, 0xe5, 0xf1, 0xba, 0x77, 0x9e, 0xab, 0xfb, 0x5f, 0x7b,
+            0xfb, 0xd6, 0x9c, 0xfc, 0x4e, 0x96, 0x7e, 0xdb, 0x80, 0x8d, 0x67, 0x9f, 0x77, 0x7b,
+            0xc6, 0x70, 0x2c, 0x7d,
+        ];
+
+        let mut context = AESCBCContext::aes256(&key, &IV, true);
+        assert_eq!(context.update(&PLAINTEXT).unwrap(), ciphertext);
+
+        let mut context = AESCBCContext::aes256(&key, &IV, false);
+        assert_eq!(context.update(&ciphertext).unwrap(), PLAINTEXT);
+        context.restart();
+        assert_eq!(context.update(&ciphertext).unwrap(), PLAINTEXT);
+    }
+}
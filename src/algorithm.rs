@@ -0,0 +1,384 @@
+use crate::aes_cbc::AESCBCContext;
+use crate::des_cbc::DESCBCContext;
+use crate::digest_info::DigestAlg;
+use crate::r_random::RandomStruct;
+use crate::RSAError;
+use alloc::vec::Vec;
+use md2::Md2;
+use md5::{Digest, Md5};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithmId {
+    Md2 = 3,
+    Md5 = 5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionAlgorithmId {
+    DesCbc = 1,
+    DesEde2Cbc = 2,
+    DesEde3Cbc = 3,
+    DesxCbc = 4,
+    /* Not part of RSAREF; numbered clear of the legacy range. */
+    Aes128Cbc = 0x100,
+    Aes256Cbc = 0x101,
+    /* Passes content through unchanged while the content key is still
+       generated and transported. For testing and interop debugging only.
+    */
+    Identity = 0x1ff,
+}
+
+pub const DA_MD2: i32 = DigestAlgorithmId::Md2 as i32;
+pub const DA_MD5: i32 = DigestAlgorithmId::Md5 as i32;
+pub const EA_DES_CBC: i32 = EncryptionAlgorithmId::DesCbc as i32;
+pub const EA_DES_EDE2_CBC: i32 = EncryptionAlgorithmId::DesEde2Cbc as i32;
+pub const EA_DES_EDE3_CBC: i32 = EncryptionAlgorithmId::DesEde3Cbc as i32;
+pub const EA_DESX_CBC: i32 = EncryptionAlgorithmId::DesxCbc as i32;
+pub const EA_AES_128_CBC: i32 = EncryptionAlgorithmId::Aes128Cbc as i32;
+pub const EA_AES_256_CBC: i32 = EncryptionAlgorithmId::Aes256Cbc as i32;
+pub const EA_IDENTITY: i32 = EncryptionAlgorithmId::Identity as i32;
+
+impl TryFrom<i32> for DigestAlgorithmId {
+    type Error = RSAError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            DA_MD2 => Ok(DigestAlgorithmId::Md2),
+            DA_MD5 => Ok(DigestAlgorithmId::Md5),
+            _ => Err(RSAError::DigestAlgorithm),
+        }
+    }
+}
+
+impl TryFrom<i32> for EncryptionAlgorithmId {
+    type Error = RSAError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            EA_DES_CBC => Ok(EncryptionAlgorithmId::DesCbc),
+            EA_DES_EDE2_CBC => Ok(EncryptionAlgorithmId::DesEde2Cbc),
+            EA_DES_EDE3_CBC => Ok(EncryptionAlgorithmId::DesEde3Cbc),
+            EA_DESX_CBC => Ok(EncryptionAlgorithmId::DesxCbc),
+            EA_AES_128_CBC => Ok(EncryptionAlgorithmId::Aes128Cbc),
+            EA_AES_256_CBC => Ok(EncryptionAlgorithmId::Aes256Cbc),
+            EA_IDENTITY => Ok(EncryptionAlgorithmId::Identity),
+            _ => Err(RSAError::EncryptionAlgorithm),
+        }
+    }
+}
+
+impl DigestAlgorithmId {
+    pub fn digest_alg(&self) -> DigestAlg {
+        match self {
+            DigestAlgorithmId::Md2 => DigestAlg::Md2,
+            DigestAlgorithmId::Md5 => DigestAlg::Md5,
+        }
+    }
+}
+
+impl EncryptionAlgorithmId {
+    pub fn key_len(&self) -> usize {
+        match self {
+            EncryptionAlgorithmId::DesCbc | EncryptionAlgorithmId::Identity => 8,
+            EncryptionAlgorithmId::DesEde2Cbc
+            | EncryptionAlgorithmId::DesEde3Cbc
+            | EncryptionAlgorithmId::DesxCbc => 24,
+            EncryptionAlgorithmId::Aes128Cbc => 16,
+            EncryptionAlgorithmId::Aes256Cbc => 32,
+        }
+    }
+
+    pub fn iv_len(&self) -> usize {
+        self.block_len()
+    }
+
+    pub fn block_len(&self) -> usize {
+        match self {
+            EncryptionAlgorithmId::Aes128Cbc | EncryptionAlgorithmId::Aes256Cbc => 16,
+            _ => 8,
+        }
+    }
+
+    pub fn generate_key(&self, random_struct: &mut RandomStruct) -> Result<Vec<u8>, RSAError> {
+        let mut key = random_struct.generate_bytes(self.key_len())?;
+        self.normalize_key(&mut key);
+        Ok(key)
+    }
+
+    /* Two-key EDE uses K3 = K1; other keys are used as they are. */
+    pub(crate) fn normalize_key(&self, key: &mut [u8]) {
+        if *self == EncryptionAlgorithmId::DesEde2Cbc && key.len() == 24 {
+            key.copy_within(0..8, 16);
+        }
+    }
+}
+
+/* Digest context selected by RSAREF digest algorithm ID, as in
+   R_DigestInit/R_DigestUpdate/R_DigestFinal.
+*/
+pub enum DigestContext {
+    Md2(Md2),
+    Md5(Md5),
+}
+
+impl DigestContext {
+    pub fn new(algorithm: DigestAlgorithmId) -> Self {
+        match algorithm {
+            DigestAlgorithmId::Md2 => DigestContext::Md2(Md2::new()),
+            DigestAlgorithmId::Md5 => DigestContext::Md5(Md5::new()),
+        }
+    }
+
+    pub fn algorithm(&self) -> DigestAlgorithmId {
+        match self {
+            DigestContext::Md2(_) => DigestAlgorithmId::Md2,
+            DigestContext::Md5(_) => DigestAlgorithmId::Md5,
+        }
+    }
+
+    pub fn update(&mut self, block: &[u8]) {
+        match self {
+            DigestContext::Md2(context) => context.update(block),
+            DigestContext::Md5(context) => context.update(block),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            DigestContext::Md2(context) => context.finalize().to_vec(),
+            DigestContext::Md5(context) => context.finalize().to_vec(),
+        }
+    }
+}
+
+pub fn digest(algorithm: DigestAlgorithmId, block: &[u8]) -> Vec<u8> {
+    let mut context = DigestContext::new(algorithm);
+    context.update(block);
+    context.finalize()
+}
+
+enum CipherCore {
+    Des(DESCBCContext),
+    Aes(AESCBCContext),
+    Identity,
+}
+
+/* Block cipher context selected by RSAREF encryption algorithm ID. The key
+   and IV lengths must match the algorithm.
+*/
+
+pub struct CipherContext {
+    algorithm: EncryptionAlgorithmId,
+    core: CipherCore,
+}
+
+impl CipherContext {
+    pub fn new(
+        algorithm: EncryptionAlgorithmId,
+        key: &[u8],
+        iv: &[u8],
+        encrypt: bool,
+    ) -> Result<Self, RSAError> {
+        if key.len() != algorithm.key_len() {
+            return Err(RSAError::Key);
+        }
+        if iv.len() != algorithm.iv_len() {
+            return Err(RSAError::Len);
+        }
+
+        let core = match algorithm {
+            EncryptionAlgorithmId::DesCbc => CipherCore::Des(DESCBCContext::des(
+                key.try_into().map_err(|_| RSAError::Key)?,
+                iv.try_into().map_err(|_| RSAError::Len)?,
+                encrypt,
+            )),
+            EncryptionAlgorithmId::DesEde2Cbc | EncryptionAlgorithmId::DesEde3Cbc => {
+                CipherCore::Des(DESCBCContext::des3(
+                    key.try_into().map_err(|_| RSAError::Key)?,
+                    iv.try_into().map_err(|_| RSAError::Len)?,
+                    encrypt,
+                ))
+            }
+            EncryptionAlgorithmId::DesxCbc => CipherCore::Des(DESCBCContext::desx(
+                key.try_into().map_err(|_| RSAError::Key)?,
+                iv.try_into().map_err(|_| RSAError::Len)?,
+                encrypt,
+            )),
+            EncryptionAlgorithmId::Aes128Cbc => CipherCore::Aes(AESCBCContext::aes128(
+                key.try_into().map_err(|_| RSAError::Key)?,
+                iv.try_into().map_err(|_| RSAError::Len)?,
+                encrypt,
+            )),
+            EncryptionAlgorithmId::Aes256Cbc => CipherCore::Aes(AESCBCContext::aes256(
+                key.try_into().map_err(|_| RSAError::Key)?,
+                iv.try_into().map_err(|_| RSAError::Len)?,
+                encrypt,
+            )),
+            EncryptionAlgorithmId::Identity => CipherCore::Identity,
+        };
+
+        Ok(Self { algorithm, core })
+    }
+
+    pub fn algorithm(&self) -> EncryptionAlgorithmId {
+        self.algorithm
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        match &mut self.core {
+            CipherCore::Des(context) => context.update(input),
+            CipherCore::Aes(context) => context.update(input),
+            CipherCore::Identity => {
+                if !input.len().is_multiple_of(self.algorithm.block_len()) {
+                    return Err(RSAError::Len);
+                }
+                Ok(input.to_vec())
+            }
+        }
+    }
+
+    pub fn restart(&mut self) {
+        match &mut self.core {
+            CipherCore::Des(context) => context.restart(),
+            CipherCore::Aes(context) => context.restart(),
+            CipherCore::Identity => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_random_struct;
+
+    #[test]
+    fn test_algorithm_ids() {
+        assert_eq!(DA_MD2, 3);
+        assert_eq!(DA_MD5, 5);
+        assert_eq!(EA_DES_CBC, 1);
+        assert_eq!(EA_DES_EDE2_CBC, 2);
+        assert_eq!(EA_DES_EDE3_CBC, 3);
+        assert_eq!(EA_DESX_CBC, 4);
+
+        assert_eq!(
+            DigestAlgorithmId::try_from(DA_MD5).unwrap(),
+            DigestAlgorithmId::Md5
+        );
+        assert!(matches!(
+            DigestAlgorithmId::try_from(4),
+            Err(RSAError::DigestAlgorithm)
+        ));
+        assert_eq!(
+            EncryptionAlgorithmId::try_from(EA_DESX_CBC).unwrap(),
+            EncryptionAlgorithmId::DesxCbc
+        );
+        assert_eq!(
+            EncryptionAlgorithmId::try_from(EA_AES_256_CBC).unwrap(),
+            EncryptionAlgorithmId::Aes256Cbc
+        );
+        assert!(matches!(
+            EncryptionAlgorithmId::try_from(0),
+            Err(RSAError::EncryptionAlgorithm)
+        ));
+    }
+
+    #[test]
+    fn test_digest_dispatch() {
+        // RFC 1319 and RFC 1321 test suites
+        assert_eq!(
+            digest(DigestAlgorithmId::Md2, b"abc"),
+            [
+                0xda, 0x85, 0x3b, 0x0d, 0x3f, 0x88, 0xd9, 0x9b, 0x30, 0x28, 0x3a, 0x69, 0xe6, 0xde,
+                0xd6, 0xbb
+            ]
+        );
+        assert_eq!(
+            digest(DigestAlgorithmId::Md5, b"abc"),
+            [
+                0x90, 0x01, 0x50, 0x98, 0x3c, 0xd2, 0x4f, 0xb0, 0xd6, 0x96, 0x3f, 0x7d, 0x28, 0xe1,
+                0x7f, 0x72
+            ]
+        );
+
+        let mut context = DigestContext::new(DigestAlgorithmId::Md5);
+        context.update(b"a");
+        context.update(b"bc");
+        assert_eq!(context.finalize(), digest(DigestAlgorithmId::Md5, b"abc"));
+    }
+
+    #[test]
+    fn test_md2_digest_traits() {
+        use hmac::{Hmac, Mac};
+
+        fn generic_digest<D: Digest>(data: &[u8]) -> Vec<u8> {
+            D::digest(data).to_vec()
+        }
+        assert_eq!(
+            generic_digest::<Md2>(b"abc"),
+            digest(DigestAlgorithmId::Md2, b"abc")
+        );
+
+        /* HMAC over the trait matches HMAC built by hand on digest(). */
+        let key = [0x0bu8; 16];
+        let data = b"Hi There";
+        let mut inner = key.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>();
+        inner.resize(16, 0x36);
+        inner.extend(data);
+        let mut outer = key.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>();
+        outer.resize(16, 0x5c);
+        outer.extend(digest(DigestAlgorithmId::Md2, &inner));
+
+        let mut mac = Hmac::<Md2>::new_from_slice(&key).unwrap();
+        mac.update(data);
+        assert_eq!(
+            mac.finalize().into_bytes().to_vec(),
+            digest(DigestAlgorithmId::Md2, &outer)
+        );
+    }
+
+    #[test]
+    fn test_cipher_dispatch() {
+        let mut random_struct = test_random_struct(0);
+
+        let data = (0u8..32).collect::<Vec<u8>>();
+        let iv = [0x42u8; 8];
+
+        for algorithm in [
+            EncryptionAlgorithmId::DesCbc,
+            EncryptionAlgorithmId::DesEde2Cbc,
+            EncryptionAlgorithmId::DesEde3Cbc,
+            EncryptionAlgorithmId::DesxCbc,
+            EncryptionAlgorithmId::Aes128Cbc,
+            EncryptionAlgorithmId::Aes256Cbc,
+        ] {
+            let key = algorithm.generate_key(&mut random_struct).unwrap();
+            assert_eq!(key.len(), algorithm.key_len());
+            let iv = random_struct.generate_bytes(algorithm.iv_len()).unwrap();
+
+            let encrypted = CipherContext::new(algorithm, &key, &iv, true)
+                .unwrap()
+                .update(&data)
+                .unwrap();
+            assert_ne!(encrypted, data);
+            let decrypted = CipherContext::new(algorithm, &key, &iv, false)
+                .unwrap()
+                .update(&encrypted)
+                .unwrap();
+            assert_eq!(decrypted, data);
+        }
+
+        let key = EncryptionAlgorithmId::DesEde2Cbc
+            .generate_key(&mut random_struct)
+            .unwrap();
+        assert_eq!(key[0..8], key[16..24]);
+
+        assert!(matches!(
+            CipherContext::new(EncryptionAlgorithmId::DesCbc, &[0u8; 24], &iv, true),
+            Err(RSAError::Key)
+        ));
+        assert!(matches!(
+            CipherContext::new(EncryptionAlgorithmId::Aes128Cbc, &[0u8; 16], &iv, true),
+            Err(RSAError::Len)
+        ));
+    }
+}
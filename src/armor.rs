@@ -0,0 +1,241 @@
+use crate::r_encode::{decode_pem_block, encode_pem_block};
+use crate::RSAError;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+const ARMOR_PREFIX: &str = "-----BEGIN PGP ";
+const ARMOR_END_PREFIX: &str = "-----END PGP ";
+const ARMOR_SUFFIX: &str = "-----";
+
+const CRC24_INIT: u32 = 0xb704ce;
+const CRC24_POLY: u32 = 0x1864cfb;
+
+/* The CRC-24 of RFC 4880 section 6.1, carried in the armor checksum. */
+pub fn crc24(data: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xffffff
+}
+
+/* OpenPGP radix-64 armor (RFC 4880 section 6.2):
+     -----BEGIN PGP <label>-----
+     Key: Value
+     <blank line>
+     base64 data
+     =<base64 CRC-24>
+     -----END PGP <label>-----
+   The checksum is always written. On input it may be absent, as later
+   OpenPGP revisions allow, but a present checksum must match.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArmoredBlock {
+    label: String,
+    headers: Vec<(String, String)>,
+    data: Vec<u8>,
+}
+
+impl ArmoredBlock {
+    /* label is the text after "PGP ", e.g. "MESSAGE" or
+       "PUBLIC KEY BLOCK".
+    */
+    pub fn new(label: &str, data: &[u8]) -> Self {
+        Self {
+            label: label.to_string(),
+            headers: Vec::new(),
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn encode(&self) -> String {
+        let mut output = format!("{}{}{}\n", ARMOR_PREFIX, self.label, ARMOR_SUFFIX);
+        for (key, value) in &self.headers {
+            output.push_str(&format!("{}: {}\n", key, value));
+        }
+        output.push('\n');
+        if !self.data.is_empty() {
+            output.push_str(&encode_pem_block(&self.data));
+            output.push('\n');
+        }
+        output.push('=');
+        output.push_str(&encode_pem_block(&crc24(&self.data).to_be_bytes()[1..]));
+        output.push('\n');
+        output.push_str(&format!(
+            "{}{}{}\n",
+            ARMOR_END_PREFIX, self.label, ARMOR_SUFFIX
+        ));
+        output
+    }
+
+    /* Decodes the first armored block in encoded, ignoring any text
+       before it.
+    */
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = encoded.len()), err(level = "debug"))
+)]
+    pub fn decode(encoded: &str) -> Result<Self, RSAError> {
+        let mut lines = encoded.lines().map(|line| line.trim_end());
+
+        let label = lines
+            .by_ref()
+            .find_map(|line| {
+                line.strip_prefix(ARMOR_PREFIX)
+                    .and_then(|rest| rest.strip_suffix(ARMOR_SUFFIX))
+            })
+            .ok_or(RSAError::Encoding)?
+            .to_string();
+
+        let mut headers = Vec::new();
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once(": ").ok_or(RSAError::Encoding)?;
+            headers.push((key.to_string(), value.to_string()));
+        }
+
+        let mut body = String::new();
+        let mut checksum = None;
+        let end = loop {
+            let line = lines.next().ok_or(RSAError::Encoding)?;
+            if let Some(end) = line.strip_prefix(ARMOR_END_PREFIX) {
+                break end;
+            }
+            if checksum.is_some() {
+                return Err(RSAError::Encoding);
+            }
+            match line.strip_prefix('=') {
+                Some(crc) if crc.len() == 4 => checksum = Some(decode_pem_block(crc)?),
+                _ => body.push_str(line),
+            }
+        };
+        if end.strip_suffix(ARMOR_SUFFIX) != Some(label.as_str()) {
+            return Err(RSAError::Encoding);
+        }
+
+        let data = decode_pem_block(&body)?;
+        if let Some(checksum) = checksum {
+            if checksum != crc24(&data).to_be_bytes()[1..] {
+                return Err(RSAError::Encoding);
+            }
+        }
+
+        Ok(Self {
+            label,
+            headers,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc24() {
+        assert_eq!(crc24(b""), CRC24_INIT);
+        assert_eq!(crc24(b"123456789"), 0x21cf02);
+    }
+
+    #[test]
+    fn test_armor_round_trip() {
+        let data = (0u8..=200).collect::<Vec<u8>>();
+        let armored = ArmoredBlock::new("PUBLIC KEY BLOCK", &data).with_header("Version", "2.6.2");
+        let encoded = armored.encode();
+        assert!(encoded.starts_with("-----BEGIN PGP PUBLIC KEY BLOCK-----\nVersion: 2.6.2\n\n"));
+        assert!(encoded.ends_with("-----END PGP PUBLIC KEY BLOCK-----\n"));
+
+        let decoded = ArmoredBlock::decode(&format!("Leading text\r\n{}", encoded)).unwrap();
+        assert_eq!(decoded, armored);
+        assert_eq!(decoded.header("Version"), Some("2.6.2"));
+
+        let empty = ArmoredBlock::new("MESSAGE", b"");
+        assert_eq!(ArmoredBlock::decode(&empty.encode()).unwrap(), empty);
+    }
+
+    #[test]
+    fn test_armor_decode_gnupg() {
+        /* gpg --enarmor of "hello\n". */
+        let encoded = "-----BEGIN PGP ARMORED FILE-----
+Comment: Use \"gpg --dearmor\" for unpacking
+
+aGVsbG8K
+=WALx
+-----END PGP ARMORED FILE-----
+";
+        let decoded = ArmoredBlock::decode(encoded).unwrap();
+        assert_eq!(decoded.label(), "ARMORED FILE");
+        assert_eq!(decoded.data(), b"hello\n");
+    }
+
+    #[test]
+    fn test_armor_decode_rejects_malformed() {
+        let encoded = ArmoredBlock::new("MESSAGE", b"archived message").encode();
+
+        let lines: Vec<&str> = encoded.lines().collect();
+        let mut corrupted = lines.clone();
+        let crc_line = corrupted
+            .iter()
+            .position(|line| line.starts_with('='))
+            .unwrap();
+        corrupted[crc_line] = "=AAAA";
+
+        for malformed in [
+            corrupted.join("\n"),
+            encoded.replace("END PGP MESSAGE", "END PGP SIGNATURE"),
+            encoded.replace("\n\n", "\nBroken header\n\n"),
+            encoded.replace("-----END PGP MESSAGE-----\n", ""),
+        ] {
+            assert!(matches!(
+                ArmoredBlock::decode(&malformed),
+                Err(RSAError::Encoding)
+            ));
+        }
+
+        let without_checksum: Vec<&str> = lines
+            .into_iter()
+            .filter(|line| !line.starts_with('='))
+            .collect();
+        assert_eq!(
+            ArmoredBlock::decode(&without_checksum.join("\n"))
+                .unwrap()
+                .data(),
+            b"archived message"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+/* Layouts of the raw key and random structure blobs other than RSAREF's
+   own. Ports of RSAREF that copied its structs byte for byte wrote the
+   32-bit words (a key's bits, a random structure's bytesNeeded and
+   outputAvailable) in the byte order of their machine, and some
+   serialized the fields last to first. The integers themselves are
+   big-endian in every layout.
+
+   The encode_with and decode_with methods convert through RSAREF's
+   layout, which encode and decode use.
+*/
+use crate::RSAError;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WordOrder {
+    /* RSAREF's, from its x86 builds */
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComponentOrder {
+    /* The order the C structs declare their fields in */
+    #[default]
+    Declared,
+    Reversed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlobLayout {
+    pub word_order: WordOrder,
+    pub component_order: ComponentOrder,
+}
+
+/* One field of a blob, in RSAREF's layout. */
+#[derive(Clone, Copy)]
+pub(crate) enum Field {
+    Word,
+    Bytes(usize),
+}
+
+impl Field {
+    fn len(self) -> usize {
+        match self {
+            Field::Word => 4,
+            Field::Bytes(len) => len,
+        }
+    }
+}
+
+impl BlobLayout {
+    /* The first blob in data, laid out as self, in RSAREF's layout. Fails
+       with InputLen if data is too short for fields.
+    */
+    pub(crate) fn to_rsaref(self, data: &[u8], fields: &[Field]) -> Result<Vec<u8>, RSAError> {
+        let total_len = fields.iter().map(|field| field.len()).sum();
+        let data = data.get(..total_len).ok_or(RSAError::InputLen {
+            expected: total_len,
+            actual: data.len(),
+        })?;
+        Ok(self.rearrange(data, fields, false))
+    }
+
+    /* A blob in RSAREF's layout, as encode gives it, laid out as self. */
+    pub(crate) fn lay_out(self, data: &[u8], fields: &[Field]) -> Vec<u8> {
+        self.rearrange(data, fields, true)
+    }
+
+    /* Both directions swap the same words and reverse the same fields;
+       they differ only in which order the fields are found in data.
+    */
+    fn rearrange(self, data: &[u8], fields: &[Field], lay_out: bool) -> Vec<u8> {
+        let reversed = self.component_order == ComponentOrder::Reversed;
+        let mut in_data = fields.to_vec();
+        if reversed && !lay_out {
+            in_data.reverse();
+        }
+        let mut parts = Vec::with_capacity(fields.len());
+        let mut rest = data;
+        for field in in_data {
+            let (part, tail) = rest.split_at(field.len().min(rest.len()));
+            parts.push((field, part));
+            rest = tail;
+        }
+        if reversed {
+            parts.reverse();
+        }
+
+        let mut result = Vec::with_capacity(data.len());
+        for (field, part) in parts {
+            let start = result.len();
+            result.extend_from_slice(part);
+            if let (Field::Word, WordOrder::BigEndian) = (field, self.word_order) {
+                if let Some(word) = result.get_mut(start..) {
+                    word.reverse();
+                }
+            }
+        }
+        result
+    }
+}
+
+/* Every layout test round-trips keys or random structures, which need full. */
+#[cfg(all(test, feature = "full"))]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_key_pair;
+    use crate::r_random::RandomStruct;
+    use crate::rsa::{RSAPrivateKey, RSAPublicKey};
+
+    const LAYOUTS: [BlobLayout; 4] = [
+        BlobLayout {
+            word_order: WordOrder::LittleEndian,
+            component_order: ComponentOrder::Declared,
+        },
+        BlobLayout {
+            word_order: WordOrder::BigEndian,
+            component_order: ComponentOrder::Declared,
+        },
+        BlobLayout {
+            word_order: WordOrder::LittleEndian,
+            component_order: ComponentOrder::Reversed,
+        },
+        BlobLayout {
+            word_order: WordOrder::BigEndian,
+            component_order: ComponentOrder::Reversed,
+        },
+    ];
+
+    #[test]
+    fn test_key_layouts() {
+        let (public_key, private_key) = test_key_pair(512);
+        let public_blob = public_key.encode();
+        let private_blob = private_key.encode();
+        assert_eq!(public_key.encode_with(BlobLayout::default()), public_blob);
+
+        for layout in LAYOUTS {
+            let encoded = public_key.encode_with(layout);
+            assert_eq!(encoded.len(), 260);
+            let decoded = RSAPublicKey::decode_with(&encoded, layout).unwrap();
+            assert_eq!(decoded.encode(), public_blob);
+
+            let encoded = private_key.encode_with(layout);
+            assert_eq!(encoded.len(), 708);
+            let decoded = RSAPrivateKey::decode_with(&encoded, layout).unwrap();
+            assert_eq!(decoded.encode(), private_blob);
+        }
+
+        let big_endian = LAYOUTS[1];
+        let encoded = public_key.encode_with(big_endian);
+        assert_eq!(encoded[..4], 512u32.to_be_bytes());
+        assert_eq!(encoded[4..], public_blob[4..]);
+        assert!(RSAPublicKey::decode(&encoded).is_err());
+
+        let reversed = LAYOUTS[2];
+        let encoded = private_key.encode_with(reversed);
+        assert_eq!(encoded[..64], private_blob[708 - 64..]);
+        assert_eq!(encoded[708 - 4..], private_blob[..4]);
+        assert_eq!(
+            RSAPrivateKey::decode_with(&encoded[..707], reversed).err(),
+            Some(RSAError::InputLen {
+                expected: 708,
+                actual: 707,
+            })
+        );
+    }
+
+    #[test]
+    fn test_random_layouts() {
+        let mut random_struct = RandomStruct::new();
+        random_struct.random_update(&[0x5au8; 200]);
+        let blob = random_struct.encode();
+
+        for layout in LAYOUTS {
+            let encoded = random_struct.encode_with(layout);
+            let decoded = RandomStruct::decode_with(&encoded, layout).unwrap();
+            assert_eq!(decoded.encode(), blob);
+            assert_eq!(
+                RandomStruct::decode_with(&[encoded.as_slice(), &[0]].concat(), layout).err(),
+                Some(RSAError::Encoding)
+            );
+        }
+
+        let encoded = random_struct.encode_with(LAYOUTS[3]);
+        assert_eq!(encoded[..16], blob[24..]);
+        assert_eq!(encoded[16..20], 0u32.to_be_bytes());
+        assert_eq!(encoded[36..], 56u32.to_be_bytes());
+    }
+}
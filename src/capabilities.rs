@@ -0,0 +1,81 @@
+/* What this build of the crate supports, for applications that negotiate
+   algorithms with peers instead of assuming them. Everything is fixed at
+   compile time except min_modulus_bits, which is the key size floor in
+   force when capabilities is called. Digest algorithms registered at run
+   time are found through find_digest_algorithm_by_oid, not here.
+*/
+use crate::algorithm::{DigestAlgorithmId, EncryptionAlgorithmId};
+use crate::digest_info::{DigestAlg, ALL_DIGEST_ALGS};
+use crate::key_policy::minimum_key_bits;
+use crate::rsa::MAX_RSA_MODULUS_BITS;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    pub min_modulus_bits: u32,
+    pub max_modulus_bits: u32,
+    /* RSAREF digest IDs, for the digest and PEM routines. */
+    pub digest_algorithms: &'static [DigestAlgorithmId],
+    /* Digests that signatures and DigestInfo can name. */
+    pub signature_digests: &'static [DigestAlg],
+    /* Envelope ciphers. Identity is left out, being for testing only. */
+    pub encryption_algorithms: &'static [EncryptionAlgorithmId],
+    /* The RSA private-key block runs in constant time; see constant_time. */
+    pub constant_time_private_key: bool,
+    /* BigUint secrets are wiped too; byte buffers always are. */
+    pub zeroize: bool,
+    pub locked_memory: bool,
+    pub os_rng: bool,
+    pub parallel: bool,
+    pub compression: bool,
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        min_modulus_bits: minimum_key_bits(),
+        max_modulus_bits: MAX_RSA_MODULUS_BITS as u32,
+        digest_algorithms: &[DigestAlgorithmId::Md2, DigestAlgorithmId::Md5],
+        signature_digests: &ALL_DIGEST_ALGS,
+        encryption_algorithms: &[
+            EncryptionAlgorithmId::DesCbc,
+            EncryptionAlgorithmId::DesEde2Cbc,
+            EncryptionAlgorithmId::DesEde3Cbc,
+            EncryptionAlgorithmId::DesxCbc,
+            EncryptionAlgorithmId::Aes128Cbc,
+            EncryptionAlgorithmId::Aes256Cbc,
+        ],
+        constant_time_private_key: true,
+        zeroize: cfg!(feature = "zeroize"),
+        locked_memory: cfg!(all(feature = "mlock", any(unix, windows))),
+        os_rng: cfg!(all(feature = "os-rng", not(feature = "deterministic"))),
+        parallel: cfg!(feature = "parallel"),
+        compression: cfg!(feature = "deflate"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::CipherContext;
+    use crate::rsa::MIN_RSA_MODULUS_BITS;
+
+    #[test]
+    fn test_capabilities_match_the_build() {
+        let capabilities = capabilities();
+        assert!(capabilities.min_modulus_bits >= MIN_RSA_MODULUS_BITS as u32);
+        assert_eq!(capabilities.max_modulus_bits, 1024);
+        assert_eq!(capabilities.zeroize, cfg!(feature = "zeroize"));
+        for algorithm in capabilities.encryption_algorithms {
+            let key = vec![0x42u8; algorithm.key_len()];
+            let iv = vec![0u8; algorithm.iv_len()];
+            assert!(CipherContext::new(*algorithm, &key, &iv, true).is_ok());
+        }
+        for algorithm in capabilities.digest_algorithms {
+            assert_eq!(
+                DigestAlgorithmId::try_from(*algorithm as i32),
+                Ok(*algorithm)
+            );
+        }
+        assert_eq!(capabilities.signature_digests.len(), 6);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::RSAError;
+use alloc::vec::Vec;
+
+/* A block cipher with BLOCK_LEN-byte blocks that can be run in CBC mode. */
+pub(crate) trait BlockCipher<const BLOCK_LEN: usize> {
+    fn encrypt_block(&self, block: &mut [u8; BLOCK_LEN]);
+    fn decrypt_block(&self, block: &mut [u8; BLOCK_LEN]);
+}
+
+pub(crate) fn xor_block<const BLOCK_LEN: usize>(
+    block: &mut [u8; BLOCK_LEN],
+    other: &[u8; BLOCK_LEN],
+) {
+    for (b, o) in block.iter_mut().zip(other) {
+        *b ^= o;
+    }
+}
+
+/* Processes input in CBC mode, carrying the chaining value in iv. Input
+   must be a whole number of blocks.
+*/
+pub(crate) fn cbc_update<const BLOCK_LEN: usize, C: BlockCipher<BLOCK_LEN>>(
+    cipher: &C,
+    iv: &mut [u8; BLOCK_LEN],
+    encrypt: bool,
+    input: &[u8],
+) -> Result<Vec<u8>, RSAError> {
+    let (chunks, rest) = input.as_chunks::<BLOCK_LEN>();
+    if !rest.is_empty() {
+        return Err(RSAError::Len);
+    }
+
+    let mut output = Vec::<u8>::with_capacity(input.len());
+
+    for chunk in chunks {
+        let mut block = *chunk;
+
+        if encrypt {
+            xor_block(&mut block, iv);
+            cipher.encrypt_block(&mut block);
+            *iv = block;
+        } else {
+            cipher.decrypt_block(&mut block);
+            xor_block(&mut block, iv);
+            *iv = *chunk;
+        }
+
+        output.extend(block);
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,1364 @@
+use crate::algorithm::{CipherContext, DigestAlgorithmId, EncryptionAlgorithmId};
+use crate::constant_time::ct_eq;
+use crate::der::{
+    ber_to_der, consumed, context_tag, integer, read_any_tlv, read_integer, read_optional_tlv,
+    read_tlv, read_unix_time, set_of, tlv, unix_time, Located, TAG_NULL, TAG_OBJECT_IDENTIFIER,
+    TAG_OCTET_STRING, TAG_SEQUENCE, TAG_SET,
+};
+#[cfg(feature = "std")]
+use crate::der::{system_time, unix_seconds};
+use crate::digest_registry::{
+    encode_digest_info_with, find_digest_algorithm_by_id, find_digest_algorithm_by_oid,
+    DigestAlgorithm,
+};
+use crate::parallel;
+use crate::r_random::RandomStruct;
+use crate::rsa::{PrivateKeyOps, RSAPublicKey};
+use crate::secure_box::SecureBox;
+use crate::wipe::{SecretBuffer, SecretVec, Wiped};
+use crate::RSAError;
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+pub const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+pub const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+pub const OID_ENVELOPED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x03];
+pub const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+const OID_DES_CBC: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x07];
+const OID_DES_EDE3_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07];
+const OID_AES_128_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x02];
+const OID_AES_256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+
+/* Two-key EDE keys are carried as K1 || K2 || K1, which is exactly a
+   des-ede3-cbc key. DESX and the identity cipher have no CMS identifier.
+*/
+fn content_encryption_oid(algorithm: EncryptionAlgorithmId) -> Result<&'static [u8], RSAError> {
+    match algorithm {
+        EncryptionAlgorithmId::DesCbc => Ok(OID_DES_CBC),
+        EncryptionAlgorithmId::DesEde2Cbc | EncryptionAlgorithmId::DesEde3Cbc => {
+            Ok(OID_DES_EDE3_CBC)
+        }
+        EncryptionAlgorithmId::Aes128Cbc => Ok(OID_AES_128_CBC),
+        EncryptionAlgorithmId::Aes256Cbc => Ok(OID_AES_256_CBC),
+        EncryptionAlgorithmId::DesxCbc | EncryptionAlgorithmId::Identity => {
+            Err(RSAError::EncryptionAlgorithm)
+        }
+    }
+}
+
+fn content_encryption_algorithm_from_oid(oid: &[u8]) -> Result<EncryptionAlgorithmId, RSAError> {
+    match oid {
+        OID_DES_CBC => Ok(EncryptionAlgorithmId::DesCbc),
+        OID_DES_EDE3_CBC => Ok(EncryptionAlgorithmId::DesEde3Cbc),
+        OID_AES_128_CBC => Ok(EncryptionAlgorithmId::Aes128Cbc),
+        OID_AES_256_CBC => Ok(EncryptionAlgorithmId::Aes256Cbc),
+        _ => Err(RSAError::EncryptionAlgorithm),
+    }
+}
+
+fn rsa_encryption_identifier() -> Vec<u8> {
+    let mut algorithm = tlv(TAG_OBJECT_IDENTIFIER, OID_RSA_ENCRYPTION);
+    algorithm.extend(tlv(TAG_NULL, &[]));
+    tlv(TAG_SEQUENCE, &algorithm)
+}
+
+/* Accepts rsaEncryption with NULL or absent parameters. */
+fn read_rsa_encryption_identifier(input: &[u8]) -> Result<&[u8], RSAError> {
+    let (algorithm, rest) = read_tlv(input, TAG_SEQUENCE)?;
+    let (oid, parameters) = read_tlv(algorithm, TAG_OBJECT_IDENTIFIER)?;
+    if oid != OID_RSA_ENCRYPTION {
+        return Err(RSAError::EncryptionAlgorithm);
+    }
+    if !parameters.is_empty() && parameters != [TAG_NULL, 0] {
+        return Err(RSAError::Encoding);
+    }
+    Ok(rest)
+}
+
+fn content_info(content_type: &[u8], content: &[u8]) -> Vec<u8> {
+    let mut content_info = tlv(TAG_OBJECT_IDENTIFIER, content_type);
+    content_info.extend(tlv(context_tag(0, true), content));
+    tlv(TAG_SEQUENCE, &content_info)
+}
+
+/* Unwraps a ContentInfo of the expected type, returning the explicit
+   content.
+*/
+fn read_content_info<'a>(data: &'a [u8], expected_type: &[u8]) -> Result<&'a [u8], RSAError> {
+    let (content_info, rest) = read_tlv(data, TAG_SEQUENCE).at(data, data, "ContentInfo")?;
+    if !rest.is_empty() {
+        return Err(RSAError::Encoding).at(data, rest, "trailing data");
+    }
+    let (content_type, rest) =
+        read_tlv(content_info, TAG_OBJECT_IDENTIFIER).at(data, content_info, "contentType")?;
+    if content_type != expected_type {
+        return Err(RSAError::ContentEncoding).at(data, content_info, "contentType");
+    }
+    let (content, after) = read_tlv(rest, context_tag(0, true)).at(data, rest, "content")?;
+    if !after.is_empty() {
+        return Err(RSAError::Encoding).at(data, after, "trailing data");
+    }
+    Ok(content)
+}
+
+/* Pads to a whole number of blocks as in PKCS #5: n bytes of value n. */
+pub(crate) fn pad_content(content: &[u8], block_len: usize) -> Vec<u8> {
+    let pad_len = block_len - content.len() % block_len;
+    let mut padded = Vec::<u8>::with_capacity(content.len() + pad_len);
+    padded.extend(content);
+    padded.extend(core::iter::repeat_n(pad_len as u8, pad_len));
+    padded
+}
+
+pub(crate) fn unpad_content(padded: Vec<u8>, block_len: usize) -> Result<Vec<u8>, RSAError> {
+    let mut padded: SecretVec = Wiped(padded);
+    let pad_len = *padded.last().ok_or(RSAError::Key)? as usize;
+    if pad_len == 0 || pad_len > block_len || pad_len > padded.len() {
+        return Err(RSAError::Key);
+    }
+    if padded
+        .iter()
+        .rev()
+        .take(pad_len)
+        .any(|b| *b as usize != pad_len)
+    {
+        return Err(RSAError::Key);
+    }
+    let content_len = padded.len() - pad_len;
+    padded.truncate(content_len);
+    Ok(padded.into_vec())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecipientIdentifier {
+    /* DER encoding of the IssuerAndSerialNumber SEQUENCE, kept verbatim. */
+    IssuerAndSerialNumber(Vec<u8>),
+    SubjectKeyIdentifier(Vec<u8>),
+}
+
+impl RecipientIdentifier {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            RecipientIdentifier::IssuerAndSerialNumber(issuer_and_serial) => {
+                issuer_and_serial.clone()
+            }
+            RecipientIdentifier::SubjectKeyIdentifier(key_id) => tlv(context_tag(0, false), key_id),
+        }
+    }
+
+    fn decode(input: &[u8]) -> Result<(Self, &[u8]), RSAError> {
+        match read_any_tlv(input)? {
+            (TAG_SEQUENCE, _, rest) => {
+                let encoded = consumed(input, rest);
+                Ok((
+                    RecipientIdentifier::IssuerAndSerialNumber(encoded.to_vec()),
+                    rest,
+                ))
+            }
+            (tag, key_id, rest) if tag == context_tag(0, false) => Ok((
+                RecipientIdentifier::SubjectKeyIdentifier(key_id.to_vec()),
+                rest,
+            )),
+            _ => Err(RSAError::Encoding),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyTransRecipientInfo {
+    rid: RecipientIdentifier,
+    encrypted_key: Vec<u8>,
+}
+
+impl KeyTransRecipientInfo {
+    pub fn rid(&self) -> &RecipientIdentifier {
+        &self.rid
+    }
+
+    pub fn encrypted_key(&self) -> &[u8] {
+        &self.encrypted_key
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let version = match self.rid {
+            RecipientIdentifier::IssuerAndSerialNumber(_) => 0u8,
+            RecipientIdentifier::SubjectKeyIdentifier(_) => 2u8,
+        };
+
+        let mut content = integer(&[version]);
+        content.extend(self.rid.encode());
+        content.extend(rsa_encryption_identifier());
+        content.extend(tlv(TAG_OCTET_STRING, &self.encrypted_key));
+        tlv(TAG_SEQUENCE, &content)
+    }
+
+    fn decode(content: &[u8]) -> Result<Self, RSAError> {
+        let (_version, rest) = read_integer(content).at(content, content, "version")?;
+        let (rid, after) = RecipientIdentifier::decode(rest).at(content, rest, "rid")?;
+        let rest =
+            read_rsa_encryption_identifier(after).at(content, after, "keyEncryptionAlgorithm")?;
+
+        let (encrypted_key, after) =
+            read_tlv(rest, TAG_OCTET_STRING).at(content, rest, "encryptedKey")?;
+        if !after.is_empty() {
+            return Err(RSAError::Encoding).at(content, after, "trailing data");
+        }
+
+        Ok(Self {
+            rid,
+            encrypted_key: encrypted_key.to_vec(),
+        })
+    }
+}
+
+/* CMS EnvelopedData (RFC 5652) with RSA key transport, wrapped in a
+   ContentInfo.
+*/
+#[derive(Debug, Clone)]
+pub struct EnvelopedData {
+    recipients: Vec<KeyTransRecipientInfo>,
+    content_encryption_algorithm: EncryptionAlgorithmId,
+    iv: Vec<u8>,
+    encrypted_content: Vec<u8>,
+}
+
+impl EnvelopedData {
+    /* Encrypts content under a fresh content-encryption key and transports
+       that key to every recipient.
+    */
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(content_len = content.len(), recipients = recipients.len(), algorithm = ?algorithm), err(level = "debug"))
+)]
+    pub fn seal(
+        content: &[u8],
+        recipients: &[(RecipientIdentifier, &RSAPublicKey)],
+        algorithm: EncryptionAlgorithmId,
+        random_struct: &mut RandomStruct,
+    ) -> Result<Self, RSAError> {
+        content_encryption_oid(algorithm)?;
+        if recipients.is_empty() {
+            return Err(RSAError::PublicKey);
+        }
+
+        let key = SecureBox::from_vec(algorithm.generate_key(random_struct)?)?;
+        let iv = random_struct.generate_bytes(algorithm.iv_len())?;
+
+        let mut recipient_infos = Vec::<KeyTransRecipientInfo>::with_capacity(recipients.len());
+        for (rid, public_key) in recipients {
+            recipient_infos.push(KeyTransRecipientInfo {
+                rid: rid.clone(),
+                encrypted_key: public_key.rsa_public_encrypt(&key, random_struct)?,
+            });
+        }
+
+        let mut context = CipherContext::new(algorithm, &key, &iv, true)?;
+        let padded: SecretVec = Wiped(pad_content(content, algorithm.block_len()));
+        let encrypted_content = context.update(&padded)?;
+
+        Ok(Self {
+            recipients: recipient_infos,
+            content_encryption_algorithm: algorithm,
+            iv,
+            encrypted_content,
+        })
+    }
+
+    /* Recovers the content. With a recipient identifier only the matching
+       recipient is tried; otherwise every recipient is tried in turn.
+    */
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(recipients = self.recipients.len()), err(level = "debug"))
+)]
+    pub fn open(
+        &self,
+        rid: Option<&RecipientIdentifier>,
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Vec<u8>, RSAError> {
+        let algorithm = self.content_encryption_algorithm;
+        let mut result = Err(RSAError::PrivateKey);
+
+        for recipient in &self.recipients {
+            if rid.is_some_and(|rid| *rid != recipient.rid) {
+                continue;
+            }
+
+            result = private_key
+                .rsa_private_decrypt(&recipient.encrypted_key)
+                .and_then(|key| {
+                    let key = SecureBox::from_vec(key)?;
+                    let mut context = CipherContext::new(algorithm, &key, &self.iv, false)?;
+                    let padded = context
+                        .update(&self.encrypted_content)
+                        .map_err(|_| RSAError::ContentEncoding)?;
+                    unpad_content(padded, algorithm.block_len())
+                });
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /* open with the content in a buffer wiped on drop. */
+    pub fn open_secret(
+        &self,
+        rid: Option<&RecipientIdentifier>,
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<SecretBuffer, RSAError> {
+        self.open(rid, private_key).map(SecretBuffer::from)
+    }
+
+    pub fn recipients(&self) -> &[KeyTransRecipientInfo] {
+        &self.recipients
+    }
+
+    pub fn content_encryption_algorithm(&self) -> EncryptionAlgorithmId {
+        self.content_encryption_algorithm
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        /* CMS requires version 2 once any recipient uses a subject key
+           identifier.
+        */
+        let version = if self
+            .recipients
+            .iter()
+            .all(|r| matches!(r.rid, RecipientIdentifier::IssuerAndSerialNumber(_)))
+        {
+            0u8
+        } else {
+            2u8
+        };
+
+        let recipient_infos = set_of(self.recipients.iter().map(|r| r.encode()).collect());
+
+        /* Sealing and decoding have both checked the algorithm. */
+        let algorithm_oid =
+            content_encryption_oid(self.content_encryption_algorithm).unwrap_or_default();
+        let mut content_encryption_algorithm = tlv(TAG_OBJECT_IDENTIFIER, algorithm_oid);
+        content_encryption_algorithm.extend(tlv(TAG_OCTET_STRING, &self.iv));
+
+        let mut encrypted_content_info = tlv(TAG_OBJECT_IDENTIFIER, OID_DATA);
+        encrypted_content_info.extend(tlv(TAG_SEQUENCE, &content_encryption_algorithm));
+        encrypted_content_info.extend(tlv(context_tag(0, false), &self.encrypted_content));
+
+        let mut enveloped_data = integer(&[version]);
+        enveloped_data.extend(recipient_infos);
+        enveloped_data.extend(tlv(TAG_SEQUENCE, &encrypted_content_info));
+
+        content_info(OID_ENVELOPED_DATA, &tlv(TAG_SEQUENCE, &enveloped_data))
+    }
+
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+)]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        Self::decode_with(data, false)
+    }
+
+    /* Lenient decoding of BER input, as written by many older PKCS #7
+       implementations: indefinite lengths and a constructed, chunked
+       encryptedContent are accepted.
+    */
+    pub fn decode_ber(data: &[u8]) -> Result<Self, RSAError> {
+        Self::decode_with(&ber_to_der(data)?, true)
+    }
+
+    fn decode_with(data: &[u8], lenient: bool) -> Result<Self, RSAError> {
+        let explicit = read_content_info(data, OID_ENVELOPED_DATA)?;
+        let (enveloped_data, rest) =
+            read_tlv(explicit, TAG_SEQUENCE).at(data, explicit, "EnvelopedData")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+        let (_version, rest) = read_integer(enveloped_data).at(data, enveloped_data, "version")?;
+        let (_originator_info, after) =
+            read_optional_tlv(rest, context_tag(0, true)).at(data, rest, "originatorInfo")?;
+
+        /* Only key transport recipients are understood; other kinds of
+           RecipientInfo are skipped.
+        */
+        let (mut recipient_infos, rest) =
+            read_tlv(after, TAG_SET).at(data, after, "recipientInfos")?;
+        let mut recipients = Vec::<KeyTransRecipientInfo>::new();
+        while !recipient_infos.is_empty() {
+            let (tag, content, after) =
+                read_any_tlv(recipient_infos).at(data, recipient_infos, "RecipientInfo")?;
+            if tag == TAG_SEQUENCE {
+                recipients.push(KeyTransRecipientInfo::decode(content).at(
+                    data,
+                    content,
+                    "KeyTransRecipientInfo",
+                )?);
+            }
+            recipient_infos = after;
+        }
+
+        let (encrypted_content_info, after) =
+            read_tlv(rest, TAG_SEQUENCE).at(data, rest, "encryptedContentInfo")?;
+        let (_unprotected_attrs, rest) =
+            read_optional_tlv(after, context_tag(1, true)).at(data, after, "unprotectedAttrs")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+
+        let (content_type, rest) = read_tlv(encrypted_content_info, TAG_OBJECT_IDENTIFIER).at(
+            data,
+            encrypted_content_info,
+            "contentType",
+        )?;
+        if content_type != OID_DATA {
+            return Err(RSAError::ContentEncoding).at(data, encrypted_content_info, "contentType");
+        }
+        let (content_encryption_algorithm, after) =
+            read_tlv(rest, TAG_SEQUENCE).at(data, rest, "contentEncryptionAlgorithm")?;
+        let (encrypted_content, rest) = match read_optional_tlv(after, context_tag(0, true)).at(
+            data,
+            after,
+            "encryptedContent",
+        )? {
+            (Some(mut chunks), rest) if lenient => {
+                let mut encrypted_content = Vec::<u8>::new();
+                while !chunks.is_empty() {
+                    let (chunk, after) =
+                        read_tlv(chunks, TAG_OCTET_STRING).at(data, chunks, "encryptedContent")?;
+                    encrypted_content.extend(chunk);
+                    chunks = after;
+                }
+                (encrypted_content, rest)
+            }
+            _ => {
+                let (encrypted_content, rest) =
+                    read_tlv(after, context_tag(0, false)).at(data, after, "encryptedContent")?;
+                (encrypted_content.to_vec(), rest)
+            }
+        };
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+
+        let (oid, parameters) = read_tlv(content_encryption_algorithm, TAG_OBJECT_IDENTIFIER).at(
+            data,
+            content_encryption_algorithm,
+            "contentEncryptionAlgorithm",
+        )?;
+        let algorithm = content_encryption_algorithm_from_oid(oid).at(
+            data,
+            content_encryption_algorithm,
+            "contentEncryptionAlgorithm",
+        )?;
+        let (iv, rest) = read_tlv(parameters, TAG_OCTET_STRING).at(data, parameters, "iv")?;
+        if !rest.is_empty() || iv.len() != algorithm.iv_len() {
+            return Err(RSAError::Encoding).at(data, parameters, "iv");
+        }
+        if !encrypted_content
+            .len()
+            .is_multiple_of(algorithm.block_len())
+        {
+            return Err(RSAError::ContentEncoding).at(data, after, "encryptedContent");
+        }
+
+        Ok(Self {
+            recipients,
+            content_encryption_algorithm: algorithm,
+            iv: iv.to_vec(),
+            encrypted_content,
+        })
+    }
+}
+
+/* SignerIdentifier has the same shape as RecipientIdentifier. */
+pub type SignerIdentifier = RecipientIdentifier;
+
+fn digest_algorithm_identifier(algorithm: &dyn DigestAlgorithm) -> Vec<u8> {
+    let mut identifier = tlv(TAG_OBJECT_IDENTIFIER, algorithm.oid());
+    identifier.extend(tlv(TAG_NULL, &[]));
+    tlv(TAG_SEQUENCE, &identifier)
+}
+
+/* Digest algorithms are looked up in the digest registry, so digests
+   registered by the caller are accepted as well as the built-in ones.
+*/
+fn read_digest_algorithm_identifier(
+    input: &[u8],
+) -> Result<(Arc<dyn DigestAlgorithm>, &[u8]), RSAError> {
+    let (identifier, rest) = read_tlv(input, TAG_SEQUENCE)?;
+    let (oid, parameters) = read_tlv(identifier, TAG_OBJECT_IDENTIFIER)?;
+    if !parameters.is_empty() && parameters != [TAG_NULL, 0] {
+        return Err(RSAError::Encoding);
+    }
+    let algorithm = find_digest_algorithm_by_oid(oid).ok_or(RSAError::DigestAlgorithm)?;
+    Ok((algorithm, rest))
+}
+
+const OID_CONTENT_TYPE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+const OID_MESSAGE_DIGEST: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04];
+const OID_SIGNING_TIME: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x05];
+
+fn attribute(attribute_type: &[u8], value: Vec<u8>) -> Vec<u8> {
+    let mut attribute = tlv(TAG_OBJECT_IDENTIFIER, attribute_type);
+    attribute.extend(tlv(TAG_SET, &value));
+    tlv(TAG_SEQUENCE, &attribute)
+}
+
+/* Returns the single value of the attribute of the given type, checking
+   that every attribute is well formed and that no type occurs twice.
+*/
+fn read_attribute<'a>(
+    attributes: &'a [u8],
+    attribute_type: &[u8],
+) -> Result<Option<&'a [u8]>, RSAError> {
+    let mut found = None;
+    let mut rest = attributes;
+    let mut seen = Vec::<&[u8]>::new();
+    while !rest.is_empty() {
+        let (attribute, after) = read_tlv(rest, TAG_SEQUENCE)?;
+        let (oid, values) = read_tlv(attribute, TAG_OBJECT_IDENTIFIER)?;
+        let (values, trailing) = read_tlv(values, TAG_SET)?;
+        if !trailing.is_empty() || values.is_empty() || seen.contains(&oid) {
+            return Err(RSAError::Encoding);
+        }
+        seen.push(oid);
+
+        if oid == attribute_type {
+            let (_, _, after_value) = read_any_tlv(values)?;
+            if !after_value.is_empty() {
+                return Err(RSAError::Encoding);
+            }
+            found = Some(values);
+        }
+        rest = after;
+    }
+    Ok(found)
+}
+
+/* A signer over the content digest, or over signed attributes carrying
+   the content type, the message digest and optionally the signing time
+   (RFC 5652, 5.3 and 11).
+*/
+#[derive(Debug, Clone)]
+pub struct SignerInfo {
+    sid: SignerIdentifier,
+    digest_algorithm: Arc<dyn DigestAlgorithm>,
+    signed_attributes: Option<Vec<u8>>,
+    /* Seconds since the Unix epoch. */
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    signing_time: Option<i64>,
+    signature: Vec<u8>,
+}
+
+impl SignerInfo {
+    fn new(
+        sid: SignerIdentifier,
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+        content: &[u8],
+        with_attributes: bool,
+        signing_time: Option<i64>,
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Self, RSAError> {
+        let content_digest = digest_algorithm.digest(content);
+        let (signed_attributes, digest) = if with_attributes {
+            let mut attributes = vec![
+                attribute(OID_CONTENT_TYPE, tlv(TAG_OBJECT_IDENTIFIER, OID_DATA)),
+                attribute(OID_MESSAGE_DIGEST, tlv(TAG_OCTET_STRING, &content_digest)),
+            ];
+            if let Some(signing_time) = signing_time {
+                attributes.push(attribute(OID_SIGNING_TIME, unix_time(signing_time)));
+            }
+            let encoded = set_of(attributes);
+            let digest = digest_algorithm.digest(&encoded);
+            (Some(read_tlv(&encoded, TAG_SET)?.0.to_vec()), digest)
+        } else {
+            (None, content_digest)
+        };
+
+        let digest_info = encode_digest_info_with(digest_algorithm.as_ref(), &digest)?;
+        Ok(Self {
+            sid,
+            digest_algorithm,
+            signing_time: signed_attributes.as_ref().and(signing_time),
+            signed_attributes,
+            signature: private_key.rsa_private_encrypt(&digest_info)?,
+        })
+    }
+
+    pub fn sid(&self) -> &SignerIdentifier {
+        &self.sid
+    }
+
+    pub fn digest_algorithm(&self) -> &dyn DigestAlgorithm {
+        self.digest_algorithm.as_ref()
+    }
+
+    pub fn has_signed_attributes(&self) -> bool {
+        self.signed_attributes.is_some()
+    }
+
+    /* The authenticated signing time, to the second, if one was signed. */
+    #[cfg(feature = "std")]
+    pub fn signing_time(&self) -> Option<SystemTime> {
+        self.signing_time.map(system_time)
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    fn verify(&self, content: &[u8], public_key: &RSAPublicKey) -> Result<(), RSAError> {
+        let digest_info = public_key
+            .rsa_public_decrypt(&self.signature)
+            .map_err(|_| RSAError::Signature)?;
+        let algorithm = self.digest_algorithm.as_ref();
+        let content_digest = algorithm.digest(content);
+
+        let digest = match &self.signed_attributes {
+            Some(attributes) => {
+                let expected = tlv(TAG_OCTET_STRING, &content_digest);
+                if !read_attribute(attributes, OID_MESSAGE_DIGEST)?
+                    .is_some_and(|attribute| ct_eq(attribute, &expected))
+                {
+                    return Err(RSAError::Signature);
+                }
+                algorithm.digest(&tlv(TAG_SET, attributes))
+            }
+            None => content_digest,
+        };
+
+        if !ct_eq(&digest_info, &encode_digest_info_with(algorithm, &digest)?) {
+            return Err(RSAError::Signature);
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let version = match self.sid {
+            SignerIdentifier::IssuerAndSerialNumber(_) => 1u8,
+            SignerIdentifier::SubjectKeyIdentifier(_) => 3u8,
+        };
+
+        let mut content = integer(&[version]);
+        content.extend(self.sid.encode());
+        content.extend(digest_algorithm_identifier(self.digest_algorithm.as_ref()));
+        if let Some(attributes) = &self.signed_attributes {
+            content.extend(tlv(context_tag(0, true), attributes));
+        }
+        content.extend(rsa_encryption_identifier());
+        content.extend(tlv(TAG_OCTET_STRING, &self.signature));
+        tlv(TAG_SEQUENCE, &content)
+    }
+
+    /* Signed attributes must include the content type (id-data) and the
+       message digest. Other attributes are kept and covered by the
+       signature but not interpreted.
+    */
+    fn decode(content: &[u8]) -> Result<Self, RSAError> {
+        let (_version, rest) = read_integer(content).at(content, content, "version")?;
+        let (sid, after) = SignerIdentifier::decode(rest).at(content, rest, "sid")?;
+        let (digest_algorithm, rest) =
+            read_digest_algorithm_identifier(after).at(content, after, "digestAlgorithm")?;
+
+        let (signed_attributes, after) =
+            read_optional_tlv(rest, context_tag(0, true)).at(content, rest, "signedAttrs")?;
+        let mut signing_time = None;
+        if let Some(attributes) = signed_attributes {
+            if read_attribute(attributes, OID_CONTENT_TYPE).at(
+                content,
+                attributes,
+                "signedAttrs",
+            )? != Some(&tlv(TAG_OBJECT_IDENTIFIER, OID_DATA))
+            {
+                return Err(RSAError::ContentEncoding).at(content, attributes, "contentType");
+            }
+            match read_attribute(attributes, OID_MESSAGE_DIGEST).at(
+                content,
+                attributes,
+                "signedAttrs",
+            )? {
+                Some(value) if read_tlv(value, TAG_OCTET_STRING).is_ok() => {}
+                _ => {
+                    return Err(RSAError::ContentEncoding).at(content, attributes, "messageDigest")
+                }
+            }
+            if let Some(value) = read_attribute(attributes, OID_SIGNING_TIME).at(
+                content,
+                attributes,
+                "signedAttrs",
+            )? {
+                signing_time = Some(read_unix_time(value).at(content, value, "signingTime")?.0);
+            }
+        }
+
+        let rest =
+            read_rsa_encryption_identifier(after).at(content, after, "signatureAlgorithm")?;
+        let (signature, after) = read_tlv(rest, TAG_OCTET_STRING).at(content, rest, "signature")?;
+        let (_unsigned_attrs, rest) =
+            read_optional_tlv(after, context_tag(1, true)).at(content, after, "unsignedAttrs")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(content, rest, "trailing data");
+        }
+
+        Ok(Self {
+            sid,
+            digest_algorithm,
+            signed_attributes: signed_attributes.map(|a| a.to_vec()),
+            signing_time,
+            signature: signature.to_vec(),
+        })
+    }
+}
+
+/* CMS SignedData (RFC 5652) with RSA signers, wrapped in a ContentInfo.
+   The content may be detached, in which case it must be supplied again on
+   verification.
+*/
+#[derive(Debug, Clone)]
+pub struct SignedData {
+    content: Option<Vec<u8>>,
+    signers: Vec<SignerInfo>,
+}
+
+impl SignedData {
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(content_len = content.len()), err(level = "debug"))
+)]
+    pub fn sign(
+        content: &[u8],
+        detached: bool,
+        signers: &[(SignerIdentifier, &dyn PrivateKeyOps)],
+        digest_algorithm: DigestAlgorithmId,
+    ) -> Result<Self, RSAError> {
+        let digest_algorithm = find_digest_algorithm_by_id(digest_algorithm as i32)
+            .ok_or(RSAError::DigestAlgorithm)?;
+        Self::sign_with_digest(content, detached, signers, digest_algorithm)
+    }
+
+    /* As sign, with any digest from the digest registry or supplied by the
+       caller.
+    */
+    pub fn sign_with_digest(
+        content: &[u8],
+        detached: bool,
+        signers: &[(SignerIdentifier, &dyn PrivateKeyOps)],
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+    ) -> Result<Self, RSAError> {
+        Self::sign_inner(content, detached, signers, digest_algorithm, false, None)
+    }
+
+    /* Signs over signed attributes: content-type and message-digest, plus
+       signing-time when one is given.
+    */
+    #[cfg(feature = "std")]
+    pub fn sign_with_attributes(
+        content: &[u8],
+        detached: bool,
+        signers: &[(SignerIdentifier, &dyn PrivateKeyOps)],
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+        signing_time: Option<SystemTime>,
+    ) -> Result<Self, RSAError> {
+        Self::sign_inner(
+            content,
+            detached,
+            signers,
+            digest_algorithm,
+            true,
+            signing_time.map(unix_seconds),
+        )
+    }
+
+    fn sign_inner(
+        content: &[u8],
+        detached: bool,
+        signers: &[(SignerIdentifier, &dyn PrivateKeyOps)],
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+        with_attributes: bool,
+        signing_time: Option<i64>,
+    ) -> Result<Self, RSAError> {
+        if signers.is_empty() {
+            return Err(RSAError::PrivateKey);
+        }
+
+        let mut signer_infos = Vec::<SignerInfo>::with_capacity(signers.len());
+        for (sid, private_key) in signers {
+            signer_infos.push(SignerInfo::new(
+                sid.clone(),
+                digest_algorithm.clone(),
+                content,
+                with_attributes,
+                signing_time,
+                *private_key,
+            )?);
+        }
+
+        Ok(Self {
+            content: if detached {
+                None
+            } else {
+                Some(content.to_vec())
+            },
+            signers: signer_infos,
+        })
+    }
+
+    /* Checks the signature of the matching signer, or of any signer when no
+       identifier is given. detached_content is required exactly when the
+       content is not encapsulated.
+    */
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(signers = self.signers.len()), err(level = "debug"))
+)]
+    pub fn verify(
+        &self,
+        detached_content: Option<&[u8]>,
+        sid: Option<&SignerIdentifier>,
+        public_key: &RSAPublicKey,
+    ) -> Result<(), RSAError> {
+        let content = match (&self.content, detached_content) {
+            (Some(content), None) => content.as_slice(),
+            (None, Some(content)) => content,
+            _ => return Err(RSAError::Data),
+        };
+
+        let signers = self
+            .signers
+            .iter()
+            .filter(|signer| !sid.is_some_and(|sid| *sid != signer.sid))
+            .collect::<Vec<_>>();
+        let mut results = parallel::map(&signers, |signer| signer.verify(content, public_key));
+        if results.iter().any(Result::is_ok) {
+            return Ok(());
+        }
+        results.pop().unwrap_or(Err(RSAError::PublicKey))
+    }
+
+    pub fn content(&self) -> Option<&[u8]> {
+        self.content.as_deref()
+    }
+
+    pub fn signers(&self) -> &[SignerInfo] {
+        &self.signers
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let version = if self
+            .signers
+            .iter()
+            .all(|s| matches!(s.sid, SignerIdentifier::IssuerAndSerialNumber(_)))
+        {
+            1u8
+        } else {
+            3u8
+        };
+
+        let mut digest_algorithms = self
+            .signers
+            .iter()
+            .map(|s| digest_algorithm_identifier(s.digest_algorithm.as_ref()))
+            .collect::<Vec<Vec<u8>>>();
+        digest_algorithms.sort();
+        digest_algorithms.dedup();
+
+        let mut encap_content_info = tlv(TAG_OBJECT_IDENTIFIER, OID_DATA);
+        if let Some(content) = &self.content {
+            encap_content_info.extend(tlv(context_tag(0, true), &tlv(TAG_OCTET_STRING, content)));
+        }
+
+        let mut signed_data = integer(&[version]);
+        signed_data.extend(set_of(digest_algorithms));
+        signed_data.extend(tlv(TAG_SEQUENCE, &encap_content_info));
+        signed_data.extend(set_of(self.signers.iter().map(|s| s.encode()).collect()));
+
+        content_info(OID_SIGNED_DATA, &tlv(TAG_SEQUENCE, &signed_data))
+    }
+
+    /* Lenient decoding of BER input. Only the transfer encoding is relaxed;
+       signatures are still checked over the content octets.
+    */
+    pub fn decode_ber(data: &[u8]) -> Result<Self, RSAError> {
+        Self::decode(&ber_to_der(data)?)
+    }
+
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+)]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        let explicit = read_content_info(data, OID_SIGNED_DATA)?;
+        let (signed_data, rest) =
+            read_tlv(explicit, TAG_SEQUENCE).at(data, explicit, "SignedData")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+        let (_version, rest) = read_integer(signed_data).at(data, signed_data, "version")?;
+
+        let (mut digest_algorithms, rest) =
+            read_tlv(rest, TAG_SET).at(data, rest, "digestAlgorithms")?;
+        while !digest_algorithms.is_empty() {
+            digest_algorithms = read_tlv(digest_algorithms, TAG_SEQUENCE)
+                .at(data, digest_algorithms, "digestAlgorithms")?
+                .1;
+        }
+
+        let (encap_content_info, rest) =
+            read_tlv(rest, TAG_SEQUENCE).at(data, rest, "encapContentInfo")?;
+        let (content_type, encap_rest) = read_tlv(encap_content_info, TAG_OBJECT_IDENTIFIER).at(
+            data,
+            encap_content_info,
+            "eContentType",
+        )?;
+        if content_type != OID_DATA {
+            return Err(RSAError::ContentEncoding).at(data, encap_content_info, "eContentType");
+        }
+        let (explicit_content, after) =
+            read_optional_tlv(encap_rest, context_tag(0, true)).at(data, encap_rest, "eContent")?;
+        if !after.is_empty() {
+            return Err(RSAError::Encoding).at(data, after, "trailing data");
+        }
+        let content = match explicit_content {
+            Some(explicit_content) => {
+                let (content, after) = read_tlv(explicit_content, TAG_OCTET_STRING).at(
+                    data,
+                    explicit_content,
+                    "eContent",
+                )?;
+                if !after.is_empty() {
+                    return Err(RSAError::Encoding).at(data, after, "trailing data");
+                }
+                Some(content.to_vec())
+            }
+            None => None,
+        };
+
+        /* Certificates and CRLs are not interpreted. */
+        let (_certificates, after) =
+            read_optional_tlv(rest, context_tag(0, true)).at(data, rest, "certificates")?;
+        let (_crls, rest) =
+            read_optional_tlv(after, context_tag(1, true)).at(data, after, "crls")?;
+
+        let (mut signer_infos, after) = read_tlv(rest, TAG_SET).at(data, rest, "signerInfos")?;
+        if !after.is_empty() {
+            return Err(RSAError::Encoding).at(data, after, "trailing data");
+        }
+        let mut signers = Vec::<SignerInfo>::new();
+        while !signer_infos.is_empty() {
+            let (content, after) =
+                read_tlv(signer_infos, TAG_SEQUENCE).at(data, signer_infos, "SignerInfo")?;
+            signers.push(SignerInfo::decode(content).at(data, content, "SignerInfo")?);
+            signer_infos = after;
+        }
+
+        Ok(Self { content, signers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use crate::rsa::RSAPrivateKey;
+    #[cfg(feature = "std")]
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn generate_keys() -> (RSAPublicKey, RSAPrivateKey) {
+        test_key_pair(512)
+    }
+
+    #[test]
+    fn test_pkcs5_padding() {
+        for len in 0..20 {
+            let content = vec![0xaau8; len];
+            let padded = pad_content(&content, 8);
+            assert_eq!(padded.len(), (len / 8 + 1) * 8);
+            assert_eq!(unpad_content(padded, 8).unwrap(), content);
+        }
+
+        assert!(unpad_content(vec![0u8; 8], 8).is_err());
+        assert!(unpad_content(vec![9u8; 8], 8).is_err());
+        assert!(unpad_content(vec![1, 1, 1, 1, 1, 1, 3, 2], 8).is_err());
+    }
+
+    #[test]
+    fn test_enveloped_data_round_trip() {
+        let mut random_struct = test_random_struct(0x3c);
+        let (public_a, private_a) = generate_keys();
+        let (public_b, private_b) = generate_keys();
+        let (_, private_c) = generate_keys();
+
+        let rid_a = RecipientIdentifier::SubjectKeyIdentifier(b"alice".to_vec());
+        let rid_b = RecipientIdentifier::SubjectKeyIdentifier(b"bob".to_vec());
+        let content = b"Enveloped content for two recipients.";
+
+        for algorithm in [
+            EncryptionAlgorithmId::DesCbc,
+            EncryptionAlgorithmId::DesEde2Cbc,
+            EncryptionAlgorithmId::DesEde3Cbc,
+            EncryptionAlgorithmId::Aes128Cbc,
+            EncryptionAlgorithmId::Aes256Cbc,
+        ] {
+            let sealed = EnvelopedData::seal(
+                content,
+                &[(rid_a.clone(), &public_a), (rid_b.clone(), &public_b)],
+                algorithm,
+                &mut random_struct,
+            )
+            .unwrap();
+
+            let decoded = EnvelopedData::decode(&sealed.encode()).unwrap();
+            assert_eq!(decoded.recipients().len(), 2);
+
+            assert_eq!(decoded.open(Some(&rid_a), &private_a).unwrap(), content);
+            assert_eq!(decoded.open(None, &private_b).unwrap(), content);
+            assert_eq!(&decoded.open_secret(None, &private_b).unwrap()[..], content);
+            assert!(decoded.open(Some(&rid_a), &private_b).is_err());
+            assert!(decoded.open(None, &private_c).is_err());
+        }
+    }
+
+    #[test]
+    fn test_enveloped_data_decode_error_location() {
+        let mut random_struct = test_random_struct(0x3c);
+        let (public_key, _) = generate_keys();
+        let rid = RecipientIdentifier::SubjectKeyIdentifier(b"key".to_vec());
+        let sealed = EnvelopedData::seal(
+            b"located",
+            &[(rid, &public_key)],
+            EncryptionAlgorithmId::DesCbc,
+            &mut random_struct,
+        )
+        .unwrap();
+        let mut encoded = sealed.encode();
+        let encrypted_key = sealed.recipients()[0].encrypted_key();
+        let key_offset = encoded
+            .windows(encrypted_key.len())
+            .position(|window| window == encrypted_key)
+            .unwrap();
+
+        /* The OCTET STRING tag two bytes before the key, reported as an
+           offset into the whole ContentInfo.
+        */
+        encoded[key_offset - 2] = TAG_NULL;
+        let error = EnvelopedData::decode(&encoded).err().unwrap();
+        assert_eq!(error, RSAError::Encoding);
+        assert!(matches!(
+            error,
+            RSAError::DecodeAt { offset, field: "encryptedKey", .. } if offset == key_offset - 2
+        ));
+    }
+
+    #[test]
+    fn test_enveloped_data_rejects_desx() {
+        let mut random_struct = test_random_struct(0x3c);
+        let (public_key, _) = generate_keys();
+        let rid = RecipientIdentifier::SubjectKeyIdentifier(b"key".to_vec());
+
+        assert!(matches!(
+            EnvelopedData::seal(
+                b"data",
+                &[(rid, &public_key)],
+                EncryptionAlgorithmId::DesxCbc,
+                &mut random_struct
+            ),
+            Err(RSAError::EncryptionAlgorithm)
+        ));
+    }
+
+    #[test]
+    fn test_enveloped_data_decode_rejects_malformed() {
+        let mut random_struct = test_random_struct(0x3c);
+        let (public_key, _) = generate_keys();
+        let rid = RecipientIdentifier::SubjectKeyIdentifier(b"key".to_vec());
+
+        let encoded = EnvelopedData::seal(
+            b"data",
+            &[(rid, &public_key)],
+            EncryptionAlgorithmId::DesCbc,
+            &mut random_struct,
+        )
+        .unwrap()
+        .encode();
+
+        for len in 0..encoded.len() {
+            assert!(EnvelopedData::decode(&encoded[..len]).is_err());
+        }
+
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(EnvelopedData::decode(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_signed_data_round_trip() {
+        let (public_a, private_a) = generate_keys();
+        let (public_b, private_b) = generate_keys();
+
+        let sid_a = SignerIdentifier::SubjectKeyIdentifier(b"alice".to_vec());
+        let sid_b = SignerIdentifier::SubjectKeyIdentifier(b"bob".to_vec());
+        let content = b"Signed content for archival.";
+
+        for digest_algorithm in [DigestAlgorithmId::Md2, DigestAlgorithmId::Md5] {
+            let signed = SignedData::sign(
+                content,
+                false,
+                &[(sid_a.clone(), &private_a), (sid_b.clone(), &private_b)],
+                digest_algorithm,
+            )
+            .unwrap();
+
+            let decoded = SignedData::decode(&signed.encode()).unwrap();
+            assert_eq!(decoded.content().unwrap(), content);
+            assert_eq!(decoded.signers().len(), 2);
+
+            assert!(decoded.verify(None, Some(&sid_a), &public_a).is_ok());
+            assert!(decoded.verify(None, None, &public_b).is_ok());
+            assert!(decoded.verify(None, Some(&sid_a), &public_b).is_err());
+            assert!(decoded.verify(Some(content), None, &public_a).is_err());
+        }
+    }
+
+    #[test]
+    fn test_signed_data_registry_digest() {
+        let (public_key, private_key) = generate_keys();
+        let sid = SignerIdentifier::SubjectKeyIdentifier(b"carol".to_vec());
+        let content = b"Signed with a digest from the registry.";
+
+        let sha256 =
+            find_digest_algorithm_by_oid(crate::digest_info::DigestAlg::Sha256.oid()).unwrap();
+        let signed =
+            SignedData::sign_with_digest(content, false, &[(sid, &private_key)], sha256).unwrap();
+
+        let decoded = SignedData::decode(&signed.encode()).unwrap();
+        assert_eq!(decoded.signers()[0].digest_algorithm().digest_len(), 32);
+        assert!(decoded.verify(None, None, &public_key).is_ok());
+    }
+
+    /* Stands in for a token that only exposes raw private exponentiation. */
+    struct ExternalKey {
+        key: RSAPrivateKey,
+        operations: core::cell::Cell<usize>,
+    }
+
+    impl PrivateKeyOps for ExternalKey {
+        fn bits(&self) -> u32 {
+            self.key.bits()
+        }
+
+        fn public_key(&self) -> RSAPublicKey {
+            self.key.public_key()
+        }
+
+        fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+            self.operations.set(self.operations.get() + 1);
+            self.key.rsa_private_block(input)
+        }
+    }
+
+    #[test]
+    fn test_external_private_key_ops() {
+        let mut random_struct = test_random_struct(0x3c);
+        let (public_key, private_key) = generate_keys();
+        let sid = SignerIdentifier::SubjectKeyIdentifier(b"token".to_vec());
+        let rid = RecipientIdentifier::SubjectKeyIdentifier(b"token".to_vec());
+        let content = b"Signed and opened through an external key.";
+
+        let expected = SignedData::sign(
+            content,
+            false,
+            &[(sid.clone(), &private_key)],
+            DigestAlgorithmId::Md5,
+        )
+        .unwrap();
+
+        let external = ExternalKey {
+            key: private_key,
+            operations: core::cell::Cell::new(0),
+        };
+        let signed =
+            SignedData::sign(content, false, &[(sid, &external)], DigestAlgorithmId::Md5).unwrap();
+        assert_eq!(signed.encode(), expected.encode());
+        assert!(signed.verify(None, None, &public_key).is_ok());
+
+        let sealed = EnvelopedData::seal(
+            content,
+            &[(rid, &public_key)],
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &mut random_struct,
+        )
+        .unwrap();
+        assert_eq!(sealed.open(None, &external).unwrap(), content);
+        assert_eq!(external.operations.get(), 2);
+    }
+
+    #[test]
+    fn test_signed_data_detached() {
+        let (public_key, private_key) = generate_keys();
+        let sid = SignerIdentifier::IssuerAndSerialNumber(vec![0x30, 0x00]);
+        let content = b"Detached content.";
+
+        let signed = SignedData::sign(
+            content,
+            true,
+            &[(sid, &private_key)],
+            DigestAlgorithmId::Md5,
+        )
+        .unwrap();
+        let decoded = SignedData::decode(&signed.encode()).unwrap();
+        assert!(decoded.content().is_none());
+
+        assert!(decoded.verify(Some(content), None, &public_key).is_ok());
+        assert!(matches!(
+            decoded.verify(Some(b"Tampered content."), None, &public_key),
+            Err(RSAError::Signature)
+        ));
+        assert!(matches!(
+            decoded.verify(None, None, &public_key),
+            Err(RSAError::Data)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_signed_data_signed_attributes() {
+        let (public_key, private_key) = generate_keys();
+        let sid = SignerIdentifier::SubjectKeyIdentifier(b"dave".to_vec());
+        let content = b"Timestamped content.";
+        let signing_time = UNIX_EPOCH + Duration::from_secs(1700000000);
+        let sha256 =
+            find_digest_algorithm_by_oid(crate::digest_info::DigestAlg::Sha256.oid()).unwrap();
+
+        for detached in [false, true] {
+            let signed = SignedData::sign_with_attributes(
+                content,
+                detached,
+                &[(sid.clone(), &private_key)],
+                sha256.clone(),
+                Some(signing_time),
+            )
+            .unwrap();
+
+            let decoded = SignedData::decode(&signed.encode()).unwrap();
+            let signer = &decoded.signers()[0];
+            assert!(signer.has_signed_attributes());
+            assert_eq!(signer.signing_time(), Some(signing_time));
+
+            let detached_content = detached.then_some(&content[..]);
+            assert!(decoded.verify(detached_content, None, &public_key).is_ok());
+            if detached {
+                assert!(matches!(
+                    decoded.verify(Some(b"Other content."), None, &public_key),
+                    Err(RSAError::Signature)
+                ));
+            }
+
+            /* Altering the signed time keeps the encoding valid but breaks
+               the signature.
+            */
+            let mut altered = signed.encode();
+            let position = altered.windows(6).position(|w| w == b"231114").unwrap();
+            altered[position + 5] = b'5';
+            let decoded = SignedData::decode(&altered).unwrap();
+            assert_ne!(decoded.signers()[0].signing_time(), Some(signing_time));
+            assert!(matches!(
+                decoded.verify(detached_content, None, &public_key),
+                Err(RSAError::Signature)
+            ));
+        }
+
+        let signed =
+            SignedData::sign_with_attributes(content, false, &[(sid, &private_key)], sha256, None)
+                .unwrap();
+        let decoded = SignedData::decode(&signed.encode()).unwrap();
+        assert!(decoded.signers()[0].signing_time().is_none());
+        assert!(decoded.verify(None, None, &public_key).is_ok());
+    }
+
+    #[test]
+    fn test_signed_data_decode_rejects_malformed() {
+        let (_, private_key) = generate_keys();
+        let sid = SignerIdentifier::SubjectKeyIdentifier(b"key".to_vec());
+
+        let encoded = SignedData::sign(
+            b"data",
+            false,
+            &[(sid, &private_key)],
+            DigestAlgorithmId::Md5,
+        )
+        .unwrap()
+        .encode();
+
+        for len in 0..encoded.len() {
+            assert!(SignedData::decode(&encoded[..len]).is_err());
+        }
+        assert!(EnvelopedData::decode(&encoded).is_err());
+    }
+
+    /* Re-encodes DER with indefinite lengths throughout, splitting the
+       encryptedContent (the only long [0] primitive; subject key
+       identifiers here are short) into constructed OCTET STRING chunks.
+    */
+    fn to_ber(der: &[u8]) -> Vec<u8> {
+        let mut ber = Vec::<u8>::new();
+        let mut input = der;
+        while !input.is_empty() {
+            let (tag, content, rest) = read_any_tlv(input).unwrap();
+            if tag & 0x20 != 0 {
+                ber.extend([tag, 0x80]);
+                ber.extend(to_ber(content));
+                ber.extend([0, 0]);
+            } else if tag == context_tag(0, false) && content.len() >= 16 {
+                ber.extend([context_tag(0, true), 0x80]);
+                for chunk in content.chunks(5) {
+                    ber.extend(tlv(TAG_OCTET_STRING, chunk));
+                }
+                ber.extend([0, 0]);
+            } else {
+                ber.extend(&input[..input.len() - rest.len()]);
+            }
+            input = rest;
+        }
+        ber
+    }
+
+    #[test]
+    fn test_decode_ber() {
+        let mut random_struct = test_random_struct(0x3c);
+        let (public_key, private_key) = generate_keys();
+        let rid = RecipientIdentifier::SubjectKeyIdentifier(b"key".to_vec());
+
+        let enveloped = EnvelopedData::seal(
+            b"Archived enveloped content.",
+            &[(rid.clone(), &public_key)],
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &mut random_struct,
+        )
+        .unwrap()
+        .encode();
+        let ber = to_ber(&enveloped);
+        assert!(EnvelopedData::decode(&ber).is_err());
+        let decoded = EnvelopedData::decode_ber(&ber).unwrap();
+        assert_eq!(
+            decoded.open(Some(&rid), &private_key).unwrap(),
+            b"Archived enveloped content."
+        );
+        assert!(EnvelopedData::decode_ber(&enveloped).is_ok());
+
+        let signed = SignedData::sign(
+            b"Archived signed content.",
+            false,
+            &[(rid, &private_key)],
+            DigestAlgorithmId::Md5,
+        )
+        .unwrap()
+        .encode();
+        let ber = to_ber(&signed);
+        assert!(SignedData::decode(&ber).is_err());
+        let decoded = SignedData::decode_ber(&ber).unwrap();
+        assert!(decoded.verify(None, None, &public_key).is_ok());
+    }
+}
@@ -0,0 +1,229 @@
+/* Glue for the legacy client/server protocols built directly on RSAREF,
+   ActiveWorlds among them. The handshake runs:
+
+     1. The client generates a 512-bit key pair and sends its public key
+        as the 260-byte R_RSA_PUBLIC_KEY blob.
+     2. The server checks the key, draws a session key and returns it
+        encrypted to the client's public key in one PKCS #1 v1.5 block.
+     3. The client decrypts the session key. From then on each direction
+        of the connection is RC4 keyed with it, each running its own
+        keystream from the start.
+
+   Keys of any other size are refused in both directions, as the peers
+   these protocols talk to accept nothing else.
+*/
+use crate::r_keygen::generate_pem_keys_with_random;
+use crate::r_random::RandomStruct;
+use crate::rsa::{PrivateKeyOps, RSAPrivateKey, RSAProtoKey, RSAPublicKey};
+use crate::wipe::{Wipe, Wiped};
+use crate::RSAError;
+use alloc::vec::Vec;
+use rc4::cipher::generic_array::GenericArray;
+use rc4::consts::U16;
+use rc4::{KeyInit, Rc4, StreamCipher};
+
+pub const COMPAT_MODULUS_BITS: u32 = 512;
+pub const PUBLIC_KEY_BLOB_LEN: usize = 260;
+pub const SESSION_KEY_LEN: usize = 16;
+
+pub fn generate_compat_keys(
+    random_struct: &mut RandomStruct,
+) -> Result<(RSAPublicKey, RSAPrivateKey), RSAError> {
+    let proto_key = RSAProtoKey {
+        bits: COMPAT_MODULUS_BITS,
+        use_fermat4: true,
+    };
+    generate_pem_keys_with_random(&proto_key, random_struct)
+}
+
+/* Both the declared length and the modulus itself must be 512 bits. */
+pub fn check_compat_key(public_key: &RSAPublicKey) -> Result<(), RSAError> {
+    if public_key.bits() != COMPAT_MODULUS_BITS
+        || public_key.modulus().bits() != COMPAT_MODULUS_BITS as usize
+    {
+        return Err(RSAError::ModulusLen);
+    }
+    Ok(())
+}
+
+pub fn encode_public_key_blob(
+    public_key: &RSAPublicKey,
+) -> Result<[u8; PUBLIC_KEY_BLOB_LEN], RSAError> {
+    check_compat_key(public_key)?;
+    public_key
+        .encode()
+        .try_into()
+        .map_err(|_| RSAError::PublicKey)
+}
+
+/* The blob must be exactly 260 bytes; trailing data is refused rather
+   than ignored as RSAPublicKey::decode does.
+*/
+pub fn decode_public_key_blob(blob: &[u8]) -> Result<RSAPublicKey, RSAError> {
+    if blob.len() != PUBLIC_KEY_BLOB_LEN {
+        return Err(RSAError::InputLen {
+            expected: PUBLIC_KEY_BLOB_LEN,
+            actual: blob.len(),
+        });
+    }
+    let public_key = RSAPublicKey::decode(blob)?;
+    check_compat_key(&public_key)?;
+    Ok(public_key)
+}
+
+/* The RC4 key shared by both ends once the handshake is done. */
+pub struct SessionKey([u8; SESSION_KEY_LEN]);
+
+impl Drop for SessionKey {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl SessionKey {
+    pub fn generate(random_struct: &mut RandomStruct) -> Result<Self, RSAError> {
+        random_struct.generate_array().map(Self)
+    }
+
+    /* Encrypts the key to the peer's public key, which is checked first. */
+    pub fn seal(
+        &self,
+        public_key: &RSAPublicKey,
+        random_struct: &mut RandomStruct,
+    ) -> Result<Vec<u8>, RSAError> {
+        check_compat_key(public_key)?;
+        public_key.rsa_public_encrypt(&self.0, random_struct)
+    }
+
+    pub fn open(private_key: &dyn PrivateKeyOps, encrypted: &[u8]) -> Result<Self, RSAError> {
+        check_compat_key(&private_key.public_key())?;
+        let key = Wiped(private_key.rsa_private_decrypt(encrypted)?);
+        key.as_slice()
+            .try_into()
+            .map(Self)
+            .map_err(|_| RSAError::Key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SESSION_KEY_LEN] {
+        &self.0
+    }
+
+    /* A fresh keystream; use one per direction. */
+    pub fn rc4(&self) -> RC4Context {
+        RC4Context::new(&self.0)
+    }
+}
+
+/* RC4 with a 128-bit key. Encryption and decryption are the same
+   operation, so there is no direction flag.
+*/
+pub struct RC4Context(Rc4<U16>);
+
+impl RC4Context {
+    pub fn new(key: &[u8; SESSION_KEY_LEN]) -> Self {
+        Self(Rc4::new(GenericArray::from_slice(key)))
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut output = input.to_vec();
+        self.0.apply_keystream(&mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_random_struct;
+
+    #[test]
+    fn test_rc4_vector() {
+        // RFC 6229, 128-bit key, offsets 0 and 16
+        let key = core::array::from_fn(|i| i as u8 + 1);
+        let mut context = RC4Context::new(&key);
+        assert_eq!(
+            context.update(&[0u8; 16]),
+            [
+                0x9a, 0xc7, 0xcc, 0x9a, 0x60, 0x9d, 0x1e, 0xf7, 0xb2, 0x93, 0x28, 0x99, 0xcd, 0xe4,
+                0x1b, 0x97
+            ]
+        );
+        assert_eq!(
+            context.update(&[0u8; 16]),
+            [
+                0x52, 0x48, 0xc4, 0x95, 0x90, 0x14, 0x12, 0x6a, 0x6e, 0x8a, 0x84, 0xf1, 0x1d, 0x1a,
+                0x9e, 0x1c
+            ]
+        );
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut client_random = test_random_struct(0x11);
+        let mut server_random = test_random_struct(0x22);
+
+        /* Client hello: the public key blob. */
+        let (client_public, client_private) = generate_compat_keys(&mut client_random).unwrap();
+        let blob = encode_public_key_blob(&client_public).unwrap();
+        assert_eq!(&blob[..4], 512u32.to_le_bytes());
+
+        /* Server reply: the session key under the client's key. */
+        let received_public = decode_public_key_blob(&blob).unwrap();
+        assert_eq!(received_public.modulus(), client_public.modulus());
+        let server_key = SessionKey::generate(&mut server_random).unwrap();
+        let encrypted = server_key
+            .seal(&received_public, &mut server_random)
+            .unwrap();
+        assert_eq!(encrypted.len(), 64);
+
+        let client_key = SessionKey::open(&client_private, &encrypted).unwrap();
+        assert_eq!(client_key.as_bytes(), server_key.as_bytes());
+
+        /* Traffic both ways, over several messages. */
+        let (mut client_send, mut server_receive) = (client_key.rc4(), server_key.rc4());
+        let (mut server_send, mut client_receive) = (server_key.rc4(), client_key.rc4());
+        for message in [&b"login: guest"[..], b"enter world"] {
+            let wire = client_send.update(message);
+            assert_ne!(wire, message);
+            assert_eq!(server_receive.update(&wire), message);
+        }
+        let wire = server_send.update(b"welcome");
+        assert_eq!(client_receive.update(&wire), b"welcome");
+
+        /* Anything other than a 512-bit key or an exact blob is refused. */
+        assert_eq!(
+            decode_public_key_blob(&blob[..259]).err(),
+            Some(RSAError::InputLen {
+                expected: 260,
+                actual: 259
+            })
+        );
+        assert!(decode_public_key_blob(&[blob.as_slice(), &[0]].concat()).is_err());
+        let (long_public, long_private) = generate_pem_keys_with_random(
+            &RSAProtoKey {
+                bits: 768,
+                use_fermat4: true,
+            },
+            &mut client_random,
+        )
+        .unwrap();
+        assert_eq!(
+            decode_public_key_blob(&long_public.encode()).err(),
+            Some(RSAError::ModulusLen)
+        );
+        assert_eq!(
+            encode_public_key_blob(&long_public),
+            Err(RSAError::ModulusLen)
+        );
+        assert!(server_key.seal(&long_public, &mut server_random).is_err());
+        let long_encrypted = long_public
+            .rsa_public_encrypt(server_key.as_bytes(), &mut server_random)
+            .unwrap();
+        assert!(SessionKey::open(&long_private, &long_encrypted).is_err());
+
+        let short_secret = client_public
+            .rsa_public_encrypt(&[0x42; 8], &mut server_random)
+            .unwrap();
+        assert!(SessionKey::open(&client_private, &short_secret).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+/* Constant-time helpers over subtle for comparisons and selections on
+   secret data.
+
+   What is and is not constant-time:
+     - Signature and checksum comparisons (DigestInfo against the expected
+       encoding, PEM MICs, secret-share digests) take time that depends
+       only on the lengths compared.
+     - PKCS #1 block type 2 unpadding, pkcs1::unpad_type2 as every
+       private-key decryption uses it, scans the whole block and makes a
+       single accept/reject decision, so the position of the separator and
+       which check failed are not revealed by timing. The message length is revealed by the output itself.
+     - The RSA private-key block, exponentiations and CRT recombination
+       both, runs on the fixed-length Montgomery arithmetic in montgomery,
+       whose branches and memory accesses depend only on the lengths of
+       the primes.
+     - Padding-byte generation branches only on whether a freshly drawn
+       byte is zero; zero bytes are discarded, so the padding kept is not
+       revealed.
+     - Everything else uses num-bigint-dig, which is not constant-time:
+       the blinding step of the hardened profile, DH and DSA private
+       operations, and key generation.
+*/
+use subtle::ConstantTimeEq;
+
+/* Compares two byte strings in time that depends only on their lengths. */
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"digest", b"digest"));
+        assert!(!ct_eq(b"digest", b"digesT"));
+        assert!(!ct_eq(b"digest", b"diges"));
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_type2_unpadding_decides_once() {
+        use crate::r_keygen::{test_key_pair, test_random_struct};
+        use crate::rsa::{PrivateKeyOps, RSASerialize};
+        use crate::RSAError;
+        use num_bigint_dig::BigUint;
+
+        let (public_key, private_key) = test_key_pair(512);
+        let encrypt_block = |block: &[u8]| {
+            BigUint::from_bytes_be(block)
+                .modpow(public_key.exponent(), public_key.modulus())
+                .to_be(64)
+        };
+
+        let mut random_struct = test_random_struct(0x11);
+        let ciphertext = public_key
+            .rsa_public_encrypt(b"content key", &mut random_struct)
+            .unwrap();
+        assert_eq!(
+            private_key.rsa_private_decrypt(&ciphertext).unwrap(),
+            b"content key"
+        );
+
+        /* No separator at all, a separator only in the last byte, too few
+           padding bytes, and the wrong block type.
+        */
+        let mut no_separator = [0xffu8; 64];
+        no_separator[0] = 0;
+        no_separator[1] = 2;
+        let mut last_byte_separator = no_separator;
+        last_byte_separator[63] = 0;
+        let mut short_padding = no_separator;
+        short_padding[9] = 0;
+        let mut block_type_1 = no_separator;
+        block_type_1[1] = 1;
+        block_type_1[20] = 0;
+        for block in [
+            no_separator,
+            last_byte_separator,
+            short_padding,
+            block_type_1,
+        ] {
+            assert_eq!(
+                PrivateKeyOps::rsa_private_decrypt(&private_key, &encrypt_block(&block)),
+                Err(RSAError::Data)
+            );
+        }
+
+        let mut shortest_padding = no_separator;
+        shortest_padding[10] = 0;
+        assert_eq!(
+            private_key
+                .rsa_private_decrypt(&encrypt_block(&shortest_padding))
+                .unwrap(),
+            [0xffu8; 53]
+        );
+    }
+}
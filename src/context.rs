@@ -0,0 +1,385 @@
+/* A single entry point over profiles, envelopes and detached signatures,
+   for callers who would rather not assemble SealContext, OpenContext and
+   DetachedSignature themselves:
+
+     let mut context = Context::new().recipient(&public_key);
+     let envelope = context.seal(reader, writer)?;
+
+   Defaults are the Hardened profile, SHA-256 and AES-256-CBC. Without a
+   random structure given through random, one is seeded from the operating
+   system when it is first needed.
+*/
+use crate::algorithm::EncryptionAlgorithmId;
+use crate::digest_info::DigestAlg;
+use crate::profile::Profile;
+use crate::r_enhanc::{OpenContext, OpenReader, SealContext, SealWriter};
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAPublicKey};
+use crate::signature_file::{DetachedSignature, SignatureFileError};
+use crate::RSAError;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+use rand::{rngs::OsRng, RngCore};
+use std::io::{self, Read, Write};
+
+#[derive(Debug)]
+pub enum ContextError {
+    Io(io::Error),
+    Crypto(RSAError),
+}
+
+impl core::fmt::Display for ContextError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ContextError::Io(error) => write!(f, "context I/O failed: {}", error),
+            ContextError::Crypto(error) => write!(f, "context operation failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ContextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ContextError::Io(error) => Some(error),
+            ContextError::Crypto(error) => Some(error),
+        }
+    }
+}
+
+/* The streaming layers report crypto failures as InvalidData wrapping the
+   RSAError; those come back out as Crypto.
+*/
+impl From<io::Error> for ContextError {
+    fn from(error: io::Error) -> Self {
+        match error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<RSAError>())
+        {
+            Some(inner) => ContextError::Crypto(inner.clone()),
+            None => ContextError::Io(error),
+        }
+    }
+}
+
+impl From<RSAError> for ContextError {
+    fn from(error: RSAError) -> Self {
+        ContextError::Crypto(error)
+    }
+}
+
+impl From<SignatureFileError> for ContextError {
+    fn from(error: SignatureFileError) -> Self {
+        match error {
+            SignatureFileError::Io(error) => error.into(),
+            SignatureFileError::Signature(error) => ContextError::Crypto(error),
+        }
+    }
+}
+
+/* What the opener needs besides the ciphertext: the algorithm, the IV and
+   the content-encryption key encrypted once per recipient, in recipient
+   order.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    algorithm: EncryptionAlgorithmId,
+    encrypted_keys: Vec<Vec<u8>>,
+    iv: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn new(
+        algorithm: EncryptionAlgorithmId,
+        encrypted_keys: Vec<Vec<u8>>,
+        iv: Vec<u8>,
+    ) -> Self {
+        Self {
+            algorithm,
+            encrypted_keys,
+            iv,
+        }
+    }
+
+    pub fn algorithm(&self) -> EncryptionAlgorithmId {
+        self.algorithm
+    }
+
+    pub fn encrypted_keys(&self) -> &[Vec<u8>] {
+        &self.encrypted_keys
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+}
+
+pub struct Context<'a> {
+    profile: Profile,
+    digest: DigestAlg,
+    cipher: EncryptionAlgorithmId,
+    recipients: Vec<&'a RSAPublicKey>,
+    random_struct: Option<RandomStruct>,
+}
+
+impl Default for Context<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Context<'a> {
+    pub fn new() -> Self {
+        Self {
+            profile: Profile::Hardened,
+            digest: DigestAlg::Sha256,
+            cipher: EncryptionAlgorithmId::Aes256Cbc,
+            recipients: Vec::new(),
+            random_struct: None,
+        }
+    }
+
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn digest(mut self, digest: DigestAlg) -> Self {
+        self.digest = digest;
+        self
+    }
+
+    pub fn cipher(mut self, cipher: EncryptionAlgorithmId) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /* Adds a recipient for seal; may be called once per recipient. */
+    pub fn recipient(mut self, public_key: &'a RSAPublicKey) -> Self {
+        self.recipients.push(public_key);
+        self
+    }
+
+    /* Uses random_struct, which must already be seeded, instead of one
+       seeded from the operating system.
+    */
+    pub fn random(mut self, random_struct: RandomStruct) -> Self {
+        self.random_struct = Some(random_struct);
+        self
+    }
+
+    fn random_struct(&mut self) -> Result<&mut RandomStruct, RSAError> {
+        let random_struct = match self.random_struct.take() {
+            Some(random_struct) => random_struct,
+            None => os_random_struct()?,
+        };
+        let random_struct = self.random_struct.insert(random_struct);
+        self.profile.stir_random(random_struct)?;
+        Ok(random_struct)
+    }
+
+    /* Encrypts everything read from reader to every recipient, writing the
+       ciphertext to writer.
+    */
+    pub fn seal<R: Read, W: Write>(
+        &mut self,
+        mut reader: R,
+        writer: W,
+    ) -> Result<Envelope, ContextError> {
+        for public_key in &self.recipients {
+            self.profile.check_public_key(public_key)?;
+        }
+        let recipients = self.recipients.clone();
+        let algorithm = self.cipher;
+        let context = SealContext::new(algorithm, &recipients, self.random_struct()?)?;
+        let envelope = Envelope::new(
+            algorithm,
+            context.encrypted_keys().to_vec(),
+            context.iv().to_vec(),
+        );
+
+        let mut sealer = SealWriter::new(writer, context);
+        io::copy(&mut reader, &mut sealer)?;
+        sealer.finish()?;
+        Ok(envelope)
+    }
+
+    /* Decrypts a sealed stream into writer with the first encrypted key in
+       envelope that private_key opens.
+    */
+    pub fn open<R: Read, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+        envelope: &Envelope,
+        private_key: &RSAPrivateKey,
+    ) -> Result<(), ContextError> {
+        let private_key = self.profile.private_key(private_key)?;
+        let mut result = Err(RSAError::PrivateKey);
+        for encrypted_key in &envelope.encrypted_keys {
+            result = OpenContext::new(
+                envelope.algorithm,
+                encrypted_key,
+                &envelope.iv,
+                &private_key,
+            );
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        let mut opener = OpenReader::new(reader, result?);
+        io::copy(&mut opener, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn sign<R: Read>(
+        &self,
+        reader: R,
+        private_key: &RSAPrivateKey,
+    ) -> Result<DetachedSignature, ContextError> {
+        let private_key = self.profile.private_key(private_key)?;
+        Ok(DetachedSignature::sign_reader(
+            reader,
+            Arc::new(self.digest),
+            &private_key,
+        )?)
+    }
+
+    /* Signatures made with any digest other than the context's are
+       refused, so a forger cannot fall back to a weaker one.
+    */
+    pub fn verify<R: Read>(
+        &self,
+        reader: R,
+        signature: &DetachedSignature,
+        public_key: &RSAPublicKey,
+    ) -> Result<(), ContextError> {
+        self.profile.check_public_key(public_key)?;
+        if signature.digest_algorithm().oid() != self.digest.oid() {
+            return Err(RSAError::DigestAlgorithm.into());
+        }
+        Ok(signature.verify_reader(reader, public_key)?)
+    }
+}
+
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+fn os_random_struct() -> Result<RandomStruct, RSAError> {
+    let mut random_struct = RandomStruct::new();
+    let mut seed = vec![0u8; random_struct.get_random_bytes_needed()];
+    OsRng
+        .try_fill_bytes(&mut seed)
+        .map_err(|_| RSAError::NeedRandom)?;
+    random_struct.random_update(&seed);
+    Ok(random_struct)
+}
+
+#[cfg(not(all(feature = "os-rng", not(feature = "deterministic"))))]
+fn os_random_struct() -> Result<RandomStruct, RSAError> {
+    Err(RSAError::NeedRandom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::generate_pem_keys_with_random;
+    use crate::r_keygen::test_random_struct;
+    use crate::rsa::RSAProtoKey;
+
+    fn key_pair(bits: u32, seed: u8) -> (RSAPublicKey, RSAPrivateKey) {
+        let proto_key = RSAProtoKey {
+            bits,
+            use_fermat4: true,
+        };
+        generate_pem_keys_with_random(&proto_key, &mut test_random_struct(seed)).unwrap()
+    }
+
+    #[test]
+    fn test_legacy_context() {
+        let (alice_public, alice_private) = key_pair(512, 0x01);
+        let (bob_public, bob_private) = key_pair(512, 0x02);
+        let content = (0..5000u32).map(|i| i as u8).collect::<Vec<u8>>();
+
+        let mut context = Context::new()
+            .profile(Profile::Legacy)
+            .digest(DigestAlg::Md5)
+            .cipher(EncryptionAlgorithmId::DesCbc)
+            .recipient(&alice_public)
+            .recipient(&bob_public)
+            .random(test_random_struct(0x03));
+        let mut sealed = Vec::<u8>::new();
+        let envelope = context.seal(content.as_slice(), &mut sealed).unwrap();
+        assert_eq!(envelope.algorithm(), EncryptionAlgorithmId::DesCbc);
+        assert_eq!(envelope.encrypted_keys().len(), 2);
+
+        for private_key in [&alice_private, &bob_private] {
+            let mut opened = Vec::<u8>::new();
+            context
+                .open(sealed.as_slice(), &mut opened, &envelope, private_key)
+                .unwrap();
+            assert_eq!(opened, content);
+        }
+        let (_, stranger) = key_pair(512, 0x04);
+        assert!(context
+            .open(sealed.as_slice(), io::sink(), &envelope, &stranger)
+            .is_err());
+
+        let signature = context.sign(content.as_slice(), &alice_private).unwrap();
+        context
+            .verify(content.as_slice(), &signature, &alice_public)
+            .unwrap();
+        assert!(matches!(
+            context.verify(&b"tampered"[..], &signature, &alice_public),
+            Err(ContextError::Crypto(RSAError::Signature))
+        ));
+        let sha1 = Context::new()
+            .profile(Profile::Legacy)
+            .digest(DigestAlg::Sha1);
+        assert!(matches!(
+            sha1.verify(content.as_slice(), &signature, &alice_public),
+            Err(ContextError::Crypto(RSAError::DigestAlgorithm))
+        ));
+
+        assert!(matches!(
+            Context::new()
+                .profile(Profile::Legacy)
+                .random(test_random_struct(0x05))
+                .seal(&b"nobody"[..], io::sink()),
+            Err(ContextError::Crypto(RSAError::PublicKey))
+        ));
+    }
+
+    #[test]
+    fn test_hardened_defaults() {
+        let (short_public, short_private) = key_pair(512, 0x06);
+        let mut context = Context::new().recipient(&short_public);
+        assert!(matches!(
+            context.seal(&b"short key"[..], io::sink()),
+            Err(ContextError::Crypto(RSAError::ModulusLen))
+        ));
+        assert!(context.sign(&b"short key"[..], &short_private).is_err());
+
+        let (public_key, private_key) = key_pair(1024, 0x07);
+        let mut context = Context::new().recipient(&public_key);
+        let mut sealed = Vec::<u8>::new();
+        let envelope = context.seal(&b"hardened"[..], &mut sealed);
+        if !cfg!(all(feature = "os-rng", not(feature = "deterministic"))) {
+            assert!(envelope.is_err());
+            return;
+        }
+        let envelope = envelope.unwrap();
+        assert_eq!(envelope.algorithm(), EncryptionAlgorithmId::Aes256Cbc);
+        let mut opened = Vec::<u8>::new();
+        context
+            .open(sealed.as_slice(), &mut opened, &envelope, &private_key)
+            .unwrap();
+        assert_eq!(opened, b"hardened");
+
+        let signature = context.sign(&b"hardened"[..], &private_key).unwrap();
+        assert_eq!(signature.digest_algorithm().oid(), DigestAlg::Sha256.oid());
+        context
+            .verify(&b"hardened"[..], &signature, &public_key)
+            .unwrap();
+    }
+}
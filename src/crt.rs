@@ -0,0 +1,115 @@
+/* Chinese Remainder Theorem recombination: the one x below the product
+   of pairwise coprime moduli with x = residues[i] mod moduli[i] for each
+   i, by Garner's method. The private-key block does its two-prime case
+   through montgomery's crt_combine_into, which runs in constant time;
+   crt_combine works on BigUint and does not, so it is for public values
+   and for secrets only where timing cannot be observed.
+*/
+use crate::RSAError;
+use core::ops::Rem;
+use num_bigint_dig::{BigUint, ModInverse};
+
+/* Fails with Len if residues and moduli differ in length or are empty,
+   and with Data if a modulus is zero, a residue is not below its modulus
+   or two moduli share a factor.
+*/
+pub fn crt_combine(residues: &[BigUint], moduli: &[BigUint]) -> Result<BigUint, RSAError> {
+    if residues.len() != moduli.len() || residues.is_empty() {
+        return Err(RSAError::Len);
+    }
+    if residues.iter().zip(moduli).any(|(r, m)| r >= m) {
+        return Err(RSAError::Data);
+    }
+
+    if let ([r1, r2], [m1, m2]) = (residues, moduli) {
+        return combine_pair(r1, m1, r2, m2);
+    }
+
+    let mut pairs = residues.iter().zip(moduli);
+    let Some((r, m)) = pairs.next() else {
+        return Err(RSAError::Len);
+    };
+    let (mut x, mut product) = (r.clone(), m.clone());
+    for (r, m) in pairs {
+        x = combine_pair(&x, &product, r, m)?;
+        product *= m;
+    }
+    Ok(x)
+}
+
+/* The x below m1 * m2 with x = r1 mod m1 and x = r2 mod m2:
+   x = ((r1 - r2) * m2^-1 mod m1) * m2 + r2.
+*/
+fn combine_pair(
+    r1: &BigUint,
+    m1: &BigUint,
+    r2: &BigUint,
+    m2: &BigUint,
+) -> Result<BigUint, RSAError> {
+    let inverse = m2
+        .rem(m1)
+        .mod_inverse(m1)
+        .and_then(|inverse| inverse.to_biguint())
+        .ok_or(RSAError::Data)?;
+    let h = ((r1 + m1 - r2.rem(m1)) * inverse).rem(m1);
+    Ok(h * m2 + r2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn values(values: &[u64]) -> Vec<BigUint> {
+        values.iter().map(|&value| BigUint::from(value)).collect()
+    }
+
+    #[test]
+    fn test_crt_combine() {
+        let moduli = values(&[3, 5, 7]);
+        assert_eq!(
+            crt_combine(&values(&[2, 3, 2]), &moduli),
+            Ok(BigUint::from(23u32))
+        );
+        assert_eq!(
+            crt_combine(&values(&[2, 3]), &moduli[..2]),
+            Ok(BigUint::from(8u32))
+        );
+        assert_eq!(
+            crt_combine(&values(&[4]), &values(&[9])),
+            Ok(BigUint::from(4u32))
+        );
+
+        /* A private key's primes recombine m^dP and m^dQ into m^d. */
+        let p = (BigUint::from(1u32) << 127) - BigUint::from(1u32);
+        let q = (BigUint::from(1u32) << 89) - BigUint::from(1u32);
+        let r = (BigUint::from(1u32) << 61) - BigUint::from(1u32);
+        let x = BigUint::from(0x1234_5678_9abc_def0_u64) << 200;
+        let x = x.rem(&p * &q * &r);
+        let residues = [x.clone().rem(&p), x.clone().rem(&q), x.clone().rem(&r)];
+        let moduli = [p.clone(), q.clone(), r];
+        assert_eq!(crt_combine(&residues, &moduli), Ok(x.clone()));
+        assert_eq!(
+            crt_combine(&residues[..2], &moduli[..2]),
+            Ok(x.rem(&p * &q))
+        );
+
+        assert_eq!(crt_combine(&[], &[]), Err(RSAError::Len));
+        assert_eq!(
+            crt_combine(&values(&[1, 2]), &values(&[3])),
+            Err(RSAError::Len)
+        );
+        assert_eq!(
+            crt_combine(&values(&[1, 2]), &values(&[4, 6])),
+            Err(RSAError::Data)
+        );
+        assert_eq!(
+            crt_combine(&values(&[5, 2]), &values(&[5, 7])),
+            Err(RSAError::Data)
+        );
+        assert_eq!(
+            crt_combine(&values(&[0, 2]), &values(&[0, 7])),
+            Err(RSAError::Data)
+        );
+    }
+}
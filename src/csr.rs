@@ -0,0 +1,401 @@
+use crate::constant_time::ct_eq;
+use crate::der::{
+    algorithm_identifier, bit_string, consumed, context_tag, integer, read_algorithm_identifier,
+    read_any_tlv, read_bit_string, read_integer, read_tlv, set_of, tlv, Located, TAG_IA5_STRING,
+    TAG_OBJECT_IDENTIFIER, TAG_PRINTABLE_STRING, TAG_SEQUENCE, TAG_SET, TAG_UTF8_STRING,
+};
+use crate::digest_info::{encode_digest_info, DigestAlg};
+use crate::r_encode::{decode_pem_block, encode_pem_block};
+use crate::rsa::{PrivateKeyOps, RSAPublicKey};
+use crate::RSAError;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use num_bigint_dig::BigUint;
+use sha2::{Digest, Sha256};
+
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+const PEM_HEADER: &str = "-----BEGIN CERTIFICATE REQUEST-----";
+const PEM_FOOTER: &str = "-----END CERTIFICATE REQUEST-----";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameAttribute {
+    CommonName,
+    Country,
+    Locality,
+    State,
+    Organization,
+    OrganizationalUnit,
+    EmailAddress,
+}
+
+impl NameAttribute {
+    fn oid(&self) -> &'static [u8] {
+        match self {
+            NameAttribute::CommonName => &[0x55, 0x04, 0x03],
+            NameAttribute::Country => &[0x55, 0x04, 0x06],
+            NameAttribute::Locality => &[0x55, 0x04, 0x07],
+            NameAttribute::State => &[0x55, 0x04, 0x08],
+            NameAttribute::Organization => &[0x55, 0x04, 0x0a],
+            NameAttribute::OrganizationalUnit => &[0x55, 0x04, 0x0b],
+            NameAttribute::EmailAddress => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01],
+        }
+    }
+
+    /* Country codes are PrintableString and e-mail addresses IA5String, as
+       RFC 5280 requires; everything else is UTF8String.
+    */
+    fn string_tag(&self) -> u8 {
+        match self {
+            NameAttribute::Country => TAG_PRINTABLE_STRING,
+            NameAttribute::EmailAddress => TAG_IA5_STRING,
+            _ => TAG_UTF8_STRING,
+        }
+    }
+
+    fn validate(&self, value: &str) -> Result<(), RSAError> {
+        let valid = match self {
+            NameAttribute::Country => {
+                value.len() == 2 && value.bytes().all(|c| c.is_ascii_uppercase())
+            }
+            NameAttribute::EmailAddress => value.is_ascii(),
+            _ => true,
+        };
+        if valid && !value.is_empty() {
+            Ok(())
+        } else {
+            Err(RSAError::Data)
+        }
+    }
+}
+
+/* Signature algorithm OIDs for <digest>WithRSAEncryption. */
+fn signature_algorithm_oid(alg: DigestAlg) -> &'static [u8] {
+    match alg {
+        DigestAlg::Md2 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x02],
+        DigestAlg::Md5 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x04],
+        DigestAlg::Sha1 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x05],
+        DigestAlg::Sha256 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b],
+        DigestAlg::Sha384 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c],
+        DigestAlg::Sha512 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d],
+    }
+}
+
+fn signature_algorithm_from_oid(oid: &[u8]) -> Result<DigestAlg, RSAError> {
+    [
+        DigestAlg::Md2,
+        DigestAlg::Md5,
+        DigestAlg::Sha1,
+        DigestAlg::Sha256,
+        DigestAlg::Sha384,
+        DigestAlg::Sha512,
+    ]
+    .into_iter()
+    .find(|alg| signature_algorithm_oid(*alg) == oid)
+    .ok_or(RSAError::DigestAlgorithm)
+}
+
+/* SubjectPublicKeyInfo holding a PKCS #1 RSAPublicKey. */
+pub(crate) fn encode_subject_public_key_info(public_key: &RSAPublicKey) -> Vec<u8> {
+    let mut rsa_public_key = integer(&public_key.modulus().to_bytes_be());
+    rsa_public_key.extend(integer(&public_key.exponent().to_bytes_be()));
+
+    let mut spki = algorithm_identifier(OID_RSA_ENCRYPTION);
+    spki.extend(bit_string(&tlv(TAG_SEQUENCE, &rsa_public_key)));
+    tlv(TAG_SEQUENCE, &spki)
+}
+
+/* SHA-256 of the key's SubjectPublicKeyInfo, as most tools display it. */
+pub fn key_fingerprint(public_key: &RSAPublicKey) -> [u8; 32] {
+    Sha256::digest(encode_subject_public_key_info(public_key)).into()
+}
+
+pub(crate) fn decode_subject_public_key_info(
+    input: &[u8],
+) -> Result<(RSAPublicKey, &[u8]), RSAError> {
+    let (spki, rest) = read_tlv(input, TAG_SEQUENCE).at(input, input, "SubjectPublicKeyInfo")?;
+    let (oid, spki_rest) = read_algorithm_identifier(spki).at(input, spki, "algorithm")?;
+    if oid != OID_RSA_ENCRYPTION {
+        return Err(RSAError::KeyEncoding).at(input, spki, "algorithm");
+    }
+    let (key_bits, after) = read_bit_string(spki_rest).at(input, spki_rest, "subjectPublicKey")?;
+    if !after.is_empty() {
+        return Err(RSAError::Encoding).at(input, after, "trailing data");
+    }
+
+    let (rsa_public_key, after) =
+        read_tlv(key_bits, TAG_SEQUENCE).at(input, key_bits, "RSAPublicKey")?;
+    if !after.is_empty() {
+        return Err(RSAError::Encoding).at(input, after, "trailing data");
+    }
+    let (modulus, key_rest) = read_integer(rsa_public_key).at(input, rsa_public_key, "modulus")?;
+    let (exponent, after) = read_integer(key_rest).at(input, key_rest, "publicExponent")?;
+    if !after.is_empty() {
+        return Err(RSAError::Encoding).at(input, after, "trailing data");
+    }
+
+    let modulus = BigUint::from_bytes_be(modulus);
+    let bits = modulus.bits() as u32;
+    Ok((
+        RSAPublicKey::from_components(bits, modulus, BigUint::from_bytes_be(exponent)),
+        rest,
+    ))
+}
+
+/* PKCS #10 CertificationRequest with an RSA subject key and an empty
+   attribute set.
+*/
+#[derive(Debug)]
+pub struct CertificationRequest {
+    subject: Vec<(NameAttribute, String)>,
+    public_key: RSAPublicKey,
+    signature_algorithm: DigestAlg,
+    info: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CertificationRequest {
+    /* Builds and signs a request. The subject is an ordered list of
+       attributes, each forming its own relative distinguished name.
+    */
+    pub fn new(
+        subject: &[(NameAttribute, &str)],
+        public_key: &RSAPublicKey,
+        private_key: &dyn PrivateKeyOps,
+        signature_algorithm: DigestAlg,
+    ) -> Result<Self, RSAError> {
+        let mut name = Vec::<u8>::new();
+        for (attribute, value) in subject {
+            attribute.validate(value)?;
+            let mut type_and_value = tlv(TAG_OBJECT_IDENTIFIER, attribute.oid());
+            type_and_value.extend(tlv(attribute.string_tag(), value.as_bytes()));
+            name.extend(set_of(vec![tlv(TAG_SEQUENCE, &type_and_value)]));
+        }
+
+        let mut info = integer(&[0]);
+        info.extend(tlv(TAG_SEQUENCE, &name));
+        info.extend(encode_subject_public_key_info(public_key));
+        info.extend(tlv(context_tag(0, true), &[]));
+        let info = tlv(TAG_SEQUENCE, &info);
+
+        let digest_info =
+            encode_digest_info(signature_algorithm, &signature_algorithm.digest(&info))?;
+        let signature = private_key.rsa_private_encrypt(&digest_info)?;
+
+        Ok(Self {
+            subject: subject
+                .iter()
+                .map(|(attribute, value)| (*attribute, value.to_string()))
+                .collect(),
+            public_key: RSAPublicKey::from_components(
+                public_key.bits(),
+                public_key.modulus().clone(),
+                public_key.exponent().clone(),
+            ),
+            signature_algorithm,
+            info,
+            signature,
+        })
+    }
+
+    pub fn subject(&self) -> &[(NameAttribute, String)] {
+        &self.subject
+    }
+
+    pub fn public_key(&self) -> &RSAPublicKey {
+        &self.public_key
+    }
+
+    pub fn signature_algorithm(&self) -> DigestAlg {
+        self.signature_algorithm
+    }
+
+    /* Checks the request's self-signature against its own public key. */
+    pub fn verify(&self) -> Result<(), RSAError> {
+        let expected = encode_digest_info(
+            self.signature_algorithm,
+            &self.signature_algorithm.digest(&self.info),
+        )?;
+        match self.public_key.rsa_public_decrypt(&self.signature) {
+            Ok(digest_info) if ct_eq(&digest_info, &expected) => Ok(()),
+            _ => Err(RSAError::Signature),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut request = self.info.clone();
+        request.extend(algorithm_identifier(signature_algorithm_oid(
+            self.signature_algorithm,
+        )));
+        request.extend(bit_string(&self.signature));
+        tlv(TAG_SEQUENCE, &request)
+    }
+
+    pub fn encode_pem(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n",
+            PEM_HEADER,
+            encode_pem_block(&self.encode()),
+            PEM_FOOTER
+        )
+    }
+
+    /* Parses a DER request. Only the subject attributes listed in
+       NameAttribute are understood, and the signature is not checked.
+    */
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+)]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        let (request, rest) =
+            read_tlv(data, TAG_SEQUENCE).at(data, data, "CertificationRequest")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+
+        let (info_content, rest) =
+            read_tlv(request, TAG_SEQUENCE).at(data, request, "certificationRequestInfo")?;
+        let info = consumed(request, rest);
+        let (oid, after) = read_algorithm_identifier(rest).at(data, rest, "signatureAlgorithm")?;
+        let signature_algorithm =
+            signature_algorithm_from_oid(oid).at(data, rest, "signatureAlgorithm")?;
+        let (signature, rest) = read_bit_string(after).at(data, after, "signature")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+
+        let (version, rest) = read_integer(info_content).at(data, info_content, "version")?;
+        if version != [0] {
+            return Err(RSAError::Encoding).at(data, info_content, "version");
+        }
+        let (mut name, after) = read_tlv(rest, TAG_SEQUENCE).at(data, rest, "subject")?;
+        let (public_key, rest) =
+            decode_subject_public_key_info(after).at(data, after, "subjectPKInfo")?;
+        let (_attributes, after) =
+            read_tlv(rest, context_tag(0, true)).at(data, rest, "attributes")?;
+        if !after.is_empty() {
+            return Err(RSAError::Encoding).at(data, after, "trailing data");
+        }
+
+        let mut subject = Vec::<(NameAttribute, String)>::new();
+        while !name.is_empty() {
+            let (mut rdn, after) = read_tlv(name, TAG_SET).at(data, name, "subject")?;
+            while !rdn.is_empty() {
+                let (type_and_value, rdn_rest) =
+                    read_tlv(rdn, TAG_SEQUENCE).at(data, rdn, "AttributeTypeAndValue")?;
+                let (oid, value) = read_tlv(type_and_value, TAG_OBJECT_IDENTIFIER).at(
+                    data,
+                    type_and_value,
+                    "type",
+                )?;
+                let (_, value, value_rest) = read_any_tlv(value).at(data, value, "value")?;
+                if !value_rest.is_empty() {
+                    return Err(RSAError::Encoding).at(data, value_rest, "trailing data");
+                }
+                let attribute = [
+                    NameAttribute::CommonName,
+                    NameAttribute::Country,
+                    NameAttribute::Locality,
+                    NameAttribute::State,
+                    NameAttribute::Organization,
+                    NameAttribute::OrganizationalUnit,
+                    NameAttribute::EmailAddress,
+                ]
+                .into_iter()
+                .find(|attribute| attribute.oid() == oid)
+                .ok_or(RSAError::Encoding)
+                .at(data, type_and_value, "type")?;
+                let value = String::from_utf8(value.to_vec())
+                    .map_err(|_| RSAError::Encoding)
+                    .at(data, value, "value")?;
+                subject.push((attribute, value));
+                rdn = rdn_rest;
+            }
+            name = after;
+        }
+
+        Ok(Self {
+            subject,
+            public_key,
+            signature_algorithm,
+            info: info.to_vec(),
+            signature: signature.to_vec(),
+        })
+    }
+
+    pub fn decode_pem(encoded: &str) -> Result<Self, RSAError> {
+        let body = encoded
+            .trim()
+            .strip_prefix(PEM_HEADER)
+            .and_then(|body| body.strip_suffix(PEM_FOOTER))
+            .ok_or(RSAError::Encoding)?;
+        Self::decode(&decode_pem_block(body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_key_pair;
+
+    #[test]
+    fn test_certification_request_round_trip() {
+        let (public_key, private_key) = test_key_pair(1024);
+
+        let subject = [
+            (NameAttribute::Country, "US"),
+            (NameAttribute::Organization, "RSAREF Users"),
+            (NameAttribute::CommonName, "test.example"),
+        ];
+
+        for alg in [DigestAlg::Md5, DigestAlg::Sha1, DigestAlg::Sha256] {
+            let request =
+                CertificationRequest::new(&subject, &public_key, &private_key, alg).unwrap();
+            assert!(request.verify().is_ok());
+
+            let decoded = CertificationRequest::decode_pem(&request.encode_pem()).unwrap();
+            assert_eq!(decoded.encode(), request.encode());
+            assert_eq!(decoded.signature_algorithm(), alg);
+            assert_eq!(decoded.subject().len(), 3);
+            assert_eq!(decoded.subject()[2].1, "test.example");
+            assert_eq!(decoded.public_key().modulus(), public_key.modulus());
+            assert!(decoded.verify().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_certification_request_rejects_tampering() {
+        let (public_key, private_key) = test_key_pair(512);
+
+        let request = CertificationRequest::new(
+            &[(NameAttribute::CommonName, "original")],
+            &public_key,
+            &private_key,
+            DigestAlg::Md5,
+        )
+        .unwrap();
+
+        let mut tampered = request.encode();
+        let position = tampered
+            .windows(8)
+            .position(|window| window == b"original")
+            .unwrap();
+        tampered[position] = b'O';
+        let decoded = CertificationRequest::decode(&tampered).unwrap();
+        assert_eq!(decoded.subject()[0].1, "Original");
+        assert!(matches!(decoded.verify(), Err(RSAError::Signature)));
+
+        assert!(matches!(
+            CertificationRequest::new(
+                &[(NameAttribute::Country, "usa")],
+                &public_key,
+                &private_key,
+                DigestAlg::Md5
+            ),
+            Err(RSAError::Data)
+        ));
+    }
+}
@@ -0,0 +1,669 @@
+use crate::RSAError;
+use alloc::vec::Vec;
+#[cfg(all(feature = "std", feature = "full"))]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "full")]
+pub const TAG_INTEGER: u8 = 0x02;
+#[cfg(feature = "full")]
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OBJECT_IDENTIFIER: u8 = 0x06;
+#[cfg(feature = "full")]
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+#[cfg(feature = "full")]
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+#[cfg(feature = "full")]
+pub const TAG_IA5_STRING: u8 = 0x16;
+#[cfg(feature = "full")]
+pub const TAG_UTC_TIME: u8 = 0x17;
+#[cfg(feature = "full")]
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+#[cfg(feature = "full")]
+pub const TAG_BMP_STRING: u8 = 0x1e;
+pub const TAG_SEQUENCE: u8 = 0x30;
+#[cfg(feature = "full")]
+pub const TAG_SET: u8 = 0x31;
+
+/* Decoders label a failure with the field being read and where it starts
+   in their input: read_integer(rest).at(input, rest, "version"). An error
+   coming up from a nested decoder keeps its field, and each level adds
+   the offset of the part it handed down, so the offset at the top is
+   into the caller's buffer.
+*/
+pub trait Located<T> {
+    fn at(self, input: &[u8], part: &[u8], field: &'static str) -> Result<T, RSAError>;
+}
+
+impl<T> Located<T> for Result<T, RSAError> {
+    fn at(self, input: &[u8], part: &[u8], field: &'static str) -> Result<T, RSAError> {
+        self.map_err(|error| error.located(offset_in(input, part), field))
+    }
+}
+
+/* How far into input part, a subslice of it, starts. */
+fn offset_in(input: &[u8], part: &[u8]) -> usize {
+    (part.as_ptr() as usize)
+        .saturating_sub(input.as_ptr() as usize)
+        .min(input.len())
+}
+
+/* Context-specific [n] tags. */
+#[cfg(feature = "full")]
+pub const fn context_tag(n: u8, constructed: bool) -> u8 {
+    0x80 | if constructed { 0x20 } else { 0 } | n
+}
+
+pub fn write_tlv(output: &mut Vec<u8>, tag: u8, content: &[u8]) {
+    output.push(tag);
+
+    let len = content.len();
+    if len < 0x80 {
+        output.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let skip = len_bytes.iter().take_while(|b| **b == 0).count();
+        output.push(0x80 | (len_bytes.len() - skip) as u8);
+        output.extend(len_bytes.iter().skip(skip));
+    }
+
+    output.extend(content);
+}
+
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut output = Vec::<u8>::with_capacity(content.len() + 6);
+    write_tlv(&mut output, tag, content);
+    output
+}
+
+/* Reads one DER element with the expected tag, returning its contents and
+   the remaining input. Indefinite lengths and non-minimal length encodings
+   are rejected.
+*/
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+pub fn read_tlv(input: &[u8], tag: u8) -> Result<(&[u8], &[u8]), RSAError> {
+    match read_any_tlv(input)? {
+        (actual_tag, content, rest) if actual_tag == tag => Ok((content, rest)),
+        _ => Err(RSAError::Encoding),
+    }
+}
+
+/* Reads an optional element: returns None without consuming input when the
+   next tag differs.
+*/
+#[cfg(feature = "full")]
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+pub fn read_optional_tlv(input: &[u8], tag: u8) -> Result<(Option<&[u8]>, &[u8]), RSAError> {
+    if input.first() == Some(&tag) {
+        let (content, rest) = read_tlv(input, tag)?;
+        Ok((Some(content), rest))
+    } else {
+        Ok((None, input))
+    }
+}
+
+/* Reads the next DER element whatever its tag. Only single-byte tags are
+   supported. Failures return through let-else: ok_or would build and drop
+   an RSAError on success, and no_panic cannot see that the drop of its
+   boxed variant never unwinds.
+*/
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+pub fn read_any_tlv(input: &[u8]) -> Result<(u8, &[u8], &[u8]), RSAError> {
+    let [tag, first_len, after @ ..] = input else {
+        return Err(RSAError::Encoding);
+    };
+    if tag & 0x1f == 0x1f {
+        return Err(RSAError::Encoding);
+    }
+
+    let (len, after) = if *first_len < 0x80 {
+        (*first_len as usize, after)
+    } else {
+        let len_len = (first_len & 0x7f) as usize;
+        if len_len == 0 || len_len > core::mem::size_of::<usize>() {
+            return Err(RSAError::Encoding);
+        }
+        let Some((len_bytes, after)) = after.split_at_checked(len_len) else {
+            return Err(RSAError::Encoding);
+        };
+        if len_bytes.first() == Some(&0) {
+            return Err(RSAError::Encoding);
+        }
+        let len = len_bytes
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | *b as usize);
+        if len < 0x80 {
+            return Err(RSAError::Encoding);
+        }
+        (len, after)
+    };
+
+    let Some((content, rest)) = after.split_at_checked(len) else {
+        return Err(RSAError::Encoding);
+    };
+    Ok((*tag, content, rest))
+}
+
+/* The front of input up to rest, which must be a suffix of it: the
+   element a read_* call has just consumed.
+*/
+#[cfg(feature = "full")]
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+pub fn consumed<'a>(input: &'a [u8], rest: &[u8]) -> &'a [u8] {
+    input.split_at(input.len().saturating_sub(rest.len())).0
+}
+
+/* Encodes a non-negative INTEGER from big-endian magnitude bytes. */
+#[cfg(feature = "full")]
+pub fn integer(value: &[u8]) -> Vec<u8> {
+    let skip = value.iter().take_while(|b| **b == 0).count();
+    let mut content = Vec::<u8>::with_capacity(value.len() - skip + 1);
+    if value.get(skip).is_none_or(|b| b & 0x80 != 0) {
+        content.push(0);
+    }
+    content.extend(value.iter().skip(skip));
+    tlv(TAG_INTEGER, &content)
+}
+
+/* Reads a non-negative INTEGER and returns its big-endian magnitude. */
+#[cfg(feature = "full")]
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+pub fn read_integer(input: &[u8]) -> Result<(&[u8], &[u8]), RSAError> {
+    let (content, rest) = read_tlv(input, TAG_INTEGER)?;
+    match content {
+        [] => Err(RSAError::Encoding),
+        [first, ..] if first & 0x80 != 0 => Err(RSAError::Encoding),
+        [0, second, ..] if second & 0x80 == 0 => Err(RSAError::Encoding),
+        [0, magnitude @ ..] if !magnitude.is_empty() => Ok((magnitude, rest)),
+        _ => Ok((content, rest)),
+    }
+}
+
+/* Builds a SET OF from already-encoded elements, sorted as DER requires. */
+#[cfg(feature = "full")]
+pub fn set_of(mut elements: Vec<Vec<u8>>) -> Vec<u8> {
+    elements.sort();
+    tlv(TAG_SET, &elements.concat())
+}
+
+/* AlgorithmIdentifier with a NULL parameter. */
+#[cfg(feature = "full")]
+pub fn algorithm_identifier(oid: &[u8]) -> Vec<u8> {
+    let mut identifier = tlv(TAG_OBJECT_IDENTIFIER, oid);
+    identifier.extend(tlv(TAG_NULL, &[]));
+    tlv(TAG_SEQUENCE, &identifier)
+}
+
+/* Reads an AlgorithmIdentifier whose parameter is NULL or absent,
+   returning its OID.
+*/
+#[cfg(feature = "full")]
+pub fn read_algorithm_identifier(input: &[u8]) -> Result<(&[u8], &[u8]), RSAError> {
+    let (identifier, rest) = read_tlv(input, TAG_SEQUENCE)?;
+    let (oid, parameters) = read_tlv(identifier, TAG_OBJECT_IDENTIFIER)?;
+    if !parameters.is_empty() && parameters != [TAG_NULL, 0] {
+        return Err(RSAError::Encoding);
+    }
+    Ok((oid, rest))
+}
+
+/* BIT STRING with no unused bits. */
+#[cfg(feature = "full")]
+pub fn bit_string(content: &[u8]) -> Vec<u8> {
+    let mut bits = Vec::<u8>::with_capacity(content.len() + 1);
+    bits.push(0);
+    bits.extend(content);
+    tlv(TAG_BIT_STRING, &bits)
+}
+
+#[cfg(feature = "full")]
+pub fn read_bit_string(input: &[u8]) -> Result<(&[u8], &[u8]), RSAError> {
+    let (bits, rest) = read_tlv(input, TAG_BIT_STRING)?;
+    match bits {
+        [0, content @ ..] => Ok((content, rest)),
+        _ => Err(RSAError::Encoding),
+    }
+}
+
+/* Days since 1970-01-01 of a proleptic Gregorian date, and back. */
+#[cfg(feature = "full")]
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(feature = "full")]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/* Whole seconds since 1970-01-01T00:00:00Z, rounded down. */
+#[cfg(all(feature = "std", feature = "full"))]
+pub fn unix_seconds(value: SystemTime) -> i64 {
+    match value.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs_f64().ceil() as i64),
+    }
+}
+
+#[cfg(all(feature = "std", feature = "full"))]
+pub fn system_time(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+/* Encodes seconds since the Unix epoch as UTCTime for 1950 through 2049
+   and as GeneralizedTime otherwise (RFC 5280, 4.1.2.5).
+*/
+#[cfg(feature = "full")]
+pub fn unix_time(seconds: i64) -> Vec<u8> {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let second_of_day = seconds.rem_euclid(86400);
+    let clock = format!(
+        "{:02}{:02}{:02}{:02}{:02}Z",
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60
+    );
+
+    if (1950..2050).contains(&year) {
+        tlv(
+            TAG_UTC_TIME,
+            format!("{:02}{}", year % 100, clock).as_bytes(),
+        )
+    } else {
+        tlv(
+            TAG_GENERALIZED_TIME,
+            format!("{:04}{}", year, clock).as_bytes(),
+        )
+    }
+}
+
+/* Reads a UTCTime or GeneralizedTime in the DER form written by unix_time:
+   seconds present, no fraction, and a Z suffix.
+*/
+#[cfg(feature = "full")]
+pub fn read_unix_time(input: &[u8]) -> Result<(i64, &[u8]), RSAError> {
+    let (tag, content, rest) = read_any_tlv(input)?;
+    let year_len = match tag {
+        TAG_UTC_TIME => 2,
+        TAG_GENERALIZED_TIME => 4,
+        _ => return Err(RSAError::Encoding),
+    };
+    if content.len() != year_len + 11
+        || content.last() != Some(&b'Z')
+        || !content
+            .iter()
+            .take(content.len() - 1)
+            .all(u8::is_ascii_digit)
+    {
+        return Err(RSAError::Encoding);
+    }
+
+    let digits = |start: usize, len: usize| {
+        content
+            .iter()
+            .skip(start)
+            .take(len)
+            .fold(0u32, |value, digit| value * 10 + (digit - b'0') as u32)
+    };
+    let mut year = digits(0, year_len) as i64;
+    if year_len == 2 {
+        year += if year < 50 { 2000 } else { 1900 };
+    }
+    let (month, day) = (digits(year_len, 2), digits(year_len + 2, 2));
+    let (hour, minute, second) = (
+        digits(year_len + 4, 2),
+        digits(year_len + 6, 2),
+        digits(year_len + 8, 2),
+    );
+    if !(1..=12).contains(&month)
+        || day == 0
+        || civil_from_days(days_from_civil(year, month, day)) != (year, month, day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(RSAError::Encoding);
+    }
+
+    let seconds =
+        days_from_civil(year, month, day) * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    Ok((seconds, rest))
+}
+
+/* Limit on BER nesting, so hostile input cannot exhaust the stack. */
+#[cfg(feature = "full")]
+const MAX_BER_DEPTH: usize = 64;
+
+/* Reads a BER header, returning the tag, the content length (None for an
+   indefinite length) and the input after the header. Non-minimal lengths
+   are accepted.
+*/
+#[cfg(feature = "full")]
+fn read_ber_header(input: &[u8]) -> Result<(u8, Option<usize>, &[u8]), RSAError> {
+    let [tag, first_len, after @ ..] = input else {
+        return Err(RSAError::Encoding);
+    };
+    let tag = *tag;
+    if tag & 0x1f == 0x1f {
+        return Err(RSAError::Encoding);
+    }
+
+    if *first_len == 0x80 {
+        /* Indefinite lengths are only allowed on constructed encodings. */
+        if tag & 0x20 == 0 {
+            return Err(RSAError::Encoding);
+        }
+        return Ok((tag, None, after));
+    }
+    if *first_len < 0x80 {
+        return Ok((tag, Some(*first_len as usize), after));
+    }
+
+    let len_len = (first_len & 0x7f) as usize;
+    let (len_bytes, after) = after.split_at_checked(len_len).ok_or(RSAError::Encoding)?;
+    let significant = len_bytes.iter().skip_while(|b| **b == 0).count();
+    if significant > core::mem::size_of::<usize>() {
+        return Err(RSAError::Encoding);
+    }
+    let len = len_bytes
+        .iter()
+        .fold(0usize, |len, b| (len << 8) | *b as usize);
+    Ok((tag, Some(len), after))
+}
+
+/* Converts one BER element to DER, returning it and the remaining input. */
+#[cfg(feature = "full")]
+fn ber_element_to_der(input: &[u8], depth: usize) -> Result<(Vec<u8>, &[u8]), RSAError> {
+    if depth > MAX_BER_DEPTH {
+        return Err(RSAError::Encoding);
+    }
+
+    let (tag, len, body) = read_ber_header(input)?;
+
+    if tag & 0x20 == 0 {
+        let len = len.ok_or(RSAError::Encoding)?;
+        let (content, rest) = body.split_at_checked(len).ok_or(RSAError::Encoding)?;
+        return Ok((tlv(tag, content), rest));
+    }
+
+    let mut children = Vec::<Vec<u8>>::new();
+    let rest = match len {
+        Some(len) => {
+            let (mut content, rest) = body.split_at_checked(len).ok_or(RSAError::Encoding)?;
+            while !content.is_empty() {
+                let (child, after) = ber_element_to_der(content, depth + 1)?;
+                children.push(child);
+                content = after;
+            }
+            rest
+        }
+        None => {
+            let mut content = body;
+            loop {
+                if let Some(after) = content.strip_prefix(&[0, 0]) {
+                    break after;
+                }
+                let (child, after) = ber_element_to_der(content, depth + 1)?;
+                children.push(child);
+                content = after;
+            }
+        }
+    };
+
+    /* Constructed strings become primitive; the universal string types are
+       only ever primitive in DER.
+    */
+    let primitive_tag = tag & !0x20;
+    if matches!(
+        primitive_tag,
+        TAG_OCTET_STRING | TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING | TAG_BMP_STRING
+    ) {
+        let mut content = Vec::<u8>::new();
+        for child in &children {
+            content.extend(read_tlv(child, primitive_tag)?.0);
+        }
+        return Ok((tlv(primitive_tag, &content), rest));
+    }
+    if primitive_tag == TAG_BIT_STRING {
+        /* Only the last segment may have unused bits. */
+        let mut unused_bits = 0u8;
+        let mut bits = Vec::<u8>::new();
+        for (i, child) in children.iter().enumerate() {
+            let (segment, _) = read_tlv(child, TAG_BIT_STRING)?;
+            match segment {
+                [0, segment_bits @ ..] => bits.extend(segment_bits),
+                [unused, segment_bits @ ..] if i + 1 == children.len() => {
+                    unused_bits = *unused;
+                    bits.extend(segment_bits);
+                }
+                _ => return Err(RSAError::Encoding),
+            }
+        }
+        let mut content = vec![unused_bits];
+        content.extend(bits);
+        return Ok((tlv(TAG_BIT_STRING, &content), rest));
+    }
+
+    Ok((tlv(tag, &children.concat()), rest))
+}
+
+/* Lenient decoding support: re-encodes BER input (indefinite lengths,
+   non-minimal lengths, constructed strings) as DER so it can be handed to
+   the strict readers. SET OF elements are not re-sorted.
+*/
+#[cfg(feature = "full")]
+pub fn ber_to_der(input: &[u8]) -> Result<Vec<u8>, RSAError> {
+    let (der, rest) = ber_element_to_der(input, 0)?;
+    if !rest.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+    Ok(der)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_time_round_trip() {
+        assert_eq!(unix_time(0), tlv(TAG_UTC_TIME, b"700101000000Z"));
+        assert_eq!(unix_time(951782400), tlv(TAG_UTC_TIME, b"000229000000Z"));
+        assert_eq!(
+            unix_time(2524608000),
+            tlv(TAG_GENERALIZED_TIME, b"20500101000000Z")
+        );
+        assert_eq!(
+            unix_time(-631152001),
+            tlv(TAG_GENERALIZED_TIME, b"19491231235959Z")
+        );
+
+        for seconds in [0, 951782400, 1700000000, 2524608000, -631152001] {
+            assert_eq!(read_unix_time(&unix_time(seconds)).unwrap().0, seconds);
+        }
+
+        for bad in [
+            tlv(TAG_UTC_TIME, b"000230000000Z"),
+            tlv(TAG_UTC_TIME, b"7001010000Z"),
+            tlv(TAG_UTC_TIME, b"700101000000+0100"),
+            tlv(TAG_UTC_TIME, b"700101240000Z"),
+            tlv(TAG_OCTET_STRING, b"700101000000Z"),
+        ] {
+            assert!(read_unix_time(&bad).is_err());
+        }
+    }
+
+    #[cfg(all(feature = "std", feature = "full"))]
+    #[test]
+    fn test_system_time_round_trip() {
+        for seconds in [0, 951782400, 1700000000, 2524608000, -631152001] {
+            assert_eq!(unix_seconds(system_time(seconds)), seconds);
+        }
+    }
+
+    #[test]
+    fn test_tlv_round_trip() {
+        for len in [0usize, 1, 0x7f, 0x80, 0xff, 0x100, 0x10000] {
+            let content = vec![0xa5u8; len];
+            let encoded = tlv(TAG_OCTET_STRING, &content);
+            let (decoded, rest) = read_tlv(&encoded, TAG_OCTET_STRING).unwrap();
+            assert_eq!(decoded, content.as_slice());
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_integer_round_trip() {
+        let vectors: [(&[u8], &[u8]); 5] = [
+            (&[], &[0x02, 0x01, 0x00]),
+            (&[0x00, 0x00], &[0x02, 0x01, 0x00]),
+            (&[0x7f], &[0x02, 0x01, 0x7f]),
+            (&[0x80], &[0x02, 0x02, 0x00, 0x80]),
+            (&[0x00, 0x01, 0x00], &[0x02, 0x02, 0x01, 0x00]),
+        ];
+        for (value, encoded) in vectors {
+            assert_eq!(integer(value), encoded);
+            let (magnitude, rest) = read_integer(encoded).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(
+                magnitude
+                    .iter()
+                    .skip_while(|b| **b == 0)
+                    .collect::<Vec<_>>(),
+                value.iter().skip_while(|b| **b == 0).collect::<Vec<_>>()
+            );
+        }
+
+        for invalid in [
+            &[0x02, 0x00][..],
+            &[0x02, 0x01, 0x80],
+            &[0x02, 0x02, 0x00, 0x7f],
+        ] {
+            assert!(read_integer(invalid).is_err());
+        }
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_set_of_is_sorted() {
+        let set = set_of(vec![
+            tlv(TAG_OCTET_STRING, &[2]),
+            tlv(TAG_OCTET_STRING, &[1]),
+        ]);
+        assert_eq!(set, [0x31, 0x06, 0x04, 0x01, 0x01, 0x04, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_read_tlv_rejects_non_der_lengths() {
+        let invalid: [&[u8]; 6] = [
+            &[0x04, 0x80, 0x00, 0x00],
+            &[0x04, 0x81, 0x01, 0x00],
+            &[0x04, 0x82, 0x00, 0x81],
+            &[0x04, 0x02, 0x00],
+            &[0x04],
+            &[0x05, 0x00],
+        ];
+        for input in invalid {
+            assert!(matches!(
+                read_tlv(input, TAG_OCTET_STRING),
+                Err(RSAError::Encoding)
+            ));
+        }
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_ber_to_der() {
+        let vectors: [(&[u8], &[u8]); 5] = [
+            /* DER is unchanged. */
+            (
+                &[0x30, 0x03, 0x02, 0x01, 0x05],
+                &[0x30, 0x03, 0x02, 0x01, 0x05],
+            ),
+            /* indefinite length */
+            (
+                &[0x30, 0x80, 0x02, 0x01, 0x05, 0x00, 0x00],
+                &[0x30, 0x03, 0x02, 0x01, 0x05],
+            ),
+            /* non-minimal length */
+            (&[0x04, 0x82, 0x00, 0x01, 0xaa], &[0x04, 0x01, 0xaa]),
+            /* constructed OCTET STRING, nested */
+            (
+                &[
+                    0x24, 0x80, 0x04, 0x01, 0x01, 0x24, 0x04, 0x04, 0x02, 0x02, 0x03, 0x00, 0x00,
+                ],
+                &[0x04, 0x03, 0x01, 0x02, 0x03],
+            ),
+            /* constructed BIT STRING */
+            (
+                &[0x23, 0x08, 0x03, 0x02, 0x00, 0xaa, 0x03, 0x02, 0x04, 0xb0],
+                &[0x03, 0x03, 0x04, 0xaa, 0xb0],
+            ),
+        ];
+        for (ber, der) in vectors {
+            assert_eq!(ber_to_der(ber).unwrap(), der);
+        }
+
+        let invalid: [&[u8]; 5] = [
+            &[0x04, 0x80, 0x00, 0x00],
+            &[0x30, 0x80, 0x02, 0x01, 0x05],
+            &[0x24, 0x03, 0x02, 0x01, 0x05],
+            &[0x30, 0x03, 0x02, 0x01, 0x05, 0x00],
+            &[0x23, 0x08, 0x03, 0x02, 0x04, 0xaa, 0x03, 0x02, 0x00, 0xb0],
+        ];
+        for input in invalid {
+            assert!(ber_to_der(input).is_err());
+        }
+
+        let mut deep = [0x30u8, 0x80].repeat(MAX_BER_DEPTH + 2);
+        deep.extend([0u8; 2].repeat(MAX_BER_DEPTH + 2));
+        assert!(ber_to_der(&deep).is_err());
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_truncated_input_is_an_error() {
+        let mut element = tlv(TAG_OCTET_STRING, &[0x5a; 300]);
+        element = tlv(TAG_SEQUENCE, &[integer(&[0x80]), element].concat());
+        for len in 0..element.len() {
+            assert!(read_any_tlv(&element[..len]).is_err());
+            assert!(ber_to_der(&element[..len]).is_err());
+        }
+        assert_eq!(ber_to_der(&element).unwrap(), element);
+
+        let huge: [&[u8]; 3] = [
+            &[0x04, 0x88, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            &[0x04, 0xff],
+            &[0x30, 0x80, 0x04, 0x84, 0xff, 0xff, 0xff, 0xff],
+        ];
+        for input in huge {
+            assert!(read_any_tlv(input).is_err());
+            assert!(ber_to_der(input).is_err());
+        }
+    }
+}
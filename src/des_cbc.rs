@@ -0,0 +1,246 @@
+use crate::cbc::{cbc_update, xor_block, BlockCipher};
+#[cfg(feature = "zeroize")]
+use crate::wipe::Wipe;
+use crate::RSAError;
+use alloc::{boxed::Box, vec::Vec};
+use des::cipher::consts::{U16, U24, U8};
+use des::cipher::generic_array::{sequence::Split, GenericArray};
+use des::cipher::{self, BlockDecrypt, BlockEncrypt, Key, KeyInit, KeySizeUser};
+use des::{Des, TdesEde3};
+
+pub const DES_BLOCK_LEN: usize = 8;
+
+/* DESX block cipher: DES with an input and an output whitener. The key is
+   the DES key, then the input whitener, then the output whitener. Like Des
+   and TdesEde3 it implements the RustCrypto cipher traits, so it can be used
+   with the generic block mode crates.
+*/
+#[derive(Clone)]
+pub struct DESX {
+    des: Des,
+    input_whitener: [u8; 8],
+    output_whitener: [u8; 8],
+}
+
+impl cipher::BlockCipher for DESX {}
+
+impl KeySizeUser for DESX {
+    type KeySize = U24;
+}
+
+impl KeyInit for DESX {
+    fn new(key: &Key<Self>) -> Self {
+        let (des_key, whiteners): (GenericArray<u8, U8>, GenericArray<u8, U16>) = (*key).split();
+        let (input_whitener, output_whitener): (GenericArray<u8, U8>, GenericArray<u8, U8>) =
+            whiteners.split();
+        Self {
+            des: Des::new(&des_key),
+            input_whitener: input_whitener.into(),
+            output_whitener: output_whitener.into(),
+        }
+    }
+}
+
+cipher::impl_simple_block_encdec!(
+    DESX, U8, desx, block,
+    encrypt: {
+        let mut data: [u8; 8] = block.clone_in().into();
+        xor_block(&mut data, &desx.input_whitener);
+        desx.des.encrypt_block(GenericArray::from_mut_slice(&mut data));
+        xor_block(&mut data, &desx.output_whitener);
+        *block.get_out() = data.into();
+    }
+    decrypt: {
+        let mut data: [u8; 8] = block.clone_in().into();
+        xor_block(&mut data, &desx.output_whitener);
+        desx.des.decrypt_block(GenericArray::from_mut_slice(&mut data));
+        xor_block(&mut data, &desx.input_whitener);
+        *block.get_out() = data.into();
+    }
+);
+
+/* The DES key schedule wipes itself through des/zeroize. */
+#[cfg(feature = "zeroize")]
+impl Drop for DESX {
+    fn drop(&mut self) {
+        self.input_whitener.wipe();
+        self.output_whitener.wipe();
+    }
+}
+
+enum DESCore {
+    Des(Des),
+    Des3(Box<TdesEde3>),
+    DesX(Box<DESX>),
+}
+
+impl BlockCipher<DES_BLOCK_LEN> for DESCore {
+    fn encrypt_block(&self, block: &mut [u8; 8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            DESCore::Des(des) => des.encrypt_block(block),
+            DESCore::Des3(des3) => des3.encrypt_block(block),
+            DESCore::DesX(desx) => desx.encrypt_block(block),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8; 8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            DESCore::Des(des) => des.decrypt_block(block),
+            DESCore::Des3(des3) => des3.decrypt_block(block),
+            DESCore::DesX(desx) => desx.decrypt_block(block),
+        }
+    }
+}
+
+/* CBC-mode context over DES, DES-EDE3 or DESX, following the
+   Init/Update/Restart interface of RSAREF's desc.c.
+*/
+pub struct DESCBCContext {
+    core: DESCore,
+    iv: [u8; 8],
+    original_iv: [u8; 8],
+    encrypt: bool,
+}
+
+impl DESCBCContext {
+    pub fn des(key: &[u8; 8], iv: &[u8; 8], encrypt: bool) -> Self {
+        Self::with_core(DESCore::Des(Des::new(key.into())), iv, encrypt)
+    }
+
+    /* key is K1 || K2 || K3; encryption is E_K3(D_K2(E_K1(x))). */
+    pub fn des3(key: &[u8; 24], iv: &[u8; 8], encrypt: bool) -> Self {
+        Self::with_core(
+            DESCore::Des3(Box::new(TdesEde3::new(key.into()))),
+            iv,
+            encrypt,
+        )
+    }
+
+    /* key is the DES key, then the input whitener, then the output
+       whitener.
+    */
+    pub fn desx(key: &[u8; 24], iv: &[u8; 8], encrypt: bool) -> Self {
+        Self::with_core(DESCore::DesX(Box::new(DESX::new(key.into()))), iv, encrypt)
+    }
+
+    fn with_core(core: DESCore, iv: &[u8; 8], encrypt: bool) -> Self {
+        Self {
+            core,
+            iv: *iv,
+            original_iv: *iv,
+            encrypt,
+        }
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        cbc_update(&self.core, &mut self.iv, self.encrypt, input)
+    }
+
+    pub fn restart(&mut self) {
+        self.iv = self.original_iv;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const IV: [u8; 8] = [0x12, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef];
+
+    #[test]
+    fn test_des_cbc_vector() {
+        // FIPS 81, appendix C
+        let plaintext = b"Now is the time for all ";
+        let ciphertext = [
+            0xe5, 0xc7, 0xcd, 0xde, 0x87, 0x2b, 0xf2, 0x7c, 0x43, 0xe9, 0x34, 0x00, 0x8c, 0x38,
+            0x9c, 0x0f, 0x68, 0x37, 0x88, 0x49, 0x9a, 0x7c, 0x05, 0xf6,
+        ];
+
+        let mut context = DESCBCContext::des(&KEY, &IV, true);
+        assert_eq!(context.update(plaintext).unwrap(), ciphertext);
+
+        /* Chaining carries across updates. */
+        let mut context = DESCBCContext::des(&KEY, &IV, false);
+        let mut decrypted = context.update(&ciphertext[..8]).unwrap();
+        decrypted.extend(context.update(&ciphertext[8..]).unwrap());
+        assert_eq!(decrypted, plaintext);
+
+        context.restart();
+        assert_eq!(context.update(&ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_des3_with_equal_keys_is_des() {
+        let mut key = [0u8; 24];
+        for chunk in key.chunks_mut(8) {
+            chunk.copy_from_slice(&KEY);
+        }
+
+        let data = (0u8..64).collect::<Vec<u8>>();
+        let des = DESCBCContext::des(&KEY, &IV, true).update(&data).unwrap();
+        let des3 = DESCBCContext::des3(&key, &IV, true).update(&data).unwrap();
+        assert_eq!(des, des3);
+
+        let decrypted = DESCBCContext::des3(&key, &IV, false).update(&des3).unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_desx_round_trip() {
+        let mut key = [0u8; 24];
+        key[0..8].copy_from_slice(&KEY);
+
+        /* Zero whiteners reduce DESX to DES. */
+        let data = (0u8..64).collect::<Vec<u8>>();
+        let des = DESCBCContext::des(&KEY, &IV, true).update(&data).unwrap();
+        let desx = DESCBCContext::desx(&key, &IV, true).update(&data).unwrap();
+        assert_eq!(des, desx);
+
+        for (i, b) in key[8..].iter_mut().enumerate() {
+            *b = i as u8 * 17;
+        }
+        let desx = DESCBCContext::desx(&key, &IV, true).update(&data).unwrap();
+        assert_ne!(des, desx);
+        let decrypted = DESCBCContext::desx(&key, &IV, false).update(&desx).unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn test_cipher_traits_with_generic_cbc() {
+        use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+
+        let mut key = [0u8; 24];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = (i as u8).wrapping_mul(29) + 1;
+        }
+        let data = (0u8..64).collect::<Vec<u8>>();
+
+        let mut blocks = data.clone();
+        cbc::Encryptor::<DESX>::new(&key.into(), &IV.into())
+            .encrypt_padded_mut::<cipher::block_padding::NoPadding>(&mut blocks, data.len())
+            .unwrap();
+        let expected = DESCBCContext::desx(&key, &IV, true).update(&data).unwrap();
+        assert_eq!(blocks, expected);
+
+        cbc::Decryptor::<DESX>::new(&key.into(), &IV.into())
+            .decrypt_padded_mut::<cipher::block_padding::NoPadding>(&mut blocks)
+            .unwrap();
+        assert_eq!(blocks, data);
+
+        let mut blocks = data.clone();
+        cbc::Encryptor::<TdesEde3>::new(&key.into(), &IV.into())
+            .encrypt_padded_mut::<cipher::block_padding::NoPadding>(&mut blocks, data.len())
+            .unwrap();
+        let expected = DESCBCContext::des3(&key, &IV, true).update(&data).unwrap();
+        assert_eq!(blocks, expected);
+    }
+
+    #[test]
+    fn test_des_cbc_rejects_partial_block() {
+        let mut context = DESCBCContext::des(&KEY, &IV, true);
+        assert!(matches!(context.update(&[0u8; 9]), Err(RSAError::Len)));
+    }
+}
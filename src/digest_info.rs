@@ -0,0 +1,317 @@
+use crate::der::{
+    read_tlv, tlv, Located, TAG_NULL, TAG_OBJECT_IDENTIFIER, TAG_OCTET_STRING, TAG_SEQUENCE,
+};
+use crate::RSAError;
+use alloc::vec::Vec;
+use md2::Md2;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlg {
+    Md2,
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+pub(crate) const ALL_DIGEST_ALGS: [DigestAlg; 6] = [
+    DigestAlg::Md2,
+    DigestAlg::Md5,
+    DigestAlg::Sha1,
+    DigestAlg::Sha256,
+    DigestAlg::Sha384,
+    DigestAlg::Sha512,
+];
+
+impl DigestAlg {
+    /* DER contents of the algorithm's OBJECT IDENTIFIER. */
+    pub fn oid(&self) -> &'static [u8] {
+        match self {
+            DigestAlg::Md2 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x02],
+            DigestAlg::Md5 => &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x05],
+            DigestAlg::Sha1 => &[0x2b, 0x0e, 0x03, 0x02, 0x1a],
+            DigestAlg::Sha256 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+            DigestAlg::Sha384 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02],
+            DigestAlg::Sha512 => &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03],
+        }
+    }
+
+    pub fn digest_len(&self) -> usize {
+        match self {
+            DigestAlg::Md2 | DigestAlg::Md5 => 16,
+            DigestAlg::Sha1 => 20,
+            DigestAlg::Sha256 => 32,
+            DigestAlg::Sha384 => 48,
+            DigestAlg::Sha512 => 64,
+        }
+    }
+
+    pub fn from_oid(oid: &[u8]) -> Option<Self> {
+        ALL_DIGEST_ALGS.into_iter().find(|alg| alg.oid() == oid)
+    }
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlg::Md2 => Md2::digest(data).to_vec(),
+            DigestAlg::Md5 => Md5::digest(data).to_vec(),
+            DigestAlg::Sha1 => Sha1::digest(data).to_vec(),
+            DigestAlg::Sha256 => Sha256::digest(data).to_vec(),
+            DigestAlg::Sha384 => Sha384::digest(data).to_vec(),
+            DigestAlg::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+}
+
+/* Builds the DER encoding of
+     DigestInfo ::= SEQUENCE {
+       digestAlgorithm AlgorithmIdentifier,
+       digest OCTET STRING }
+   with a NULL algorithm parameter.
+*/
+pub fn encode_digest_info(alg: DigestAlg, digest: &[u8]) -> Result<Vec<u8>, RSAError> {
+    if digest.len() != alg.digest_len() {
+        return Err(RSAError::Len);
+    }
+
+    let mut algorithm_identifier = tlv(TAG_OBJECT_IDENTIFIER, alg.oid());
+    algorithm_identifier.extend(tlv(TAG_NULL, &[]));
+
+    let mut digest_info = tlv(TAG_SEQUENCE, &algorithm_identifier);
+    digest_info.extend(tlv(TAG_OCTET_STRING, digest));
+
+    Ok(tlv(TAG_SEQUENCE, &digest_info))
+}
+
+/* Parses a DER DigestInfo. Only the exact encoding produced by
+   encode_digest_info is accepted: no indefinite or non-minimal lengths,
+   a NULL parameter, a digest of the algorithm's length and no trailing
+   data.
+*/
+pub fn decode_digest_info(data: &[u8]) -> Result<(DigestAlg, Vec<u8>), RSAError> {
+    let (digest_info, rest) = read_tlv(data, TAG_SEQUENCE).at(data, data, "DigestInfo")?;
+    if !rest.is_empty() {
+        return Err(RSAError::Encoding).at(data, rest, "trailing data");
+    }
+
+    let (algorithm_identifier, rest) =
+        read_tlv(digest_info, TAG_SEQUENCE).at(data, digest_info, "digestAlgorithm")?;
+    let (digest, trailing) = read_tlv(rest, TAG_OCTET_STRING).at(data, rest, "digest")?;
+    if !trailing.is_empty() {
+        return Err(RSAError::Encoding).at(data, trailing, "trailing data");
+    }
+
+    let (oid, rest) = read_tlv(algorithm_identifier, TAG_OBJECT_IDENTIFIER).at(
+        data,
+        algorithm_identifier,
+        "algorithm",
+    )?;
+    let (parameters, trailing) = read_tlv(rest, TAG_NULL).at(data, rest, "parameters")?;
+    if !parameters.is_empty() || !trailing.is_empty() {
+        return Err(RSAError::Encoding).at(data, rest, "parameters");
+    }
+
+    let alg = DigestAlg::from_oid(oid)
+        .ok_or(RSAError::DigestAlgorithm)
+        .at(data, algorithm_identifier, "algorithm")?;
+    if digest.len() != alg.digest_len() {
+        return Err(RSAError::Encoding).at(data, digest, "digest");
+    }
+
+    Ok((alg, digest.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_digest_info_prefixes() {
+        // prefixes from PKCS #1 v2.2, section 9.2
+        let prefixes: [(DigestAlg, &[u8]); 6] = [
+            (
+                DigestAlg::Md2,
+                &[
+                    0x30, 0x20, 0x30, 0x0c, 0x06, 0x08, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02,
+                    0x02, 0x05, 0x00, 0x04, 0x10,
+                ],
+            ),
+            (
+                DigestAlg::Md5,
+                &[
+                    0x30, 0x20, 0x30, 0x0c, 0x06, 0x08, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02,
+                    0x05, 0x05, 0x00, 0x04, 0x10,
+                ],
+            ),
+            (
+                DigestAlg::Sha1,
+                &[
+                    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00,
+                    0x04, 0x14,
+                ],
+            ),
+            (
+                DigestAlg::Sha256,
+                &[
+                    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04,
+                    0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+                ],
+            ),
+            (
+                DigestAlg::Sha384,
+                &[
+                    0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04,
+                    0x02, 0x02, 0x05, 0x00, 0x04, 0x30,
+                ],
+            ),
+            (
+                DigestAlg::Sha512,
+                &[
+                    0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04,
+                    0x02, 0x03, 0x05, 0x00, 0x04, 0x40,
+                ],
+            ),
+        ];
+
+        for (alg, prefix) in prefixes {
+            let digest = (0..alg.digest_len() as u8).collect::<Vec<u8>>();
+            let encoded = encode_digest_info(alg, &digest).unwrap();
+            assert_eq!(&encoded[..prefix.len()], prefix);
+            assert_eq!(&encoded[prefix.len()..], digest.as_slice());
+
+            let (decoded_alg, decoded_digest) = decode_digest_info(&encoded).unwrap();
+            assert_eq!(decoded_alg, alg);
+            assert_eq!(decoded_digest, digest);
+        }
+    }
+
+    #[test]
+    fn test_digest_info_rejects_wrong_digest_len() {
+        assert!(matches!(
+            encode_digest_info(DigestAlg::Md5, &[0u8; 20]),
+            Err(RSAError::Len)
+        ));
+    }
+
+    #[test]
+    fn test_digest_info_rejects_malformed() {
+        let valid = encode_digest_info(DigestAlg::Md5, &[0x11u8; 16]).unwrap();
+
+        // trailing data
+        let mut trailing = valid.clone();
+        trailing.push(0);
+        assert!(matches!(
+            decode_digest_info(&trailing),
+            Err(RSAError::DecodeAt {
+                offset: 34,
+                field: "trailing data",
+                ..
+            })
+        ));
+
+        // indefinite outer length
+        let mut indefinite = valid.clone();
+        indefinite[1] = 0x80;
+        indefinite.extend([0, 0]);
+        assert!(decode_digest_info(&indefinite).is_err());
+
+        // non-minimal long-form outer length
+        let mut long_form = vec![0x30, 0x81, valid[1]];
+        long_form.extend(&valid[2..]);
+        assert!(decode_digest_info(&long_form).is_err());
+
+        // missing NULL parameter
+        let mut no_params = tlv(
+            TAG_SEQUENCE,
+            &tlv(TAG_OBJECT_IDENTIFIER, DigestAlg::Md5.oid()),
+        );
+        no_params.extend(tlv(TAG_OCTET_STRING, &[0x11u8; 16]));
+        assert!(decode_digest_info(&tlv(TAG_SEQUENCE, &no_params)).is_err());
+
+        // truncated digest
+        let mut truncated = valid.clone();
+        truncated[1] -= 1;
+        truncated[valid.len() - 17] -= 1;
+        truncated.pop();
+        assert!(decode_digest_info(&truncated).is_err());
+
+        // unknown algorithm
+        let mut unknown = valid.clone();
+        unknown[13] = 0x04;
+        let error = decode_digest_info(&unknown).unwrap_err();
+        assert_eq!(error, RSAError::DigestAlgorithm);
+        assert_eq!(
+            error.to_string(),
+            "digest algorithm is unsupported at byte 4 (algorithm)"
+        );
+    }
+
+    #[test]
+    fn test_digest_alg_digest() {
+        for alg in ALL_DIGEST_ALGS {
+            assert_eq!(alg.digest(b"abc").len(), alg.digest_len());
+        }
+
+        // FIPS 180-2, appendix B.1
+        assert_eq!(
+            DigestAlg::Sha256.digest(b"abc"),
+            [
+                0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+                0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+                0xf2, 0x00, 0x15, 0xad
+            ]
+        );
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_verify_prehashed() {
+        use crate::r_keygen::test_key_pair;
+
+        let (public_key, private_key) = test_key_pair(512);
+        let digest = DigestAlg::Sha1.digest(b"hashed elsewhere");
+        let digest_info = encode_digest_info(DigestAlg::Sha1, &digest).unwrap();
+        let signature = private_key.rsa_private_encrypt(&digest_info).unwrap();
+        assert_eq!(
+            public_key.verify_prehashed(DigestAlg::Sha1, &digest, &signature),
+            Ok(())
+        );
+
+        let reject = |alg, digest: &[u8], signature: &[u8]| {
+            assert_eq!(
+                public_key.verify_prehashed(alg, digest, signature),
+                Err(RSAError::Signature)
+            )
+        };
+        reject(DigestAlg::Md5, &digest[..16], &signature);
+        reject(
+            DigestAlg::Sha1,
+            &DigestAlg::Sha1.digest(b"other"),
+            &signature,
+        );
+        reject(DigestAlg::Sha1, &digest, &signature[1..]);
+        reject(DigestAlg::Sha1, &digest, &[&[0], &signature[..]].concat());
+
+        /* Encodings a lenient parser would take: the NULL left out, and a
+           trailing byte after the DigestInfo.
+        */
+        let mut no_null = tlv(TAG_OBJECT_IDENTIFIER, DigestAlg::Sha1.oid());
+        no_null = tlv(TAG_SEQUENCE, &no_null);
+        no_null.extend(tlv(TAG_OCTET_STRING, &digest));
+        let no_null = tlv(TAG_SEQUENCE, &no_null);
+        let trailing = [&digest_info[..], &[0]].concat();
+        for variant in [no_null, trailing] {
+            let signature = private_key.rsa_private_encrypt(&variant).unwrap();
+            reject(DigestAlg::Sha1, &digest, &signature);
+        }
+
+        assert_eq!(
+            public_key.verify_prehashed(DigestAlg::Sha1, &digest[1..], &signature),
+            Err(RSAError::Len)
+        );
+    }
+}
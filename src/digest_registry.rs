@@ -0,0 +1,241 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+use crate::algorithm::{DigestContext, DA_MD2, DA_MD5};
+use crate::der::{algorithm_identifier, tlv, TAG_OCTET_STRING, TAG_SEQUENCE};
+use crate::digest_info::DigestAlg;
+use crate::RSAError;
+use md2::Md2;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+
+/* Incremental state of a digest started by DigestAlgorithm::new_context. */
+pub trait DigestUpdate: Send {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+/* A hash usable by the signing code, identified by its OBJECT IDENTIFIER
+   and, for the algorithms RSAREF numbered, by its RSAREF ID.
+*/
+pub trait DigestAlgorithm: Debug + Send + Sync {
+    /* DER contents of the algorithm's OBJECT IDENTIFIER. */
+    fn oid(&self) -> &[u8];
+
+    fn id(&self) -> Option<i32> {
+        None
+    }
+
+    fn digest_len(&self) -> usize;
+
+    fn new_context(&self) -> Box<dyn DigestUpdate>;
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut context = self.new_context();
+        context.update(data);
+        context.finalize()
+    }
+}
+
+struct HashUpdate<D>(D);
+
+impl<D: Digest + Send> DigestUpdate for HashUpdate<D> {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.0, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.finalize().to_vec()
+    }
+}
+
+impl DigestUpdate for DigestContext {
+    fn update(&mut self, data: &[u8]) {
+        DigestContext::update(self, data);
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        DigestContext::finalize(*self)
+    }
+}
+
+impl DigestAlgorithm for DigestAlg {
+    fn oid(&self) -> &[u8] {
+        DigestAlg::oid(self)
+    }
+
+    fn id(&self) -> Option<i32> {
+        match self {
+            DigestAlg::Md2 => Some(DA_MD2),
+            DigestAlg::Md5 => Some(DA_MD5),
+            _ => None,
+        }
+    }
+
+    fn digest_len(&self) -> usize {
+        DigestAlg::digest_len(self)
+    }
+
+    fn new_context(&self) -> Box<dyn DigestUpdate> {
+        match self {
+            DigestAlg::Md2 => Box::new(HashUpdate(Md2::new())),
+            DigestAlg::Md5 => Box::new(HashUpdate(Md5::new())),
+            DigestAlg::Sha1 => Box::new(HashUpdate(Sha1::new())),
+            DigestAlg::Sha256 => Box::new(HashUpdate(Sha256::new())),
+            DigestAlg::Sha384 => Box::new(HashUpdate(Sha384::new())),
+            DigestAlg::Sha512 => Box::new(HashUpdate(Sha512::new())),
+        }
+    }
+}
+
+fn builtin_algorithms() -> Vec<Arc<dyn DigestAlgorithm>> {
+    vec![
+        Arc::new(DigestAlg::Md2),
+        Arc::new(DigestAlg::Md5),
+        Arc::new(DigestAlg::Sha1),
+        Arc::new(DigestAlg::Sha256),
+        Arc::new(DigestAlg::Sha384),
+        Arc::new(DigestAlg::Sha512),
+    ]
+}
+
+#[cfg(feature = "std")]
+type Registry = RwLock<Vec<Arc<dyn DigestAlgorithm>>>;
+
+#[cfg(feature = "std")]
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(builtin_algorithms()))
+}
+
+/* Adds a digest to the process-wide registry. An algorithm whose OID or
+   RSAREF ID is already registered is refused with DigestAlgorithm.
+*/
+#[cfg(feature = "std")]
+pub fn register_digest_algorithm(algorithm: Arc<dyn DigestAlgorithm>) -> Result<(), RSAError> {
+    let mut algorithms = registry().write().unwrap_or_else(PoisonError::into_inner);
+    if algorithms.iter().any(|registered| {
+        registered.oid() == algorithm.oid()
+            || (algorithm.id().is_some() && registered.id() == algorithm.id())
+    }) {
+        return Err(RSAError::DigestAlgorithm);
+    }
+    algorithms.push(algorithm);
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn find_digest_algorithm<F>(predicate: F) -> Option<Arc<dyn DigestAlgorithm>>
+where
+    F: Fn(&dyn DigestAlgorithm) -> bool,
+{
+    let algorithms = registry().read().unwrap_or_else(PoisonError::into_inner);
+    algorithms
+        .iter()
+        .find(|alg| predicate(alg.as_ref()))
+        .cloned()
+}
+
+/* Without std there is no process-wide registry, only the built-in
+   digests.
+*/
+#[cfg(not(feature = "std"))]
+fn find_digest_algorithm<F>(predicate: F) -> Option<Arc<dyn DigestAlgorithm>>
+where
+    F: Fn(&dyn DigestAlgorithm) -> bool,
+{
+    builtin_algorithms()
+        .into_iter()
+        .find(|alg| predicate(alg.as_ref()))
+}
+
+pub fn find_digest_algorithm_by_oid(oid: &[u8]) -> Option<Arc<dyn DigestAlgorithm>> {
+    find_digest_algorithm(|alg| alg.oid() == oid)
+}
+
+pub fn find_digest_algorithm_by_id(id: i32) -> Option<Arc<dyn DigestAlgorithm>> {
+    find_digest_algorithm(|alg| alg.id() == Some(id))
+}
+
+/* DigestInfo for a registered algorithm, the same encoding as
+   encode_digest_info.
+*/
+pub(crate) fn encode_digest_info_with(
+    algorithm: &dyn DigestAlgorithm,
+    digest: &[u8],
+) -> Result<Vec<u8>, RSAError> {
+    if digest.len() != algorithm.digest_len() {
+        return Err(RSAError::Len);
+    }
+
+    let mut digest_info = algorithm_identifier(algorithm.oid());
+    digest_info.extend(tlv(TAG_OCTET_STRING, digest));
+    Ok(tlv(TAG_SEQUENCE, &digest_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest_info::encode_digest_info;
+
+    /* SHA-224 stands in for a digest registered by downstream code. */
+    #[cfg(feature = "std")]
+    #[derive(Debug)]
+    struct Sha224Algorithm;
+
+    #[cfg(feature = "std")]
+    impl DigestAlgorithm for Sha224Algorithm {
+        fn oid(&self) -> &[u8] {
+            &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04]
+        }
+
+        fn digest_len(&self) -> usize {
+            28
+        }
+
+        fn new_context(&self) -> Box<dyn DigestUpdate> {
+            Box::new(HashUpdate(sha2::Sha224::new()))
+        }
+    }
+
+    #[test]
+    fn test_builtin_digests_registered() {
+        for alg in [DigestAlg::Md2, DigestAlg::Sha256, DigestAlg::Sha512] {
+            let found = find_digest_algorithm_by_oid(alg.oid()).unwrap();
+            assert_eq!(found.digest(b"abc"), alg.digest(b"abc"));
+            assert_eq!(
+                encode_digest_info_with(found.as_ref(), &alg.digest(b"abc")).unwrap(),
+                encode_digest_info(alg, &alg.digest(b"abc")).unwrap()
+            );
+        }
+        assert_eq!(
+            find_digest_algorithm_by_id(DA_MD5).unwrap().oid(),
+            DigestAlg::Md5.oid()
+        );
+
+        let mut context = find_digest_algorithm_by_id(DA_MD2).unwrap().new_context();
+        context.update(b"a");
+        context.update(b"bc");
+        assert_eq!(context.finalize(), DigestAlg::Md2.digest(b"abc"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_register_custom_digest() {
+        assert!(matches!(
+            register_digest_algorithm(Arc::new(DigestAlg::Sha1)),
+            Err(RSAError::DigestAlgorithm)
+        ));
+
+        let algorithm = Sha224Algorithm;
+        if find_digest_algorithm_by_oid(algorithm.oid()).is_none() {
+            register_digest_algorithm(Arc::new(Sha224Algorithm)).unwrap();
+        }
+        let found = find_digest_algorithm_by_oid(algorithm.oid()).unwrap();
+        assert_eq!(found.digest(b"abc"), sha2::Sha224::digest(b"abc").to_vec());
+        assert!(found.id().is_none());
+    }
+}
@@ -0,0 +1,487 @@
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Rem, Sub};
+
+use crate::der::{integer, read_integer, read_tlv, tlv, TAG_SEQUENCE};
+use crate::digest_info::DigestAlg;
+use crate::r_dh::{generate_dh_params, DHParams};
+use crate::r_random::RandomStruct;
+use crate::wipe::Redacted;
+use crate::wipe::{SecretVec, Wipe, Wiped};
+use crate::RSAError;
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::{Mac, SimpleHmac};
+use md2::Md2;
+use md5::{Digest, Md5};
+use num_bigint_dig::BigUint;
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+
+#[derive(Debug, Clone)]
+pub struct DSAParams {
+    prime: BigUint,
+    subprime: BigUint,
+    generator: BigUint,
+}
+
+impl DSAParams {
+    /* Fails with Data unless p > 1, q > 1, q divides p-1 and 1 < g < p,
+       which every modpow here relies on; call validate for the full
+       checks.
+    */
+    pub fn from_components(
+        prime: BigUint,
+        subprime: BigUint,
+        generator: BigUint,
+    ) -> Result<Self, RSAError> {
+        let one = BigUint::from(1u32);
+        if prime <= one
+            || subprime <= one
+            || generator <= one
+            || generator >= prime
+            || (&prime).sub(&one).rem(&subprime) != BigUint::from(0u32)
+        {
+            return Err(RSAError::Data);
+        }
+        Ok(Self {
+            prime,
+            subprime,
+            generator,
+        })
+    }
+
+    pub fn prime(&self) -> &BigUint {
+        &self.prime
+    }
+
+    pub fn subprime(&self) -> &BigUint {
+        &self.subprime
+    }
+
+    pub fn generator(&self) -> &BigUint {
+        &self.generator
+    }
+
+    /* p and q are probable primes, q divides p-1 and g has order q. */
+    pub fn validate(&self) -> Result<(), RSAError> {
+        DHParams::from_components(self.prime.clone(), self.generator.clone())?
+            .with_subprime(self.subprime.clone())
+            .map(|_| ())
+    }
+
+    pub fn generate_key(
+        &self,
+        random_struct: &mut RandomStruct,
+    ) -> Result<(DSAPublicKey, DSAPrivateKey), RSAError> {
+        let x = random_below_subprime(&self.subprime, random_struct)?;
+        let y = self.generator.modpow(&x, &self.prime);
+
+        Ok((
+            DSAPublicKey {
+                params: self.clone(),
+                y: y.clone(),
+            },
+            DSAPrivateKey {
+                params: self.clone(),
+                x,
+                y,
+            },
+        ))
+    }
+
+    /* Leftmost qlen bits of the digest, as FIPS 186-4 4.6 and bits2int
+       in RFC 6979.
+    */
+    fn bits_to_int(&self, bits: &[u8]) -> BigUint {
+        let value = BigUint::from_bytes_be(bits);
+        let qlen = self.subprime.bits();
+        if bits.len() * 8 > qlen {
+            value >> (bits.len() * 8 - qlen)
+        } else {
+            value
+        }
+    }
+
+    fn subprime_len(&self) -> usize {
+        self.subprime.bits().div_ceil(8)
+    }
+}
+
+/* Generates p, q and g the way generate_dh_params does: q is a
+   subprime_bits prime, p = 1 mod 2q and g = 2^((p-1)/q) mod p.
+*/
+pub fn generate_dsa_params(
+    prime_bits: usize,
+    subprime_bits: usize,
+    random_struct: &mut RandomStruct,
+) -> Result<DSAParams, RSAError> {
+    let params = generate_dh_params(prime_bits, subprime_bits, random_struct)?;
+    if *params.generator() == BigUint::from(1u32) {
+        return Err(RSAError::Data);
+    }
+
+    Ok(DSAParams {
+        prime: params.prime().clone(),
+        subprime: params.subprime().ok_or(RSAError::Data)?.clone(),
+        generator: params.generator().clone(),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct DSAPublicKey {
+    params: DSAParams,
+    y: BigUint,
+}
+
+impl DSAPublicKey {
+    pub fn from_components(params: DSAParams, y: BigUint) -> Self {
+        Self { params, y }
+    }
+
+    pub fn params(&self) -> &DSAParams {
+        &self.params
+    }
+
+    pub fn value(&self) -> &BigUint {
+        &self.y
+    }
+
+    /* Verifies a DER Dss-Sig-Value over data hashed with alg. */
+    pub fn verify(&self, alg: DigestAlg, data: &[u8], signature: &[u8]) -> Result<(), RSAError> {
+        let (r, s) = decode_signature(signature)?;
+        let params = &self.params;
+        let q = &params.subprime;
+        let zero = BigUint::from(0u32);
+        if r == zero || s == zero || r >= *q || s >= *q {
+            return Err(RSAError::Signature);
+        }
+
+        let z = params.bits_to_int(&alg.digest(data));
+        let w = mod_inverse(&s, q);
+        let u1 = z.mul(&w).rem(q);
+        let u2 = r.clone().mul(&w).rem(q);
+        let v = params
+            .generator
+            .modpow(&u1, &params.prime)
+            .mul(self.y.modpow(&u2, &params.prime))
+            .rem(&params.prime)
+            .rem(q);
+
+        if v != r {
+            return Err(RSAError::Signature);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct DSAPrivateKey {
+    params: DSAParams,
+    x: BigUint,
+    y: BigUint,
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for DSAPrivateKey {
+    fn drop(&mut self) {
+        self.x.wipe();
+    }
+}
+
+impl core::fmt::Debug for DSAPrivateKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("DSAPrivateKey")
+            .field("params", &self.params)
+            .field("x", &Redacted)
+            .field("y", &self.y)
+            .finish()
+    }
+}
+
+impl DSAPrivateKey {
+    pub fn from_components(params: DSAParams, x: BigUint) -> Result<Self, RSAError> {
+        if x == BigUint::from(0u32) || x >= params.subprime {
+            return Err(RSAError::PrivateKey);
+        }
+        let y = params.generator.modpow(&x, &params.prime);
+        Ok(Self { params, x, y })
+    }
+
+    pub fn params(&self) -> &DSAParams {
+        &self.params
+    }
+
+    pub fn value(&self) -> &BigUint {
+        &self.x
+    }
+
+    pub fn public_key(&self) -> DSAPublicKey {
+        DSAPublicKey {
+            params: self.params.clone(),
+            y: self.y.clone(),
+        }
+    }
+
+    /* Signs data hashed with alg using a per-message k from random_struct,
+       returning a DER Dss-Sig-Value.
+    */
+    pub fn sign(
+        &self,
+        alg: DigestAlg,
+        data: &[u8],
+        random_struct: &mut RandomStruct,
+    ) -> Result<Vec<u8>, RSAError> {
+        let z = self.params.bits_to_int(&alg.digest(data));
+        loop {
+            let mut k = random_below_subprime(&self.params.subprime, random_struct)?;
+            let signature = self.sign_with_k(&z, &k);
+            k.wipe();
+            if let Some((r, s)) = signature {
+                return Ok(encode_signature(&r, &s));
+            }
+        }
+    }
+
+    /* Signs with k derived from the key and digest as in RFC 6979, 3.2,
+       using HMAC over alg. No random input is needed and the same message
+       always gives the same signature.
+    */
+    pub fn sign_deterministic(&self, alg: DigestAlg, data: &[u8]) -> Result<Vec<u8>, RSAError> {
+        let params = &self.params;
+        let q = &params.subprime;
+        let digest = alg.digest(data);
+        let z = params.bits_to_int(&digest);
+
+        let rlen = params.subprime_len();
+        let x_octets: SecretVec = Wiped(to_be_padded(&self.x, rlen));
+        let h_octets = to_be_padded(&(&z).rem(q), rlen);
+
+        let mut v = vec![0x01u8; alg.digest_len()];
+        let mut k = vec![0x00u8; alg.digest_len()];
+        k = hmac(alg, &k, &[&v, &[0x00], &x_octets, &h_octets])?;
+        v = hmac(alg, &k, &[&v])?;
+        k = hmac(alg, &k, &[&v, &[0x01], &x_octets, &h_octets])?;
+        v = hmac(alg, &k, &[&v])?;
+
+        loop {
+            let mut t = Vec::<u8>::with_capacity(rlen + alg.digest_len());
+            while t.len() < rlen {
+                v = hmac(alg, &k, &[&v])?;
+                t.extend(&v);
+            }
+
+            let candidate = params.bits_to_int(&t);
+            if candidate != BigUint::from(0u32) && candidate < *q {
+                if let Some((r, s)) = self.sign_with_k(&z, &candidate) {
+                    return Ok(encode_signature(&r, &s));
+                }
+            }
+
+            k = hmac(alg, &k, &[&v, &[0x00]])?;
+            v = hmac(alg, &k, &[&v])?;
+        }
+    }
+
+    /* r = (g^k mod p) mod q and s = k^-1 (z + xr) mod q, or None if
+       either is zero and another k is needed.
+    */
+    fn sign_with_k(&self, z: &BigUint, k: &BigUint) -> Option<(BigUint, BigUint)> {
+        let params = &self.params;
+        let q = &params.subprime;
+        let zero = BigUint::from(0u32);
+
+        let r = params.generator.modpow(k, &params.prime).rem(q);
+        if r == zero {
+            return None;
+        }
+
+        let s = mod_inverse(k, q).mul(z.add(self.x.clone().mul(&r))).rem(q);
+        if s == zero {
+            return None;
+        }
+
+        Some((r, s))
+    }
+}
+
+/* Dss-Sig-Value ::= SEQUENCE { r INTEGER, s INTEGER } */
+fn encode_signature(r: &BigUint, s: &BigUint) -> Vec<u8> {
+    let mut content = integer(&r.to_bytes_be());
+    content.extend(integer(&s.to_bytes_be()));
+    tlv(TAG_SEQUENCE, &content)
+}
+
+fn decode_signature(signature: &[u8]) -> Result<(BigUint, BigUint), RSAError> {
+    let (content, rest) =
+        read_tlv(signature, TAG_SEQUENCE).map_err(|_| RSAError::SignatureEncoding)?;
+    let (r, content) = read_integer(content).map_err(|_| RSAError::SignatureEncoding)?;
+    let (s, content) = read_integer(content).map_err(|_| RSAError::SignatureEncoding)?;
+    if !rest.is_empty() || !content.is_empty() {
+        return Err(RSAError::SignatureEncoding);
+    }
+    Ok((BigUint::from_bytes_be(r), BigUint::from_bytes_be(s)))
+}
+
+/* Uniform enough value in [1, q-1]: 64 extra random bits reduced mod q-1. */
+fn random_below_subprime(
+    q: &BigUint,
+    random_struct: &mut RandomStruct,
+) -> Result<BigUint, RSAError> {
+    let one = BigUint::from(1u32);
+    let random = random_struct.generate_bytes(q.bits().div_ceil(8) + 8)?;
+    Ok(BigUint::from_bytes_be(&random)
+        .rem(&q.clone().sub(&one))
+        .add(&one))
+}
+
+/* Inverse modulo the prime q, as a^(q-2) mod q. */
+fn mod_inverse(a: &BigUint, q: &BigUint) -> BigUint {
+    a.modpow(&q.clone().sub(BigUint::from(2u32)), q)
+}
+
+/* The private value passes through here, so the unpadded bytes are
+   wiped.
+*/
+fn to_be_padded(value: &BigUint, len: usize) -> Vec<u8> {
+    let be: SecretVec = Wiped(value.to_bytes_be());
+    let mut result = vec![0u8; len.saturating_sub(be.len())];
+    result.extend(be.iter());
+    result
+}
+
+fn hmac(alg: DigestAlg, key: &[u8], data: &[&[u8]]) -> Result<Vec<u8>, RSAError> {
+    fn mac<D: Digest + BlockSizeUser>(key: &[u8], data: &[&[u8]]) -> Result<Vec<u8>, RSAError> {
+        let mut mac = SimpleHmac::<D>::new_from_slice(key).map_err(|_| RSAError::Key)?;
+        for part in data {
+            Mac::update(&mut mac, part);
+        }
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    match alg {
+        DigestAlg::Md2 => mac::<Md2>(key, data),
+        DigestAlg::Md5 => mac::<Md5>(key, data),
+        DigestAlg::Sha1 => mac::<Sha1>(key, data),
+        DigestAlg::Sha256 => mac::<Sha256>(key, data),
+        DigestAlg::Sha384 => mac::<Sha384>(key, data),
+        DigestAlg::Sha512 => mac::<Sha512>(key, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_random_struct;
+
+    fn hex(value: &str) -> BigUint {
+        BigUint::parse_bytes(value.as_bytes(), 16).unwrap()
+    }
+
+    /* RFC 6979, A.2.1 */
+    fn rfc6979_key() -> DSAPrivateKey {
+        let params = DSAParams::from_components(
+            hex(
+                "86F5CA03DCFEB225063FF830A0C769B9DD9D6153AD91D7CE27F787C43278B447\
+                 E6533B86B18BED6E8A48B784A14C252C5BE0DBF60B86D6385BD2F12FB763ED88\
+                 73ABFD3F5BA2E0A8C0A59082EAC056935E529DAF7C610467899C77ADEDFC846C\
+                 881870B7B19B2B58F9BE0521A17002E3BDD6B86685EE90B3D9A1B02B782B1779",
+            ),
+            hex("996F967F6C8E388D9E28D01E205FBA957A5698B1"),
+            hex(
+                "07B0F92546150B62514BB771E2A0C0CE387F03BDA6C56B505209FF25FD3C133D\
+                 89BBCD97E904E09114D9A7DEFDEADFC9078EA544D2E401AEECC40BB9FBBF78FD\
+                 87995A10A1C27CB7789B594BA7EFB5C4326A9FE59A070E136DB77175464ADCA4\
+                 17BE5DCE2F40D10A46A3A3943F26AB7FD9C0398FF8C76EE0A56826A8A88F1DBD",
+            ),
+        )
+        .unwrap();
+        DSAPrivateKey::from_components(params, hex("411602CB19A6CCC34494D79D98EF1E7ED5AF25F7"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_dsa_rfc6979_vectors() {
+        let key = rfc6979_key();
+        key.params().validate().unwrap();
+
+        for (alg, r, s) in [
+            (
+                DigestAlg::Sha1,
+                "2E1A0C2562B2912CAAF89186FB0F42001585DA55",
+                "29EFB6B0AFF2D7A68EB70CA313022253B9A88DF5",
+            ),
+            (
+                DigestAlg::Sha256,
+                "81F2F5850BE5BC123C43F71A3033E9384611C545",
+                "4CDD914B65EB6C66A8AAAD27299BEE6B035F5E89",
+            ),
+        ] {
+            let signature = key.sign_deterministic(alg, b"sample").unwrap();
+            assert_eq!(signature, encode_signature(&hex(r), &hex(s)));
+            key.public_key().verify(alg, b"sample", &signature).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_dsa_generate_sign_verify() {
+        let mut random_struct = test_random_struct(0xd5);
+
+        let params = generate_dsa_params(512, 160, &mut random_struct).unwrap();
+        params.validate().unwrap();
+        let (public_key, private_key) = params.generate_key(&mut random_struct).unwrap();
+
+        let first = private_key
+            .sign(DigestAlg::Sha1, b"message", &mut random_struct)
+            .unwrap();
+        let second = private_key
+            .sign(DigestAlg::Sha1, b"message", &mut random_struct)
+            .unwrap();
+        assert_ne!(first, second);
+        for signature in [&first, &second] {
+            public_key
+                .verify(DigestAlg::Sha1, b"message", signature)
+                .unwrap();
+            assert!(matches!(
+                public_key.verify(DigestAlg::Sha1, b"massage", signature),
+                Err(RSAError::Signature)
+            ));
+        }
+
+        let mut tampered = first.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            public_key.verify(DigestAlg::Sha1, b"message", &tampered),
+            Err(RSAError::Signature)
+        ));
+        assert!(matches!(
+            public_key.verify(DigestAlg::Sha1, b"message", &first[1..]),
+            Err(RSAError::SignatureEncoding)
+        ));
+
+        let (other_public_key, _) = params.generate_key(&mut random_struct).unwrap();
+        assert!(other_public_key
+            .verify(DigestAlg::Sha1, b"message", &first)
+            .is_err());
+    }
+
+    #[test]
+    fn test_dsa_params_reject_degenerate() {
+        let (p, q, g) = (23u32, 11u32, 4u32);
+        DSAParams::from_components(p.into(), q.into(), g.into()).unwrap();
+        for (p, q, g) in [
+            (0, q, g),
+            (1, q, g),
+            (p, 0, g),
+            (p, 1, g),
+            (p, 7, g),
+            (p, q, 0),
+            (p, q, 1),
+            (p, q, p),
+            (p, q, 30),
+        ] {
+            assert!(matches!(
+                DSAParams::from_components(p.into(), q.into(), g.into()),
+                Err(RSAError::Data)
+            ));
+        }
+    }
+}
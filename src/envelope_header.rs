@@ -0,0 +1,360 @@
+/* A self-describing header for sealed envelopes, so that an opener needs
+   nothing but its private key:
+     magic       "RSAE"
+     version     1 byte
+     flags       1 byte, 1 authenticated, 2 compressed
+     cipher      2 bytes, the EncryptionAlgorithmId
+     digest      1-byte length, then the OID of the MAC digest; empty
+                 unless authenticated
+     IV          1-byte length, then the IV
+     recipients  2-byte count, then for each recipient
+       fingerprint  32 bytes, key_fingerprint of its public key
+       key len      2 bytes
+       key          the content key or secret, RSA-encrypted to it
+   Integers are big-endian. The ciphertext follows the header exactly as
+   in the raw RSAREF format, which stays the default; see
+   SealContext::with_header.
+*/
+use crate::algorithm::EncryptionAlgorithmId;
+use crate::csr::key_fingerprint;
+use crate::digest_info::DigestAlg;
+use crate::r_enhanc::OpenContext;
+use crate::rsa::PrivateKeyOps;
+use crate::RSAError;
+use alloc::vec::Vec;
+
+const HEADER_MAGIC: &[u8; 4] = b"RSAE";
+const HEADER_VERSION: u8 = 1;
+const FLAG_AUTHENTICATED: u8 = 1;
+const FLAG_COMPRESSED: u8 = 2;
+const FINGERPRINT_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeRecipient {
+    pub fingerprint: [u8; FINGERPRINT_LEN],
+    pub encrypted_key: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeHeader {
+    pub algorithm: EncryptionAlgorithmId,
+    /* The digest of the envelope's HMAC, if it is authenticated. */
+    pub mac_digest: Option<DigestAlg>,
+    pub compressed: bool,
+    pub iv: Vec<u8>,
+    pub recipients: Vec<EnvelopeRecipient>,
+}
+
+/* Reads the fields of a header in order. Running out of data fails with
+   InputLen, expecting the length up to the end of the field being read.
+*/
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RSAError> {
+        let end = self.position.saturating_add(len);
+        let field = self
+            .data
+            .get(self.position..end)
+            .ok_or(RSAError::InputLen {
+                expected: end,
+                actual: self.data.len(),
+            })?;
+        self.position = end;
+        Ok(field)
+    }
+
+    fn byte(&mut self) -> Result<u8, RSAError> {
+        let [byte] = self.take(1)? else {
+            return Err(RSAError::Encoding);
+        };
+        Ok(*byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, RSAError> {
+        let [high, low] = self.take(2)? else {
+            return Err(RSAError::Encoding);
+        };
+        Ok(u16::from_be_bytes([*high, *low]))
+    }
+}
+
+/* Appends len and data, failing with Len if data is longer than
+   len_bytes can count.
+*/
+fn push_counted(result: &mut Vec<u8>, data: &[u8], len_bytes: usize) -> Result<(), RSAError> {
+    let len = u16::try_from(data.len()).map_err(|_| RSAError::Len)?;
+    if len_bytes == 1 {
+        result.push(u8::try_from(len).map_err(|_| RSAError::Len)?);
+    } else {
+        result.extend_from_slice(&len.to_be_bytes());
+    }
+    result.extend_from_slice(data);
+    Ok(())
+}
+
+impl EnvelopeHeader {
+    pub fn authenticated(&self) -> bool {
+        self.mac_digest.is_some()
+    }
+
+    /* Fails with Len if the IV, a key or the recipient count is too long
+       for its length field.
+    */
+    pub fn encode(&self) -> Result<Vec<u8>, RSAError> {
+        let mut flags = 0;
+        if self.authenticated() {
+            flags |= FLAG_AUTHENTICATED;
+        }
+        if self.compressed {
+            flags |= FLAG_COMPRESSED;
+        }
+
+        let mut result = Vec::new();
+        result.extend_from_slice(HEADER_MAGIC);
+        result.push(HEADER_VERSION);
+        result.push(flags);
+        result.extend_from_slice(&(self.algorithm as u16).to_be_bytes());
+        push_counted(
+            &mut result,
+            self.mac_digest.map_or(&[][..], |digest| digest.oid()),
+            1,
+        )?;
+        push_counted(&mut result, &self.iv, 1)?;
+        let count = u16::try_from(self.recipients.len()).map_err(|_| RSAError::Len)?;
+        result.extend_from_slice(&count.to_be_bytes());
+        for recipient in &self.recipients {
+            result.extend_from_slice(&recipient.fingerprint);
+            push_counted(&mut result, &recipient.encrypted_key, 2)?;
+        }
+        Ok(result)
+    }
+
+    /* The header at the start of data and its length; the ciphertext
+       starts there. A header cut short fails with InputLen, so a caller
+       reading a stream can read more and try again. Anything but a
+       version 1 header fails with Encoding, an unknown cipher or digest
+       with EncryptionAlgorithm or DigestAlgorithm.
+    */
+    pub fn decode(data: &[u8]) -> Result<(Self, usize), RSAError> {
+        let mut reader = Reader { data, position: 0 };
+        let magic_len = data.len().min(HEADER_MAGIC.len());
+        if data.get(..magic_len) != HEADER_MAGIC.get(..magic_len) {
+            return Err(RSAError::Encoding);
+        }
+        reader.take(HEADER_MAGIC.len())?;
+        if reader.byte()? != HEADER_VERSION {
+            return Err(RSAError::Encoding);
+        }
+        let flags = reader.byte()?;
+        if flags & !(FLAG_AUTHENTICATED | FLAG_COMPRESSED) != 0 {
+            return Err(RSAError::Encoding);
+        }
+        let algorithm = EncryptionAlgorithmId::try_from(reader.u16()? as i32)?;
+
+        let digest_len = reader.byte()? as usize;
+        let digest_oid = reader.take(digest_len)?;
+        let mac_digest = match (flags & FLAG_AUTHENTICATED != 0, digest_oid) {
+            (false, []) => None,
+            (true, oid) => Some(DigestAlg::from_oid(oid).ok_or(RSAError::DigestAlgorithm)?),
+            (false, _) => return Err(RSAError::Encoding),
+        };
+        if mac_digest.is_some_and(|digest| digest != DigestAlg::Sha256) {
+            return Err(RSAError::DigestAlgorithm);
+        }
+
+        let iv_len = reader.byte()? as usize;
+        let iv = reader.take(iv_len)?.to_vec();
+        if iv.len() != algorithm.iv_len() {
+            return Err(RSAError::Encoding);
+        }
+
+        let count = reader.u16()? as usize;
+        let mut recipients = Vec::with_capacity(count);
+        for _ in 0..count {
+            let fingerprint = reader
+                .take(FINGERPRINT_LEN)?
+                .try_into()
+                .map_err(|_| RSAError::Encoding)?;
+            let key_len = reader.u16()? as usize;
+            recipients.push(EnvelopeRecipient {
+                fingerprint,
+                encrypted_key: reader.take(key_len)?.to_vec(),
+            });
+        }
+
+        let header = Self {
+            algorithm,
+            mac_digest,
+            compressed: flags & FLAG_COMPRESSED != 0,
+            iv,
+            recipients,
+        };
+        Ok((header, reader.position))
+    }
+
+    /* An OpenContext for the recipient whose fingerprint matches
+       private_key, set up as the header describes. Fails with PrivateKey
+       if the envelope was not sealed to it, and with ContentEncoding for
+       a compressed envelope in a build without the deflate feature.
+    */
+    pub fn open(&self, private_key: &dyn PrivateKeyOps) -> Result<OpenContext, RSAError> {
+        let fingerprint = key_fingerprint(&private_key.public_key());
+        let recipient = self
+            .recipients
+            .iter()
+            .find(|recipient| recipient.fingerprint == fingerprint)
+            .ok_or(RSAError::PrivateKey)?;
+
+        let context = if self.authenticated() {
+            OpenContext::new_authenticated(
+                self.algorithm,
+                &recipient.encrypted_key,
+                &self.iv,
+                private_key,
+            )?
+        } else {
+            OpenContext::new(
+                self.algorithm,
+                &recipient.encrypted_key,
+                &self.iv,
+                private_key,
+            )?
+        };
+        if self.compressed {
+            #[cfg(feature = "deflate")]
+            return Ok(context.with_decompression());
+            #[cfg(not(feature = "deflate"))]
+            return Err(RSAError::ContentEncoding);
+        }
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_enhanc::SealContext;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    #[test]
+    fn test_envelope_header() {
+        let mut random_struct = test_random_struct(0x3c);
+        let recipients = [test_key_pair(512), test_key_pair(512)];
+        let public_keys = recipients.iter().map(|(p, _)| p).collect::<Vec<_>>();
+        let (_, stranger) = test_key_pair(512);
+        let content = (0u8..=200).collect::<Vec<u8>>();
+
+        for authenticated in [false, true] {
+            let algorithm = EncryptionAlgorithmId::Aes128Cbc;
+            let mut seal = if authenticated {
+                SealContext::new_authenticated(algorithm, &public_keys, &mut random_struct)
+            } else {
+                SealContext::new(algorithm, &public_keys, &mut random_struct)
+            }
+            .unwrap()
+            .with_header();
+            let header = seal.header().unwrap();
+
+            let mut sealed = Vec::<u8>::new();
+            for chunk in content.chunks(50) {
+                sealed.extend(seal.update(chunk).unwrap());
+            }
+            sealed.extend(seal.finalize().unwrap());
+            assert_eq!(sealed[..header.len()], header);
+
+            let (decoded, header_len) = EnvelopeHeader::decode(&sealed).unwrap();
+            assert_eq!(header_len, header.len());
+            assert_eq!(decoded.algorithm, algorithm);
+            assert_eq!(decoded.authenticated(), authenticated);
+            assert_eq!(decoded.recipients.len(), 2);
+            assert_eq!(decoded.encode().unwrap(), header);
+
+            for (public_key, private_key) in &recipients {
+                let (opened_header, len) = EnvelopeHeader::decode(&sealed).unwrap();
+                assert!(opened_header
+                    .recipients
+                    .iter()
+                    .any(|recipient| recipient.fingerprint == key_fingerprint(public_key)));
+                let mut open = opened_header.open(private_key).unwrap();
+                let mut opened = open.update(&sealed[len..]).unwrap();
+                opened.extend(open.finalize().unwrap());
+                assert_eq!(opened, content);
+            }
+            assert_eq!(decoded.open(&stranger).err(), Some(RSAError::PrivateKey));
+
+            assert_eq!(
+                EnvelopeHeader::decode(&header[..header.len() - 1]).err(),
+                Some(RSAError::InputLen {
+                    expected: header.len(),
+                    actual: header.len() - 1,
+                })
+            );
+        }
+
+        assert_eq!(
+            EnvelopeHeader::decode(b"RSAK").err(),
+            Some(RSAError::Encoding)
+        );
+        assert!(matches!(
+            EnvelopeHeader::decode(b"RS"),
+            Err(RSAError::InputLen { .. })
+        ));
+        assert_eq!(
+            EnvelopeHeader::decode(b"RSAE\x01\x00\x00\x09").err(),
+            Some(RSAError::EncryptionAlgorithm)
+        );
+    }
+
+    /* Hands out a few bytes per read, so the header arrives in pieces. */
+    #[cfg(feature = "std")]
+    struct Trickle<'a>(&'a [u8]);
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(7);
+            let (chunk, rest) = self.0.split_at(len);
+            buf[..len].copy_from_slice(chunk);
+            self.0 = rest;
+            Ok(len)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_envelope_header_streams() {
+        use crate::r_enhanc::{OpenReader, SealWriter};
+        use std::io::{Read, Write};
+
+        let mut random_struct = test_random_struct(0x3d);
+        let (public_key, private_key) = test_key_pair(512);
+        let content = (0..5000u32).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+
+        let seal = SealContext::new(
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap()
+        .with_header();
+        let mut writer = SealWriter::new(Vec::<u8>::new(), seal);
+        writer.write_all(&content).unwrap();
+        let sealed = writer.finish().unwrap();
+
+        let mut opened = Vec::<u8>::new();
+        OpenReader::with_header(Trickle(&sealed), &private_key)
+            .unwrap()
+            .read_to_end(&mut opened)
+            .unwrap();
+        assert_eq!(opened, content);
+
+        let error = OpenReader::with_header(Trickle(&sealed[..100]), &private_key)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
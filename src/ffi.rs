@@ -0,0 +1,751 @@
+#![allow(non_camel_case_types, non_snake_case)]
+
+use core::ffi::{c_int, c_uchar, c_uint};
+use core::slice;
+
+use crate::r_dh::{generate_dh_params, DHParams};
+use crate::r_keygen::generate_pem_keys_with_random;
+use crate::r_random::RandomStruct;
+use crate::rsa::{
+    RSAPrivateKey, RSAProtoKey, RSAPublicKey, RSASerialize, MAX_RSA_MODULUS_LEN, MAX_RSA_PRIME_LEN,
+};
+use crate::RSAError;
+use num_bigint_dig::BigUint;
+
+pub const RE_CONTENT_ENCODING: c_int = 0x0400;
+pub const RE_DATA: c_int = 0x0401;
+pub const RE_DIGEST_ALGORITHM: c_int = 0x0402;
+pub const RE_ENCODING: c_int = 0x0403;
+pub const RE_KEY: c_int = 0x0404;
+pub const RE_KEY_ENCODING: c_int = 0x0405;
+pub const RE_LEN: c_int = 0x0406;
+pub const RE_MODULUS_LEN: c_int = 0x0407;
+pub const RE_NEED_RANDOM: c_int = 0x0408;
+pub const RE_PRIVATE_KEY: c_int = 0x0409;
+pub const RE_PUBLIC_KEY: c_int = 0x040a;
+pub const RE_SIGNATURE: c_int = 0x040b;
+pub const RE_SIGNATURE_ENCODING: c_int = 0x040c;
+pub const RE_ENCRYPTION_ALGORITHM: c_int = 0x040d;
+
+fn error_code(error: RSAError) -> c_int {
+    c_int::from(error.code())
+}
+
+/* include/rsaref.h spells the array bounds out as the original rsaref.h
+   does, and C callers allocate these structures themselves, so their
+   layout is fixed here.
+*/
+const _: () = {
+    use crate::rsa::{MAX_RSA_MODULUS_BITS, MIN_RSA_MODULUS_BITS};
+    use core::mem::size_of;
+    assert!(MIN_RSA_MODULUS_BITS == 508 && MAX_RSA_MODULUS_BITS == 1024);
+    assert!(MAX_RSA_MODULUS_LEN == 128 && MAX_RSA_PRIME_LEN == 64);
+    assert!(size_of::<R_RANDOM_STRUCT>() == 40);
+    assert!(size_of::<R_RSA_PUBLIC_KEY>() == 4 + 2 * 128);
+    assert!(size_of::<R_RSA_PRIVATE_KEY>() == 4 + 3 * 128 + 5 * 64);
+    assert!(size_of::<R_RSA_PROTO_KEY>() == 8);
+};
+
+fn status(result: Result<(), RSAError>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(error) => error_code(error),
+    }
+}
+
+#[repr(C)]
+pub struct R_RANDOM_STRUCT {
+    pub bytesNeeded: c_uint,
+    pub state: [c_uchar; 16],
+    pub outputAvailable: c_uint,
+    pub output: [c_uchar; 16],
+}
+
+#[repr(C)]
+pub struct R_RSA_PUBLIC_KEY {
+    pub bits: c_uint,
+    pub modulus: [c_uchar; MAX_RSA_MODULUS_LEN],
+    pub exponent: [c_uchar; MAX_RSA_MODULUS_LEN],
+}
+
+#[repr(C)]
+pub struct R_RSA_PRIVATE_KEY {
+    pub bits: c_uint,
+    pub modulus: [c_uchar; MAX_RSA_MODULUS_LEN],
+    pub publicExponent: [c_uchar; MAX_RSA_MODULUS_LEN],
+    pub exponent: [c_uchar; MAX_RSA_MODULUS_LEN],
+    pub prime: [[c_uchar; MAX_RSA_PRIME_LEN]; 2],
+    pub primeExponent: [[c_uchar; MAX_RSA_PRIME_LEN]; 2],
+    pub coefficient: [c_uchar; MAX_RSA_PRIME_LEN],
+}
+
+#[repr(C)]
+pub struct R_RSA_PROTO_KEY {
+    pub bits: c_uint,
+    pub useFermat4: c_int,
+}
+
+#[repr(C)]
+pub struct R_DH_PARAMS {
+    pub prime: *mut c_uchar,
+    pub primeLen: c_uint,
+    pub generator: *mut c_uchar,
+    pub generatorLen: c_uint,
+}
+
+impl From<&R_RANDOM_STRUCT> for RandomStruct {
+    fn from(raw: &R_RANDOM_STRUCT) -> Self {
+        Self {
+            bytes_needed: raw.bytesNeeded as usize,
+            state: raw.state,
+            output_available: raw.outputAvailable as usize,
+            output: raw.output,
+        }
+    }
+}
+
+impl R_RANDOM_STRUCT {
+    fn store(&mut self, random_struct: &RandomStruct) {
+        self.bytesNeeded = random_struct.bytes_needed as c_uint;
+        self.state = random_struct.state;
+        self.outputAvailable = random_struct.output_available as c_uint;
+        self.output = random_struct.output;
+    }
+}
+
+impl From<&R_RSA_PUBLIC_KEY> for RSAPublicKey {
+    fn from(raw: &R_RSA_PUBLIC_KEY) -> Self {
+        RSAPublicKey::from_components(
+            raw.bits,
+            BigUint::from_bytes_be(&raw.modulus),
+            BigUint::from_bytes_be(&raw.exponent),
+        )
+    }
+}
+
+/* Writes value big-endian and zero-padded across the whole of field. */
+fn store_be(field: &mut [u8], value: &BigUint) {
+    let be = value.to_be(field.len());
+    for (byte, value_byte) in field.iter_mut().zip(be) {
+        *byte = value_byte;
+    }
+}
+
+impl R_RSA_PUBLIC_KEY {
+    fn store(&mut self, public_key: &RSAPublicKey) {
+        self.bits = public_key.bits();
+        store_be(&mut self.modulus, public_key.modulus());
+        store_be(&mut self.exponent, public_key.exponent());
+    }
+}
+
+impl From<&R_RSA_PRIVATE_KEY> for RSAPrivateKey {
+    fn from(raw: &R_RSA_PRIVATE_KEY) -> Self {
+        RSAPrivateKey::from_components(
+            raw.bits,
+            BigUint::from_bytes_be(&raw.modulus),
+            BigUint::from_bytes_be(&raw.publicExponent),
+            BigUint::from_bytes_be(&raw.exponent),
+            [
+                BigUint::from_bytes_be(&raw.prime[0]),
+                BigUint::from_bytes_be(&raw.prime[1]),
+            ],
+            [
+                BigUint::from_bytes_be(&raw.primeExponent[0]),
+                BigUint::from_bytes_be(&raw.primeExponent[1]),
+            ],
+            BigUint::from_bytes_be(&raw.coefficient),
+        )
+    }
+}
+
+impl R_RSA_PRIVATE_KEY {
+    fn store(&mut self, private_key: &RSAPrivateKey) {
+        self.bits = private_key.bits();
+        store_be(&mut self.modulus, private_key.modulus());
+        store_be(&mut self.publicExponent, private_key.public_exponent());
+        store_be(&mut self.exponent, private_key.exponent());
+        for (field, prime) in self.prime.iter_mut().zip(private_key.prime()) {
+            store_be(field, prime);
+        }
+        for (field, prime_exponent) in self
+            .primeExponent
+            .iter_mut()
+            .zip(private_key.prime_exponent())
+        {
+            store_be(field, prime_exponent);
+        }
+        store_be(&mut self.coefficient, private_key.coefficient());
+    }
+}
+
+unsafe fn input_slice<'a>(input: *const c_uchar, input_len: c_uint) -> &'a [u8] {
+    if input_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(input, input_len as usize)
+    }
+}
+
+unsafe fn write_output(output: *mut c_uchar, output_len: *mut c_uint, data: &[u8]) {
+    core::ptr::copy_nonoverlapping(data.as_ptr(), output, data.len());
+    *output_len = data.len() as c_uint;
+}
+
+/// # Safety
+///
+/// `random_struct` must point to a valid `R_RANDOM_STRUCT`.
+#[no_mangle]
+pub unsafe extern "C" fn R_RandomInit(random_struct: *mut R_RANDOM_STRUCT) -> c_int {
+    let mut rs = RandomStruct::new();
+    rs.random_init();
+    (*random_struct).store(&rs);
+    0
+}
+
+/// # Safety
+///
+/// `random_struct` must point to a valid `R_RANDOM_STRUCT` and `block` to
+/// `block_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn R_RandomUpdate(
+    random_struct: *mut R_RANDOM_STRUCT,
+    block: *const c_uchar,
+    block_len: c_uint,
+) -> c_int {
+    let mut rs = RandomStruct::from(&*random_struct);
+    rs.random_update(input_slice(block, block_len));
+    (*random_struct).store(&rs);
+    0
+}
+
+/// # Safety
+///
+/// `bytes_needed` must be writable and `random_struct` must point to a
+/// valid `R_RANDOM_STRUCT`.
+#[no_mangle]
+pub unsafe extern "C" fn R_GetRandomBytesNeeded(
+    bytes_needed: *mut c_uint,
+    random_struct: *mut R_RANDOM_STRUCT,
+) -> c_int {
+    *bytes_needed = (*random_struct).bytesNeeded;
+    0
+}
+
+/// # Safety
+///
+/// `block` must have room for `block_len` bytes and `random_struct` must
+/// point to a valid `R_RANDOM_STRUCT`.
+#[no_mangle]
+pub unsafe extern "C" fn R_GenerateBytes(
+    block: *mut c_uchar,
+    block_len: c_uint,
+    random_struct: *mut R_RANDOM_STRUCT,
+) -> c_int {
+    let mut rs = RandomStruct::from(&*random_struct);
+    let result = rs.generate_bytes(block_len as usize).map(|bytes| {
+        core::ptr::copy_nonoverlapping(bytes.as_ptr(), block, bytes.len());
+    });
+    (*random_struct).store(&rs);
+    status(result)
+}
+
+/// # Safety
+///
+/// `random_struct` must point to a valid `R_RANDOM_STRUCT`.
+#[no_mangle]
+pub unsafe extern "C" fn R_RandomFinal(random_struct: *mut R_RANDOM_STRUCT) {
+    let mut rs = RandomStruct::from(&*random_struct);
+    rs.random_final();
+    (*random_struct).store(&rs);
+}
+
+/// # Safety
+///
+/// `output` must have room for a modulus-length block, `output_len` must be
+/// writable, `input` must point to `input_len` readable bytes and the key
+/// and random structures must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn RSAPublicEncrypt(
+    output: *mut c_uchar,
+    output_len: *mut c_uint,
+    input: *const c_uchar,
+    input_len: c_uint,
+    public_key: *const R_RSA_PUBLIC_KEY,
+    random_struct: *mut R_RANDOM_STRUCT,
+) -> c_int {
+    let key = RSAPublicKey::from(&*public_key);
+    let mut rs = RandomStruct::from(&*random_struct);
+    let result = key
+        .rsa_public_encrypt(input_slice(input, input_len), &mut rs)
+        .map(|block| write_output(output, output_len, &block));
+    (*random_struct).store(&rs);
+    status(result)
+}
+
+/// # Safety
+///
+/// `output` must have room for a modulus-length block, `output_len` must be
+/// writable, `input` must point to `input_len` readable bytes and
+/// `public_key` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn RSAPublicDecrypt(
+    output: *mut c_uchar,
+    output_len: *mut c_uint,
+    input: *const c_uchar,
+    input_len: c_uint,
+    public_key: *const R_RSA_PUBLIC_KEY,
+) -> c_int {
+    let key = RSAPublicKey::from(&*public_key);
+    status(
+        key.rsa_public_decrypt(input_slice(input, input_len))
+            .map(|block| write_output(output, output_len, &block)),
+    )
+}
+
+/// # Safety
+///
+/// `output` must have room for a modulus-length block, `output_len` must be
+/// writable, `input` must point to `input_len` readable bytes and
+/// `private_key` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn RSAPrivateEncrypt(
+    output: *mut c_uchar,
+    output_len: *mut c_uint,
+    input: *const c_uchar,
+    input_len: c_uint,
+    private_key: *const R_RSA_PRIVATE_KEY,
+) -> c_int {
+    let key = RSAPrivateKey::from(&*private_key);
+    status(
+        key.rsa_private_encrypt(input_slice(input, input_len))
+            .map(|block| write_output(output, output_len, &block)),
+    )
+}
+
+/// # Safety
+///
+/// `output` must have room for a modulus-length block, `output_len` must be
+/// writable, `input` must point to `input_len` readable bytes and
+/// `private_key` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn RSAPrivateDecrypt(
+    output: *mut c_uchar,
+    output_len: *mut c_uint,
+    input: *const c_uchar,
+    input_len: c_uint,
+    private_key: *const R_RSA_PRIVATE_KEY,
+) -> c_int {
+    let key = RSAPrivateKey::from(&*private_key);
+    status(
+        key.rsa_private_decrypt(input_slice(input, input_len))
+            .map(|block| write_output(output, output_len, &block)),
+    )
+}
+
+/// # Safety
+///
+/// All pointers must refer to valid structures of the named types.
+#[no_mangle]
+pub unsafe extern "C" fn R_GeneratePEMKeys(
+    public_key: *mut R_RSA_PUBLIC_KEY,
+    private_key: *mut R_RSA_PRIVATE_KEY,
+    proto_key: *const R_RSA_PROTO_KEY,
+    random_struct: *mut R_RANDOM_STRUCT,
+) -> c_int {
+    let proto_key = RSAProtoKey {
+        bits: (*proto_key).bits,
+        use_fermat4: (*proto_key).useFermat4 != 0,
+    };
+    let mut rs = RandomStruct::from(&*random_struct);
+    let result = generate_pem_keys_with_random(&proto_key, &mut rs);
+    (*random_struct).store(&rs);
+    status(result.map(|(generated_public, generated_private)| {
+        (*public_key).store(&generated_public);
+        (*private_key).store(&generated_private);
+    }))
+}
+
+/// # Safety
+///
+/// `params` must point to an `R_DH_PARAMS` whose `prime` and `generator`
+/// buffers can each hold `(prime_bits + 7) / 8` bytes, and `random_struct`
+/// must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn R_GenerateDHParams(
+    params: *mut R_DH_PARAMS,
+    prime_bits: c_uint,
+    subprime_bits: c_uint,
+    random_struct: *mut R_RANDOM_STRUCT,
+) -> c_int {
+    let mut rs = RandomStruct::from(&*random_struct);
+    let result =
+        generate_dh_params(prime_bits as usize, subprime_bits as usize, &mut rs).map(|generated| {
+            let prime_len = generated.prime_len();
+            let prime = generated.prime().to_be(prime_len);
+            let generator = generated.generator().to_be(prime_len);
+
+            let params = &mut *params;
+            write_output(params.prime, &mut params.primeLen, &prime);
+            write_output(params.generator, &mut params.generatorLen, &generator);
+        });
+    (*random_struct).store(&rs);
+    status(result)
+}
+
+unsafe fn dh_params_from_raw(params: &R_DH_PARAMS) -> Result<DHParams, RSAError> {
+    DHParams::from_components(
+        BigUint::from_bytes_be(input_slice(params.prime, params.primeLen)),
+        BigUint::from_bytes_be(input_slice(params.generator, params.generatorLen)),
+    )
+}
+
+/// # Safety
+///
+/// `public_value` must have room for `params.primeLen` bytes,
+/// `private_value` for `private_value_len` bytes, and `params` and
+/// `random_struct` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn R_SetupDHAgreement(
+    public_value: *mut c_uchar,
+    private_value: *mut c_uchar,
+    private_value_len: c_uint,
+    params: *const R_DH_PARAMS,
+    random_struct: *mut R_RANDOM_STRUCT,
+) -> c_int {
+    let mut rs = RandomStruct::from(&*random_struct);
+    let result = dh_params_from_raw(&*params)
+        .and_then(|dh_params| dh_params.setup_agreement(private_value_len as usize, &mut rs))
+        .map(|(generated_public, generated_private)| {
+            core::ptr::copy_nonoverlapping(
+                generated_public.as_ptr(),
+                public_value,
+                generated_public.len(),
+            );
+            core::ptr::copy_nonoverlapping(
+                generated_private.as_ptr(),
+                private_value,
+                generated_private.len(),
+            );
+        });
+    (*random_struct).store(&rs);
+    status(result)
+}
+
+/// # Safety
+///
+/// `agreed_key` and `other_public_value` must each have room for
+/// `params.primeLen` bytes, `private_value` must point to
+/// `private_value_len` readable bytes and `params` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn R_ComputeDHAgreedKey(
+    agreed_key: *mut c_uchar,
+    other_public_value: *const c_uchar,
+    private_value: *const c_uchar,
+    private_value_len: c_uint,
+    params: *const R_DH_PARAMS,
+) -> c_int {
+    let params = &*params;
+    status(
+        dh_params_from_raw(params)
+            .and_then(|dh_params| {
+                dh_params.compute_agreed_key(
+                    input_slice(other_public_value, params.primeLen),
+                    input_slice(private_value, private_value_len),
+                )
+            })
+            .map(|key| core::ptr::copy_nonoverlapping(key.as_ptr(), agreed_key, key.len())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::mem::{size_of, zeroed};
+
+    #[test]
+    fn test_struct_layouts() {
+        assert_eq!(size_of::<R_RANDOM_STRUCT>(), 40);
+        assert_eq!(size_of::<R_RSA_PUBLIC_KEY>(), 260);
+        assert_eq!(size_of::<R_RSA_PRIVATE_KEY>(), 708);
+        assert_eq!(size_of::<R_RSA_PROTO_KEY>(), 8);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let codes = [
+            (RSAError::ContentEncoding, RE_CONTENT_ENCODING),
+            (RSAError::Data, RE_DATA),
+            (RSAError::DigestAlgorithm, RE_DIGEST_ALGORITHM),
+            (RSAError::Encoding, RE_ENCODING),
+            (RSAError::Key, RE_KEY),
+            (RSAError::KeyEncoding, RE_KEY_ENCODING),
+            (RSAError::Len, RE_LEN),
+            (RSAError::ModulusLen, RE_MODULUS_LEN),
+            (RSAError::NeedRandom, RE_NEED_RANDOM),
+            (RSAError::PrivateKey, RE_PRIVATE_KEY),
+            (RSAError::PublicKey, RE_PUBLIC_KEY),
+            (RSAError::Signature, RE_SIGNATURE),
+            (RSAError::SignatureEncoding, RE_SIGNATURE_ENCODING),
+            (RSAError::EncryptionAlgorithm, RE_ENCRYPTION_ALGORITHM),
+        ];
+        for (error, code) in codes {
+            assert_eq!(c_int::from(error.code()), code);
+            assert_eq!(RSAError::try_from(error.code()), Ok(error));
+        }
+
+        let located = RSAError::InputLen {
+            expected: 4,
+            actual: 3,
+        }
+        .located(8, "bits");
+        assert_eq!(error_code(located), RE_LEN);
+        assert_eq!(RSAError::try_from(0x040e), Err(0x040e));
+        assert_eq!(RSAError::try_from(0), Err(0));
+    }
+
+    #[test]
+    fn test_random_matches_rust_api() {
+        let random_buf = (0..=255).collect::<Vec<u8>>();
+
+        let mut random_struct = RandomStruct::new();
+        random_struct.random_update(&random_buf);
+        let expected = random_struct.generate_bytes(100).unwrap();
+
+        unsafe {
+            let mut raw: R_RANDOM_STRUCT = zeroed();
+            let mut bytes_needed: c_uint = 0;
+            let mut output = [0u8; 100];
+
+            assert_eq!(R_RandomInit(&mut raw), 0);
+            assert_eq!(
+                R_GenerateBytes(output.as_mut_ptr(), 100, &mut raw),
+                RE_NEED_RANDOM
+            );
+
+            R_RandomUpdate(&mut raw, random_buf.as_ptr(), random_buf.len() as c_uint);
+            R_GetRandomBytesNeeded(&mut bytes_needed, &mut raw);
+            assert_eq!(bytes_needed, 0);
+
+            /* Split across calls to exercise the saved output state. */
+            assert_eq!(R_GenerateBytes(output.as_mut_ptr(), 30, &mut raw), 0);
+            assert_eq!(R_GenerateBytes(output[30..].as_mut_ptr(), 70, &mut raw), 0);
+            assert_eq!(output.to_vec(), expected);
+
+            R_RandomFinal(&mut raw);
+        }
+    }
+
+    #[test]
+    fn test_rsa_round_trip() {
+        unsafe {
+            let mut random_struct: R_RANDOM_STRUCT = zeroed();
+            let mut public_key: R_RSA_PUBLIC_KEY = zeroed();
+            let mut private_key: R_RSA_PRIVATE_KEY = zeroed();
+            let proto_key = R_RSA_PROTO_KEY {
+                bits: 512,
+                useFermat4: 1,
+            };
+
+            R_RandomInit(&mut random_struct);
+            assert_eq!(
+                R_GeneratePEMKeys(
+                    &mut public_key,
+                    &mut private_key,
+                    &proto_key,
+                    &mut random_struct
+                ),
+                RE_NEED_RANDOM
+            );
+
+            let random_buf = [0x5au8; 256];
+            R_RandomUpdate(&mut random_struct, random_buf.as_ptr(), 256);
+            assert_eq!(
+                R_GeneratePEMKeys(
+                    &mut public_key,
+                    &mut private_key,
+                    &proto_key,
+                    &mut random_struct
+                ),
+                0
+            );
+
+            let message = b"attack at dawn";
+            let mut encrypted = [0u8; MAX_RSA_MODULUS_LEN];
+            let mut encrypted_len: c_uint = 0;
+            let mut decrypted = [0u8; MAX_RSA_MODULUS_LEN];
+            let mut decrypted_len: c_uint = 0;
+
+            assert_eq!(
+                RSAPublicEncrypt(
+                    encrypted.as_mut_ptr(),
+                    &mut encrypted_len,
+                    message.as_ptr(),
+                    message.len() as c_uint,
+                    &public_key,
+                    &mut random_struct,
+                ),
+                0
+            );
+            assert_eq!(encrypted_len, 64);
+            assert_eq!(
+                RSAPrivateDecrypt(
+                    decrypted.as_mut_ptr(),
+                    &mut decrypted_len,
+                    encrypted.as_ptr(),
+                    encrypted_len,
+                    &private_key,
+                ),
+                0
+            );
+            assert_eq!(&decrypted[..decrypted_len as usize], message);
+
+            assert_eq!(
+                RSAPrivateEncrypt(
+                    encrypted.as_mut_ptr(),
+                    &mut encrypted_len,
+                    message.as_ptr(),
+                    message.len() as c_uint,
+                    &private_key,
+                ),
+                0
+            );
+            assert_eq!(
+                RSAPublicDecrypt(
+                    decrypted.as_mut_ptr(),
+                    &mut decrypted_len,
+                    encrypted.as_ptr(),
+                    encrypted_len,
+                    &public_key,
+                ),
+                0
+            );
+            assert_eq!(&decrypted[..decrypted_len as usize], message);
+
+            let too_long = [0u8; 64];
+            assert_eq!(
+                RSAPrivateEncrypt(
+                    encrypted.as_mut_ptr(),
+                    &mut encrypted_len,
+                    too_long.as_ptr(),
+                    too_long.len() as c_uint,
+                    &private_key,
+                ),
+                RE_LEN
+            );
+        }
+    }
+
+    #[test]
+    fn test_dh_agreement() {
+        unsafe {
+            let mut random_struct: R_RANDOM_STRUCT = zeroed();
+            let random_buf = [0xa5u8; 256];
+            R_RandomInit(&mut random_struct);
+            R_RandomUpdate(&mut random_struct, random_buf.as_ptr(), 256);
+
+            let mut prime = [0u8; 64];
+            let mut generator = [0u8; 64];
+            let mut params = R_DH_PARAMS {
+                prime: prime.as_mut_ptr(),
+                primeLen: 0,
+                generator: generator.as_mut_ptr(),
+                generatorLen: 0,
+            };
+            assert_eq!(
+                R_GenerateDHParams(&mut params, 512, 160, &mut random_struct),
+                0
+            );
+            assert_eq!(params.primeLen, 64);
+            assert_eq!(params.generatorLen, 64);
+
+            let mut public_a = [0u8; 64];
+            let mut private_a = [0u8; 20];
+            let mut public_b = [0u8; 64];
+            let mut private_b = [0u8; 20];
+            assert_eq!(
+                R_SetupDHAgreement(
+                    public_a.as_mut_ptr(),
+                    private_a.as_mut_ptr(),
+                    20,
+                    &params,
+                    &mut random_struct
+                ),
+                0
+            );
+            assert_eq!(
+                R_SetupDHAgreement(
+                    public_b.as_mut_ptr(),
+                    private_b.as_mut_ptr(),
+                    20,
+                    &params,
+                    &mut random_struct
+                ),
+                0
+            );
+
+            let mut agreed_a = [0u8; 64];
+            let mut agreed_b = [0u8; 64];
+            assert_eq!(
+                R_ComputeDHAgreedKey(
+                    agreed_a.as_mut_ptr(),
+                    public_b.as_ptr(),
+                    private_a.as_ptr(),
+                    20,
+                    &params
+                ),
+                0
+            );
+            assert_eq!(
+                R_ComputeDHAgreedKey(
+                    agreed_b.as_mut_ptr(),
+                    public_a.as_ptr(),
+                    private_b.as_ptr(),
+                    20,
+                    &params
+                ),
+                0
+            );
+            assert_eq!(agreed_a, agreed_b);
+        }
+    }
+
+    #[test]
+    fn test_dh_rejects_tiny_prime() {
+        unsafe {
+            let mut random_struct: R_RANDOM_STRUCT = zeroed();
+            R_RandomInit(&mut random_struct);
+            R_RandomUpdate(&mut random_struct, [0xa5u8; 256].as_ptr(), 256);
+
+            let (mut prime, mut generator) = ([2u8], [2u8]);
+            let params = R_DH_PARAMS {
+                prime: prime.as_mut_ptr(),
+                primeLen: 1,
+                generator: generator.as_mut_ptr(),
+                generatorLen: 1,
+            };
+            let (mut public_value, mut private_value, mut agreed_key) = ([0u8], [1u8], [0u8]);
+            assert_eq!(
+                R_SetupDHAgreement(
+                    public_value.as_mut_ptr(),
+                    private_value.as_mut_ptr(),
+                    1,
+                    &params,
+                    &mut random_struct
+                ),
+                RE_DATA
+            );
+            assert_eq!(
+                R_ComputeDHAgreedKey(
+                    agreed_key.as_mut_ptr(),
+                    public_value.as_ptr(),
+                    private_value.as_ptr(),
+                    1,
+                    &params
+                ),
+                RE_DATA
+            );
+        }
+    }
+}
@@ -0,0 +1,379 @@
+/* RSA keys of at most 512 bits held entirely in fixed arrays, for
+   embedded peers of the 512-bit legacy protocol that have no allocator to
+   spare. Decoding, encoding and the block routines never allocate; the
+   arithmetic is montgomery's over arrays instead of vectors, so the
+   private block has the same constant-time behaviour as RSAPrivateKey's.
+
+   They read and write the same 260- and 708-byte layout as RSAPublicKey
+   and RSAPrivateKey, so a key moves between the two kinds through
+   encode and decode. Failures carry no decode location, as that would
+   mean boxing the error.
+*/
+use crate::key_policy::check_key_bits;
+use crate::montgomery::{crt_combine_into, is_below, Limb, Modulus};
+use crate::rsa::{read_exact, MAX_RSA_MODULUS_LEN, MAX_RSA_PRIME_LEN, MIN_RSA_MODULUS_BITS};
+use crate::wipe::{Redacted, SecretArray, Wipe, Wiped};
+use crate::RSAError;
+
+pub const FIXED_MODULUS_BITS: u32 = 512;
+pub const FIXED_MODULUS_LEN: usize = FIXED_MODULUS_BITS.div_ceil(8) as usize;
+
+const LIMBS: usize = FIXED_MODULUS_LEN / 8;
+const PRIME_LIMBS: usize = LIMBS / 2;
+
+const PUBLIC_KEY_LEN: usize = 4 + 2 * MAX_RSA_MODULUS_LEN;
+const PRIVATE_KEY_LEN: usize = 4 + 3 * MAX_RSA_MODULUS_LEN + 5 * MAX_RSA_PRIME_LEN;
+
+/* The big-endian bytes as N limbs, or None if they do not fit. */
+fn limbs_from_be<const N: usize>(bytes: &[u8]) -> Option<[Limb; N]> {
+    let mut limbs = [0; N];
+    let mut overflow = 0;
+    for (i, byte) in bytes.iter().rev().enumerate() {
+        match limbs.get_mut(i / 8) {
+            Some(limb) => *limb |= (*byte as Limb) << (8 * (i % 8)),
+            None => overflow |= *byte,
+        }
+    }
+    (overflow == 0).then_some(limbs)
+}
+
+/* limbs as big-endian bytes filling output, zero-padded at the front. */
+fn limbs_to_be(limbs: &[Limb], output: &mut [u8]) {
+    for (i, byte) in output.iter_mut().rev().enumerate() {
+        *byte = limbs
+            .get(i / 8)
+            .map_or(0, |limb| (limb >> (8 * (i % 8))) as u8);
+    }
+}
+
+/* The next field of an encoded key, which must fit in N limbs. */
+fn read_field<const N: usize, const LEN: usize>(reader: &mut &[u8]) -> Result<[Limb; N], RSAError> {
+    let mut buf: SecretArray<LEN> = Wiped([0u8; LEN]);
+    read_exact(reader, buf.as_mut_slice())?;
+    limbs_from_be(buf.as_slice()).ok_or(RSAError::ModulusLen)
+}
+
+/* The same checks as RSAPublicKey::decode, with 512 bits as the limit. */
+fn read_bits_and_modulus(reader: &mut &[u8]) -> Result<(u32, [Limb; LIMBS]), RSAError> {
+    let mut bits_buf = [0u8; 4];
+    read_exact(reader, &mut bits_buf)?;
+    let bits = u32::from_le_bytes(bits_buf);
+    let modulus = read_field::<LIMBS, MAX_RSA_MODULUS_LEN>(reader)?;
+    let modulus_bits = modulus
+        .iter()
+        .enumerate()
+        .rfind(|(_, limb)| **limb != 0)
+        .map_or(0, |(i, limb)| 64 * (i as u32 + 1) - limb.leading_zeros());
+    if !(MIN_RSA_MODULUS_BITS as u32..=FIXED_MODULUS_BITS).contains(&bits) || modulus_bits > bits {
+        return Err(RSAError::ModulusLen);
+    }
+    check_key_bits(bits)?;
+    Ok((bits, modulus))
+}
+
+/* input as a number below modulus, or Data. */
+fn block_input(input: &[u8], modulus: &[Limb]) -> Result<[Limb; LIMBS], RSAError> {
+    match limbs_from_be::<LIMBS>(input) {
+        Some(value) if is_below(&value, modulus) => Ok(value),
+        _ => Err(RSAError::Data),
+    }
+}
+
+/* Montgomery arithmetic needs an odd modulus; RSA moduli and primes
+   always are.
+*/
+fn is_odd(limbs: &[Limb]) -> bool {
+    limbs.first().is_some_and(|low| low & 1 == 1)
+}
+
+#[derive(Clone)]
+pub struct RSAPublicKey512 {
+    bits: u32,
+    modulus: Modulus<[Limb; LIMBS]>,
+    exponent: [Limb; LIMBS],
+}
+
+impl RSAPublicKey512 {
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn encode(&self) -> [u8; PUBLIC_KEY_LEN] {
+        let mut result = [0u8; PUBLIC_KEY_LEN];
+        let (bits, rest) = result.split_at_mut(4);
+        let (modulus, exponent) = rest.split_at_mut(MAX_RSA_MODULUS_LEN);
+        bits.copy_from_slice(&self.bits.to_le_bytes());
+        limbs_to_be(self.modulus.limbs(), modulus);
+        limbs_to_be(&self.exponent, exponent);
+        result
+    }
+
+    /* Fails with ModulusLen for a key of more than 512 bits. */
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        if data.len() < PUBLIC_KEY_LEN {
+            return Err(RSAError::InputLen {
+                expected: PUBLIC_KEY_LEN,
+                actual: data.len(),
+            });
+        }
+
+        let mut reader = data;
+        let (bits, modulus) = read_bits_and_modulus(&mut reader)?;
+        let exponent = read_field::<LIMBS, MAX_RSA_MODULUS_LEN>(&mut reader)?;
+        if !is_odd(&modulus) {
+            return Err(RSAError::PublicKey);
+        }
+
+        Ok(Self {
+            bits,
+            modulus: Modulus::from_limbs(modulus),
+            exponent,
+        })
+    }
+
+    /* input^e mod n, as RSAPublicKey's raw block. */
+    pub fn rsa_public_block(&self, input: &[u8]) -> Result<[u8; FIXED_MODULUS_LEN], RSAError> {
+        check_key_bits(self.bits)?;
+        let m = Wiped(block_input(input, self.modulus.limbs())?);
+
+        /* The exponent is public, so only its significant limbs are used. */
+        let exponent_len = self
+            .exponent
+            .iter()
+            .rposition(|limb| *limb != 0)
+            .map_or(0, |i| i + 1);
+        let exponent = self.exponent.get(..exponent_len).unwrap_or(&[]);
+
+        let mut output = [0u8; FIXED_MODULUS_LEN];
+        limbs_to_be(&self.modulus.pow(m.as_slice(), exponent), &mut output);
+        Ok(output)
+    }
+}
+
+/* Shows the size only. */
+impl core::fmt::Debug for RSAPublicKey512 {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("RSAPublicKey512")
+            .field("bits", &self.bits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone)]
+pub struct RSAPrivateKey512 {
+    bits: u32,
+    modulus: [Limb; LIMBS],
+    public_exponent: [Limb; LIMBS],
+    exponent: [Limb; LIMBS],
+    p: Modulus<[Limb; PRIME_LIMBS]>,
+    q: Modulus<[Limb; PRIME_LIMBS]>,
+    dp: [Limb; PRIME_LIMBS],
+    dq: [Limb; PRIME_LIMBS],
+    coefficient: [Limb; PRIME_LIMBS],
+    /* coefficient reduced mod p */
+    qinv: [Limb; PRIME_LIMBS],
+}
+
+impl RSAPrivateKey512 {
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn public_key(&self) -> RSAPublicKey512 {
+        RSAPublicKey512 {
+            bits: self.bits,
+            modulus: Modulus::from_limbs(self.modulus),
+            exponent: self.public_exponent,
+        }
+    }
+
+    /* Writes the encoding into output, which the caller owns and should
+       wipe.
+    */
+    pub fn encode_into(&self, output: &mut [u8; PRIVATE_KEY_LEN]) {
+        let (bits, mut rest) = output.split_at_mut(4);
+        bits.copy_from_slice(&self.bits.to_le_bytes());
+        for value in [&self.modulus, &self.public_exponent, &self.exponent] {
+            let (field, after) = rest.split_at_mut(MAX_RSA_MODULUS_LEN);
+            limbs_to_be(value, field);
+            rest = after;
+        }
+        for value in [
+            self.p.limbs(),
+            self.q.limbs(),
+            &self.dp,
+            &self.dq,
+            &self.coefficient,
+        ] {
+            let (field, after) = rest.split_at_mut(MAX_RSA_PRIME_LEN);
+            limbs_to_be(value, field);
+            rest = after;
+        }
+    }
+
+    /* Fails with ModulusLen for a key of more than 512 bits or with a
+       prime of more than 256, and with PrivateKey for an even prime.
+    */
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        if data.len() < PRIVATE_KEY_LEN {
+            return Err(RSAError::InputLen {
+                expected: PRIVATE_KEY_LEN,
+                actual: data.len(),
+            });
+        }
+
+        let mut reader = data;
+        let (bits, modulus) = read_bits_and_modulus(&mut reader)?;
+        let public_exponent = read_field::<LIMBS, MAX_RSA_MODULUS_LEN>(&mut reader)?;
+        let exponent = read_field::<LIMBS, MAX_RSA_MODULUS_LEN>(&mut reader)?;
+        let prime = read_field::<PRIME_LIMBS, MAX_RSA_PRIME_LEN>;
+        let (p, q) = (prime(&mut reader)?, prime(&mut reader)?);
+        let (dp, dq) = (prime(&mut reader)?, prime(&mut reader)?);
+        let coefficient = prime(&mut reader)?;
+        if !is_odd(&p) || !is_odd(&q) {
+            return Err(RSAError::PrivateKey);
+        }
+
+        let p = Modulus::from_limbs(p);
+        Ok(Self {
+            bits,
+            modulus,
+            public_exponent,
+            exponent,
+            qinv: p.reduce(&coefficient),
+            p,
+            q: Modulus::from_limbs(q),
+            dp,
+            dq,
+            coefficient,
+        })
+    }
+
+    /* input^d mod n by CRT, as RSAPrivateKey's raw block. */
+    pub fn rsa_private_block(&self, input: &[u8]) -> Result<[u8; FIXED_MODULUS_LEN], RSAError> {
+        check_key_bits(self.bits)?;
+        let c = Wiped(block_input(input, &self.modulus)?);
+
+        let cp = Wiped(self.p.reduce(c.as_slice()));
+        let cq = Wiped(self.q.reduce(c.as_slice()));
+        let mp = Wiped(self.p.pow(cp.as_slice(), &self.dp));
+        let mq = Wiped(self.q.pow(cq.as_slice(), &self.dq));
+
+        /* Chinese Remainder Theorem:
+        m = ((((mP - mQ) mod p) * qInv) mod p) * q + mQ.
+        */
+        let mut m = Wiped([0; LIMBS]);
+        crt_combine_into(
+            &self.p,
+            &self.qinv,
+            self.q.limbs(),
+            mp.as_slice(),
+            mq.as_slice(),
+            &mut m.0,
+        );
+
+        let mut output = [0u8; FIXED_MODULUS_LEN];
+        limbs_to_be(m.as_slice(), &mut output);
+        Ok(output)
+    }
+}
+
+impl Drop for RSAPrivateKey512 {
+    fn drop(&mut self) {
+        self.exponent.wipe();
+        for value in [
+            &mut self.dp,
+            &mut self.dq,
+            &mut self.coefficient,
+            &mut self.qinv,
+        ] {
+            value.wipe();
+        }
+    }
+}
+
+/* Never shows the private components. */
+impl core::fmt::Debug for RSAPrivateKey512 {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("RSAPrivateKey512")
+            .field("bits", &self.bits)
+            .field("exponent", &Redacted)
+            .field("prime", &Redacted)
+            .field("prime_exponent", &Redacted)
+            .field("coefficient", &Redacted)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_key_pair;
+
+    #[test]
+    fn test_fixed_keys_match_heap_keys() {
+        let (public_key, private_key) = test_key_pair(512);
+        let encoded = private_key.encode();
+        let fixed_public = RSAPublicKey512::decode(&public_key.encode()).unwrap();
+        let fixed_private = RSAPrivateKey512::decode(&encoded).unwrap();
+        assert_eq!(fixed_public.bits(), 512);
+        assert_eq!(&fixed_public.encode()[..], &public_key.encode()[..]);
+        assert_eq!(
+            &fixed_private.public_key().encode()[..],
+            &public_key.encode()[..]
+        );
+        let mut fixed_encoded = [0u8; PRIVATE_KEY_LEN];
+        fixed_private.encode_into(&mut fixed_encoded);
+        assert_eq!(&fixed_encoded[..], &encoded[..]);
+
+        for input in [&[0x01u8, 0x02, 0x03][..], &[0x5au8; 63], &[0u8; 64]] {
+            let signature = fixed_private.rsa_private_block(input).unwrap();
+            assert_eq!(
+                &signature[..],
+                &private_key.rsa_private_block(input).unwrap()[..]
+            );
+            assert_eq!(
+                &fixed_public.rsa_public_block(&signature).unwrap()[..],
+                &public_key.rsa_public_block(&signature).unwrap()[..]
+            );
+        }
+
+        assert_eq!(
+            fixed_public.rsa_public_block(&[0xff; 64]).err(),
+            Some(RSAError::Data)
+        );
+        assert_eq!(
+            fixed_private.rsa_private_block(&[0x01; 65]).err(),
+            Some(RSAError::Data)
+        );
+        assert!(format!("{:?}", fixed_private).contains("exponent: <redacted>"));
+    }
+
+    #[test]
+    fn test_fixed_key_decode_errors() {
+        let (public_key, private_key) = test_key_pair(1024);
+        assert_eq!(
+            RSAPublicKey512::decode(&public_key.encode()).err(),
+            Some(RSAError::ModulusLen)
+        );
+        assert_eq!(
+            RSAPrivateKey512::decode(&private_key.encode()).err(),
+            Some(RSAError::ModulusLen)
+        );
+
+        let (public_key, private_key) = test_key_pair(512);
+        assert_eq!(
+            RSAPublicKey512::decode(&public_key.encode()[..259]).err(),
+            Some(RSAError::InputLen {
+                expected: 260,
+                actual: 259,
+            })
+        );
+        let mut even_prime = private_key.encode();
+        even_prime[4 + 3 * MAX_RSA_MODULUS_LEN + MAX_RSA_PRIME_LEN - 1] ^= 1;
+        assert_eq!(
+            RSAPrivateKey512::decode(&even_prime).err(),
+            Some(RSAError::PrivateKey)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+/* Usage accounting for private keys. Every RSAPrivateKey counts its
+   signatures, decryptions and raw block operations, and may carry a
+   PrivateKeyAudit hook that hears of each one as it completes, through
+   the key itself or through a ProfiledKey over it. Events hold the
+   operation, the key size and byte counts, never inputs, outputs or key
+   material.
+
+   Keys decoded from an encoding start with zero counts and no hook, so a
+   LockedPrivateKey, which decodes its key for each operation, is not
+   counted. A cloned key keeps the hook but counts from zero; holders of
+   a SharedPrivateKey share one set of counters.
+*/
+use crate::RSAError;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivateKeyOperation {
+    /* PKCS #1 v1.5 block type 1, rsa_private_encrypt. */
+    Sign,
+    /* PKCS #1 v1.5 block type 2, rsa_private_decrypt. */
+    Decrypt,
+    /* rsa_private_block called directly, unpadded. */
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrivateKeyEvent {
+    operation: PrivateKeyOperation,
+    bits: u32,
+    input_len: usize,
+    output_len: Option<usize>,
+}
+
+impl PrivateKeyEvent {
+    pub fn operation(&self) -> PrivateKeyOperation {
+        self.operation
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    /* None if the operation failed. */
+    pub fn output_len(&self) -> Option<usize> {
+        self.output_len
+    }
+}
+
+/* Called after every private-key operation, on the thread that did it.
+   record must not panic; it may be called concurrently when the key is
+   shared.
+*/
+pub trait PrivateKeyAudit: Send + Sync {
+    fn record(&self, event: &PrivateKeyEvent);
+}
+
+/* A snapshot of a key's counters. Failed operations are counted under
+   failures only, and the byte totals cover successful ones.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PrivateKeyUsage {
+    pub signatures: usize,
+    pub decryptions: usize,
+    pub blocks: usize,
+    pub failures: usize,
+    pub input_bytes: usize,
+    pub output_bytes: usize,
+}
+
+/* Counters wrap rather than saturate; usize is the widest atomic every
+   target has.
+*/
+#[derive(Debug, Default)]
+pub(crate) struct UsageCounters {
+    signatures: AtomicUsize,
+    decryptions: AtomicUsize,
+    blocks: AtomicUsize,
+    failures: AtomicUsize,
+    input_bytes: AtomicUsize,
+    output_bytes: AtomicUsize,
+}
+
+impl UsageCounters {
+    fn count(&self, event: &PrivateKeyEvent) {
+        let Some(output_len) = event.output_len else {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        match event.operation {
+            PrivateKeyOperation::Sign => &self.signatures,
+            PrivateKeyOperation::Decrypt => &self.decryptions,
+            PrivateKeyOperation::Block => &self.blocks,
+        }
+        .fetch_add(1, Ordering::Relaxed);
+        self.input_bytes
+            .fetch_add(event.input_len, Ordering::Relaxed);
+        self.output_bytes.fetch_add(output_len, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> PrivateKeyUsage {
+        PrivateKeyUsage {
+            signatures: self.signatures.load(Ordering::Relaxed),
+            decryptions: self.decryptions.load(Ordering::Relaxed),
+            blocks: self.blocks.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            input_bytes: self.input_bytes.load(Ordering::Relaxed),
+            output_bytes: self.output_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/* The hook and counters an RSAPrivateKey carries. */
+#[derive(Default)]
+pub(crate) struct KeyAudit {
+    hook: Option<Arc<dyn PrivateKeyAudit>>,
+    counters: UsageCounters,
+}
+
+impl Clone for KeyAudit {
+    fn clone(&self) -> Self {
+        Self {
+            hook: self.hook.clone(),
+            counters: UsageCounters::default(),
+        }
+    }
+}
+
+impl KeyAudit {
+    pub(crate) fn set_hook(&mut self, hook: Option<Arc<dyn PrivateKeyAudit>>) {
+        self.hook = hook;
+    }
+
+    pub(crate) fn usage(&self) -> PrivateKeyUsage {
+        self.counters.snapshot()
+    }
+
+    /* Runs operation and records its outcome. */
+    pub(crate) fn audited(
+        &self,
+        operation: PrivateKeyOperation,
+        bits: u32,
+        input_len: usize,
+        f: impl FnOnce() -> Result<Vec<u8>, RSAError>,
+    ) -> Result<Vec<u8>, RSAError> {
+        let result = f();
+        let event = PrivateKeyEvent {
+            operation,
+            bits,
+            input_len,
+            output_len: result.as_ref().ok().map(Vec::len),
+        };
+        self.counters.count(&event);
+        if let Some(hook) = &self.hook {
+            hook.record(&event);
+        }
+        result
+    }
+}
+
+/* Shows whether a hook is set, not the hook. */
+impl core::fmt::Debug for KeyAudit {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("KeyAudit")
+            .field("hook", &self.hook.is_some())
+            .field("usage", &self.usage())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::Profile;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use crate::rsa::PrivateKeyOps;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<PrivateKeyEvent>>);
+
+    impl PrivateKeyAudit for Recorder {
+        fn record(&self, event: &PrivateKeyEvent) {
+            self.0.lock().unwrap().push(*event);
+        }
+    }
+
+    #[test]
+    fn test_audit_hook_and_counters() {
+        let (public_key, mut private_key) = test_key_pair(512);
+        let recorder = Arc::new(Recorder::default());
+        private_key.set_audit(Some(recorder.clone()));
+
+        let signature = private_key.rsa_private_encrypt(b"audited").unwrap();
+        let mut random_struct = test_random_struct(0x17);
+        let encrypted = public_key
+            .rsa_public_encrypt(b"audited", &mut random_struct)
+            .unwrap();
+        let key: &dyn PrivateKeyOps = &private_key;
+        assert_eq!(key.rsa_private_decrypt(&encrypted).unwrap(), b"audited");
+        assert!(private_key.rsa_private_decrypt(&signature).is_err());
+        private_key.rsa_private_block(&[1]).unwrap();
+        Profile::Legacy
+            .private_key(&private_key)
+            .unwrap()
+            .rsa_private_encrypt(b"profiled")
+            .unwrap();
+
+        let event = |operation, input_len, output_len| PrivateKeyEvent {
+            operation,
+            bits: 512,
+            input_len,
+            output_len,
+        };
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                event(PrivateKeyOperation::Sign, 7, Some(64)),
+                event(PrivateKeyOperation::Decrypt, 64, Some(7)),
+                event(PrivateKeyOperation::Decrypt, 64, None),
+                event(PrivateKeyOperation::Block, 1, Some(64)),
+                event(PrivateKeyOperation::Sign, 8, Some(64)),
+            ]
+        );
+        assert_eq!(
+            private_key.usage(),
+            PrivateKeyUsage {
+                signatures: 2,
+                decryptions: 1,
+                blocks: 1,
+                failures: 1,
+                input_bytes: 7 + 64 + 1 + 8,
+                output_bytes: 64 + 7 + 64 + 64,
+            }
+        );
+
+        /* Counting goes on without a hook; the hook sees nothing more. */
+        private_key.set_audit(None);
+        private_key.rsa_private_encrypt(b"unheard").unwrap();
+        assert_eq!(private_key.usage().signatures, 3);
+        assert_eq!(recorder.0.lock().unwrap().len(), 5);
+        assert!(format!("{:?}", private_key).contains("hook: false"));
+    }
+}
@@ -0,0 +1,334 @@
+/* A self-describing wrapper around the raw 260- and 708-byte key blobs:
+     magic       "RSAK"
+     version     1 byte
+     key type    1 byte, 1 public or 2 private
+     max bits    2 bytes, MAX_RSA_MODULUS_BITS of the build that wrote it
+     bits        4 bytes, the key's modulus bits
+     payload len 4 bytes
+     payload     the raw blob, laid out for max bits
+     CRC-32      4 bytes, over everything before it
+   Integers are big-endian. The CRC catches corruption and truncation, not
+   tampering; keystore encrypts and authenticates keys at rest.
+*/
+use crate::rsa::{RSAPrivateKey, RSAPublicKey, MAX_RSA_MODULUS_BITS};
+use crate::wipe::Wiped;
+use crate::RSAError;
+use alloc::vec::Vec;
+
+const CONTAINER_MAGIC: &[u8; 4] = b"RSAK";
+const CONTAINER_VERSION: u8 = 1;
+const HEADER_LEN: usize = 16;
+const CRC_LEN: usize = 4;
+
+const PUBLIC_KEY_LEN: usize = 260;
+const PRIVATE_KEY_LEN: usize = 708;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKeyType {
+    Public = 1,
+    Private = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyContainerError {
+    /* The input does not start with the container magic. */
+    NotContainer,
+    Truncated {
+        expected: usize,
+        actual: usize,
+    },
+    /* The checksum does not match, or the header disagrees with itself. */
+    Corrupt,
+    /* A version or key type this build does not know. */
+    Unsupported,
+    WrongKeyType {
+        expected: ContainerKeyType,
+        found: ContainerKeyType,
+    },
+    /* Written by a build with another MAX_RSA_MODULUS_BITS. */
+    MaxModulusBits(u16),
+    /* The payload is intact but is not a usable key. */
+    Key(RSAError),
+}
+
+impl core::fmt::Display for KeyContainerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            KeyContainerError::NotContainer => f.write_str("input is not a key container"),
+            KeyContainerError::Truncated { expected, actual } => {
+                write!(f, "key container is {} bytes, need {}", actual, expected)
+            }
+            KeyContainerError::Corrupt => f.write_str("key container is corrupt"),
+            KeyContainerError::Unsupported => f.write_str("key container version is unsupported"),
+            KeyContainerError::WrongKeyType { expected, found } => {
+                write!(
+                    f,
+                    "key container holds a {:?} key, need {:?}",
+                    found, expected
+                )
+            }
+            KeyContainerError::MaxModulusBits(bits) => {
+                write!(f, "key container is laid out for {}-bit moduli", bits)
+            }
+            KeyContainerError::Key(error) => {
+                write!(f, "key container payload is unusable: {}", error)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyContainerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KeyContainerError::Key(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<RSAError> for KeyContainerError {
+    fn from(error: RSAError) -> Self {
+        KeyContainerError::Key(error)
+    }
+}
+
+/* The reflected CRC-32 of ISO-HDLC, as zip and PNG use. */
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn encode_container(key_type: ContainerKeyType, bits: u32, payload: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(HEADER_LEN + payload.len() + CRC_LEN);
+    result.extend_from_slice(CONTAINER_MAGIC);
+    result.push(CONTAINER_VERSION);
+    result.push(key_type as u8);
+    result.extend_from_slice(&(MAX_RSA_MODULUS_BITS as u16).to_be_bytes());
+    result.extend_from_slice(&bits.to_be_bytes());
+    result.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    result.extend_from_slice(payload);
+    let crc = crc32(&result);
+    result.extend_from_slice(&crc.to_be_bytes());
+    result
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Result<u32, KeyContainerError> {
+    data.get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+        .ok_or(KeyContainerError::Corrupt)
+}
+
+/* The type of key data holds, once its framing and checksum are good. */
+pub fn key_container_type(data: &[u8]) -> Result<ContainerKeyType, KeyContainerError> {
+    container_payload(data).map(|(key_type, _, _)| key_type)
+}
+
+fn container_payload(data: &[u8]) -> Result<(ContainerKeyType, u32, &[u8]), KeyContainerError> {
+    let magic_len = data.len().min(CONTAINER_MAGIC.len());
+    if data.get(..magic_len) != CONTAINER_MAGIC.get(..magic_len) {
+        return Err(KeyContainerError::NotContainer);
+    }
+    if data.len() < HEADER_LEN {
+        return Err(KeyContainerError::Truncated {
+            expected: HEADER_LEN + CRC_LEN,
+            actual: data.len(),
+        });
+    }
+    let payload_len = be_u32(data, 12)? as usize;
+    let total_len = HEADER_LEN
+        .checked_add(payload_len)
+        .and_then(|len| len.checked_add(CRC_LEN))
+        .ok_or(KeyContainerError::Corrupt)?;
+    if data.len() < total_len {
+        return Err(KeyContainerError::Truncated {
+            expected: total_len,
+            actual: data.len(),
+        });
+    }
+    let checked = data
+        .get(..total_len - CRC_LEN)
+        .ok_or(KeyContainerError::Corrupt)?;
+    if data.len() != total_len || crc32(checked) != be_u32(data, total_len - CRC_LEN)? {
+        return Err(KeyContainerError::Corrupt);
+    }
+
+    let header = checked.get(4..8).ok_or(KeyContainerError::Corrupt)?;
+    let key_type = match header {
+        [CONTAINER_VERSION, 1, ..] => ContainerKeyType::Public,
+        [CONTAINER_VERSION, 2, ..] => ContainerKeyType::Private,
+        _ => return Err(KeyContainerError::Unsupported),
+    };
+    let max_bits = match header {
+        [_, _, high, low] => u16::from_be_bytes([*high, *low]),
+        _ => return Err(KeyContainerError::Corrupt),
+    };
+    if max_bits as usize != MAX_RSA_MODULUS_BITS {
+        return Err(KeyContainerError::MaxModulusBits(max_bits));
+    }
+    let bits = be_u32(data, 8)?;
+    let payload = checked
+        .get(HEADER_LEN..)
+        .ok_or(KeyContainerError::Corrupt)?;
+    Ok((key_type, bits, payload))
+}
+
+fn expect_payload(
+    data: &[u8],
+    expected: ContainerKeyType,
+    payload_len: usize,
+) -> Result<(u32, &[u8]), KeyContainerError> {
+    let (found, bits, payload) = container_payload(data)?;
+    if found != expected {
+        return Err(KeyContainerError::WrongKeyType { expected, found });
+    }
+    if payload.len() != payload_len {
+        return Err(KeyContainerError::Corrupt);
+    }
+    Ok((bits, payload))
+}
+
+pub fn encode_public_key_container(public_key: &RSAPublicKey) -> Vec<u8> {
+    encode_container(
+        ContainerKeyType::Public,
+        public_key.bits(),
+        &public_key.encode(),
+    )
+}
+
+pub fn encode_private_key_container(private_key: &RSAPrivateKey) -> Vec<u8> {
+    let payload = Wiped(private_key.encode());
+    encode_container(ContainerKeyType::Private, private_key.bits(), &payload)
+}
+
+pub fn decode_public_key_container(data: &[u8]) -> Result<RSAPublicKey, KeyContainerError> {
+    let (bits, payload) = expect_payload(data, ContainerKeyType::Public, PUBLIC_KEY_LEN)?;
+    let public_key = RSAPublicKey::decode(payload)?;
+    if public_key.bits() != bits {
+        return Err(KeyContainerError::Corrupt);
+    }
+    Ok(public_key)
+}
+
+pub fn decode_private_key_container(data: &[u8]) -> Result<RSAPrivateKey, KeyContainerError> {
+    let (bits, payload) = expect_payload(data, ContainerKeyType::Private, PRIVATE_KEY_LEN)?;
+    let private_key = RSAPrivateKey::decode(payload)?;
+    if private_key.bits() != bits {
+        return Err(KeyContainerError::Corrupt);
+    }
+    Ok(private_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_key_pair;
+
+    fn reseal(container: &mut Vec<u8>) {
+        let body_len = container.len() - CRC_LEN;
+        container.truncate(body_len);
+        let crc = crc32(container);
+        container.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_key_container() {
+        let (public_key, private_key) = test_key_pair(512);
+        let public_container = encode_public_key_container(&public_key);
+        let private_container = encode_private_key_container(&private_key);
+        assert_eq!(
+            public_container.len(),
+            HEADER_LEN + PUBLIC_KEY_LEN + CRC_LEN
+        );
+        assert_eq!(
+            key_container_type(&private_container),
+            Ok(ContainerKeyType::Private)
+        );
+        assert_eq!(
+            decode_public_key_container(&public_container)
+                .unwrap()
+                .encode(),
+            public_key.encode()
+        );
+        assert_eq!(
+            decode_private_key_container(&private_container)
+                .unwrap()
+                .encode(),
+            private_key.encode()
+        );
+
+        assert_eq!(
+            decode_private_key_container(&public_container).err(),
+            Some(KeyContainerError::WrongKeyType {
+                expected: ContainerKeyType::Private,
+                found: ContainerKeyType::Public,
+            })
+        );
+        assert_eq!(
+            decode_public_key_container(&public_key.encode()).err(),
+            Some(KeyContainerError::NotContainer)
+        );
+        assert_eq!(
+            decode_public_key_container(&public_container[..100]).err(),
+            Some(KeyContainerError::Truncated {
+                expected: public_container.len(),
+                actual: 100,
+            })
+        );
+        assert_eq!(
+            decode_public_key_container(&public_container[..2]).err(),
+            Some(KeyContainerError::Truncated {
+                expected: HEADER_LEN + CRC_LEN,
+                actual: 2,
+            })
+        );
+
+        let mut corrupt = public_container.clone();
+        corrupt[40] ^= 1;
+        assert_eq!(
+            decode_public_key_container(&corrupt).err(),
+            Some(KeyContainerError::Corrupt)
+        );
+        let mut trailing = public_container.clone();
+        trailing.push(0);
+        assert_eq!(
+            decode_public_key_container(&trailing).err(),
+            Some(KeyContainerError::Corrupt)
+        );
+
+        let mut other_build = public_container.clone();
+        other_build[6..8].copy_from_slice(&2048u16.to_be_bytes());
+        reseal(&mut other_build);
+        assert_eq!(
+            decode_public_key_container(&other_build).err(),
+            Some(KeyContainerError::MaxModulusBits(2048))
+        );
+        let mut later_version = public_container.clone();
+        later_version[4] = 2;
+        reseal(&mut later_version);
+        assert_eq!(
+            key_container_type(&later_version),
+            Err(KeyContainerError::Unsupported)
+        );
+        let mut wrong_bits = public_container;
+        wrong_bits[8..12].copy_from_slice(&1024u32.to_be_bytes());
+        reseal(&mut wrong_bits);
+        assert_eq!(
+            decode_public_key_container(&wrong_bits).err(),
+            Some(KeyContainerError::Corrupt)
+        );
+    }
+}
@@ -0,0 +1,42 @@
+/* A process-wide floor on RSA key sizes. It is checked when keys are
+   generated and decoded, and by the block routines for keys built from
+   components. A key below it fails with ModulusLen.
+
+   The default is RSAREF's own minimum, as everything this crate exists
+   to talk to uses keys of at most MAX_RSA_MODULUS_BITS. Applications that
+   only need the crate's formats can raise the floor once at startup, up
+   to that maximum, and allow_legacy_key_sizes puts it back.
+*/
+use crate::rsa::{MAX_RSA_MODULUS_BITS, MIN_RSA_MODULUS_BITS};
+use crate::RSAError;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+static MIN_KEY_BITS: AtomicU32 = AtomicU32::new(MIN_RSA_MODULUS_BITS as u32);
+
+pub fn minimum_key_bits() -> u32 {
+    MIN_KEY_BITS.load(Ordering::Relaxed)
+}
+
+/* Fails with ModulusLen, leaving the floor as it was, if bits is below
+   RSAREF's minimum or above the largest key the crate handles, which
+   would refuse every key.
+*/
+pub fn set_minimum_key_bits(bits: u32) -> Result<(), RSAError> {
+    if !(MIN_RSA_MODULUS_BITS..=MAX_RSA_MODULUS_BITS).contains(&(bits as usize)) {
+        return Err(RSAError::ModulusLen);
+    }
+    MIN_KEY_BITS.store(bits, Ordering::Relaxed);
+    Ok(())
+}
+
+/* Back to RSAREF's minimum, for interoperating with legacy peers. */
+pub fn allow_legacy_key_sizes() {
+    MIN_KEY_BITS.store(MIN_RSA_MODULUS_BITS as u32, Ordering::Relaxed);
+}
+
+pub(crate) fn check_key_bits(bits: u32) -> Result<(), RSAError> {
+    if bits < minimum_key_bits() {
+        return Err(RSAError::ModulusLen);
+    }
+    Ok(())
+}
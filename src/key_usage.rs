@@ -0,0 +1,430 @@
+/* Keys restricted to one use. RSAPrivateKey and RSAPublicKey offer both
+   PKCS #1 v1.5 block types, and rsa_private_encrypt (signing) is easily
+   mistaken for rsa_private_decrypt. A SigningKey can only sign, a
+   DecryptionKey only decrypt, and likewise for the public halves. Keys
+   are wrapped and unwrapped explicitly with From and into_inner.
+
+   What passes between them is typed as well: EncryptionKey takes a
+   PlaintextBlock and gives a CiphertextBlock, DecryptionKey the reverse,
+   and SigningKey gives a Signature that only VerifyingKey takes. Each
+   carries the modulus length of the key it was made for and is refused
+   by a key of another size.
+*/
+use crate::constant_time::ct_eq;
+use crate::digest_info::{encode_digest_info, DigestAlg};
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAPublicKey};
+use crate::wipe::SecretBuffer;
+use crate::RSAError;
+use alloc::vec::Vec;
+
+#[derive(Debug)]
+pub struct SigningKey(RSAPrivateKey);
+
+#[derive(Debug)]
+pub struct DecryptionKey(RSAPrivateKey);
+
+#[derive(Debug)]
+pub struct VerifyingKey(RSAPublicKey);
+
+#[derive(Debug)]
+pub struct EncryptionKey(RSAPublicKey);
+
+/* At most the modulus length less 11 bytes, to be encrypted in one block,
+   or the result of decrypting one. Wiped on drop like SecretBuffer.
+*/
+#[derive(Debug)]
+pub struct PlaintextBlock {
+    data: SecretBuffer,
+    modulus_len: usize,
+}
+
+/* Exactly the modulus length. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiphertextBlock {
+    data: Vec<u8>,
+    modulus_len: usize,
+}
+
+/* Exactly the modulus length. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    data: Vec<u8>,
+    modulus_len: usize,
+}
+
+fn modulus_len(bits: u32) -> usize {
+    bits.div_ceil(8) as usize
+}
+
+impl PlaintextBlock {
+    pub fn new(data: &[u8], encryption_key: &EncryptionKey) -> Result<Self, RSAError> {
+        let modulus_len = modulus_len(encryption_key.bits());
+        if data.len() + 11 > modulus_len {
+            return Err(RSAError::Len);
+        }
+        Ok(Self {
+            data: data.to_vec().into(),
+            modulus_len,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn modulus_len(&self) -> usize {
+        self.modulus_len
+    }
+}
+
+impl CiphertextBlock {
+    /* A block received for decryption_key; fails with Len unless it is
+       the key's modulus length.
+    */
+    pub fn from_bytes(data: &[u8], decryption_key: &DecryptionKey) -> Result<Self, RSAError> {
+        let modulus_len = modulus_len(decryption_key.bits());
+        if data.len() != modulus_len {
+            return Err(RSAError::Len);
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            modulus_len,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn modulus_len(&self) -> usize {
+        self.modulus_len
+    }
+}
+
+impl Signature {
+    /* A signature received for verifying_key; fails with Signature unless
+       it is the key's modulus length.
+    */
+    pub fn from_bytes(data: &[u8], verifying_key: &VerifyingKey) -> Result<Self, RSAError> {
+        let modulus_len = modulus_len(verifying_key.bits());
+        if data.len() != modulus_len {
+            return Err(RSAError::Signature);
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            modulus_len,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn modulus_len(&self) -> usize {
+        self.modulus_len
+    }
+}
+
+impl From<RSAPrivateKey> for SigningKey {
+    fn from(private_key: RSAPrivateKey) -> Self {
+        Self(private_key)
+    }
+}
+
+impl From<RSAPrivateKey> for DecryptionKey {
+    fn from(private_key: RSAPrivateKey) -> Self {
+        Self(private_key)
+    }
+}
+
+impl From<RSAPublicKey> for VerifyingKey {
+    fn from(public_key: RSAPublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+impl From<RSAPublicKey> for EncryptionKey {
+    fn from(public_key: RSAPublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+impl SigningKey {
+    pub fn into_inner(self) -> RSAPrivateKey {
+        self.0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0.bits()
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.public_key())
+    }
+
+    /* PKCS #1 v1.5 signature on the digest of data. */
+    pub fn sign(&self, data: &[u8], digest_alg: DigestAlg) -> Result<Signature, RSAError> {
+        self.sign_digest_info(&encode_digest_info(digest_alg, &digest_alg.digest(data))?)
+    }
+
+    /* Signs an already encoded DigestInfo, as R_SignFinal does. */
+    pub fn sign_digest_info(&self, digest_info: &[u8]) -> Result<Signature, RSAError> {
+        Ok(Signature {
+            data: self.0.rsa_private_encrypt(digest_info)?,
+            modulus_len: modulus_len(self.bits()),
+        })
+    }
+}
+
+impl DecryptionKey {
+    pub fn into_inner(self) -> RSAPrivateKey {
+        self.0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0.bits()
+    }
+
+    pub fn encryption_key(&self) -> EncryptionKey {
+        EncryptionKey(self.0.public_key())
+    }
+
+    /* Undoes EncryptionKey::encrypt. A block made for a key of another
+       size fails with Len.
+    */
+    pub fn decrypt(&self, ciphertext: &CiphertextBlock) -> Result<PlaintextBlock, RSAError> {
+        let modulus_len = modulus_len(self.bits());
+        if ciphertext.modulus_len != modulus_len {
+            return Err(RSAError::Len);
+        }
+        Ok(PlaintextBlock {
+            data: self.0.rsa_private_decrypt(&ciphertext.data)?.into(),
+            modulus_len,
+        })
+    }
+}
+
+impl VerifyingKey {
+    pub fn into_inner(self) -> RSAPublicKey {
+        self.0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0.bits()
+    }
+
+    /* Fails with Signature unless signature is SigningKey::sign's output
+       for data under the same digest.
+    */
+    pub fn verify(
+        &self,
+        data: &[u8],
+        signature: &Signature,
+        digest_alg: DigestAlg,
+    ) -> Result<(), RSAError> {
+        self.verify_digest_info(
+            &encode_digest_info(digest_alg, &digest_alg.digest(data))?,
+            signature,
+        )
+    }
+
+    pub fn verify_digest_info(
+        &self,
+        digest_info: &[u8],
+        signature: &Signature,
+    ) -> Result<(), RSAError> {
+        if signature.modulus_len != modulus_len(self.bits()) {
+            return Err(RSAError::Signature);
+        }
+        let recovered = self
+            .0
+            .rsa_public_decrypt(&signature.data)
+            .map_err(|_| RSAError::Signature)?;
+        if !ct_eq(&recovered, digest_info) {
+            return Err(RSAError::Signature);
+        }
+        Ok(())
+    }
+}
+
+impl EncryptionKey {
+    pub fn into_inner(self) -> RSAPublicKey {
+        self.0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0.bits()
+    }
+
+    /* PKCS #1 v1.5 block type 2. A block made for a key of another size
+       fails with Len.
+    */
+    pub fn encrypt(
+        &self,
+        plaintext: &PlaintextBlock,
+        random_struct: &mut RandomStruct,
+    ) -> Result<CiphertextBlock, RSAError> {
+        let modulus_len = modulus_len(self.bits());
+        if plaintext.modulus_len != modulus_len {
+            return Err(RSAError::Len);
+        }
+        Ok(CiphertextBlock {
+            data: self.0.rsa_public_encrypt(&plaintext.data, random_struct)?,
+            modulus_len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_random_struct;
+    use crate::r_keygen::{generate_pem_keys_with_random, test_key_pair};
+    use crate::rsa::RSAProtoKey;
+
+    fn key_pair() -> (RSAPublicKey, RSAPrivateKey) {
+        let proto_key = RSAProtoKey {
+            bits: 512,
+            use_fermat4: true,
+        };
+        generate_pem_keys_with_random(&proto_key, &mut test_random_struct(0x61)).unwrap()
+    }
+
+    #[test]
+    fn test_signing_and_verifying() {
+        let (public_key, private_key) = key_pair();
+        let data = b"scoped keys";
+        let expected = private_key
+            .rsa_private_encrypt(
+                &encode_digest_info(DigestAlg::Md5, &DigestAlg::Md5.digest(data)).unwrap(),
+            )
+            .unwrap();
+
+        let signing_key = SigningKey::from(private_key);
+        let signature = signing_key.sign(data, DigestAlg::Md5).unwrap();
+        assert_eq!(signature.as_bytes(), expected);
+        assert_eq!(signature.modulus_len(), 64);
+
+        let verifying_key = VerifyingKey::from(public_key);
+        assert_eq!(
+            verifying_key.verify(data, &signature, DigestAlg::Md5),
+            Ok(())
+        );
+        assert_eq!(
+            signing_key
+                .verifying_key()
+                .verify(data, &signature, DigestAlg::Md5),
+            Ok(())
+        );
+        assert_eq!(
+            verifying_key.verify(data, &signature, DigestAlg::Sha1),
+            Err(RSAError::Signature)
+        );
+        assert_eq!(
+            verifying_key.verify(b"other data", &signature, DigestAlg::Md5),
+            Err(RSAError::Signature)
+        );
+
+        /* Received bytes must be a whole block. */
+        let received = Signature::from_bytes(signature.as_bytes(), &verifying_key).unwrap();
+        assert_eq!(received, signature);
+        assert_eq!(
+            Signature::from_bytes(&expected[1..], &verifying_key).err(),
+            Some(RSAError::Signature)
+        );
+
+        /* An encryption block is not a signature. */
+        let encrypted = verifying_key
+            .into_inner()
+            .rsa_public_encrypt(data, &mut test_random_struct(0x61))
+            .unwrap();
+        let verifying_key = signing_key.verifying_key();
+        assert_eq!(
+            verifying_key.verify_digest_info(
+                data,
+                &Signature::from_bytes(&encrypted, &verifying_key).unwrap()
+            ),
+            Err(RSAError::Signature)
+        );
+
+        /* Nor is a signature from a key of another size. */
+        let other = SigningKey::from(test_key_pair(768).1);
+        let other_signature = other.sign(data, DigestAlg::Md5).unwrap();
+        assert_eq!(
+            verifying_key.verify(data, &other_signature, DigestAlg::Md5),
+            Err(RSAError::Signature)
+        );
+        assert_eq!(signing_key.into_inner().bits(), 512);
+    }
+
+    #[test]
+    fn test_encryption_and_decryption() {
+        let (public_key, private_key) = key_pair();
+        let encryption_key = EncryptionKey::from(public_key);
+        let decryption_key = DecryptionKey::from(private_key);
+        assert_eq!(encryption_key.bits(), decryption_key.bits());
+
+        let plaintext = PlaintextBlock::new(b"session key", &encryption_key).unwrap();
+        let encrypted = encryption_key
+            .encrypt(&plaintext, &mut test_random_struct(0x61))
+            .unwrap();
+        assert_eq!(encrypted.as_bytes().len(), 64);
+        assert_eq!(
+            decryption_key.decrypt(&encrypted).unwrap().as_bytes(),
+            b"session key"
+        );
+        let encrypted = decryption_key
+            .encryption_key()
+            .encrypt(&plaintext, &mut test_random_struct(0x61))
+            .unwrap();
+        let received = CiphertextBlock::from_bytes(encrypted.as_bytes(), &decryption_key).unwrap();
+        assert_eq!(
+            decryption_key.decrypt(&received).unwrap().as_bytes(),
+            b"session key"
+        );
+
+        assert_eq!(
+            PlaintextBlock::new(&[0; 54], &encryption_key).err(),
+            Some(RSAError::Len)
+        );
+        assert_eq!(
+            CiphertextBlock::from_bytes(&encrypted.clone().into_bytes()[1..], &decryption_key)
+                .err(),
+            Some(RSAError::Len)
+        );
+
+        /* Blocks are tied to the key size they were made for. */
+        let other = EncryptionKey::from(test_key_pair(768).0);
+        let other_plaintext = PlaintextBlock::new(b"session key", &other).unwrap();
+        assert_eq!(
+            encryption_key
+                .encrypt(&other_plaintext, &mut test_random_struct(0x61))
+                .err(),
+            Some(RSAError::Len)
+        );
+        let other_encrypted = other
+            .encrypt(&other_plaintext, &mut test_random_struct(0x61))
+            .unwrap();
+        assert_eq!(
+            decryption_key.decrypt(&other_encrypted).err(),
+            Some(RSAError::Len)
+        );
+
+        /* A signature does not decrypt. */
+        let private_key = decryption_key.into_inner();
+        let signature = private_key.rsa_private_encrypt(b"session key").unwrap();
+        let decryption_key = DecryptionKey::from(private_key);
+        let signature = CiphertextBlock::from_bytes(&signature, &decryption_key).unwrap();
+        assert!(decryption_key.decrypt(&signature).is_err());
+        assert_eq!(encryption_key.into_inner().bits(), 512);
+    }
+}
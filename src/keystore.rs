@@ -0,0 +1,392 @@
+use crate::aes_cbc::{AESCBCContext, AES_BLOCK_LEN};
+use crate::cms::{pad_content, unpad_content};
+use crate::csr::key_fingerprint;
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAPublicKey};
+use crate::wipe::{SecretVec, Wiped};
+use crate::RSAError;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use hmac::Hmac;
+use sha2::Sha256;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const KEY_FILE_MAGIC: &[u8; 8] = b"RSAREFKS";
+const KEY_FILE_VERSION: u8 = 1;
+const KEY_FILE_EXTENSION: &str = "key";
+
+const PUBLIC_KEY_LEN: usize = 260;
+const PRIVATE_KEY_LEN: usize = 708;
+
+const FLAG_ENCRYPTED: u8 = 1;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 10000;
+
+#[derive(Debug)]
+pub enum KeyStoreError {
+    Io(io::Error),
+    Key(RSAError),
+    InvalidName,
+    NotFound,
+    PasswordRequired,
+}
+
+impl core::fmt::Display for KeyStoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            KeyStoreError::Io(error) => write!(f, "key store I/O failed: {}", error),
+            KeyStoreError::Key(error) => write!(f, "key file is unusable: {}", error),
+            KeyStoreError::InvalidName => f.write_str("key name is invalid"),
+            KeyStoreError::NotFound => f.write_str("key not found"),
+            KeyStoreError::PasswordRequired => f.write_str("key file needs a password"),
+        }
+    }
+}
+
+impl std::error::Error for KeyStoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KeyStoreError::Io(error) => Some(error),
+            KeyStoreError::Key(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for KeyStoreError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            KeyStoreError::NotFound
+        } else {
+            KeyStoreError::Io(error)
+        }
+    }
+}
+
+impl From<RSAError> for KeyStoreError {
+    fn from(error: RSAError) -> Self {
+        KeyStoreError::Key(error)
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> Result<Wiped<[u8; 32]>, RSAError> {
+    let mut key = Wiped([0u8; 32]);
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations, &mut key.0)
+        .map_err(|_| RSAError::Key)?;
+    Ok(key)
+}
+
+/* Key file layout:
+     magic (8) | version (1) | flags (1) | public key (260) | private part
+   The public key is always in the clear so keys can be listed and looked
+   up without a password. An unencrypted private part is the 708-byte
+   RSAPrivateKey encoding; an encrypted one is
+     iterations (4, LE) | salt (16) | IV (16) | AES-256-CBC ciphertext
+   under a PBKDF2-HMAC-SHA256 key.
+*/
+fn encode_key_file(
+    private_key: &RSAPrivateKey,
+    password: Option<&str>,
+    random_struct: &mut RandomStruct,
+) -> Result<Vec<u8>, RSAError> {
+    let mut output = Vec::<u8>::with_capacity(1024);
+    output.extend(KEY_FILE_MAGIC);
+    output.push(KEY_FILE_VERSION);
+    output.push(if password.is_some() {
+        FLAG_ENCRYPTED
+    } else {
+        0
+    });
+    output.extend(private_key.public_key().encode());
+
+    match password {
+        None => output.extend(private_key.encode()),
+        Some(password) => {
+            let salt = random_struct.generate_bytes(SALT_LEN)?;
+            let iv = random_struct.generate_array::<AES_BLOCK_LEN>()?;
+            let key = derive_key(password, &salt, PBKDF2_ITERATIONS)?;
+
+            output.extend(PBKDF2_ITERATIONS.to_le_bytes());
+            output.extend(&salt);
+            output.extend(iv);
+            let encoded: SecretVec = Wiped(private_key.encode());
+            let padded: SecretVec = Wiped(pad_content(&encoded, AES_BLOCK_LEN));
+            output.extend(AESCBCContext::aes256(&key, &iv, true).update(&padded)?);
+        }
+    }
+
+    Ok(output)
+}
+
+fn decode_public_part(data: &[u8]) -> Result<(bool, RSAPublicKey, &[u8]), RSAError> {
+    let [version, flags, rest @ ..] = data
+        .strip_prefix(KEY_FILE_MAGIC)
+        .ok_or(RSAError::KeyEncoding)?
+    else {
+        return Err(RSAError::KeyEncoding);
+    };
+    if *version != KEY_FILE_VERSION {
+        return Err(RSAError::KeyEncoding);
+    }
+    let (public_key, private_part) = rest
+        .split_at_checked(PUBLIC_KEY_LEN)
+        .ok_or(RSAError::KeyEncoding)?;
+
+    let encrypted = flags & FLAG_ENCRYPTED != 0;
+    let public_key = RSAPublicKey::decode(public_key).map_err(|_| RSAError::KeyEncoding)?;
+    Ok((encrypted, public_key, private_part))
+}
+
+fn decode_key_file(
+    data: &[u8],
+    password: Option<&str>,
+) -> Result<(RSAPublicKey, RSAPrivateKey), KeyStoreError> {
+    let (encrypted, public_key, private_part) = decode_public_part(data)?;
+
+    let private_part = if encrypted {
+        let password = password.ok_or(KeyStoreError::PasswordRequired)?;
+        let (iterations, rest) = private_part
+            .split_first_chunk::<4>()
+            .ok_or(RSAError::KeyEncoding)?;
+        let (salt, rest) = rest
+            .split_at_checked(SALT_LEN)
+            .ok_or(RSAError::KeyEncoding)?;
+        let (iv, ciphertext) = rest
+            .split_first_chunk::<AES_BLOCK_LEN>()
+            .ok_or(RSAError::KeyEncoding)?;
+        let iterations = u32::from_le_bytes(*iterations);
+        if iterations == 0 {
+            return Err(RSAError::KeyEncoding.into());
+        }
+        let key = derive_key(password, salt, iterations)?;
+
+        let padded = AESCBCContext::aes256(&key, iv, false)
+            .update(ciphertext)
+            .map_err(|_| RSAError::KeyEncoding)?;
+        unpad_content(padded, AES_BLOCK_LEN)?
+    } else {
+        private_part.to_vec()
+    };
+
+    if private_part.len() != PRIVATE_KEY_LEN {
+        return Err(RSAError::Key.into());
+    }
+    let private_key = RSAPrivateKey::decode(&private_part).map_err(|_| RSAError::KeyEncoding)?;
+
+    /* A wrong password that happens to unpad still yields a mismatched key. */
+    if private_key.modulus() != public_key.modulus() {
+        return Err(RSAError::Key.into());
+    }
+
+    Ok((public_key, private_key))
+}
+
+/* A directory of named key pairs, one file per key. Names are limited to
+   ASCII letters, digits, '-', '_' and '.', and may not start with '.'.
+*/
+pub struct KeyStore {
+    directory: PathBuf,
+}
+
+impl KeyStore {
+    /* Opens a key store, creating the directory if needed. */
+    pub fn open(directory: impl AsRef<Path>) -> Result<Self, KeyStoreError> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'_' | b'.'))
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, KeyStoreError> {
+        if !Self::is_valid_name(name) {
+            return Err(KeyStoreError::InvalidName);
+        }
+        Ok(self
+            .directory
+            .join(format!("{}.{}", name, KEY_FILE_EXTENSION)))
+    }
+
+    /* Saves a key pair, replacing any key of the same name. The file is
+       written under a temporary name and renamed into place, so readers
+       never see a partial key.
+    */
+    pub fn save(
+        &self,
+        name: &str,
+        private_key: &RSAPrivateKey,
+        password: Option<&str>,
+        random_struct: &mut RandomStruct,
+    ) -> Result<(), KeyStoreError> {
+        let path = self.path(name)?;
+        let data = encode_key_file(private_key, password, random_struct)?;
+
+        let temp_path = self
+            .directory
+            .join(format!(".{}.{}.tmp", name, KEY_FILE_EXTENSION));
+        let result = (|| {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(&data)?;
+            file.sync_all()?;
+            fs::rename(&temp_path, &path)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        Ok(result?)
+    }
+
+    pub fn load(
+        &self,
+        name: &str,
+        password: Option<&str>,
+    ) -> Result<(RSAPublicKey, RSAPrivateKey), KeyStoreError> {
+        decode_key_file(&fs::read(self.path(name)?)?, password)
+    }
+
+    pub fn load_public_key(&self, name: &str) -> Result<RSAPublicKey, KeyStoreError> {
+        Ok(decode_public_part(&fs::read(self.path(name)?)?)?.1)
+    }
+
+    pub fn is_encrypted(&self, name: &str) -> Result<bool, KeyStoreError> {
+        Ok(decode_public_part(&fs::read(self.path(name)?)?)?.0)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), KeyStoreError> {
+        Ok(fs::remove_file(self.path(name)?)?)
+    }
+
+    /* Names of the stored keys, sorted. */
+    pub fn list(&self) -> Result<Vec<String>, KeyStoreError> {
+        let mut names = Vec::<String>::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(KEY_FILE_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                if Self::is_valid_name(name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /* Finds the key whose key_fingerprint matches. Unreadable or foreign
+       files are skipped.
+    */
+    pub fn find_by_fingerprint(&self, fingerprint: &[u8]) -> Result<Option<String>, KeyStoreError> {
+        for name in self.list()? {
+            if let Ok(public_key) = self.load_public_key(&name) {
+                if key_fingerprint(&public_key) == fingerprint {
+                    return Ok(Some(name));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    fn temp_store(tag: &str) -> (PathBuf, KeyStore) {
+        let directory =
+            std::env::temp_dir().join(format!("rsaref-keystore-{}-{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let store = KeyStore::open(&directory).unwrap();
+        (directory, store)
+    }
+
+    fn generate_keys() -> (RSAPublicKey, RSAPrivateKey) {
+        test_key_pair(512)
+    }
+
+    #[test]
+    fn test_keystore_save_load() {
+        let (directory, store) = temp_store("save-load");
+        let mut random_struct = test_random_struct(0x4b);
+        let (public_a, private_a) = generate_keys();
+        let (public_b, private_b) = generate_keys();
+
+        store
+            .save("alice", &private_a, None, &mut random_struct)
+            .unwrap();
+        store
+            .save("bob", &private_b, Some("secret"), &mut random_struct)
+            .unwrap();
+        assert_eq!(store.list().unwrap(), ["alice", "bob"]);
+        assert!(!store.is_encrypted("alice").unwrap());
+        assert!(store.is_encrypted("bob").unwrap());
+
+        let (_, loaded) = store.load("alice", None).unwrap();
+        assert_eq!(loaded.encode(), private_a.encode());
+        let (loaded_public, loaded) = store.load("bob", Some("secret")).unwrap();
+        assert_eq!(loaded.encode(), private_b.encode());
+        assert_eq!(loaded_public.encode(), public_b.encode());
+
+        assert!(matches!(
+            store.load("bob", None),
+            Err(KeyStoreError::PasswordRequired)
+        ));
+        assert!(matches!(
+            store.load("bob", Some("guess")),
+            Err(KeyStoreError::Key(_))
+        ));
+        assert!(matches!(
+            store.load("carol", None),
+            Err(KeyStoreError::NotFound)
+        ));
+
+        assert_eq!(
+            store
+                .find_by_fingerprint(&key_fingerprint(&public_b))
+                .unwrap()
+                .as_deref(),
+            Some("bob")
+        );
+        assert_eq!(
+            store.load_public_key("alice").unwrap().encode(),
+            public_a.encode()
+        );
+
+        store.remove("alice").unwrap();
+        assert_eq!(store.list().unwrap(), ["bob"]);
+        assert!(store
+            .find_by_fingerprint(&key_fingerprint(&public_a))
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_keystore_rejects_bad_names() {
+        let (directory, store) = temp_store("names");
+        let mut random_struct = test_random_struct(0x4c);
+        let (_, private_key) = generate_keys();
+
+        for name in ["", ".hidden", "../escape", "a/b", "spa ce"] {
+            assert!(matches!(
+                store.save(name, &private_key, None, &mut random_struct),
+                Err(KeyStoreError::InvalidName)
+            ));
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,13 +1,306 @@
+#![no_std]
+/* No public function may panic on its input; see the README. */
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::unreachable,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::indexing_slicing
+    )
+)]
+
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+#[cfg_attr(any(feature = "full", test), macro_use)]
+extern crate alloc;
+
+mod constant_time;
+
+mod wipe;
+pub use wipe::SecretBuffer;
+
+#[cfg(feature = "full")]
+mod secure_box;
+#[cfg(feature = "full")]
+pub use secure_box::{LockedPrivateKey, SecureBox};
+
+#[cfg(feature = "full")]
+mod montgomery;
+
+mod parallel;
+
+#[cfg(feature = "full")]
 mod r_random;
+#[cfg(feature = "full")]
 pub use r_random::RandomStruct;
 
 mod rsa;
-pub use crate::rsa::{RSAPrivateKey, RSAProtoKey, RSAPublicKey};
+#[cfg(feature = "full")]
+pub use crate::rsa::{PrivateKeyOps, RSAPrivateKey, RSAProtoKey, SharedPrivateKey};
+pub use crate::rsa::{RSAPublicKey, SharedPublicKey};
+
+mod crt;
+pub use crt::crt_combine;
 
+mod blob_layout;
+pub use blob_layout::{BlobLayout, ComponentOrder, WordOrder};
+
+mod key_policy;
+pub use key_policy::{allow_legacy_key_sizes, minimum_key_bits, set_minimum_key_bits};
+
+#[cfg(feature = "full")]
+mod key_audit;
+#[cfg(feature = "full")]
+pub use key_audit::{PrivateKeyAudit, PrivateKeyEvent, PrivateKeyOperation, PrivateKeyUsage};
+
+#[cfg(feature = "full")]
 mod r_keygen;
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+#[allow(deprecated)]
 pub use r_keygen::generate_pem_keys;
+#[cfg(feature = "full")]
+pub use r_keygen::generate_pem_keys_with_random;
+#[cfg(all(feature = "full", not(feature = "deterministic")))]
+pub use r_keygen::generate_pem_keys_with_rng;
+
+#[cfg(feature = "full")]
+mod key_usage;
+#[cfg(feature = "full")]
+pub use key_usage::{
+    CiphertextBlock, DecryptionKey, EncryptionKey, PlaintextBlock, Signature, SigningKey,
+    VerifyingKey,
+};
+
+#[cfg(feature = "full")]
+mod profile;
+#[cfg(feature = "full")]
+pub use profile::{Profile, ProfiledKey, HARDENED_MIN_MODULUS_BITS, HARDENED_MIN_PUBLIC_EXPONENT};
+
+#[cfg(feature = "full")]
+mod split_key;
+#[cfg(feature = "full")]
+pub use split_key::{combine_partial_signatures, split_private_key, RSAKeyShare};
+
+#[cfg(feature = "full")]
+mod secret_sharing;
+#[cfg(feature = "full")]
+pub use secret_sharing::{
+    combine_private_key_shares, combine_shares, split_private_key_shares, split_secret, SecretShare,
+};
+
+#[cfg(feature = "full")]
+mod r_dh;
+#[cfg(feature = "full")]
+pub use r_dh::{generate_dh_params, DHParams};
+
+#[cfg(feature = "full")]
+mod dsa;
+#[cfg(feature = "full")]
+pub use dsa::{generate_dsa_params, DSAParams, DSAPrivateKey, DSAPublicKey};
+
+#[cfg(feature = "full")]
+mod fixed_key;
+#[cfg(feature = "full")]
+pub use fixed_key::{RSAPrivateKey512, RSAPublicKey512, FIXED_MODULUS_BITS, FIXED_MODULUS_LEN};
+
+#[cfg(feature = "full")]
+mod key_container;
+#[cfg(feature = "full")]
+pub use key_container::{
+    decode_private_key_container, decode_public_key_container, encode_private_key_container,
+    encode_public_key_container, key_container_type, ContainerKeyType, KeyContainerError,
+};
+
+#[cfg(feature = "full")]
+mod r_encode;
+#[cfg(feature = "full")]
+pub use r_encode::{decode_pem_block, encode_pem_block};
+#[cfg(all(feature = "std", feature = "full"))]
+pub use r_encode::{PemBlockReader, PemBlockWriter};
+
+#[cfg(feature = "full")]
+mod armor;
+#[cfg(feature = "full")]
+pub use armor::{crc24, ArmoredBlock};
+
+mod der;
+
+mod digest_info;
+pub use digest_info::{decode_digest_info, encode_digest_info, DigestAlg};
+
+#[cfg(feature = "full")]
+mod digest_registry;
+#[cfg(all(feature = "std", feature = "full"))]
+pub use digest_registry::register_digest_algorithm;
+#[cfg(feature = "full")]
+pub use digest_registry::{
+    find_digest_algorithm_by_id, find_digest_algorithm_by_oid, DigestAlgorithm, DigestUpdate,
+};
+
+#[cfg(feature = "full")]
+mod cbc;
+
+#[cfg(feature = "full")]
+mod des_cbc;
+#[cfg(feature = "full")]
+pub use des::{Des, TdesEde3};
+#[cfg(feature = "full")]
+pub use des_cbc::{DESCBCContext, DESX};
+
+#[cfg(feature = "full")]
+mod des_mac;
+#[cfg(feature = "full")]
+pub use des_mac::{des_mac, DESMACContext};
+
+#[cfg(feature = "full")]
+mod rc2_cbc;
+#[cfg(feature = "full")]
+pub use rc2_cbc::RC2CBCContext;
+
+#[cfg(feature = "full")]
+mod aes_cbc;
+#[cfg(feature = "full")]
+pub use aes_cbc::AESCBCContext;
+
+#[cfg(feature = "full")]
+mod compat;
+#[cfg(feature = "full")]
+pub use compat::{
+    check_compat_key, decode_public_key_blob, encode_public_key_blob, generate_compat_keys,
+    RC4Context, SessionKey, COMPAT_MODULUS_BITS, PUBLIC_KEY_BLOB_LEN, SESSION_KEY_LEN,
+};
+
+#[cfg(feature = "full")]
+mod algorithm;
+#[cfg(feature = "full")]
+pub use algorithm::{
+    digest, CipherContext, DigestAlgorithmId, DigestContext, EncryptionAlgorithmId, DA_MD2, DA_MD5,
+    EA_DESX_CBC, EA_DES_CBC, EA_DES_EDE2_CBC, EA_DES_EDE3_CBC,
+};
+pub use md2::Md2;
+pub use md5::Md5;
+
+#[cfg(feature = "full")]
+mod padding;
+#[cfg(feature = "full")]
+pub use padding::{kdf2, mgf1};
+
+pub mod pkcs1;
 
-#[derive(Debug)]
+#[cfg(feature = "full")]
+mod rsa_kem;
+#[cfg(feature = "full")]
+pub use rsa_kem::{rsa_kem_decapsulate, rsa_kem_encapsulate};
+
+#[cfg(feature = "full")]
+mod r_enhanc;
+#[cfg(feature = "full")]
+pub use r_enhanc::{OpenContext, SealContext};
+#[cfg(all(feature = "std", feature = "full"))]
+pub use r_enhanc::{OpenReader, SealWriter};
+
+#[cfg(feature = "full")]
+mod envelope_header;
+#[cfg(feature = "full")]
+pub use envelope_header::{EnvelopeHeader, EnvelopeRecipient};
+
+#[cfg(all(feature = "std", feature = "full"))]
+mod rsa_stream;
+#[cfg(all(feature = "std", feature = "full"))]
+pub use rsa_stream::{RSAChunkReader, RSAChunkWriter};
+
+#[cfg(feature = "tokio")]
+mod r_enhanc_async;
+#[cfg(feature = "tokio")]
+pub use r_enhanc_async::{AsyncOpenReader, AsyncSealWriter};
+
+#[cfg(feature = "full")]
+mod cms;
+#[cfg(feature = "full")]
+pub use cms::{
+    EnvelopedData, KeyTransRecipientInfo, RecipientIdentifier, SignedData, SignerIdentifier,
+    SignerInfo,
+};
+
+#[cfg(feature = "full")]
+mod csr;
+#[cfg(feature = "full")]
+pub use csr::{key_fingerprint, CertificationRequest, NameAttribute};
+
+#[cfg(feature = "full")]
+mod pkcs12;
+#[cfg(feature = "full")]
+pub use pkcs12::{export_pkcs12, PKCS12Encryption};
+
+#[cfg(feature = "full")]
+mod pem;
+#[cfg(feature = "full")]
+pub use pem::{decode_pem_message, encode_pem_message, PEMMessage, PEMProcType};
+#[cfg(all(feature = "std", feature = "full"))]
+pub use pem::{decode_pem_message_stream, encode_pem_message_stream};
+
+#[cfg(feature = "full")]
+mod ripem;
+#[cfg(feature = "full")]
+pub use ripem::{
+    decode_ripem_private_key, decode_ripem_public_keys, encode_ripem_private_key,
+    encode_ripem_public_key, RipemPrivateKey, RipemPublicKey,
+};
+
+#[cfg(feature = "full")]
+mod signature_file;
+#[cfg(all(feature = "std", feature = "full"))]
+pub use signature_file::{sign_file, verify_file, SignatureFileError};
+#[cfg(feature = "full")]
+pub use signature_file::{verify_batch, DetachedSignature};
+
+#[cfg(all(feature = "std", feature = "full"))]
+mod context;
+#[cfg(all(feature = "std", feature = "full"))]
+pub use context::{Context, ContextError, Envelope};
+
+#[cfg(all(feature = "std", feature = "full"))]
+mod keystore;
+#[cfg(all(feature = "std", feature = "full"))]
+pub use keystore::{KeyStore, KeyStoreError};
+
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+#[cfg(feature = "full")]
+mod capabilities;
+#[cfg(feature = "full")]
+pub use capabilities::{capabilities, Capabilities};
+
+#[cfg(feature = "full")]
+pub mod selftest;
+
+#[cfg(feature = "full")]
+pub mod test_vectors;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
+
+/* Errors from every part of the crate. Most variants are the RSAREF RE_*
+   codes, which code() gives; new ones may be added, so matches need a
+   wildcard arm.
+   Subsystems with errors of their own (key stores, signature files) wrap
+   an RSAError and convert from it.
+
+   The DER decoders report where they failed through DecodeAt. Equality
+   compares kind(), ignoring the location, so a located Encoding still
+   equals RSAError::Encoding.
+*/
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum RSAError {
     ContentEncoding,
     Data,
@@ -23,4 +316,153 @@ pub enum RSAError {
     Signature,
     SignatureEncoding,
     EncryptionAlgorithm,
+    /* A fixed-size or length-prefixed encoding was given fewer bytes than
+       it needs.
+    */
+    InputLen {
+        expected: usize,
+        actual: usize,
+    },
+    /* error occurred reading field, which starts offset bytes into the
+       input given to the decoder. For decode_ber the input is the DER
+       re-encoding of the caller's data.
+    */
+    DecodeAt {
+        offset: usize,
+        field: &'static str,
+        error: alloc::boxed::Box<RSAError>,
+    },
+}
+
+impl RSAError {
+    /* The error with any decode location taken off. */
+    pub fn kind(&self) -> &RSAError {
+        match self {
+            RSAError::DecodeAt { error, .. } => error.kind(),
+            error => error,
+        }
+    }
+
+    /* The RE_* code RSAREF returns for this error. InputLen is RE_LEN and
+       DecodeAt the code of the error it locates.
+    */
+    pub fn code(&self) -> u16 {
+        match self.kind() {
+            RSAError::ContentEncoding => 0x0400,
+            RSAError::Data => 0x0401,
+            RSAError::DigestAlgorithm => 0x0402,
+            RSAError::Encoding => 0x0403,
+            RSAError::Key => 0x0404,
+            RSAError::KeyEncoding => 0x0405,
+            RSAError::Len | RSAError::InputLen { .. } => 0x0406,
+            RSAError::ModulusLen => 0x0407,
+            RSAError::NeedRandom => 0x0408,
+            RSAError::PrivateKey => 0x0409,
+            RSAError::PublicKey => 0x040a,
+            RSAError::Signature => 0x040b,
+            RSAError::SignatureEncoding => 0x040c,
+            RSAError::EncryptionAlgorithm => 0x040d,
+            RSAError::DecodeAt { error, .. } => error.code(),
+        }
+    }
+
+    /* An error from reading field at offset; an error already located
+       deeper in the field keeps its field and gains the offset.
+    */
+    pub(crate) fn located(self, offset: usize, field: &'static str) -> Self {
+        match self {
+            RSAError::DecodeAt {
+                offset: inner,
+                field,
+                error,
+            } => RSAError::DecodeAt {
+                offset: offset.saturating_add(inner),
+                field,
+                error,
+            },
+            error => RSAError::DecodeAt {
+                offset,
+                field,
+                error: alloc::boxed::Box::new(error),
+            },
+        }
+    }
+}
+
+impl PartialEq for RSAError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.kind(), other.kind()) {
+            (
+                RSAError::InputLen { expected, actual },
+                RSAError::InputLen {
+                    expected: other_expected,
+                    actual: other_actual,
+                },
+            ) => expected == other_expected && actual == other_actual,
+            (kind, other_kind) => {
+                core::mem::discriminant(kind) == core::mem::discriminant(other_kind)
+            }
+        }
+    }
+}
+
+impl Eq for RSAError {}
+
+/* The error RSAREF returns as code; fails with the code itself if RSAREF
+   has no such error.
+*/
+impl TryFrom<u16> for RSAError {
+    type Error = u16;
+
+    fn try_from(code: u16) -> Result<Self, u16> {
+        match code {
+            0x0400 => Ok(RSAError::ContentEncoding),
+            0x0401 => Ok(RSAError::Data),
+            0x0402 => Ok(RSAError::DigestAlgorithm),
+            0x0403 => Ok(RSAError::Encoding),
+            0x0404 => Ok(RSAError::Key),
+            0x0405 => Ok(RSAError::KeyEncoding),
+            0x0406 => Ok(RSAError::Len),
+            0x0407 => Ok(RSAError::ModulusLen),
+            0x0408 => Ok(RSAError::NeedRandom),
+            0x0409 => Ok(RSAError::PrivateKey),
+            0x040a => Ok(RSAError::PublicKey),
+            0x040b => Ok(RSAError::Signature),
+            0x040c => Ok(RSAError::SignatureEncoding),
+            0x040d => Ok(RSAError::EncryptionAlgorithm),
+            code => Err(code),
+        }
+    }
+}
+
+impl core::fmt::Display for RSAError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            RSAError::ContentEncoding => f.write_str("content encoding is invalid"),
+            RSAError::Data => f.write_str("data is invalid"),
+            RSAError::DigestAlgorithm => f.write_str("digest algorithm is unsupported"),
+            RSAError::Encoding => f.write_str("encoded data is invalid"),
+            RSAError::Key => f.write_str("key is invalid"),
+            RSAError::KeyEncoding => f.write_str("key encoding is invalid"),
+            RSAError::Len => f.write_str("data length is out of range"),
+            RSAError::ModulusLen => f.write_str("modulus length is out of range"),
+            RSAError::NeedRandom => f.write_str("random structure is not seeded"),
+            RSAError::PrivateKey => f.write_str("private key cannot be used"),
+            RSAError::PublicKey => f.write_str("public key cannot be used"),
+            RSAError::Signature => f.write_str("signature does not verify"),
+            RSAError::SignatureEncoding => f.write_str("signature encoding is invalid"),
+            RSAError::EncryptionAlgorithm => f.write_str("encryption algorithm is unsupported"),
+            RSAError::InputLen { expected, actual } => {
+                write!(f, "input is {} bytes, need {}", actual, expected)
+            }
+            RSAError::DecodeAt {
+                offset,
+                field,
+                error,
+            } => write!(f, "{} at byte {} ({})", error, offset, field),
+        }
+    }
 }
+
+#[cfg(feature = "std")]
+impl std::error::Error for RSAError {}
@@ -0,0 +1,451 @@
+/* Constant-time modular arithmetic for the private-key block. Numbers are
+   little-endian vectors of 64-bit limbs, all as long as the modulus
+   whatever their value, and every loop runs a number of times that
+   depends only on those lengths. Reduction is bit-serial, products use
+   Montgomery multiplication with a masked final subtraction, and
+   exponentiation uses a fixed 4-bit window over the full exponent length
+   with the table read by scanning every entry. Neither operands nor
+   exponent steer a branch or a memory access.
+
+   Moduli must be odd, as RSA primes are; an even one gives a wrong result
+   rather than a panic.
+*/
+use crate::wipe::Wipe;
+use alloc::vec::Vec;
+use num_bigint_dig::BigUint;
+
+pub(crate) type Limb = u64;
+
+const LIMB_BITS: usize = 64;
+const WINDOW_BITS: usize = 4;
+
+/* All ones if bit is 1, zero if it is 0. */
+fn mask(bit: Limb) -> Limb {
+    bit.wrapping_neg()
+}
+
+/* 1 if a == b, else 0. */
+fn limb_eq(a: Limb, b: Limb) -> Limb {
+    let d = a ^ b;
+    ((d | d.wrapping_neg()) >> (LIMB_BITS - 1)) ^ 1
+}
+
+/* The low len limbs of x; the caller ensures x fits. */
+pub(crate) fn to_limbs(x: &BigUint, len: usize) -> Vec<Limb> {
+    let mut bytes = x.to_bytes_le();
+    let mut limbs = vec![0; len];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        let mut buf = [0u8; 8];
+        for (b, c) in buf.iter_mut().zip(chunk) {
+            *b = *c;
+        }
+        *limb = Limb::from_le_bytes(buf);
+        buf.wipe();
+    }
+    bytes.wipe();
+    limbs
+}
+
+/* limbs as a len-byte big-endian string, dropping higher bytes. */
+pub(crate) fn to_be_bytes(limbs: &[Limb], len: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = limbs.iter().rev().flat_map(|l| l.to_be_bytes()).collect();
+    if bytes.len() >= len {
+        let result = bytes.split_off(bytes.len() - len);
+        bytes.wipe();
+        return result;
+    }
+    let mut result = vec![0u8; len - bytes.len()];
+    result.extend_from_slice(&bytes);
+    bytes.wipe();
+    result
+}
+
+/* a * b + c for limb vectors, into result, which must be zero and
+   a.len() + b.len() limbs. The sum must fit, which it does when it is a
+   CRT recombination below p * q.
+*/
+pub(crate) fn mul_add_into(a: &[Limb], b: &[Limb], c: &[Limb], result: &mut [Limb]) {
+    for (r, c) in result.iter_mut().zip(c) {
+        *r = *c;
+    }
+    for (i, a_i) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (r, b_j) in result.iter_mut().skip(i).zip(b) {
+            let s = *r as u128 + (*a_i as u128) * (*b_j as u128) + carry;
+            *r = s as Limb;
+            carry = s >> LIMB_BITS;
+        }
+        for r in result.iter_mut().skip(i + b.len()) {
+            let s = *r as u128 + carry;
+            *r = s as Limb;
+            carry = s >> LIMB_BITS;
+        }
+    }
+}
+
+/* The x below p * q with x = mp mod p and x = mq mod q, by Garner's
+   formula x = ((mp - mq) * qinv mod p) * q + mq, where qinv is q^-1
+   reduced mod p and mp, mq are below p and q. result must be zero and
+   p.len() + q.len() limbs. This is crt_combine's two-modulus case in
+   constant time, for the private-key block.
+*/
+pub(crate) fn crt_combine_into<S: Limbs>(
+    p: &Modulus<S>,
+    qinv: &[Limb],
+    q: &[Limb],
+    mp: &[Limb],
+    mq: &[Limb],
+    result: &mut [Limb],
+) {
+    let mut mq_p = p.reduce(mq);
+    let mut t = p.sub(mp, mq_p.as_ref());
+    let mut h = p.mul(t.as_ref(), qinv);
+    mul_add_into(h.as_ref(), q, mq, result);
+    for limbs in [&mut mq_p, &mut t, &mut h] {
+        limbs.wipe();
+    }
+}
+
+/* Whether a < b for limb vectors of one length, without branching on
+   either.
+*/
+pub(crate) fn is_below(a: &[Limb], b: &[Limb]) -> bool {
+    let mut borrow = 0;
+    for (a, b) in a.iter().zip(b) {
+        let (d, b1) = a.overflowing_sub(*b);
+        let (_, b2) = d.overflowing_sub(borrow);
+        borrow = (b1 | b2) as Limb;
+    }
+    borrow == 1
+}
+
+/* Storage for numbers as long as a modulus: vectors for keys of any
+   size, or arrays, which never allocate, for the fixed-size keys.
+*/
+pub(crate) trait Limbs: AsRef<[Limb]> + AsMut<[Limb]> + Clone + Wipe {
+    fn zeroed(len: usize) -> Self;
+}
+
+impl Limbs for Vec<Limb> {
+    fn zeroed(len: usize) -> Self {
+        vec![0; len]
+    }
+}
+
+/* len is always N for arrays. */
+impl<const N: usize> Limbs for [Limb; N] {
+    fn zeroed(_len: usize) -> Self {
+        [0; N]
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Modulus<S: Limbs = Vec<Limb>> {
+    limbs: S,
+    /* -m^-1 mod 2^64 */
+    n0: Limb,
+    /* R^2 mod m, with R = 2^(64 * limbs.len()) */
+    r2: S,
+}
+
+impl Modulus {
+    pub(crate) fn new(m: &BigUint) -> Self {
+        let len = m.bits().div_ceil(LIMB_BITS).max(1);
+        Self::from_limbs(to_limbs(m, len))
+    }
+}
+
+impl<S: Limbs> Modulus<S> {
+    pub(crate) fn from_limbs(limbs: S) -> Self {
+        /* Newton's iteration doubles the correct low bits each round. */
+        let m0 = limbs.as_ref().first().copied().unwrap_or(1);
+        let mut inverse: Limb = 1;
+        for _ in 0..6 {
+            inverse = inverse.wrapping_mul(2u64.wrapping_sub(m0.wrapping_mul(inverse)));
+        }
+
+        let len = limbs.as_ref().len();
+        let mut modulus = Self {
+            r2: S::zeroed(len),
+            limbs,
+            n0: inverse.wrapping_neg(),
+        };
+        let r2_bits = core::iter::once(1).chain(core::iter::repeat_n(0, 2 * LIMB_BITS * len));
+        modulus.r2 = modulus.reduce_bits(r2_bits);
+        modulus
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.limbs.as_ref().len()
+    }
+
+    pub(crate) fn limbs(&self) -> &[Limb] {
+        self.limbs.as_ref()
+    }
+
+    /* Subtracts m from value, taken with an extra top limb of 0 or 1, if
+       the result is nonnegative. value must be below 2m.
+    */
+    fn conditional_subtract(&self, value: &mut [Limb], top: Limb) {
+        let select = mask(top | (is_below(value, self.limbs()) as Limb ^ 1));
+        let mut borrow = 0;
+        for (v, m) in value.iter_mut().zip(self.limbs()) {
+            let (d, b1) = v.overflowing_sub(m & select);
+            let (d, b2) = d.overflowing_sub(borrow);
+            *v = d;
+            borrow = (b1 | b2) as Limb;
+        }
+    }
+
+    /* The number whose bits, most significant first, are bits, mod m. */
+    fn reduce_bits(&self, bits: impl Iterator<Item = Limb>) -> S {
+        let mut result = S::zeroed(self.len());
+        for bit in bits {
+            let mut carry = bit;
+            for limb in result.as_mut().iter_mut() {
+                let next = *limb >> (LIMB_BITS - 1);
+                *limb = (*limb << 1) | carry;
+                carry = next;
+            }
+            self.conditional_subtract(result.as_mut(), carry);
+        }
+        result
+    }
+
+    /* x mod m for x of any length. */
+    pub(crate) fn reduce(&self, x: &[Limb]) -> S {
+        self.reduce_bits(
+            x.iter()
+                .rev()
+                .flat_map(|limb| (0..LIMB_BITS).rev().map(move |i| (limb >> i) & 1)),
+        )
+    }
+
+    /* a * b * R^-1 mod m for a, b below m (CIOS). */
+    fn mont_mul(&self, a: &[Limb], b: &[Limb]) -> S {
+        let mut result = S::zeroed(self.len());
+        let t = result.as_mut();
+        let mut top: Limb = 0;
+        for a_i in a {
+            let mut carry = 0u128;
+            for (t_j, b_j) in t.iter_mut().zip(b) {
+                let s = *t_j as u128 + (*a_i as u128) * (*b_j as u128) + carry;
+                *t_j = s as Limb;
+                carry = s >> LIMB_BITS;
+            }
+            let s = top as u128 + carry;
+            top = s as Limb;
+            let overflow = (s >> LIMB_BITS) as Limb;
+
+            let q = t.first().copied().unwrap_or(0).wrapping_mul(self.n0);
+            let mut carry = 0u128;
+            for (t_j, m_j) in t.iter_mut().zip(self.limbs()) {
+                let s = *t_j as u128 + (q as u128) * (*m_j as u128) + carry;
+                *t_j = s as Limb;
+                carry = s >> LIMB_BITS;
+            }
+            /* The low limb is now zero; shift it out. */
+            let s = top as u128 + carry;
+            t.rotate_left(1);
+            if let Some(last) = t.last_mut() {
+                *last = s as Limb;
+            }
+            top = overflow + (s >> LIMB_BITS) as Limb;
+        }
+        self.conditional_subtract(t, top);
+        result
+    }
+
+    /* a * b mod m for a, b below m. */
+    pub(crate) fn mul(&self, a: &[Limb], b: &[Limb]) -> S {
+        let mut product = self.mont_mul(a, b);
+        let result = self.mont_mul(product.as_ref(), self.r2.as_ref());
+        product.wipe();
+        result
+    }
+
+    /* a - b mod m for a, b below m. */
+    pub(crate) fn sub(&self, a: &[Limb], b: &[Limb]) -> S {
+        let mut difference = S::zeroed(self.len());
+        let mut borrow = 0;
+        for ((d, a), b) in difference.as_mut().iter_mut().zip(a).zip(b) {
+            let (value, b1) = a.overflowing_sub(*b);
+            let (value, b2) = value.overflowing_sub(borrow);
+            *d = value;
+            borrow = (b1 | b2) as Limb;
+        }
+        let select = mask(borrow);
+        let mut carry = 0u128;
+        for (d, m) in difference.as_mut().iter_mut().zip(self.limbs()) {
+            let s = *d as u128 + (m & select) as u128 + carry;
+            *d = s as Limb;
+            carry = s >> LIMB_BITS;
+        }
+        difference
+    }
+
+    /* base^exponent mod m for base below m. Every window of the whole
+       exponent vector is processed, so its bit length is not revealed
+       either.
+    */
+    pub(crate) fn pow(&self, base: &[Limb], exponent: &[Limb]) -> S {
+        let mut unit = S::zeroed(self.len());
+        if let Some(low) = unit.as_mut().first_mut() {
+            *low = 1;
+        }
+        let r2 = self.r2.as_ref();
+        let mut base = self.mont_mul(base, r2);
+
+        let mut power = self.mont_mul(unit.as_ref(), r2);
+        let mut table: [S; 1 << WINDOW_BITS] = core::array::from_fn(|_| S::zeroed(self.len()));
+        for entry in table.iter_mut() {
+            let next = self.mont_mul(power.as_ref(), base.as_ref());
+            *entry = core::mem::replace(&mut power, next);
+        }
+
+        let mut acc = self.mont_mul(unit.as_ref(), r2);
+        let mut entry = S::zeroed(self.len());
+        for limb in exponent.iter().rev() {
+            for shift in (0..LIMB_BITS / WINDOW_BITS).rev() {
+                for _ in 0..WINDOW_BITS {
+                    let mut square = self.mont_mul(acc.as_ref(), acc.as_ref());
+                    core::mem::swap(&mut acc, &mut square);
+                    square.wipe();
+                }
+                let window = (limb >> (shift * WINDOW_BITS)) & ((1 << WINDOW_BITS) - 1);
+                entry.as_mut().fill(0);
+                for (i, power) in table.iter().enumerate() {
+                    let select = mask(limb_eq(i as Limb, window));
+                    for (e, p) in entry.as_mut().iter_mut().zip(power.as_ref()) {
+                        *e |= p & select;
+                    }
+                }
+                let mut product = self.mont_mul(acc.as_ref(), entry.as_ref());
+                core::mem::swap(&mut acc, &mut product);
+                product.wipe();
+            }
+        }
+        let result = self.mont_mul(acc.as_ref(), unit.as_ref());
+
+        for power in table.iter_mut() {
+            power.wipe();
+        }
+        for limbs in [&mut base, &mut power, &mut acc, &mut entry] {
+            limbs.wipe();
+        }
+        result
+    }
+}
+
+/* An RSA prime is as secret as the key it belongs to. */
+impl<S: Limbs> Drop for Modulus<S> {
+    fn drop(&mut self) {
+        self.limbs.wipe();
+        self.r2.wipe();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ops::Rem;
+    use num_bigint_dig::ModInverse;
+
+    fn from_limbs(limbs: &[Limb]) -> BigUint {
+        BigUint::from_bytes_be(&to_be_bytes(limbs, limbs.len() * 8))
+    }
+
+    #[test]
+    fn test_against_biguint() {
+        let seeds = [0x5a5a_u32, 0x1234_5678, 0xdead_beef];
+        /* Moduli of one limb, of a whole number of limbs and of a
+           partial top limb.
+        */
+        let moduli = [
+            BigUint::from(0xffff_fffb_u64),
+            (BigUint::from(1u32) << 128) - BigUint::from(159u32),
+            (BigUint::from(1u32) << 255) - BigUint::from(19u32),
+            (BigUint::from(1u32) << 521) - BigUint::from(1u32),
+        ];
+        for m in &moduli {
+            let modulus = Modulus::new(m);
+            for seed in seeds {
+                let mut bytes = Vec::new();
+                let mut state = seed;
+                for _ in 0..80 {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    bytes.push((state >> 16) as u8);
+                }
+                let x = BigUint::from_bytes_be(&bytes);
+                let a = (&x).rem(m);
+                let b = (&x >> 7).rem(m);
+                let e = &x >> 3;
+
+                let x_limbs = to_limbs(&x, x.bits().div_ceil(64));
+                assert_eq!(from_limbs(&modulus.reduce(&x_limbs)), a);
+
+                let (a_limbs, b_limbs) = (to_limbs(&a, modulus.len()), to_limbs(&b, modulus.len()));
+                assert_eq!(
+                    from_limbs(&modulus.mul(&a_limbs, &b_limbs)),
+                    (&a * &b).rem(m)
+                );
+                assert_eq!(
+                    from_limbs(&modulus.sub(&a_limbs, &b_limbs)),
+                    (&a + m - &b).rem(m)
+                );
+                assert_eq!(
+                    from_limbs(&modulus.pow(&a_limbs, &to_limbs(&e, e.bits().div_ceil(64)))),
+                    a.modpow(&e, m)
+                );
+            }
+        }
+
+        /* Arrays give the same results as vectors. */
+        let m = &moduli[2];
+        let x = BigUint::from(0x1234_5678_9abc_def0_u64) << 190;
+        let (vector, array) = (
+            Modulus::new(m),
+            Modulus::<[Limb; 4]>::from_limbs(to_limbs(m, 4).try_into().unwrap()),
+        );
+        let (base, exponent) = (to_limbs(&x, 4), to_limbs(&(&x >> 5), 4));
+        assert_eq!(
+            array.pow(&base, &exponent)[..],
+            vector.pow(&base, &exponent)[..]
+        );
+        assert_eq!(array.reduce(&exponent)[..], vector.reduce(&exponent)[..]);
+
+        let (a, b, c) = (
+            BigUint::from(u64::MAX) << 70,
+            BigUint::from(0x1234_5678_9abc_u64),
+            BigUint::from(u64::MAX),
+        );
+        let mut sum = [0; 4];
+        mul_add_into(
+            &to_limbs(&a, 3),
+            &to_limbs(&b, 1),
+            &to_limbs(&c, 1),
+            &mut sum,
+        );
+        assert_eq!(from_limbs(&sum), &a * &b + &c);
+
+        let (p, q) = (moduli[1].clone(), moduli[2].clone());
+        let qinv = (&q).rem(&p).mod_inverse(&p).unwrap().to_biguint().unwrap();
+        let (mp, mq) = ((&x).rem(&p), (&x).rem(&q));
+        let p_modulus = Modulus::new(&p);
+        let mut combined = [0; 6];
+        crt_combine_into(
+            &p_modulus,
+            &to_limbs(&qinv, 2),
+            &to_limbs(&q, 4),
+            &to_limbs(&mp, 2),
+            &to_limbs(&mq, 4),
+            &mut combined,
+        );
+        let combined = from_limbs(&combined);
+        assert!(combined < &p * &q);
+        assert_eq!((&combined).rem(&p), mp);
+        assert_eq!((&combined).rem(&q), mq);
+        assert_eq!(to_be_bytes(&[0x0102], 3), [0, 1, 2]);
+        assert_eq!(to_be_bytes(&[0x0102], 1), [2]);
+    }
+}
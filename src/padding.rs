@@ -0,0 +1,78 @@
+use crate::RSAError;
+use alloc::vec::Vec;
+use md5::Digest;
+
+/* MGF1 mask generation (PKCS #1 v2.2, B.2.1) over any digest: the mask is
+   Hash(seed || C) for a 32-bit big-endian counter C = 0, 1, ..., truncated
+   to mask_len. Masks longer than 2^32 digest outputs are rejected.
+*/
+pub fn mgf1<D: Digest>(seed: &[u8], mask_len: usize) -> Result<Vec<u8>, RSAError> {
+    counter_hash::<D>(seed, mask_len, 0)
+}
+
+/* KDF2 (ISO 18033-2, 6.2.3), the key derivation of RSA-KEM: MGF1 with the
+   counter starting at 1. Outputs longer than 2^32 - 1 digest outputs are
+   rejected.
+*/
+pub fn kdf2<D: Digest>(secret: &[u8], key_len: usize) -> Result<Vec<u8>, RSAError> {
+    counter_hash::<D>(secret, key_len, 1)
+}
+
+fn counter_hash<D: Digest>(seed: &[u8], len: usize, first: u32) -> Result<Vec<u8>, RSAError> {
+    let hash_len = <D as Digest>::output_size();
+    if len.div_ceil(hash_len) as u64 > (1u64 << 32) - first as u64 {
+        return Err(RSAError::Len);
+    }
+
+    let mut output = Vec::<u8>::with_capacity(len + hash_len);
+    let mut counter = first;
+    while output.len() < len {
+        let mut hasher = D::new();
+        hasher.update(seed);
+        hasher.update(counter.to_be_bytes());
+        output.extend(hasher.finalize());
+        counter = counter.wrapping_add(1);
+    }
+    output.truncate(len);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha1::Sha1;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_mgf1_vectors() {
+        /* From the pyca/cryptography MGF1 vectors. */
+        assert_eq!(mgf1::<Sha1>(b"foo", 3).unwrap(), [0x1a, 0xc9, 0x07]);
+        assert_eq!(
+            mgf1::<Sha1>(b"foo", 5).unwrap(),
+            [0x1a, 0xc9, 0x07, 0x5c, 0xd4]
+        );
+        assert_eq!(
+            mgf1::<Sha1>(b"bar", 5).unwrap(),
+            [0xbc, 0x0c, 0x65, 0x5e, 0x01]
+        );
+        assert_eq!(
+            mgf1::<Sha256>(b"bar", 5).unwrap(),
+            [0x38, 0x25, 0x76, 0xa7, 0x84]
+        );
+    }
+
+    #[test]
+    fn test_mgf1_prefix_and_empty() {
+        let long = mgf1::<Sha1>(b"seed", 50).unwrap();
+        assert_eq!(long.len(), 50);
+        assert_eq!(mgf1::<Sha1>(b"seed", 21).unwrap(), long[..21]);
+        assert!(mgf1::<Sha1>(b"seed", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_kdf2_counts_from_one() {
+        let key = kdf2::<Sha256>(b"z", 40).unwrap();
+        assert_eq!(key[..32], Sha256::digest(b"z\0\0\0\x01")[..]);
+        assert_eq!(key, mgf1::<Sha256>(b"z", 72).unwrap()[32..]);
+    }
+}
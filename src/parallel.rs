@@ -0,0 +1,96 @@
+/* Work splitting for the parallel feature. With it the helpers below run
+   on rayon's global pool; without it they are plain loops, so the default
+   build stays single-threaded. Results come back in input order either
+   way.
+*/
+use crate::RSAError;
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/* Applies f to each chunk_len-byte chunk of input and concatenates the
+   outputs, stopping at the first error.
+*/
+pub(crate) fn map_chunks<F>(input: &[u8], chunk_len: usize, f: F) -> Result<Vec<u8>, RSAError>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>, RSAError> + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    let outputs = input
+        .par_chunks(chunk_len)
+        .map(f)
+        .collect::<Result<Vec<_>, _>>()?;
+    #[cfg(not(feature = "parallel"))]
+    let outputs = input
+        .chunks(chunk_len)
+        .map(f)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut result = Vec::<u8>::with_capacity(input.len());
+    for output in outputs {
+        result.extend(output);
+    }
+    Ok(result)
+}
+
+/* Maps f over items, in parallel when the feature is on. */
+#[cfg(feature = "full")]
+pub(crate) fn map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return items.iter().map(f).collect();
+}
+
+/* The first item, in input order, for which f holds. */
+#[cfg(feature = "full")]
+#[cfg_attr(feature = "rsa-crate", allow(dead_code))]
+pub(crate) fn find_first<T, F>(items: &[T], f: F) -> Option<&T>
+where
+    T: Sync,
+    F: Fn(&T) -> bool + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_iter().find_first(|item| f(item));
+    #[cfg(not(feature = "parallel"))]
+    return items.iter().find(|item| f(item));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_chunks_keeps_order() {
+        let input = (0..=255u8).collect::<Vec<u8>>();
+        let doubled = map_chunks(&input, 7, |chunk| Ok([chunk, chunk].concat())).unwrap();
+        let expected = input
+            .chunks(7)
+            .flat_map(|chunk| [chunk, chunk].concat())
+            .collect::<Vec<u8>>();
+        assert_eq!(doubled, expected);
+
+        let failed = map_chunks(&input, 7, |chunk| {
+            if chunk.contains(&100) {
+                Err(RSAError::Data)
+            } else {
+                Ok(chunk.to_vec())
+            }
+        });
+        assert_eq!(failed, Err(RSAError::Data));
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_map_and_find_first_keep_order() {
+        let items = (0..1000u32).collect::<Vec<u32>>();
+        assert_eq!(find_first(&items, |i| i % 97 == 96), Some(&96));
+        assert_eq!(find_first(&items, |i| *i > 1000), None);
+        assert_eq!(map(&items[..4], |i| i * 2), vec![0, 2, 4, 6]);
+    }
+}
@@ -0,0 +1,839 @@
+#[cfg(feature = "std")]
+use crate::algorithm::DigestContext;
+use crate::algorithm::{digest, DigestAlgorithmId};
+use crate::cms::{pad_content, unpad_content};
+use crate::constant_time::ct_eq;
+use crate::csr::{decode_subject_public_key_info, encode_subject_public_key_info};
+use crate::des_cbc::{DESCBCContext, DES_BLOCK_LEN};
+use crate::digest_info::{decode_digest_info, encode_digest_info};
+use crate::r_encode::{decode_pem_block, encode_pem_block};
+#[cfg(feature = "std")]
+use crate::r_encode::{Base64Decoder, Base64Encoder};
+#[cfg(feature = "std")]
+use crate::r_enhanc::{io_error, take_blocks};
+use crate::r_random::RandomStruct;
+use crate::rsa::{PrivateKeyOps, RSAPublicKey};
+use crate::wipe::{SecretArray, SecretVec, Wiped};
+use crate::RSAError;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, Seek, SeekFrom, Write};
+
+const MESSAGE_BEGIN: &str = "-----BEGIN PRIVACY-ENHANCED MESSAGE-----";
+const MESSAGE_END: &str = "-----END PRIVACY-ENHANCED MESSAGE-----";
+
+/* RFC 1421 message processing types. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PEMProcType {
+    MicClear,
+    MicOnly,
+    Encrypted,
+}
+
+impl PEMProcType {
+    fn name(&self) -> &'static str {
+        match self {
+            PEMProcType::MicClear => "MIC-CLEAR",
+            PEMProcType::MicOnly => "MIC-ONLY",
+            PEMProcType::Encrypted => "ENCRYPTED",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, RSAError> {
+        match name {
+            "MIC-CLEAR" => Ok(PEMProcType::MicClear),
+            "MIC-ONLY" => Ok(PEMProcType::MicOnly),
+            "ENCRYPTED" => Ok(PEMProcType::Encrypted),
+            _ => Err(RSAError::ContentEncoding),
+        }
+    }
+}
+
+fn mic_algorithm_name(algorithm: DigestAlgorithmId) -> &'static str {
+    match algorithm {
+        DigestAlgorithmId::Md2 => "RSA-MD2",
+        DigestAlgorithmId::Md5 => "RSA-MD5",
+    }
+}
+
+fn mic_algorithm_from_name(name: &str) -> Result<DigestAlgorithmId, RSAError> {
+    match name {
+        "RSA-MD2" => Ok(DigestAlgorithmId::Md2),
+        "RSA-MD5" => Ok(DigestAlgorithmId::Md5),
+        _ => Err(RSAError::DigestAlgorithm),
+    }
+}
+
+/* Converts local text to the canonical form of RFC 1421, section 4.3.1:
+   every line, including a final unterminated one, ends in CR LF.
+*/
+fn canonicalize(text: &str) -> Vec<u8> {
+    let mut canonical = Vec::<u8>::with_capacity(text.len() + text.len() / 32);
+    for line in text.split_inclusive('\n') {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        canonical.extend(line.as_bytes());
+        canonical.extend(b"\r\n");
+    }
+    canonical
+}
+
+fn decanonicalize(canonical: &[u8]) -> Result<String, RSAError> {
+    let text = core::str::from_utf8(canonical).map_err(|_| RSAError::ContentEncoding)?;
+    Ok(text.replace("\r\n", "\n"))
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Result<Vec<u8>, RSAError> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(RSAError::Encoding);
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| RSAError::Encoding))
+        .collect()
+}
+
+/* Writes a header field, folding long values onto continuation lines. */
+fn push_field(output: &mut String, name: &str, value: &str) {
+    output.push_str(name);
+    output.push_str(": ");
+    output.push_str(&value.replace('\n', "\n "));
+    output.push('\n');
+}
+
+/* Encodes a key field value: the prefix, then base64 folded at
+   PEM_LINE_LEN.
+*/
+fn folded_base64(prefix: &str, data: &[u8]) -> String {
+    format!("{}\n{}", prefix, encode_pem_block(data))
+}
+
+/* The DEK of an ENCRYPTED message and its IV. */
+type DesKey = (SecretArray<8>, [u8; 8]);
+
+fn des_cbc(dek: &[u8; 8], iv: &[u8; 8], encrypt: bool, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+    if encrypt {
+        let padded: SecretVec = Wiped(pad_content(input, DES_BLOCK_LEN));
+        DESCBCContext::des(dek, iv, true).update(&padded)
+    } else {
+        let padded = DESCBCContext::des(dek, iv, false).update(input)?;
+        unpad_content(padded, DES_BLOCK_LEN)
+    }
+}
+
+/* The message up to and including the blank line after its header
+   fields, and for ENCRYPTED messages the DES key and IV the body is to be
+   encrypted under. mic_digest is the digest of the canonical text.
+*/
+fn encode_header(
+    proc_type: PEMProcType,
+    mic_algorithm: DigestAlgorithmId,
+    mic_digest: &[u8],
+    originator_public_key: &RSAPublicKey,
+    originator_private_key: &dyn PrivateKeyOps,
+    recipients: &[&RSAPublicKey],
+    random_struct: &mut RandomStruct,
+) -> Result<(String, Option<DesKey>), RSAError> {
+    if proc_type == PEMProcType::Encrypted && recipients.is_empty() {
+        return Err(RSAError::PublicKey);
+    }
+
+    let digest_info = encode_digest_info(mic_algorithm.digest_alg(), mic_digest)?;
+    let mut mic = originator_private_key.rsa_private_encrypt(&digest_info)?;
+
+    let mut output = String::new();
+    output.push_str(MESSAGE_BEGIN);
+    output.push('\n');
+    push_field(&mut output, "Proc-Type", &format!("4,{}", proc_type.name()));
+    push_field(&mut output, "Content-Domain", "RFC822");
+
+    let mut des_key = None;
+    let mut key_infos = Vec::<String>::with_capacity(recipients.len());
+    if proc_type == PEMProcType::Encrypted {
+        let (dek, iv) = random_struct.generate_key_iv::<8, 8>()?;
+        let dek = Wiped(dek);
+        push_field(&mut output, "DEK-Info", &format!("DES-CBC,{}", hex(&iv)));
+
+        mic = des_cbc(&dek, &iv, true, &mic)?;
+        for recipient in recipients {
+            key_infos.push(folded_base64(
+                "RSA,",
+                &recipient.rsa_public_encrypt(dek.as_slice(), random_struct)?,
+            ));
+        }
+        des_key = Some((dek, iv));
+    }
+
+    push_field(
+        &mut output,
+        "Originator-Key-Asymmetric",
+        &encode_pem_block(&encode_subject_public_key_info(originator_public_key)),
+    );
+    push_field(
+        &mut output,
+        "MIC-Info",
+        &folded_base64(&format!("{},RSA,", mic_algorithm_name(mic_algorithm)), &mic),
+    );
+    for (recipient, key_info) in recipients.iter().zip(&key_infos) {
+        push_field(
+            &mut output,
+            "Recipient-Key-Asymmetric",
+            &encode_pem_block(&encode_subject_public_key_info(recipient)),
+        );
+        push_field(&mut output, "Key-Info", key_info);
+    }
+    output.push('\n');
+    Ok((output, des_key))
+}
+
+/* Produces an RFC 1421 message from local text. The originator signs the
+   MIC; the originator's public key is carried in an
+   Originator-Key-Asymmetric field so the message can be checked without a
+   certificate. ENCRYPTED messages protect the content and MIC under a
+   fresh DES-CBC key that is RSA-encrypted for every recipient.
+*/
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(proc_type = ?proc_type), err(level = "debug"))
+)]
+pub fn encode_pem_message(
+    text: &str,
+    proc_type: PEMProcType,
+    mic_algorithm: DigestAlgorithmId,
+    originator_public_key: &RSAPublicKey,
+    originator_private_key: &dyn PrivateKeyOps,
+    recipients: &[&RSAPublicKey],
+    random_struct: &mut RandomStruct,
+) -> Result<String, RSAError> {
+    let canonical = canonicalize(text);
+    let (mut output, des_key) = encode_header(
+        proc_type,
+        mic_algorithm,
+        &digest(mic_algorithm, &canonical),
+        originator_public_key,
+        originator_private_key,
+        recipients,
+        random_struct,
+    )?;
+
+    if proc_type == PEMProcType::MicClear {
+        /* Lines beginning with '-' are quoted with "- " (RFC 934). */
+        for line in text.lines() {
+            if line.starts_with('-') {
+                output.push_str("- ");
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+    } else {
+        let body = match &des_key {
+            Some((dek, iv)) => des_cbc(dek, iv, true, &canonical)?,
+            None => canonical,
+        };
+        output.push_str(&encode_pem_block(&body));
+        output.push('\n');
+    }
+
+    output.push_str(MESSAGE_END);
+    output.push('\n');
+    Ok(output)
+}
+
+/* A verified message: its processing type, the recovered local text and
+   the originator's public key, whose MIC has been checked.
+*/
+#[derive(Debug)]
+pub struct PEMMessage {
+    proc_type: PEMProcType,
+    text: String,
+    originator_public_key: RSAPublicKey,
+}
+
+impl PEMMessage {
+    pub fn proc_type(&self) -> PEMProcType {
+        self.proc_type
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn originator_public_key(&self) -> &RSAPublicKey {
+        &self.originator_public_key
+    }
+}
+
+/* Parses the header fields, unfolding continuation lines. */
+pub(crate) fn parse_fields<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    let mut fields = Vec::<(String, String)>::new();
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    fields
+}
+
+/* What a message's header fields say, with the MIC decrypted and, for
+   ENCRYPTED messages, the DES key recovered with the recipient's private
+   key.
+*/
+struct MessageHeader {
+    proc_type: PEMProcType,
+    originator_public_key: RSAPublicKey,
+    mic_algorithm: DigestAlgorithmId,
+    mic: Vec<u8>,
+    des_key: Option<DesKey>,
+}
+
+fn decode_header(
+    fields: &[(String, String)],
+    recipient_private_key: Option<&dyn PrivateKeyOps>,
+) -> Result<MessageHeader, RSAError> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value.as_str())
+            .ok_or(RSAError::Encoding)
+    };
+
+    let proc_type = PEMProcType::from_name(
+        field("Proc-Type")?
+            .strip_prefix("4,")
+            .ok_or(RSAError::ContentEncoding)?,
+    )?;
+
+    let originator_key = decode_pem_block(field("Originator-Key-Asymmetric")?)?;
+    let (originator_public_key, rest) = decode_subject_public_key_info(&originator_key)?;
+    if !rest.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+
+    let mic_info = field("MIC-Info")?.splitn(3, ',').collect::<Vec<&str>>();
+    let [mic_algorithm, "RSA", mic] = mic_info[..] else {
+        return Err(RSAError::Encoding);
+    };
+    let mic_algorithm = mic_algorithm_from_name(mic_algorithm)?;
+    let mut mic = decode_pem_block(mic)?;
+
+    let mut des_key = None;
+    if proc_type == PEMProcType::Encrypted {
+        let private_key = recipient_private_key.ok_or(RSAError::PrivateKey)?;
+        let own_key = encode_subject_public_key_info(&private_key.public_key());
+
+        let (dek_algorithm, iv) = field("DEK-Info")?
+            .split_once(',')
+            .ok_or(RSAError::Encoding)?;
+        if dek_algorithm != "DES-CBC" {
+            return Err(RSAError::EncryptionAlgorithm);
+        }
+        let iv: [u8; 8] = from_hex(iv)?.try_into().map_err(|_| RSAError::Encoding)?;
+
+        /* Each Recipient-Key-Asymmetric is followed by its Key-Info. */
+        let key_info = fields
+            .iter()
+            .zip(fields.iter().skip(1))
+            .find(|(recipient, key_info)| {
+                recipient.0 == "Recipient-Key-Asymmetric"
+                    && key_info.0 == "Key-Info"
+                    && decode_pem_block(&recipient.1).is_ok_and(|key| key == own_key)
+            })
+            .map(|(_, key_info)| key_info.1.as_str())
+            .ok_or(RSAError::PrivateKey)?;
+        let encrypted_dek = key_info.strip_prefix("RSA,").ok_or(RSAError::Encoding)?;
+        let decrypted = Wiped(private_key.rsa_private_decrypt(&decode_pem_block(encrypted_dek)?)?);
+        let dek = Wiped::<[u8; 8]>(decrypted.as_slice().try_into().map_err(|_| RSAError::Key)?);
+
+        mic = des_cbc(&dek, &iv, false, &mic)?;
+        des_key = Some((dek, iv));
+    }
+
+    Ok(MessageHeader {
+        proc_type,
+        originator_public_key,
+        mic_algorithm,
+        mic,
+        des_key,
+    })
+}
+
+impl MessageHeader {
+    /* Checks the MIC against mic_digest, the digest of the canonical
+       text, failing with Signature.
+    */
+    fn verify(&self, mic_digest: &[u8]) -> Result<(), RSAError> {
+        let digest_info = self
+            .originator_public_key
+            .rsa_public_decrypt(&self.mic)
+            .map_err(|_| RSAError::Signature)?;
+        let (alg, signed_digest) = decode_digest_info(&digest_info)?;
+        if alg != self.mic_algorithm.digest_alg() || !ct_eq(&signed_digest, mic_digest) {
+            return Err(RSAError::Signature);
+        }
+        Ok(())
+    }
+}
+
+/* Parses and verifies an RFC 1421 message produced by encode_pem_message.
+   ENCRYPTED messages need the private key of one of the recipients.
+*/
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = message.len()), err(level = "debug"))
+)]
+pub fn decode_pem_message(
+    message: &str,
+    recipient_private_key: Option<&dyn PrivateKeyOps>,
+) -> Result<PEMMessage, RSAError> {
+    let mut lines = message
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .skip_while(|line| *line != MESSAGE_BEGIN)
+        .skip(1);
+
+    let fields = parse_fields(&mut lines);
+    let mut body_lines = Vec::<&str>::new();
+    let mut terminated = false;
+    for line in lines {
+        if line == MESSAGE_END {
+            terminated = true;
+            break;
+        }
+        body_lines.push(line);
+    }
+    if !terminated {
+        return Err(RSAError::Encoding);
+    }
+
+    let header = decode_header(&fields, recipient_private_key)?;
+    let canonical = match (&header.proc_type, &header.des_key) {
+        (PEMProcType::MicClear, _) => {
+            let mut text = String::new();
+            for line in body_lines {
+                text.push_str(line.strip_prefix("- ").unwrap_or(line));
+                text.push('\n');
+            }
+            canonicalize(&text)
+        }
+        (_, Some((dek, iv))) => des_cbc(dek, iv, false, &decode_pem_block(&body_lines.concat())?)?,
+        (_, None) => decode_pem_block(&body_lines.concat())?,
+    };
+
+    header.verify(&digest(header.mic_algorithm, &canonical))?;
+    Ok(PEMMessage {
+        proc_type: header.proc_type,
+        text: decanonicalize(&canonical)?,
+        originator_public_key: header.originator_public_key,
+    })
+}
+
+/* Lines of streamed text longer than this fail with Len, which bounds the
+   memory the stream functions use.
+*/
+#[cfg(feature = "std")]
+const MAX_STREAM_LINE_LEN: usize = 65536;
+
+/* Reads the next line, line ending included, into line. Returns false at
+   the end of input.
+*/
+#[cfg(feature = "std")]
+fn read_line(input: &mut impl BufRead, line: &mut String) -> io::Result<bool> {
+    let mut bytes = Vec::<u8>::new();
+    io::Read::take(input, MAX_STREAM_LINE_LEN as u64 + 1).read_until(b'\n', &mut bytes)?;
+    if bytes.len() > MAX_STREAM_LINE_LEN {
+        return Err(io_error(RSAError::Len));
+    }
+    line.clear();
+    line.push_str(core::str::from_utf8(&bytes).map_err(|_| io_error(RSAError::ContentEncoding))?);
+    Ok(!bytes.is_empty())
+}
+
+/* A line read by read_line as str::lines yields it. */
+#[cfg(feature = "std")]
+fn strip_line_ending(line: &str) -> &str {
+    match line.strip_suffix('\n') {
+        Some(line) => line.strip_suffix('\r').unwrap_or(line),
+        None => line,
+    }
+}
+
+/* A line read by read_line as canonicalize sees it, without its CRLF. */
+#[cfg(feature = "std")]
+fn canonical_line(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/* encode_pem_message over streams: reads the text from input, which must
+   be seekable because the MIC heads the message and needs a first pass
+   over the text, and writes the message to output. Memory use is bounded
+   by the longest line. The output is the same as encode_pem_message
+   produces for the same text and random state.
+*/
+#[cfg(feature = "std")]
+#[allow(clippy::too_many_arguments)]
+pub fn encode_pem_message_stream<R: BufRead + Seek, W: Write>(
+    mut input: R,
+    mut output: W,
+    proc_type: PEMProcType,
+    mic_algorithm: DigestAlgorithmId,
+    originator_public_key: &RSAPublicKey,
+    originator_private_key: &dyn PrivateKeyOps,
+    recipients: &[&RSAPublicKey],
+    random_struct: &mut RandomStruct,
+) -> io::Result<()> {
+    let start = input.stream_position()?;
+    let mut context = DigestContext::new(mic_algorithm);
+    let mut line = String::new();
+    while read_line(&mut input, &mut line)? {
+        context.update(canonical_line(&line).as_bytes());
+        context.update(b"\r\n");
+    }
+
+    let (header, des_key) = encode_header(
+        proc_type,
+        mic_algorithm,
+        &context.finalize(),
+        originator_public_key,
+        originator_private_key,
+        recipients,
+        random_struct,
+    )
+    .map_err(io_error)?;
+    output.write_all(header.as_bytes())?;
+    input.seek(SeekFrom::Start(start))?;
+
+    if proc_type == PEMProcType::MicClear {
+        while read_line(&mut input, &mut line)? {
+            let line = strip_line_ending(&line);
+            if line.starts_with('-') {
+                output.write_all(b"- ")?;
+            }
+            output.write_all(line.as_bytes())?;
+            output.write_all(b"\n")?;
+        }
+    } else {
+        let mut cipher = des_key
+            .as_ref()
+            .map(|(dek, iv)| DESCBCContext::des(dek, iv, true));
+        let mut pending: SecretVec = Wiped(Vec::new());
+        let mut encoder = Base64Encoder::new();
+        let mut encoded = String::new();
+        while read_line(&mut input, &mut line)? {
+            encoded.clear();
+            if let Some(cipher) = cipher.as_mut() {
+                pending.extend(canonical_line(&line).as_bytes());
+                pending.extend(b"\r\n");
+                let blocks: SecretVec = Wiped(take_blocks(&mut pending, DES_BLOCK_LEN, false));
+                encoder.update(&cipher.update(&blocks).map_err(io_error)?, &mut encoded);
+            } else {
+                encoder.update(canonical_line(&line).as_bytes(), &mut encoded);
+                encoder.update(b"\r\n", &mut encoded);
+            }
+            output.write_all(encoded.as_bytes())?;
+        }
+
+        encoded.clear();
+        if let Some(cipher) = cipher.as_mut() {
+            let padded: SecretVec = Wiped(pad_content(&pending, DES_BLOCK_LEN));
+            encoder.update(&cipher.update(&padded).map_err(io_error)?, &mut encoded);
+        }
+        encoder.finish(&mut encoded);
+        output.write_all(encoded.as_bytes())?;
+        output.write_all(b"\n")?;
+    }
+
+    output.write_all(MESSAGE_END.as_bytes())?;
+    output.write_all(b"\n")?;
+    output.flush()
+}
+
+/* Writes decanonicalized text to output as its lines complete. The bytes
+   after the last complete line stay in buffer.
+*/
+#[cfg(feature = "std")]
+fn write_text(buffer: &mut SecretVec, output: &mut impl Write, end: bool) -> io::Result<()> {
+    let complete_len = match buffer.iter().rposition(|b| *b == b'\n') {
+        _ if end => buffer.len(),
+        Some(position) => position + 1,
+        None if buffer.len() > MAX_STREAM_LINE_LEN => return Err(io_error(RSAError::Len)),
+        None => return Ok(()),
+    };
+    let rest = buffer.split_off(complete_len);
+    let complete: SecretVec = Wiped(core::mem::replace(&mut **buffer, rest));
+    output.write_all(decanonicalize(&complete).map_err(io_error)?.as_bytes())
+}
+
+/* Reads the next line of a message into line, without its line ending
+   as decode_pem_message sees it. Returns false at MESSAGE_END and fails
+   with Encoding if the input ends first.
+*/
+#[cfg(feature = "std")]
+fn read_message_line(input: &mut impl BufRead, line: &mut String) -> io::Result<bool> {
+    if !read_line(input, line)? {
+        return Err(io_error(RSAError::Encoding));
+    }
+    let stripped = strip_line_ending(line);
+    let stripped = stripped.strip_suffix('\r').unwrap_or(stripped);
+    line.truncate(stripped.len());
+    Ok(line != MESSAGE_END)
+}
+
+/* decode_pem_message over streams: reads the message from input and
+   writes its text to output, returning the processing type and the
+   originator's public key. Memory use is bounded by the header and the
+   longest line. The MIC can only be checked at the end, so text has
+   already been written when an Err reports a bad MIC or a corrupt body;
+   the output must then be discarded.
+*/
+#[cfg(feature = "std")]
+pub fn decode_pem_message_stream<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    recipient_private_key: Option<&dyn PrivateKeyOps>,
+) -> io::Result<(PEMProcType, RSAPublicKey)> {
+    let mut line = String::new();
+    while read_message_line(&mut input, &mut line)? && line != MESSAGE_BEGIN {}
+    let mut header_lines = Vec::<String>::new();
+    while read_message_line(&mut input, &mut line)? && !line.is_empty() {
+        header_lines.push(line.clone());
+    }
+    let fields = parse_fields(&mut header_lines.iter().map(String::as_str));
+    let header = decode_header(&fields, recipient_private_key).map_err(io_error)?;
+
+    let mut context = DigestContext::new(header.mic_algorithm);
+    if header.proc_type == PEMProcType::MicClear {
+        while read_message_line(&mut input, &mut line)? {
+            let text = line.strip_prefix("- ").unwrap_or(&line);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            context.update(text.as_bytes());
+            context.update(b"\r\n");
+            output.write_all(text.as_bytes())?;
+            output.write_all(b"\n")?;
+        }
+    } else {
+        let mut cipher = header
+            .des_key
+            .as_ref()
+            .map(|(dek, iv)| DESCBCContext::des(dek, iv, false));
+        let mut decoder = Base64Decoder::new();
+        let mut pending: SecretVec = Wiped(Vec::new());
+        let mut text: SecretVec = Wiped(Vec::new());
+        while read_message_line(&mut input, &mut line)? {
+            let mut decoded: SecretVec = Wiped(Vec::new());
+            decoder
+                .update(line.as_bytes(), &mut decoded)
+                .map_err(io_error)?;
+            let plain: SecretVec = match cipher.as_mut() {
+                Some(cipher) => {
+                    pending.extend(decoded.iter());
+                    let blocks: SecretVec = Wiped(take_blocks(&mut pending, DES_BLOCK_LEN, true));
+                    Wiped(cipher.update(&blocks).map_err(io_error)?)
+                }
+                None => decoded,
+            };
+            context.update(&plain);
+            text.extend(plain.iter());
+            write_text(&mut text, &mut output, false)?;
+        }
+
+        decoder.finish().map_err(io_error)?;
+        if let Some(cipher) = cipher.as_mut() {
+            let padded = cipher.update(&pending).map_err(io_error)?;
+            let plain: SecretVec = Wiped(unpad_content(padded, DES_BLOCK_LEN).map_err(io_error)?);
+            context.update(&plain);
+            text.extend(plain.iter());
+        }
+        write_text(&mut text, &mut output, true)?;
+    }
+
+    header.verify(&context.finalize()).map_err(io_error)?;
+    output.flush()?;
+    Ok((header.proc_type, header.originator_public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use crate::rsa::RSAPrivateKey;
+
+    const TEXT: &str = "Dear Bob,\n-- not a boundary\n\nRegards,\nAlice\n";
+
+    fn generate_keys() -> (RSAPublicKey, RSAPrivateKey) {
+        test_key_pair(512)
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonicalize("a\nb\r\nc"), b"a\r\nb\r\nc\r\n");
+        assert_eq!(decanonicalize(b"a\r\nb\r\n").unwrap(), "a\nb\n");
+    }
+
+    #[test]
+    fn test_pem_message_round_trip() {
+        let mut random_struct = test_random_struct(0x21);
+        let (originator_public, originator_private) = generate_keys();
+        let (recipient_public, recipient_private) = generate_keys();
+        let (other_public, other_private) = generate_keys();
+
+        for proc_type in [
+            PEMProcType::MicClear,
+            PEMProcType::MicOnly,
+            PEMProcType::Encrypted,
+        ] {
+            for mic_algorithm in [DigestAlgorithmId::Md2, DigestAlgorithmId::Md5] {
+                let message = encode_pem_message(
+                    TEXT,
+                    proc_type,
+                    mic_algorithm,
+                    &originator_public,
+                    &originator_private,
+                    &[&other_public, &recipient_public],
+                    &mut random_struct,
+                )
+                .unwrap();
+                assert!(message.contains(&format!("Proc-Type: 4,{}", proc_type.name())));
+                assert_eq!(
+                    message.contains("- -- not a boundary"),
+                    proc_type == PEMProcType::MicClear
+                );
+
+                let decoded = decode_pem_message(&message, Some(&recipient_private)).unwrap();
+                assert_eq!(decoded.proc_type(), proc_type);
+                assert_eq!(decoded.text(), TEXT);
+                assert_eq!(
+                    decoded.originator_public_key().modulus(),
+                    originator_public.modulus()
+                );
+                assert!(decode_pem_message(&message, Some(&other_private)).is_ok());
+
+                if proc_type == PEMProcType::Encrypted {
+                    assert!(matches!(
+                        decode_pem_message(&message, None),
+                        Err(RSAError::PrivateKey)
+                    ));
+                    assert!(decode_pem_message(&message, Some(&originator_private)).is_err());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pem_message_rejects_tampering() {
+        let mut random_struct = test_random_struct(0x22);
+        let (public_key, private_key) = generate_keys();
+
+        let message = encode_pem_message(
+            TEXT,
+            PEMProcType::MicClear,
+            DigestAlgorithmId::Md5,
+            &public_key,
+            &private_key,
+            &[],
+            &mut random_struct,
+        )
+        .unwrap();
+
+        let tampered = message.replace("Regards", "Regrets");
+        assert!(matches!(
+            decode_pem_message(&tampered, None),
+            Err(RSAError::Signature)
+        ));
+
+        let truncated = message.replace(MESSAGE_END, "");
+        assert!(decode_pem_message(&truncated, None).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_pem_message_streams() {
+        use std::io::Cursor;
+
+        let mut random_struct = test_random_struct(0x23);
+        let (originator_public, originator_private) = generate_keys();
+        let (recipient_public, recipient_private) = generate_keys();
+        let text = TEXT.repeat(500) + "no newline at the end";
+
+        for proc_type in [
+            PEMProcType::MicClear,
+            PEMProcType::MicOnly,
+            PEMProcType::Encrypted,
+        ] {
+            let mut stream_random = RandomStruct::decode(&random_struct.encode()).unwrap();
+            let message = encode_pem_message(
+                &text,
+                proc_type,
+                DigestAlgorithmId::Md5,
+                &originator_public,
+                &originator_private,
+                &[&recipient_public],
+                &mut random_struct,
+            )
+            .unwrap();
+
+            let mut streamed = Vec::new();
+            encode_pem_message_stream(
+                Cursor::new(text.as_bytes()),
+                &mut streamed,
+                proc_type,
+                DigestAlgorithmId::Md5,
+                &originator_public,
+                &originator_private,
+                &[&recipient_public],
+                &mut stream_random,
+            )
+            .unwrap();
+            assert_eq!(String::from_utf8(streamed).unwrap(), message);
+
+            let mut decoded = Vec::new();
+            let (decoded_type, decoded_key) = decode_pem_message_stream(
+                message.as_bytes(),
+                &mut decoded,
+                Some(&recipient_private),
+            )
+            .unwrap();
+            assert_eq!(decoded_type, proc_type);
+            assert_eq!(decoded_key.modulus(), originator_public.modulus());
+            assert_eq!(
+                String::from_utf8(decoded).unwrap(),
+                decode_pem_message(&message, Some(&recipient_private))
+                    .unwrap()
+                    .text()
+            );
+
+            if proc_type == PEMProcType::MicClear {
+                let tampered = message.replacen("Alice", "Alicf", 1);
+                let error = decode_pem_message_stream(tampered.as_bytes(), &mut Vec::new(), None)
+                    .unwrap_err();
+                assert_eq!(
+                    error.get_ref().unwrap().downcast_ref::<RSAError>(),
+                    Some(&RSAError::Signature)
+                );
+            }
+
+            let truncated = message.replace(MESSAGE_END, "");
+            assert!(decode_pem_message_stream(
+                truncated.as_bytes(),
+                &mut Vec::new(),
+                Some(&recipient_private)
+            )
+            .is_err());
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/* PKCS #1 v1.5 block formatting (RFC 8017, 8.2 and 9.2) for callers that
+   apply the raw RSA primitive themselves. Blocks are the modulus length:
+     type 1, for private-key operations:  00 || 01 || FF ... FF || 00 || message
+     type 2, for public-key operations:   00 || 02 || nonzero random || 00 || message
+   with at least 8 padding bytes, so a message is at most modulus_len - 11
+   bytes. Type 2 is for encryption and is only in the full build.
+*/
+#[cfg(feature = "full")]
+use crate::r_random::RandomStruct;
+use crate::RSAError;
+use alloc::vec::Vec;
+#[cfg(feature = "full")]
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeLess};
+
+/* The smallest block: header, 8 padding bytes and the separator. */
+const MIN_BLOCK_LEN: usize = 11;
+
+/* Fails with Len if message is more than modulus_len - 11 bytes. */
+pub fn pad_type1(message: &[u8], modulus_len: usize) -> Result<Vec<u8>, RSAError> {
+    if message.len() + MIN_BLOCK_LEN > modulus_len {
+        return Err(RSAError::Len);
+    }
+
+    let mut block = Vec::<u8>::with_capacity(modulus_len);
+    /* block type 1 */
+    block.extend([0, 1]);
+    block.resize(modulus_len - message.len() - 1, 0xFF);
+
+    /* separator */
+    block.push(0);
+    block.extend(message);
+
+    Ok(block)
+}
+
+/* The message of a type 1 block. Fails with Len for a block shorter than
+   11 bytes and with Data for any other malformed block, including an
+   empty message. It is not constant-time, as type 1 blocks are public.
+*/
+pub fn unpad_type1(block: &[u8]) -> Result<Vec<u8>, RSAError> {
+    if block.len() < MIN_BLOCK_LEN {
+        return Err(RSAError::Len);
+    }
+
+    /* Require block type 1. */
+    let (header, padded) = block.split_at_checked(2).ok_or(RSAError::Len)?;
+    if header != [0, 1] {
+        return Err(RSAError::Data);
+    }
+
+    /* At least 8 FF bytes, then a zero separator before the last byte. */
+    let padding_len = padded.iter().take_while(|e| **e == 0xFF).count();
+    if padding_len < 8 || padding_len + 1 >= padded.len() || padded.get(padding_len) != Some(&0) {
+        return Err(RSAError::Data);
+    }
+
+    Ok(padded.iter().skip(padding_len + 1).copied().collect())
+}
+
+/* Fails with Len if message is more than modulus_len - 11 bytes, and with
+   NeedRandom if random_struct is not seeded.
+*/
+#[cfg(feature = "full")]
+pub fn pad_type2(
+    message: &[u8],
+    modulus_len: usize,
+    random_struct: &mut RandomStruct,
+) -> Result<Vec<u8>, RSAError> {
+    let mut block = Vec::new();
+    pad_type2_into(message, modulus_len, random_struct, &mut block)?;
+    Ok(block)
+}
+
+/* pad_type2 into block, cleared first, so one buffer can serve a batch. */
+#[cfg(feature = "full")]
+pub(crate) fn pad_type2_into(
+    message: &[u8],
+    modulus_len: usize,
+    random_struct: &mut RandomStruct,
+    block: &mut Vec<u8>,
+) -> Result<(), RSAError> {
+    if message.len() + MIN_BLOCK_LEN > modulus_len {
+        return Err(RSAError::Len);
+    }
+
+    block.clear();
+    block.reserve(modulus_len);
+    /* block type 2 */
+    block.extend([0, 2]);
+
+    /* Zero bytes are drawn and discarded until a nonzero one comes, so
+       the loop branches only on bytes that are not kept.
+    */
+    for _ in 0..(modulus_len - message.len() - 3) {
+        let mut e = 0u8;
+        loop {
+            let random_byte = random_struct.generate_bytes(1)?;
+            let random_byte = *random_byte.first().ok_or(RSAError::NeedRandom)?;
+            let nonzero = !random_byte.ct_eq(&0);
+            e.conditional_assign(&random_byte, nonzero);
+            if bool::from(nonzero) {
+                break;
+            }
+        }
+        block.push(e);
+    }
+
+    /* separator */
+    block.push(0);
+    block.extend(message);
+    Ok(())
+}
+
+/* The message of a type 2 block. Fails with Len for a block shorter than
+   11 bytes and with Data for any other malformed block. The checks are
+   made over the whole block with one decision at the end, so timing does
+   not show which failed; the caller must not reveal more through how it
+   reports the error.
+*/
+#[cfg(feature = "full")]
+pub fn unpad_type2(block: &[u8]) -> Result<Vec<u8>, RSAError> {
+    if block.len() < MIN_BLOCK_LEN {
+        return Err(RSAError::Len);
+    }
+
+    /* Require block type 2, a zero separator before the last byte and
+       at least 8 padding bytes, deciding once over the whole block.
+    */
+    let header = block.get(..2).ok_or(RSAError::Len)?;
+    let mut good = header.ct_eq(&[0, 2]);
+    let mut found = Choice::from(0);
+    let mut separator_start = 0u32;
+    for (i, e) in block.iter().enumerate().take(block.len() - 1).skip(2) {
+        let is_separator = e.ct_eq(&0) & !found;
+        separator_start.conditional_assign(&(i as u32), is_separator);
+        found |= is_separator;
+    }
+    good &= found & !separator_start.ct_lt(&10);
+    if !bool::from(good) {
+        return Err(RSAError::Data);
+    }
+
+    let i = separator_start as usize + 1;
+    Ok(block.iter().skip(i).copied().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "full")]
+    use crate::r_keygen::test_random_struct;
+
+    #[test]
+    fn test_type1_padding() {
+        let block = pad_type1(b"digest info", 64).unwrap();
+        assert_eq!(block.len(), 64);
+        assert_eq!(block[..2], [0, 1]);
+        assert!(block[2..52].iter().all(|b| *b == 0xFF));
+        assert_eq!(block[52], 0);
+        assert_eq!(unpad_type1(&block).unwrap(), b"digest info");
+
+        let longest = pad_type1(&[0u8; 53], 64).unwrap();
+        assert_eq!(
+            longest[..11],
+            [0, 1, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0]
+        );
+        assert_eq!(unpad_type1(&longest).unwrap(), [0u8; 53]);
+        assert_eq!(pad_type1(&[0u8; 54], 64).err(), Some(RSAError::Len));
+        assert_eq!(pad_type1(b"", 10).err(), Some(RSAError::Len));
+
+        let mut wrong_type = block.clone();
+        wrong_type[1] = 2;
+        let mut nonzero_first = block.clone();
+        nonzero_first[0] = 1;
+        let mut bad_padding = block.clone();
+        bad_padding[5] = 0xFE;
+        let mut short_padding = block.clone();
+        short_padding[9] = 0;
+        let mut no_separator = block.clone();
+        no_separator[52] = 0xFF;
+        let mut empty_message = vec![0xFFu8; 64];
+        empty_message[..2].copy_from_slice(&[0, 1]);
+        empty_message[63] = 0;
+        for bad in [
+            wrong_type,
+            nonzero_first,
+            bad_padding,
+            short_padding,
+            no_separator,
+            empty_message,
+        ] {
+            assert_eq!(unpad_type1(&bad).err(), Some(RSAError::Data));
+        }
+        assert_eq!(unpad_type1(&block[..10]).err(), Some(RSAError::Len));
+        assert_eq!(unpad_type1(&[]).err(), Some(RSAError::Len));
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_type2_padding() {
+        let mut random_struct = test_random_struct(0x2b);
+        let block = pad_type2(b"content key", 64, &mut random_struct).unwrap();
+        assert_eq!(block.len(), 64);
+        assert_eq!(block[..2], [0, 2]);
+        assert!(block[2..52].iter().all(|b| *b != 0));
+        assert_eq!(block[52], 0);
+        assert_eq!(unpad_type2(&block).unwrap(), b"content key");
+        assert_ne!(
+            pad_type2(b"content key", 64, &mut random_struct).unwrap(),
+            block
+        );
+
+        let longest = pad_type2(&[7u8; 53], 64, &mut random_struct).unwrap();
+        assert_eq!(unpad_type2(&longest).unwrap(), [7u8; 53]);
+        assert_eq!(
+            pad_type2(&[7u8; 54], 64, &mut random_struct).err(),
+            Some(RSAError::Len)
+        );
+        assert_eq!(
+            pad_type2(b"key", 64, &mut RandomStruct::new()).err(),
+            Some(RSAError::NeedRandom)
+        );
+
+        /* A separator in the last byte would leave no message. */
+        let mut empty_message = vec![0x01u8; 64];
+        empty_message[..2].copy_from_slice(&[0, 2]);
+        empty_message[63] = 0;
+        assert_eq!(unpad_type2(&empty_message).err(), Some(RSAError::Data));
+        empty_message[62] = 0;
+        assert_eq!(unpad_type2(&empty_message).unwrap(), [0]);
+
+        let mut wrong_type = block.clone();
+        wrong_type[1] = 1;
+        let mut nonzero_first = block.clone();
+        nonzero_first[0] = 1;
+        let mut short_padding = block.clone();
+        short_padding[9] = 0;
+        let mut no_separator = block.clone();
+        no_separator[52] = 0x55;
+        for bad in [wrong_type, nonzero_first, short_padding, no_separator] {
+            assert_eq!(unpad_type2(&bad).err(), Some(RSAError::Data));
+        }
+        /* Eight padding bytes are enough. */
+        let mut eight = block.clone();
+        eight[10] = 0;
+        assert_eq!(unpad_type2(&eight).unwrap(), block[11..]);
+        assert_eq!(unpad_type2(&block[..10]).err(), Some(RSAError::Len));
+    }
+}
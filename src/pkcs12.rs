@@ -0,0 +1,387 @@
+use crate::aes_cbc::AESCBCContext;
+use crate::cms::pad_content;
+use crate::der::{
+    algorithm_identifier, context_tag, integer, set_of, tlv, TAG_BMP_STRING, TAG_OBJECT_IDENTIFIER,
+    TAG_OCTET_STRING, TAG_SEQUENCE,
+};
+use crate::des_cbc::DESCBCContext;
+use crate::digest_info::{encode_digest_info, DigestAlg};
+use crate::r_random::RandomStruct;
+use crate::rc2_cbc::RC2CBCContext;
+use crate::rsa::RSAPrivateKey;
+use crate::wipe::{SecretVec, Wiped};
+use crate::RSAError;
+use alloc::vec::Vec;
+use hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+
+const OID_PBE_SHA1_RC2_40_CBC: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x01, 0x06];
+const OID_PBE_SHA1_DES_EDE3_CBC: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x01, 0x03];
+const OID_PBES2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+const OID_PBKDF2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+const OID_HMAC_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09];
+const OID_AES_256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+
+const OID_KEY_BAG_SHROUDED: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x02,
+];
+const OID_CERT_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x03,
+];
+const OID_X509_CERTIFICATE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x16, 0x01];
+const OID_FRIENDLY_NAME: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x14];
+const OID_LOCAL_KEY_ID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x15];
+
+const SALT_LEN: usize = 8;
+const PBES2_SALT_LEN: usize = 16;
+
+/* PKCS #12 KDF diversifiers (RFC 7292, appendix B.3). */
+const KDF_KEY: u8 = 1;
+const KDF_IV: u8 = 2;
+const KDF_MAC: u8 = 3;
+
+/* Private key protection. The two PKCS #12 PBE modes are what older
+   browsers, Java and Windows expect; PBES2 with AES-256 is the modern
+   choice.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PKCS12Encryption {
+    Sha1Rc2Cbc40,
+    Sha1DesEde3Cbc,
+    Pbes2Aes256Cbc,
+}
+
+/* Password as a NUL-terminated big-endian BMPString. */
+fn bmp_password(password: &str) -> Vec<u8> {
+    let mut bmp = password
+        .encode_utf16()
+        .flat_map(|c| c.to_be_bytes())
+        .collect::<Vec<u8>>();
+    bmp.extend([0, 0]);
+    bmp
+}
+
+/* Repeats input to fill a whole number of block_len blocks. */
+fn fill_blocks(input: &[u8], block_len: usize) -> Vec<u8> {
+    let len = input.len().div_ceil(block_len) * block_len;
+    input.iter().copied().cycle().take(len).collect()
+}
+
+/* RFC 7292, appendix B.2, with SHA-1. */
+pub(crate) fn pkcs12_kdf(
+    password: &[u8],
+    salt: &[u8],
+    id: u8,
+    iterations: u32,
+    output_len: usize,
+) -> Vec<u8> {
+    const U: usize = 20;
+    const V: usize = 64;
+
+    let diversifier = [id; V];
+    let mut i = fill_blocks(salt, V);
+    i.extend(fill_blocks(password, V));
+
+    let mut output = Vec::<u8>::with_capacity(output_len.div_ceil(U) * U);
+    while output.len() < output_len {
+        let mut a = Sha1::new()
+            .chain_update(diversifier)
+            .chain_update(&i)
+            .finalize();
+        for _ in 1..iterations {
+            a = Sha1::digest(a);
+        }
+        output.extend(a);
+
+        /* I_j = (I_j + B + 1) mod 2^(8v) for each block of I. */
+        let b = fill_blocks(&a, V);
+        for block in i.chunks_mut(V) {
+            let mut carry = 1u16;
+            for (x, y) in block.iter_mut().zip(&b).rev() {
+                let sum = *x as u16 + *y as u16 + carry;
+                *x = sum as u8;
+                carry = sum >> 8;
+            }
+        }
+    }
+
+    output.truncate(output_len);
+    output
+}
+
+/* PrivateKeyInfo (PKCS #8) holding a PKCS #1 RSAPrivateKey. */
+pub(crate) fn encode_private_key_info(private_key: &RSAPrivateKey) -> Vec<u8> {
+    let mut rsa_private_key = integer(&[0]);
+    for component in [
+        private_key.modulus(),
+        private_key.public_exponent(),
+        private_key.exponent(),
+        &private_key.prime()[0],
+        &private_key.prime()[1],
+        &private_key.prime_exponent()[0],
+        &private_key.prime_exponent()[1],
+        private_key.coefficient(),
+    ] {
+        rsa_private_key.extend(integer(&component.to_bytes_be()));
+    }
+
+    let mut info = integer(&[0]);
+    info.extend(algorithm_identifier(OID_RSA_ENCRYPTION));
+    info.extend(tlv(TAG_OCTET_STRING, &tlv(TAG_SEQUENCE, &rsa_private_key)));
+    tlv(TAG_SEQUENCE, &info)
+}
+
+/* Encrypts a PrivateKeyInfo, returning the EncryptedPrivateKeyInfo. */
+fn encrypt_private_key_info(
+    info: &[u8],
+    password: &str,
+    encryption: PKCS12Encryption,
+    iterations: u32,
+    random_struct: &mut RandomStruct,
+) -> Result<Vec<u8>, RSAError> {
+    let (algorithm, encrypted) = match encryption {
+        PKCS12Encryption::Sha1Rc2Cbc40 | PKCS12Encryption::Sha1DesEde3Cbc => {
+            let salt = random_struct.generate_bytes(SALT_LEN)?;
+            let bmp = bmp_password(password);
+            let iv: [u8; 8] = pkcs12_kdf(&bmp, &salt, KDF_IV, iterations, 8)
+                .try_into()
+                .map_err(|_| RSAError::Key)?;
+            let padded: SecretVec = Wiped(pad_content(info, 8));
+
+            let (oid, encrypted) = if encryption == PKCS12Encryption::Sha1Rc2Cbc40 {
+                let key = Wiped(pkcs12_kdf(&bmp, &salt, KDF_KEY, iterations, 5));
+                let mut context = RC2CBCContext::new(&key, 40, &iv, true)?;
+                (OID_PBE_SHA1_RC2_40_CBC, context.update(&padded)?)
+            } else {
+                let key = Wiped::<[u8; 24]>(
+                    pkcs12_kdf(&bmp, &salt, KDF_KEY, iterations, 24)
+                        .try_into()
+                        .map_err(|_| RSAError::Key)?,
+                );
+                let mut context = DESCBCContext::des3(&key, &iv, true);
+                (OID_PBE_SHA1_DES_EDE3_CBC, context.update(&padded)?)
+            };
+
+            let mut parameters = tlv(TAG_OCTET_STRING, &salt);
+            parameters.extend(integer(&iterations.to_be_bytes()));
+            let mut algorithm = tlv(TAG_OBJECT_IDENTIFIER, oid);
+            algorithm.extend(tlv(TAG_SEQUENCE, &parameters));
+            (algorithm, encrypted)
+        }
+        PKCS12Encryption::Pbes2Aes256Cbc => {
+            let salt = random_struct.generate_bytes(PBES2_SALT_LEN)?;
+            let iv = random_struct.generate_array::<16>()?;
+            let mut key = Wiped([0u8; 32]);
+            pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), &salt, iterations, &mut key.0)
+                .map_err(|_| RSAError::Key)?;
+            let padded: SecretVec = Wiped(pad_content(info, 16));
+            let encrypted = AESCBCContext::aes256(&key, &iv, true).update(&padded)?;
+
+            let mut pbkdf2_parameters = tlv(TAG_OCTET_STRING, &salt);
+            pbkdf2_parameters.extend(integer(&iterations.to_be_bytes()));
+            pbkdf2_parameters.extend(algorithm_identifier(OID_HMAC_WITH_SHA256));
+            let mut key_derivation = tlv(TAG_OBJECT_IDENTIFIER, OID_PBKDF2);
+            key_derivation.extend(tlv(TAG_SEQUENCE, &pbkdf2_parameters));
+
+            let mut encryption_scheme = tlv(TAG_OBJECT_IDENTIFIER, OID_AES_256_CBC);
+            encryption_scheme.extend(tlv(TAG_OCTET_STRING, &iv));
+
+            let mut parameters = tlv(TAG_SEQUENCE, &key_derivation);
+            parameters.extend(tlv(TAG_SEQUENCE, &encryption_scheme));
+            let mut algorithm = tlv(TAG_OBJECT_IDENTIFIER, OID_PBES2);
+            algorithm.extend(tlv(TAG_SEQUENCE, &parameters));
+            (algorithm, encrypted)
+        }
+    };
+
+    let mut encrypted_info = tlv(TAG_SEQUENCE, &algorithm);
+    encrypted_info.extend(tlv(TAG_OCTET_STRING, &encrypted));
+    Ok(tlv(TAG_SEQUENCE, &encrypted_info))
+}
+
+fn attribute(oid: &[u8], value: Vec<u8>) -> Vec<u8> {
+    let mut attribute = tlv(TAG_OBJECT_IDENTIFIER, oid);
+    attribute.extend(set_of(vec![value]));
+    tlv(TAG_SEQUENCE, &attribute)
+}
+
+fn safe_bag(bag_id: &[u8], value: &[u8], attributes: &[u8]) -> Vec<u8> {
+    let mut bag = tlv(TAG_OBJECT_IDENTIFIER, bag_id);
+    bag.extend(tlv(context_tag(0, true), value));
+    bag.extend(attributes);
+    tlv(TAG_SEQUENCE, &bag)
+}
+
+fn data_content_info(content: &[u8]) -> Vec<u8> {
+    let mut content_info = tlv(TAG_OBJECT_IDENTIFIER, OID_DATA);
+    content_info.extend(tlv(context_tag(0, true), &tlv(TAG_OCTET_STRING, content)));
+    tlv(TAG_SEQUENCE, &content_info)
+}
+
+pub(crate) fn pkcs12_mac(
+    password: &str,
+    salt: &[u8],
+    iterations: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, RSAError> {
+    let key = Wiped(pkcs12_kdf(
+        &bmp_password(password),
+        salt,
+        KDF_MAC,
+        iterations,
+        20,
+    ));
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).map_err(|_| RSAError::Key)?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/* Builds a PFX holding the private key, shrouded with the given scheme, and
+   its DER certificate. The certificate bag is left unencrypted and the
+   whole is integrity-protected with an HMAC-SHA1 keyed from the password.
+   Both bags carry a localKeyID (the certificate's SHA-1 hash) and, if
+   given, a friendlyName.
+*/
+pub fn export_pkcs12(
+    private_key: &RSAPrivateKey,
+    certificate: &[u8],
+    friendly_name: Option<&str>,
+    password: &str,
+    encryption: PKCS12Encryption,
+    iterations: u32,
+    random_struct: &mut RandomStruct,
+) -> Result<Vec<u8>, RSAError> {
+    if iterations == 0 {
+        return Err(RSAError::Data);
+    }
+
+    let mut attributes = Vec::<Vec<u8>>::new();
+    if let Some(friendly_name) = friendly_name {
+        let bmp_name = friendly_name
+            .encode_utf16()
+            .flat_map(|c| c.to_be_bytes())
+            .collect::<Vec<u8>>();
+        attributes.push(attribute(OID_FRIENDLY_NAME, tlv(TAG_BMP_STRING, &bmp_name)));
+    }
+    attributes.push(attribute(
+        OID_LOCAL_KEY_ID,
+        tlv(TAG_OCTET_STRING, &DigestAlg::Sha1.digest(certificate)),
+    ));
+    let attributes = set_of(attributes);
+
+    let shrouded_key = encrypt_private_key_info(
+        &encode_private_key_info(private_key),
+        password,
+        encryption,
+        iterations,
+        random_struct,
+    )?;
+    let key_bag = safe_bag(OID_KEY_BAG_SHROUDED, &shrouded_key, &attributes);
+
+    let mut cert_bag = tlv(TAG_OBJECT_IDENTIFIER, OID_X509_CERTIFICATE);
+    cert_bag.extend(tlv(
+        context_tag(0, true),
+        &tlv(TAG_OCTET_STRING, certificate),
+    ));
+    let cert_bag = safe_bag(OID_CERT_BAG, &tlv(TAG_SEQUENCE, &cert_bag), &attributes);
+
+    let mut authenticated_safe = data_content_info(&tlv(TAG_SEQUENCE, &cert_bag));
+    authenticated_safe.extend(data_content_info(&tlv(TAG_SEQUENCE, &key_bag)));
+    let authenticated_safe = tlv(TAG_SEQUENCE, &authenticated_safe);
+
+    let mac_salt = random_struct.generate_bytes(SALT_LEN)?;
+    let mac = pkcs12_mac(password, &mac_salt, iterations, &authenticated_safe)?;
+    let mut mac_data = encode_digest_info(DigestAlg::Sha1, &mac)?;
+    mac_data.extend(tlv(TAG_OCTET_STRING, &mac_salt));
+    mac_data.extend(integer(&iterations.to_be_bytes()));
+
+    let mut pfx = integer(&[3]);
+    pfx.extend(data_content_info(&authenticated_safe));
+    pfx.extend(tlv(TAG_SEQUENCE, &mac_data));
+    Ok(tlv(TAG_SEQUENCE, &pfx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::der::{read_integer, read_tlv};
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    #[test]
+    fn test_pkcs12_kdf_vectors() {
+        // PKCS #12 KDF vectors for password "smeg"
+        let bmp = bmp_password("smeg");
+        let salt = [0x0a, 0x58, 0xcf, 0x64, 0x53, 0x0d, 0x82, 0x3f];
+
+        assert_eq!(
+            pkcs12_kdf(&bmp, &salt, KDF_KEY, 1, 24),
+            [
+                0x8a, 0xaa, 0xe6, 0x29, 0x7b, 0x6c, 0xb0, 0x46, 0x42, 0xab, 0x5b, 0x07, 0x78, 0x51,
+                0x28, 0x4e, 0xb7, 0x12, 0x8f, 0x1a, 0x2a, 0x7f, 0xbc, 0xa3
+            ]
+        );
+        assert_eq!(
+            pkcs12_kdf(&bmp, &salt, KDF_IV, 1, 8),
+            [0x79, 0x99, 0x3d, 0xfe, 0x04, 0x8d, 0x3b, 0x76]
+        );
+    }
+
+    #[test]
+    fn test_export_pkcs12_mac() {
+        let mut random_struct = test_random_struct(0x5a);
+        let (_, private_key) = test_key_pair(512);
+        let certificate = tlv(TAG_SEQUENCE, b"not really a certificate");
+
+        for encryption in [
+            PKCS12Encryption::Sha1Rc2Cbc40,
+            PKCS12Encryption::Sha1DesEde3Cbc,
+            PKCS12Encryption::Pbes2Aes256Cbc,
+        ] {
+            let pfx = export_pkcs12(
+                &private_key,
+                &certificate,
+                Some("key"),
+                "password",
+                encryption,
+                2048,
+                &mut random_struct,
+            )
+            .unwrap();
+
+            let (pfx, rest) = read_tlv(&pfx, TAG_SEQUENCE).unwrap();
+            assert!(rest.is_empty());
+            let (version, rest) = read_integer(pfx).unwrap();
+            assert_eq!(version, [3]);
+
+            let (auth_safe, rest) = read_tlv(rest, TAG_SEQUENCE).unwrap();
+            let (_, explicit) = read_tlv(auth_safe, TAG_OBJECT_IDENTIFIER).unwrap();
+            let (octets, _) = read_tlv(explicit, context_tag(0, true)).unwrap();
+            let (authenticated_safe, _) = read_tlv(octets, TAG_OCTET_STRING).unwrap();
+
+            let (mac_data, _) = read_tlv(rest, TAG_SEQUENCE).unwrap();
+            let (mac, rest) = read_tlv(mac_data, TAG_SEQUENCE).unwrap();
+            let (salt, _) = read_tlv(rest, TAG_OCTET_STRING).unwrap();
+            assert!(mac.ends_with(&pkcs12_mac("password", salt, 2048, authenticated_safe).unwrap()));
+            assert!(!mac.ends_with(&pkcs12_mac("guess", salt, 2048, authenticated_safe).unwrap()));
+        }
+
+        assert!(matches!(
+            export_pkcs12(
+                &private_key,
+                &certificate,
+                None,
+                "password",
+                PKCS12Encryption::Sha1DesEde3Cbc,
+                0,
+                &mut random_struct
+            ),
+            Err(RSAError::Data)
+        ));
+    }
+}
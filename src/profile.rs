@@ -0,0 +1,244 @@
+/* Compatibility profiles. Legacy is byte-exact RSAREF behavior: any key
+   size the crate handles, e = 3 allowed, padding drawn from the MD5
+   random structure alone and unblinded private-key operations. Hardened
+   refuses short keys and small exponents, mixes operating-system entropy
+   into the random structure before padding, and blinds every private-key
+   operation with randomness from the operating system, so it needs the
+   os-rng feature for those.
+
+   PKCS #1 v1.5 unpadding is constant-time under both profiles; the
+   accept/reject rules are the same as RSAREF's.
+*/
+use crate::key_audit::PrivateKeyOperation;
+use crate::r_random::RandomStruct;
+use crate::rsa::{
+    pkcs1_decrypt, pkcs1_sign, PrivateKeyOps, RSAPrivateKey, RSAProtoKey, RSAPublicKey,
+};
+use crate::RSAError;
+use alloc::vec::Vec;
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+use rand::{rngs::OsRng, RngCore};
+
+pub const HARDENED_MIN_MODULUS_BITS: usize = 1024;
+pub const HARDENED_MIN_PUBLIC_EXPONENT: u32 = 65537;
+
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+const RESEED_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    #[default]
+    Legacy,
+    Hardened,
+}
+
+impl Profile {
+    /* Checks the parameters of a key about to be generated. */
+    pub fn check_proto_key(&self, proto_key: &RSAProtoKey) -> Result<(), RSAError> {
+        if *self == Profile::Hardened {
+            if (proto_key.bits as usize) < HARDENED_MIN_MODULUS_BITS {
+                return Err(RSAError::ModulusLen);
+            }
+            if !proto_key.use_fermat4 {
+                return Err(RSAError::Key);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check_public_key(&self, public_key: &RSAPublicKey) -> Result<(), RSAError> {
+        if *self == Profile::Hardened {
+            if public_key.modulus().bits() < HARDENED_MIN_MODULUS_BITS {
+                return Err(RSAError::ModulusLen);
+            }
+            if *public_key.exponent() < HARDENED_MIN_PUBLIC_EXPONENT.into() {
+                return Err(RSAError::PublicKey);
+            }
+        }
+        Ok(())
+    }
+
+    /* PKCS #1 v1.5 encryption after the profile's key check. Hardened
+       stirs fresh operating-system entropy into random_struct first.
+    */
+    pub fn rsa_public_encrypt(
+        &self,
+        public_key: &RSAPublicKey,
+        input: &[u8],
+        random_struct: &mut RandomStruct,
+    ) -> Result<Vec<u8>, RSAError> {
+        self.check_public_key(public_key)?;
+        self.stir_random(random_struct)?;
+        public_key.rsa_public_encrypt(input, random_struct)
+    }
+
+    /* Mixes operating-system entropy into random_struct under Hardened;
+       does nothing under Legacy.
+    */
+    pub(crate) fn stir_random(&self, random_struct: &mut RandomStruct) -> Result<(), RSAError> {
+        if *self == Profile::Hardened {
+            reseed(random_struct)?;
+        }
+        Ok(())
+    }
+
+    /* Wraps private_key for use under this profile. The result does the
+       usual PrivateKeyOps operations, blinded under Hardened.
+    */
+    pub fn private_key<'a>(
+        &self,
+        private_key: &'a RSAPrivateKey,
+    ) -> Result<ProfiledKey<'a>, RSAError> {
+        self.check_public_key(&private_key.public_key())?;
+        if *self == Profile::Hardened
+            && !cfg!(all(feature = "os-rng", not(feature = "deterministic")))
+        {
+            return Err(RSAError::NeedRandom);
+        }
+        Ok(ProfiledKey {
+            key: private_key,
+            blind: *self == Profile::Hardened,
+        })
+    }
+}
+
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+fn reseed(random_struct: &mut RandomStruct) -> Result<(), RSAError> {
+    let mut seed = [0u8; RESEED_LEN];
+    OsRng
+        .try_fill_bytes(&mut seed)
+        .map_err(|_| RSAError::NeedRandom)?;
+    random_struct.random_update(&seed);
+    Ok(())
+}
+
+#[cfg(not(all(feature = "os-rng", not(feature = "deterministic"))))]
+fn reseed(_random_struct: &mut RandomStruct) -> Result<(), RSAError> {
+    Err(RSAError::NeedRandom)
+}
+
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+fn blinded_block(private_key: &RSAPrivateKey, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+    private_key.rsa_private_block_blinded(input, &mut OsRng)
+}
+
+#[cfg(not(all(feature = "os-rng", not(feature = "deterministic"))))]
+fn blinded_block(_private_key: &RSAPrivateKey, _input: &[u8]) -> Result<Vec<u8>, RSAError> {
+    Err(RSAError::NeedRandom)
+}
+
+/* A private key checked against a profile; see Profile::private_key. */
+#[derive(Debug, Clone, Copy)]
+pub struct ProfiledKey<'a> {
+    key: &'a RSAPrivateKey,
+    blind: bool,
+}
+
+impl PrivateKeyOps for ProfiledKey<'_> {
+    fn bits(&self) -> u32 {
+        self.key.bits()
+    }
+
+    fn public_key(&self) -> RSAPublicKey {
+        self.key.public_key()
+    }
+
+    fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.key
+            .audited(PrivateKeyOperation::Block, input, || self.block(input))
+    }
+
+    /* Overridden so that the key's audit hears of one signature or
+       decryption rather than a raw block.
+    */
+    fn rsa_private_encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.key.audited(PrivateKeyOperation::Sign, input, || {
+            pkcs1_sign(self.bits(), input, |block| self.block(block))
+        })
+    }
+
+    fn rsa_private_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.key.audited(PrivateKeyOperation::Decrypt, input, || {
+            pkcs1_decrypt(self.bits(), input, |block| self.block(block))
+        })
+    }
+}
+
+impl ProfiledKey<'_> {
+    fn block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        if self.blind {
+            return blinded_block(self.key, input);
+        }
+        self.key.private_block(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    #[test]
+    fn test_profiles() {
+        let (short_public, short_private) = test_key_pair(512);
+        let (public_key, private_key) = test_key_pair(1024);
+        let e3 = RSAPublicKey::from_components(1024, public_key.modulus().clone(), 3u32.into());
+
+        assert!(Profile::Legacy.check_public_key(&short_public).is_ok());
+        assert!(Profile::Legacy.check_public_key(&e3).is_ok());
+        assert_eq!(
+            Profile::Hardened.check_public_key(&short_public),
+            Err(RSAError::ModulusLen)
+        );
+        assert_eq!(
+            Profile::Hardened.check_public_key(&e3),
+            Err(RSAError::PublicKey)
+        );
+        assert_eq!(
+            Profile::Hardened.check_proto_key(&RSAProtoKey {
+                bits: 1024,
+                use_fermat4: false,
+            }),
+            Err(RSAError::Key)
+        );
+        assert!(Profile::Hardened.private_key(&short_private).is_err());
+
+        /* The seeded structure alone fixes legacy padding; hardened
+           padding differs because of the added entropy.
+        */
+        let seeded = || test_random_struct(0x33);
+        let data = b"profiles";
+        let legacy = Profile::Legacy
+            .rsa_public_encrypt(&public_key, data, &mut seeded())
+            .unwrap();
+        assert_eq!(
+            legacy,
+            public_key.rsa_public_encrypt(data, &mut seeded()).unwrap()
+        );
+        if !cfg!(all(feature = "os-rng", not(feature = "deterministic"))) {
+            assert_eq!(
+                Profile::Hardened.rsa_public_encrypt(&public_key, data, &mut seeded()),
+                Err(RSAError::NeedRandom)
+            );
+            assert_eq!(
+                Profile::Hardened.private_key(&private_key).err(),
+                Some(RSAError::NeedRandom)
+            );
+            return;
+        }
+        let hardened = Profile::Hardened
+            .rsa_public_encrypt(&public_key, data, &mut seeded())
+            .unwrap();
+        assert_ne!(hardened, legacy);
+
+        /* Blinding does not change results. */
+        for profile in [Profile::Legacy, Profile::Hardened] {
+            let key = profile.private_key(&private_key).unwrap();
+            assert_eq!(key.rsa_private_decrypt(&hardened).unwrap(), data);
+            assert_eq!(
+                key.rsa_private_encrypt(data).unwrap(),
+                private_key.rsa_private_encrypt(data).unwrap()
+            );
+        }
+    }
+}
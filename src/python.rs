@@ -0,0 +1,227 @@
+/* Python bindings, built as the extension module "rsaref":
+     cargo rustc --release --features python --crate-type cdylib
+   then copy the library to rsaref.so (rsaref.pyd on Windows).
+
+   Algorithms are chosen by their RSAREF numbers (DA_MD5 = 5,
+   EA_DES_CBC = 1, and so on), as through the C interface. Every
+   RSAError is raised as rsaref.RSAError with the error's message.
+*/
+use crate::algorithm::{DigestAlgorithmId, EncryptionAlgorithmId};
+use crate::constant_time::ct_eq;
+use crate::digest_info::encode_digest_info;
+use crate::r_enhanc::{OpenContext, SealContext};
+use crate::r_keygen::generate_pem_keys_with_random;
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAProtoKey, RSAPublicKey};
+use alloc::vec::Vec;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::string::ToString;
+
+create_exception!(rsaref, RSAError, PyException);
+
+fn to_py_err(error: crate::RSAError) -> PyErr {
+    RSAError::new_err(error.to_string())
+}
+
+fn digest_info(data: &[u8], digest_algorithm: i32) -> Result<Vec<u8>, crate::RSAError> {
+    let alg = DigestAlgorithmId::try_from(digest_algorithm)?.digest_alg();
+    encode_digest_info(alg, &alg.digest(data))
+}
+
+#[pyclass(name = "RandomStruct")]
+struct PyRandomStruct(RandomStruct);
+
+#[pymethods]
+impl PyRandomStruct {
+    #[new]
+    fn new() -> Self {
+        Self(RandomStruct::new())
+    }
+
+    fn random_update(&mut self, data: &[u8]) {
+        self.0.random_update(data);
+    }
+
+    #[getter]
+    fn bytes_needed(&self) -> usize {
+        self.0.get_random_bytes_needed()
+    }
+
+    fn generate_bytes<'py>(
+        &mut self,
+        py: Python<'py>,
+        len: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self.0.generate_bytes(len).map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &bytes))
+    }
+}
+
+#[pyclass(name = "PublicKey")]
+struct PyPublicKey(RSAPublicKey);
+
+#[pymethods]
+impl PyPublicKey {
+    /* The RSAREF R_RSA_PUBLIC_KEY layout, as RSAPublicKey::encode. */
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        RSAPublicKey::decode(data).map(Self).map_err(to_py_err)
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.encode())
+    }
+
+    #[getter]
+    fn bits(&self) -> u32 {
+        self.0.bits()
+    }
+
+    fn encrypt<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+        random: &mut PyRandomStruct,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let encrypted = self
+            .0
+            .rsa_public_encrypt(data, &mut random.0)
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &encrypted))
+    }
+
+    /* True if signature is a PKCS #1 v1.5 signature on data. */
+    #[pyo3(signature = (data, signature, digest_algorithm = 5))]
+    fn verify(&self, data: &[u8], signature: &[u8], digest_algorithm: i32) -> PyResult<bool> {
+        let expected = digest_info(data, digest_algorithm).map_err(to_py_err)?;
+        Ok(self
+            .0
+            .rsa_public_decrypt(signature)
+            .is_ok_and(|recovered| ct_eq(&recovered, &expected)))
+    }
+}
+
+#[pyclass(name = "PrivateKey")]
+struct PyPrivateKey(RSAPrivateKey);
+
+#[pymethods]
+impl PyPrivateKey {
+    /* The RSAREF R_RSA_PRIVATE_KEY layout, as RSAPrivateKey::encode. */
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        RSAPrivateKey::decode(data).map(Self).map_err(to_py_err)
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.encode())
+    }
+
+    #[getter]
+    fn bits(&self) -> u32 {
+        self.0.bits()
+    }
+
+    fn public_key(&self) -> PyPublicKey {
+        PyPublicKey(self.0.public_key())
+    }
+
+    fn decrypt<'py>(&self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+        let decrypted = self.0.rsa_private_decrypt(data).map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &decrypted))
+    }
+
+    #[pyo3(signature = (data, digest_algorithm = 5))]
+    fn sign<'py>(
+        &self,
+        py: Python<'py>,
+        data: &[u8],
+        digest_algorithm: i32,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let signature = digest_info(data, digest_algorithm)
+            .and_then(|digest_info| self.0.rsa_private_encrypt(&digest_info))
+            .map_err(to_py_err)?;
+        Ok(PyBytes::new(py, &signature))
+    }
+}
+
+/* Draws primes from random, which must be seeded, so a given seed always
+   gives the same key pair.
+*/
+#[pyfunction]
+#[pyo3(signature = (bits, random, use_fermat4 = true))]
+fn generate_keys(
+    bits: u32,
+    random: &mut PyRandomStruct,
+    use_fermat4: bool,
+) -> PyResult<(PyPublicKey, PyPrivateKey)> {
+    let proto_key = RSAProtoKey { bits, use_fermat4 };
+    let (public_key, private_key) =
+        generate_pem_keys_with_random(&proto_key, &mut random.0).map_err(to_py_err)?;
+    Ok((PyPublicKey(public_key), PyPrivateKey(private_key)))
+}
+
+type Sealed<'py> = (
+    Vec<Bound<'py, PyBytes>>,
+    Bound<'py, PyBytes>,
+    Bound<'py, PyBytes>,
+);
+
+/* Returns (encrypted_keys, iv, ciphertext), one encrypted key per
+   recipient, as R_SealInit, R_SealUpdate and R_SealFinal do.
+*/
+#[pyfunction]
+#[pyo3(signature = (data, public_keys, random, algorithm = 1))]
+fn seal<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    public_keys: Vec<PyRef<'py, PyPublicKey>>,
+    random: &mut PyRandomStruct,
+    algorithm: i32,
+) -> PyResult<Sealed<'py>> {
+    let algorithm = EncryptionAlgorithmId::try_from(algorithm).map_err(to_py_err)?;
+    let public_keys = public_keys.iter().map(|key| &key.0).collect::<Vec<_>>();
+    let mut context =
+        SealContext::new(algorithm, &public_keys, &mut random.0).map_err(to_py_err)?;
+    let encrypted_keys = context
+        .encrypted_keys()
+        .iter()
+        .map(|key| PyBytes::new(py, key))
+        .collect();
+    let iv = PyBytes::new(py, context.iv());
+    let mut ciphertext = context.update(data).map_err(to_py_err)?;
+    ciphertext.extend(context.finalize().map_err(to_py_err)?);
+    Ok((encrypted_keys, iv, PyBytes::new(py, &ciphertext)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (ciphertext, encrypted_key, iv, private_key, algorithm = 1))]
+fn open<'py>(
+    py: Python<'py>,
+    ciphertext: &[u8],
+    encrypted_key: &[u8],
+    iv: &[u8],
+    private_key: &PyPrivateKey,
+    algorithm: i32,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let algorithm = EncryptionAlgorithmId::try_from(algorithm).map_err(to_py_err)?;
+    let mut context =
+        OpenContext::new(algorithm, encrypted_key, iv, &private_key.0).map_err(to_py_err)?;
+    let mut content = context.update(ciphertext).map_err(to_py_err)?;
+    content.extend(context.finalize().map_err(to_py_err)?);
+    Ok(PyBytes::new(py, &content))
+}
+
+#[pymodule]
+fn rsaref(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("RSAError", module.py().get_type::<RSAError>())?;
+    module.add_class::<PyRandomStruct>()?;
+    module.add_class::<PyPublicKey>()?;
+    module.add_class::<PyPrivateKey>()?;
+    module.add_function(wrap_pyfunction!(generate_keys, module)?)?;
+    module.add_function(wrap_pyfunction!(seal, module)?)?;
+    module.add_function(wrap_pyfunction!(open, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,697 @@
+use alloc::vec::Vec;
+use core::ops::{Add, Div, Mul, Rem, Sub};
+
+use crate::der::Located;
+use crate::r_random::RandomStruct;
+use crate::rsa::{read_exact, MAX_RSA_MODULUS_BITS};
+use crate::wipe::{SecretBuffer, SecretVec, Wipe, Wiped};
+use crate::RSAError;
+use num_bigint_dig::prime::probably_prime;
+use num_bigint_dig::BigUint;
+use num_traits::CheckedSub;
+
+const PRIME_TEST_ROUNDS: usize = 20;
+
+#[derive(Debug)]
+pub struct DHParams {
+    prime: BigUint,
+    generator: BigUint,
+    subprime: Option<BigUint>,
+}
+
+/* p-2 bounds both the generator and the values exchanged, so a prime
+   below 3 would leave no range at all. Even primes are refused with it.
+*/
+fn check_prime(prime: &BigUint) -> Result<(), RSAError> {
+    if *prime < BigUint::from(3u32) || prime.trailing_zeros() != Some(0) {
+        return Err(RSAError::Data);
+    }
+    Ok(())
+}
+
+impl DHParams {
+    /* Fails with Data if the prime is below 3 or even; call validate for
+       the full checks.
+    */
+    pub fn from_components(prime: BigUint, generator: BigUint) -> Result<Self, RSAError> {
+        check_prime(&prime)?;
+        Ok(Self {
+            prime,
+            generator,
+            subprime: None,
+        })
+    }
+
+    /* Attaches the order q of the generator's subgroup, after checking it
+       as in validate. With a subprime known, received public values are
+       also checked for membership in the subgroup.
+    */
+    pub fn with_subprime(mut self, subprime: BigUint) -> Result<Self, RSAError> {
+        self.subprime = Some(subprime);
+        self.validate()?;
+        Ok(self)
+    }
+
+    pub fn subprime(&self) -> Option<&BigUint> {
+        self.subprime.as_ref()
+    }
+
+    pub fn prime(&self) -> &BigUint {
+        &self.prime
+    }
+
+    pub fn generator(&self) -> &BigUint {
+        &self.generator
+    }
+
+    pub fn prime_len(&self) -> usize {
+        self.prime.bits().div_ceil(8)
+    }
+
+    /* primeLen || prime || generator [|| subprime], the numbers big-endian
+       and primeLen bytes each. The subprime is written when known, third
+       as in the X9.42 DomainParameters of RFC 2631.
+    */
+    pub fn encode(&self) -> Vec<u8> {
+        let prime_len = self.prime_len();
+        let mut result = Vec::<u8>::with_capacity(4 + 3 * prime_len);
+
+        result.extend((prime_len as u32).to_le_bytes());
+        result.extend(to_be_padded(&self.prime, prime_len));
+        result.extend(to_be_padded(&self.generator, prime_len));
+        if let Some(subprime) = &self.subprime {
+            result.extend(to_be_padded(subprime, prime_len));
+        }
+
+        result
+    }
+
+    /* Reads what encode writes. Only the shape of the prime is checked
+       here, as in from_components, and a subprime is restored but not
+       checked; call validate on parameters from an untrusted source.
+    */
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+    )]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        if data.len() < 4 {
+            return Err(RSAError::InputLen {
+                expected: 4,
+                actual: data.len(),
+            });
+        }
+
+        let mut reader = data;
+
+        let mut prime_len_buf = [0u8; 4];
+        read_exact(&mut reader, &mut prime_len_buf)?;
+        let prime_len = u32::from_le_bytes(prime_len_buf) as usize;
+
+        if prime_len == 0 || prime_len > MAX_RSA_MODULUS_BITS / 8 {
+            return Err(RSAError::ModulusLen).at(data, data, "primeLen");
+        }
+        if data.len() < 4 + 2 * prime_len {
+            return Err(RSAError::InputLen {
+                expected: 4 + 2 * prime_len,
+                actual: data.len(),
+            });
+        }
+
+        let mut prime_buf = vec![0u8; prime_len];
+        let prime_field = reader;
+        read_exact(&mut reader, &mut prime_buf)?;
+        let prime = BigUint::from_bytes_be(&prime_buf);
+        check_prime(&prime).at(data, prime_field, "prime")?;
+
+        let mut generator_buf = vec![0u8; prime_len];
+        read_exact(&mut reader, &mut generator_buf)?;
+        let generator = BigUint::from_bytes_be(&generator_buf);
+
+        let mut params = Self {
+            prime,
+            generator,
+            subprime: None,
+        };
+        if !reader.is_empty() {
+            if reader.len() < prime_len {
+                return Err(RSAError::InputLen {
+                    expected: 4 + 3 * prime_len,
+                    actual: data.len(),
+                });
+            }
+            let mut subprime_buf = vec![0u8; prime_len];
+            let subprime_field = reader;
+            read_exact(&mut reader, &mut subprime_buf)?;
+            let subprime = BigUint::from_bytes_be(&subprime_buf);
+            if subprime < BigUint::from(2u32) {
+                return Err(RSAError::Data).at(data, subprime_field, "subprime");
+            }
+            params.subprime = Some(subprime);
+        }
+
+        Ok(params)
+    }
+
+    /* Checks the parameters per RFC 2631, 2.2: p is a probable prime and
+       2 <= g <= p-2. If a subprime q is attached, q is a probable prime
+       dividing p-1 and g^q = 1 mod p.
+    */
+    pub fn validate(&self) -> Result<(), RSAError> {
+        let one = BigUint::from(1u32);
+        let two = BigUint::from(2u32);
+        check_prime(&self.prime)?;
+        if self.prime.bits() > MAX_RSA_MODULUS_BITS
+            || !probably_prime(&self.prime, PRIME_TEST_ROUNDS)
+        {
+            return Err(RSAError::Data);
+        }
+        if self.generator < two || self.generator > self.prime_minus(2)? {
+            return Err(RSAError::Data);
+        }
+
+        if let Some(q) = &self.subprime {
+            if *q < two
+                || !probably_prime(q, PRIME_TEST_ROUNDS)
+                || self.prime_minus(1)?.rem(q) != BigUint::from(0u32)
+                || self.generator.modpow(q, &self.prime) != one
+            {
+                return Err(RSAError::Data);
+            }
+        }
+
+        Ok(())
+    }
+
+    /* Checks that p is a safe prime, p = 2q+1 with q prime, and that g
+       generates the subgroup of order q.
+    */
+    pub fn validate_safe_prime(&self) -> Result<(), RSAError> {
+        self.validate()?;
+        let q = self.prime_minus(1)? >> 1;
+        if !probably_prime(&q, PRIME_TEST_ROUNDS)
+            || self.generator.modpow(&q, &self.prime) != BigUint::from(1u32)
+        {
+            return Err(RSAError::Data);
+        }
+        Ok(())
+    }
+
+    /* Checks a received public value per RFC 2631, 2.1.5: 2 <= y <= p-2,
+       which rules out the trivial subgroups {1} and {1, p-1}, and, if a
+       subprime q is attached, y^q = 1 mod p. Without a subprime only the
+       range is checked, which leaves small subgroups open unless p is a
+       safe prime; validate_subgroup_public_value refuses that case.
+    */
+    pub fn validate_public_value(&self, public_value: &[u8]) -> Result<(), RSAError> {
+        if public_value.len() > self.prime_len() {
+            return Err(RSAError::Len);
+        }
+
+        let y = BigUint::from_bytes_be(public_value);
+        if y < BigUint::from(2u32) || y > self.prime_minus(2)? {
+            return Err(RSAError::Data);
+        }
+        if let Some(q) = &self.subprime {
+            if y.modpow(q, &self.prime) != BigUint::from(1u32) {
+                return Err(RSAError::Data);
+            }
+        }
+
+        Ok(())
+    }
+
+    /* validate_public_value, failing with Data if no subprime is attached
+       rather than checking the range alone.
+    */
+    pub fn validate_subgroup_public_value(&self, public_value: &[u8]) -> Result<(), RSAError> {
+        if self.subprime.is_none() {
+            return Err(RSAError::Data);
+        }
+        self.validate_public_value(public_value)
+    }
+
+    /* p-n, failing with Data rather than underflowing on a prime that
+       skipped from_components.
+    */
+    fn prime_minus(&self, n: u32) -> Result<BigUint, RSAError> {
+        self.prime
+            .checked_sub(&BigUint::from(n))
+            .ok_or(RSAError::Data)
+    }
+
+    /* The largest private value x allowed: q-1 with a subprime attached,
+       otherwise p-2.
+    */
+    fn max_private_value(&self) -> Result<BigUint, RSAError> {
+        match &self.subprime {
+            Some(q) => q.checked_sub(&BigUint::from(1u32)).ok_or(RSAError::Data),
+            None => self.prime_minus(2),
+        }
+    }
+
+    /* Returns the public value and the private value, which is drawn
+       until it lies in [1, q-1], or [1, p-2] without a subprime.
+       private_value_len may not exceed the length of that bound.
+    */
+    pub fn setup_agreement(
+        &self,
+        private_value_len: usize,
+        random_struct: &mut RandomStruct,
+    ) -> Result<(Vec<u8>, SecretBuffer), RSAError> {
+        let prime_len = self.prime_len();
+        let max_private_value = self.max_private_value()?;
+        if private_value_len == 0 || private_value_len > max_private_value.bits().div_ceil(8) {
+            return Err(RSAError::Len);
+        }
+
+        /* Generate private value. */
+        let (private_value, mut x) = loop {
+            let private_value: SecretVec = Wiped(random_struct.generate_bytes(private_value_len)?);
+            let mut x = BigUint::from_bytes_be(&private_value);
+            if x >= BigUint::from(1u32) && x <= max_private_value {
+                break (private_value, x);
+            }
+            x.wipe();
+        };
+
+        /* Setup Diffie-Hellman agreement. Public value y = g^x mod p. */
+        let y = self.generator.modpow(&x, &self.prime);
+        let public_value = to_be_padded(&y, prime_len);
+        x.wipe();
+
+        Ok((public_value, SecretBuffer::from(private_value.into_vec())))
+    }
+
+    /* Fails with Data if the other public value fails
+       validate_public_value or the private value lies outside the range
+       setup_agreement draws it from; a private value of 0 would make the
+       agreed key 1.
+    */
+    pub fn compute_agreed_key(
+        &self,
+        other_public_value: &[u8],
+        private_value: &[u8],
+    ) -> Result<SecretBuffer, RSAError> {
+        let prime_len = self.prime_len();
+        if private_value.len() > prime_len {
+            return Err(RSAError::Len);
+        }
+        self.validate_public_value(other_public_value)?;
+
+        let max_private_value = self.max_private_value()?;
+        let y = BigUint::from_bytes_be(other_public_value);
+        let mut x = BigUint::from_bytes_be(private_value);
+        if x < BigUint::from(1u32) || x > max_private_value {
+            x.wipe();
+            return Err(RSAError::Data);
+        }
+
+        /* Compute agreed key z = y^x mod p. */
+        let mut z = y.modpow(&x, &self.prime);
+        let agreed_key = to_be_padded(&z, prime_len);
+        x.wipe();
+        z.wipe();
+
+        Ok(SecretBuffer::from(agreed_key))
+    }
+}
+
+/* The agreed key passes through here, so the unpadded bytes are wiped. */
+fn to_be_padded(value: &BigUint, len: usize) -> Vec<u8> {
+    let be: SecretVec = Wiped(value.to_bytes_be());
+    let mut result = Vec::with_capacity(len.max(be.len()));
+    result.resize(len.saturating_sub(be.len()), 0);
+    result.extend(be.iter());
+    result
+}
+
+/* Generates a probable prime a between b and c such that a-1 is
+   divisible by d.
+*/
+fn generate_prime(
+    b: &BigUint,
+    c: &BigUint,
+    d: &BigUint,
+    random_struct: &mut RandomStruct,
+) -> Result<BigUint, RSAError> {
+    let one = BigUint::from(1u32);
+
+    /* Generate random number between b and c. */
+    let random_len = c.bits().div_ceil(8);
+    let random = BigUint::from_bytes_be(&random_struct.generate_bytes(random_len)?);
+    let range = c.sub(b).add(&one);
+    let mut a = random.rem(&range).add(b);
+
+    /* Adjust so that a-1 is divisible by d. */
+    let t = (&a).rem(d);
+    a = a.sub(t).add(&one);
+    if a < *b {
+        a = a.add(d);
+    }
+    if a > *c {
+        a = a.sub(d);
+    }
+
+    /* Search to c in steps of d. */
+    let limit = c.sub(d);
+    while !probably_prime(&a, PRIME_TEST_ROUNDS) {
+        if a > limit {
+            return Err(RSAError::Data);
+        }
+        a = a.add(d);
+    }
+
+    Ok(a)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(prime_bits, subprime_bits),
+        err(level = "debug")
+    )
+)]
+pub fn generate_dh_params(
+    prime_bits: usize,
+    subprime_bits: usize,
+    random_struct: &mut RandomStruct,
+) -> Result<DHParams, RSAError> {
+    if prime_bits > MAX_RSA_MODULUS_BITS || subprime_bits < 2 || subprime_bits >= prime_bits {
+        return Err(RSAError::ModulusLen);
+    }
+
+    let one = BigUint::from(1u32);
+    let two = BigUint::from(2u32);
+
+    /* Generate subprime q between 2^(subPrimeBits-1) and
+       2^subPrimeBits-1, searching in steps of 2.
+    */
+    let low = one.clone() << (subprime_bits - 1);
+    let high = (one.clone() << subprime_bits).sub(&one);
+    let q = generate_prime(&low, &high, &two, random_struct)?;
+
+    /* Generate prime p between 2^(primeBits-1) and 2^primeBits-1,
+       searching in steps of 2*q.
+    */
+    let low = one.clone() << (prime_bits - 1);
+    let high = (one.clone() << prime_bits).sub(&one);
+    let step = q.clone().mul(&two);
+    let p = generate_prime(&low, &high, &step, random_struct)?;
+
+    /* Generate generator g for subgroup as 2^((p-1)/q) mod p. */
+    let t = p.clone().sub(&one).div(&q);
+    let g = two.modpow(&t, &p);
+
+    Ok(DHParams {
+        prime: p,
+        generator: g,
+        subprime: Some(q),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::test_random_struct;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_generate_dh_params() {
+        let mut random_struct = test_random_struct(0xd4);
+
+        match generate_dh_params(512, 160, &mut random_struct) {
+            Ok(params) => {
+                assert_eq!(params.prime().bits(), 512);
+                assert!(probably_prime(params.prime(), PRIME_TEST_ROUNDS));
+                assert!(*params.generator() > BigUint::from(1u32));
+                assert!(params.generator() < params.prime());
+
+                let decoded = DHParams::decode(&params.encode()).unwrap();
+                assert_eq!(decoded.prime(), params.prime());
+                assert_eq!(decoded.generator(), params.generator());
+                assert_eq!(decoded.subprime(), params.subprime());
+                assert_eq!(decoded.encode(), params.encode());
+            }
+            Err(_) => panic!("generate_dh_params returned an error."),
+        }
+    }
+
+    #[test]
+    fn test_dh_agreement() {
+        let mut random_struct = test_random_struct(0xd4);
+        let params = generate_dh_params(512, 160, &mut random_struct).unwrap();
+
+        let (public_a, private_a) = params.setup_agreement(20, &mut random_struct).unwrap();
+        let (public_b, private_b) = params.setup_agreement(20, &mut random_struct).unwrap();
+        assert_eq!(public_a.len(), 64);
+        assert_ne!(public_a, public_b);
+
+        let agreed_a = params.compute_agreed_key(&public_b, &private_a).unwrap();
+        let agreed_b = params.compute_agreed_key(&public_a, &private_b).unwrap();
+        assert_eq!(agreed_a[..], agreed_b[..]);
+        assert_eq!(agreed_a.len(), 64);
+    }
+
+    #[test]
+    fn test_dh_agreement_rejects_out_of_range() {
+        let mut random_struct = test_random_struct(0xd4);
+        let params = generate_dh_params(512, 160, &mut random_struct).unwrap();
+        let (_, private_value) = params.setup_agreement(20, &mut random_struct).unwrap();
+
+        let p_minus_1 = params.prime().clone().sub(BigUint::from(1u32));
+        for y in [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            p_minus_1.clone(),
+            params.prime().clone(),
+        ] {
+            assert!(matches!(
+                params.compute_agreed_key(&y.to_bytes_be(), &private_value),
+                Err(RSAError::Data)
+            ));
+        }
+
+        /* x = 0 would agree on 1, and x must stay below q. */
+        let (public_value, _) = params.setup_agreement(20, &mut random_struct).unwrap();
+        let q = params.subprime().unwrap().clone();
+        for x in [BigUint::from(0u32), q.clone()] {
+            assert!(matches!(
+                params.compute_agreed_key(&public_value, &x.to_bytes_be()),
+                Err(RSAError::Data)
+            ));
+        }
+        let q_minus_1 = q.clone().sub(BigUint::from(1u32));
+        assert!(params
+            .compute_agreed_key(&public_value, &q_minus_1.to_bytes_be())
+            .is_ok());
+        assert!(matches!(
+            params.setup_agreement(21, &mut random_struct),
+            Err(RSAError::Len)
+        ));
+
+        /* Without the subprime the bound is p-2. */
+        let range_only =
+            DHParams::from_components(params.prime().clone(), params.generator().clone()).unwrap();
+        let p_minus_2 = params.prime().clone().sub(BigUint::from(2u32));
+        assert!(range_only
+            .compute_agreed_key(&public_value, &p_minus_2.to_bytes_be())
+            .is_ok());
+        assert!(matches!(
+            range_only.compute_agreed_key(&public_value, &p_minus_1.to_bytes_be()),
+            Err(RSAError::Data)
+        ));
+    }
+
+    #[test]
+    fn test_dh_params_validation() {
+        let mut random_struct = test_random_struct(0xd4);
+        let params = generate_dh_params(512, 160, &mut random_struct).unwrap();
+        assert_eq!(params.subprime().unwrap().bits(), 160);
+        params.validate().unwrap();
+
+        let prime = params.prime().clone();
+        let subprime = params.subprime().unwrap().clone();
+        for generator in [BigUint::from(1u32), prime.clone().sub(BigUint::from(1u32))] {
+            assert!(matches!(
+                DHParams::from_components(prime.clone(), generator)
+                    .unwrap()
+                    .validate(),
+                Err(RSAError::Data)
+            ));
+        }
+        assert!(matches!(
+            DHParams::from_components(prime.clone().add(BigUint::from(2u32)), BigUint::from(2u32))
+                .unwrap()
+                .validate(),
+            Err(RSAError::Data)
+        ));
+
+        let decoded = DHParams::from_components(prime.clone(), params.generator().clone()).unwrap();
+        decoded.validate().unwrap();
+        assert!(matches!(
+            DHParams::from_components(prime.clone(), params.generator().clone())
+                .unwrap()
+                .with_subprime(subprime.clone().add(BigUint::from(2u32))),
+            Err(RSAError::Data)
+        ));
+        let params = decoded.with_subprime(subprime).unwrap();
+
+        /* -g has order 2q, so it is in range but outside the subgroup. */
+        let (public_value, _) = params.setup_agreement(20, &mut random_struct).unwrap();
+        params.validate_public_value(&public_value).unwrap();
+        params
+            .validate_subgroup_public_value(&public_value)
+            .unwrap();
+        let outside = prime.sub(params.generator());
+        assert!(matches!(
+            params.validate_public_value(&outside.to_bytes_be()),
+            Err(RSAError::Data)
+        ));
+
+        /* Without the subprime only the range is checked, and the strict
+           form refuses to go on.
+        */
+        let range_only =
+            DHParams::from_components(params.prime().clone(), params.generator().clone()).unwrap();
+        assert!(range_only
+            .validate_public_value(&outside.to_bytes_be())
+            .is_ok());
+        assert!(matches!(
+            range_only.validate_subgroup_public_value(&public_value),
+            Err(RSAError::Data)
+        ));
+        assert!(DHParams::decode(&params.encode())
+            .unwrap()
+            .validate_subgroup_public_value(&public_value)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_dh_params_safe_prime() {
+        DHParams::from_components(BigUint::from(23u32), BigUint::from(2u32))
+            .unwrap()
+            .validate_safe_prime()
+            .unwrap();
+        /* 5 generates the whole group mod 23. */
+        assert!(matches!(
+            DHParams::from_components(BigUint::from(23u32), BigUint::from(5u32))
+                .unwrap()
+                .validate_safe_prime(),
+            Err(RSAError::Data)
+        ));
+        /* 29 is prime but 14 is not. */
+        assert!(matches!(
+            DHParams::from_components(BigUint::from(29u32), BigUint::from(4u32))
+                .unwrap()
+                .validate_safe_prime(),
+            Err(RSAError::Data)
+        ));
+    }
+
+    #[test]
+    fn test_dh_params_reject_tiny_primes() {
+        for prime in [0u32, 1, 2, 4] {
+            assert!(matches!(
+                DHParams::from_components(BigUint::from(prime), BigUint::from(2u32)),
+                Err(RSAError::Data)
+            ));
+
+            let mut encoded = 1u32.to_le_bytes().to_vec();
+            encoded.extend([prime as u8, 2]);
+            assert!(matches!(
+                DHParams::decode(&encoded),
+                Err(RSAError::DecodeAt {
+                    offset: 4,
+                    field: "prime",
+                    ..
+                })
+            ));
+        }
+
+        /* 3 is the smallest prime accepted, and leaves x = 1 only. */
+        let mut random_struct = test_random_struct(0xd4);
+        let params = DHParams::from_components(BigUint::from(3u32), BigUint::from(2u32)).unwrap();
+        assert!(matches!(params.validate(), Err(RSAError::Data)));
+        let (public_value, private_value) = params.setup_agreement(1, &mut random_struct).unwrap();
+        assert_eq!(
+            (&public_value[..], &private_value[..]),
+            (&[2u8][..], &[1u8][..])
+        );
+        assert!(matches!(
+            params.compute_agreed_key(&public_value, &private_value),
+            Err(RSAError::Data)
+        ));
+    }
+
+    #[test]
+    fn test_generate_dh_params_need_random() {
+        let mut random_struct = RandomStruct::new();
+
+        assert!(matches!(
+            generate_dh_params(512, 160, &mut random_struct),
+            Err(RSAError::NeedRandom)
+        ));
+    }
+
+    #[test]
+    fn test_dh_params_decode_errors() {
+        let params = DHParams::from_components(BigUint::from(23u32), BigUint::from(5u32)).unwrap();
+        let encoded = params.encode();
+
+        assert_eq!(
+            DHParams::decode(&encoded[..3]).unwrap_err(),
+            RSAError::InputLen {
+                expected: 4,
+                actual: 3
+            }
+        );
+        let error = DHParams::decode(&encoded[..encoded.len() - 1]).unwrap_err();
+        assert_eq!(
+            error,
+            RSAError::InputLen {
+                expected: encoded.len(),
+                actual: encoded.len() - 1
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "input is {} bytes, need {}",
+                encoded.len() - 1,
+                encoded.len()
+            )
+        );
+        assert_eq!(
+            DHParams::decode(&[0u8; 8]).unwrap_err(),
+            RSAError::ModulusLen
+        );
+
+        /* A subprime, when present, fills a third primeLen field. */
+        let params = DHParams::from_components(BigUint::from(2039u32), BigUint::from(4u32))
+            .unwrap()
+            .with_subprime(BigUint::from(1019u32))
+            .unwrap();
+        let encoded = params.encode();
+        assert_eq!(encoded.len(), 4 + 3 * 2);
+        assert_eq!(
+            DHParams::decode(&encoded[..encoded.len() - 1]).unwrap_err(),
+            RSAError::InputLen {
+                expected: encoded.len(),
+                actual: encoded.len() - 1
+            }
+        );
+        let mut zero_subprime = encoded.clone();
+        zero_subprime[8..].fill(0);
+        assert!(matches!(
+            DHParams::decode(&zero_subprime),
+            Err(RSAError::DecodeAt {
+                offset: 8,
+                field: "subprime",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+#[cfg(feature = "std")]
+use crate::r_enhanc::{io_error, STREAM_CHUNK_LEN};
+use crate::RSAError;
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+const ENCODING: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const ENCODING_PAD: u8 = b'=';
+
+pub const PEM_LINE_LEN: usize = 64;
+
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+fn decode_char(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn encode_char(value: u32) -> char {
+    *ENCODING
+        .get((value & 0x3f) as usize)
+        .unwrap_or(&ENCODING_PAD) as char
+}
+
+fn encode_quantum(output: &mut String, input: &[u8]) {
+    let temp = ((*input.first().unwrap_or(&0) as u32) << 16)
+        | ((*input.get(1).unwrap_or(&0) as u32) << 8)
+        | (*input.get(2).unwrap_or(&0) as u32);
+
+    output.push(encode_char(temp >> 18));
+    output.push(encode_char(temp >> 12));
+    output.push(if input.len() > 1 {
+        encode_char(temp >> 6)
+    } else {
+        ENCODING_PAD as char
+    });
+    output.push(if input.len() > 2 {
+        encode_char(temp)
+    } else {
+        ENCODING_PAD as char
+    });
+}
+
+/* Base64 encoding fed a piece at a time. The output of every update and
+   finish, concatenated, is encode_pem_block of the input concatenated.
+*/
+pub(crate) struct Base64Encoder {
+    pending: Vec<u8>,
+    column: usize,
+}
+
+impl Base64Encoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: Vec::with_capacity(3),
+            column: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: &[u8], output: &mut String) {
+        for byte in input {
+            self.pending.push(*byte);
+            if self.pending.len() == 3 {
+                self.push_quantum(output);
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self, output: &mut String) {
+        if !self.pending.is_empty() {
+            self.push_quantum(output);
+        }
+    }
+
+    fn push_quantum(&mut self, output: &mut String) {
+        if self.column == PEM_LINE_LEN {
+            output.push('\n');
+            self.column = 0;
+        }
+        encode_quantum(output, &self.pending);
+        self.pending.clear();
+        self.column += 4;
+    }
+}
+
+/* Base64 decoding fed a piece at a time, with the checks of
+   decode_pem_block: finish fails if the input stopped inside a quantum,
+   and anything but whitespace after a padded quantum is an error.
+*/
+pub(crate) struct Base64Decoder {
+    quantum: Vec<u8>,
+    padded: bool,
+}
+
+impl Base64Decoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            quantum: Vec::with_capacity(4),
+            padded: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self, encoded: &[u8], output: &mut Vec<u8>) -> Result<(), RSAError> {
+        for c in encoded.iter().filter(|c| !c.is_ascii_whitespace()) {
+            if self.padded {
+                return Err(RSAError::Encoding);
+            }
+            self.quantum.push(*c);
+            if self.quantum.len() == 4 {
+                self.decode_quantum(output)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), RSAError> {
+        if !self.quantum.is_empty() {
+            return Err(RSAError::Encoding);
+        }
+        Ok(())
+    }
+
+    fn decode_quantum(&mut self, output: &mut Vec<u8>) -> Result<(), RSAError> {
+        let pad_len = self
+            .quantum
+            .iter()
+            .rev()
+            .take_while(|c| **c == ENCODING_PAD)
+            .count();
+        if pad_len > 2 {
+            return Err(RSAError::Encoding);
+        }
+        self.padded = pad_len > 0;
+
+        let mut temp: u32 = 0;
+        for c in self.quantum.iter().take(4 - pad_len) {
+            temp = (temp << 6) | decode_char(*c).ok_or(RSAError::Encoding)? as u32;
+        }
+        temp <<= 6 * pad_len;
+
+        /* Reject non-zero bits hidden beneath the padding. */
+        if temp & ((1 << (8 * pad_len)) - 1) != 0 {
+            return Err(RSAError::Encoding);
+        }
+
+        let bytes = temp.to_be_bytes();
+        output.extend(bytes.iter().take(4 - pad_len).skip(1));
+        self.quantum.clear();
+        Ok(())
+    }
+}
+
+/* Encodes block as printable base64, breaking lines every PEM_LINE_LEN
+   characters.
+*/
+pub fn encode_pem_block(block: &[u8]) -> String {
+    let encoded_len = block.len().div_ceil(3) * 4;
+    let mut output = String::with_capacity(encoded_len + encoded_len / PEM_LINE_LEN);
+    let mut encoder = Base64Encoder::new();
+    encoder.update(block, &mut output);
+    encoder.finish(&mut output);
+    output
+}
+
+/* Decodes a printable base64 block. Whitespace between characters is
+   ignored, but padding must be canonical and only appear at the end.
+*/
+pub fn decode_pem_block(encoded: &str) -> Result<Vec<u8>, RSAError> {
+    let mut output = Vec::<u8>::with_capacity(encoded.len() / 4 * 3);
+    let mut decoder = Base64Decoder::new();
+    decoder.update(encoded.as_bytes(), &mut output)?;
+    decoder.finish()?;
+    Ok(output)
+}
+
+/* encode_pem_block of everything written to it, written to the inner
+   writer as it goes. finish must be called to write the last quantum.
+*/
+#[cfg(feature = "std")]
+pub struct PemBlockWriter<W: Write> {
+    inner: W,
+    encoder: Base64Encoder,
+    output: String,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> PemBlockWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            encoder: Base64Encoder::new(),
+            output: String::new(),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.output.clear();
+        self.encoder.finish(&mut self.output);
+        self.inner.write_all(self.output.as_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for PemBlockWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.clear();
+        self.encoder.update(buf, &mut self.output);
+        self.inner.write_all(self.output.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/* decode_pem_block of everything the inner reader holds, in memory
+   bounded by the chunk size. Bad base64 surfaces as an InvalidData error
+   wrapping RSAError::Encoding, possibly after earlier data was returned.
+*/
+#[cfg(feature = "std")]
+pub struct PemBlockReader<R: Read> {
+    inner: R,
+    decoder: Option<Base64Decoder>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> PemBlockReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decoder: Some(Base64Decoder::new()),
+            output: Vec::new(),
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for PemBlockReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            let Some(decoder) = self.decoder.as_mut() else {
+                return Ok(0);
+            };
+
+            let mut chunk = [0u8; STREAM_CHUNK_LEN];
+            let len = match self.inner.read(&mut chunk) {
+                Ok(len) => len,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            self.output.clear();
+            self.position = 0;
+            if len == 0 {
+                self.decoder
+                    .take()
+                    .map_or(Ok(()), Base64Decoder::finish)
+                    .map_err(io_error)?;
+            } else {
+                let chunk = chunk.get(..len).ok_or(io::ErrorKind::InvalidData)?;
+                decoder.update(chunk, &mut self.output).map_err(io_error)?;
+            }
+        }
+
+        let available = self.output.get(self.position..).unwrap_or_default();
+        let len = buf.len().min(available.len());
+        for (target, byte) in buf.iter_mut().zip(available) {
+            *target = *byte;
+        }
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pem_block_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+
+        for (decoded, encoded) in vectors {
+            assert_eq!(encode_pem_block(decoded.as_bytes()), encoded);
+            assert_eq!(decode_pem_block(encoded).unwrap(), decoded.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_pem_block_line_wrapping() {
+        let data = (0u8..=255).collect::<Vec<u8>>();
+        let encoded = encode_pem_block(&data);
+
+        let lines = encoded.split('\n').collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 6);
+        for line in &lines[..lines.len() - 1] {
+            assert_eq!(line.len(), PEM_LINE_LEN);
+        }
+        assert!(lines[lines.len() - 1].len() <= PEM_LINE_LEN);
+
+        assert_eq!(decode_pem_block(&encoded).unwrap(), data);
+        assert_eq!(
+            decode_pem_block(&encoded.replace('\n', "\r\n ")).unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_pem_block_pieces() {
+        let data = (0u8..=255).cycle().take(1000).collect::<Vec<u8>>();
+        let encoded = encode_pem_block(&data);
+
+        for piece_len in [1, 2, 5, 64, 1000] {
+            let mut encoder = Base64Encoder::new();
+            let mut output = String::new();
+            for piece in data.chunks(piece_len) {
+                encoder.update(piece, &mut output);
+            }
+            encoder.finish(&mut output);
+            assert_eq!(output, encoded);
+
+            let mut decoder = Base64Decoder::new();
+            let mut decoded = Vec::new();
+            for piece in encoded.as_bytes().chunks(piece_len) {
+                decoder.update(piece, &mut decoded).unwrap();
+            }
+            decoder.finish().unwrap();
+            assert_eq!(decoded, data);
+        }
+
+        let mut decoder = Base64Decoder::new();
+        decoder.update(b"Zm9v\nZg", &mut Vec::new()).unwrap();
+        assert_eq!(decoder.finish(), Err(RSAError::Encoding));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_pem_block_streams() {
+        use std::io::{Read, Write};
+
+        let data = (0..20000u32).map(|i| (i % 253) as u8).collect::<Vec<u8>>();
+        let mut writer = PemBlockWriter::new(Vec::<u8>::new());
+        for chunk in data.chunks(77) {
+            writer.write_all(chunk).unwrap();
+        }
+        let encoded = writer.finish().unwrap();
+        assert_eq!(encoded, encode_pem_block(&data).as_bytes());
+
+        let mut decoded = Vec::new();
+        PemBlockReader::new(encoded.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+
+        let error = PemBlockReader::new(&b"Zm9vYg==Zg=="[..])
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(
+            error.get_ref().unwrap().downcast_ref::<RSAError>(),
+            Some(&RSAError::Encoding)
+        );
+    }
+
+    #[test]
+    fn test_pem_block_strict_decoding() {
+        for encoded in [
+            "Zg=", "Zg===", "Z===", "Zg==Zm8=", "Zm=v", "Zh==", "Zm9=", "Zm9v!A==", "=",
+        ] {
+            assert!(
+                matches!(decode_pem_block(encoded), Err(RSAError::Encoding)),
+                "{} should be rejected",
+                encoded
+            );
+        }
+    }
+}
@@ -0,0 +1,971 @@
+use crate::algorithm::{CipherContext, EncryptionAlgorithmId};
+use crate::csr::key_fingerprint;
+use crate::digest_info::DigestAlg;
+use crate::envelope_header::{EnvelopeHeader, EnvelopeRecipient};
+use crate::padding::kdf2;
+use crate::r_random::RandomStruct;
+use crate::rsa::{PrivateKeyOps, RSAPublicKey};
+use crate::secure_box::SecureBox;
+use crate::wipe::{SecretBuffer, SecretVec, Wiped};
+use crate::RSAError;
+use alloc::vec::Vec;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "deflate")]
+use flate2::write::{ZlibDecoder, ZlibEncoder};
+
+#[cfg(feature = "std")]
+pub(crate) const STREAM_CHUNK_LEN: usize = 8192;
+
+/* The RSAError stays reachable through get_ref and into_inner. */
+#[cfg(feature = "std")]
+pub(crate) fn io_error(error: RSAError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/* Splits buffered input into the whole blocks that may be processed now and
+   the bytes to keep. When keep_last is set a trailing whole block is also
+   kept back, so that the final block is still available to finalize.
+*/
+pub(crate) fn take_blocks(buffer: &mut Vec<u8>, block_len: usize, keep_last: bool) -> Vec<u8> {
+    let mut process_len = buffer.len() - buffer.len() % block_len;
+    if keep_last && process_len == buffer.len() && process_len > 0 {
+        process_len -= block_len;
+    }
+    let rest = buffer.split_off(process_len);
+    core::mem::replace(buffer, rest)
+}
+
+/* The secret an authenticated envelope transports, and its MAC length. */
+const AUTH_SECRET_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+
+/* The content key and the HMAC-SHA256 key of an authenticated envelope,
+   both expanded from the transported secret with KDF2.
+*/
+fn authentication_keys(
+    algorithm: EncryptionAlgorithmId,
+    secret: &[u8],
+) -> Result<(SecureBox, Hmac<Sha256>), RSAError> {
+    let derived = SecureBox::from_vec(kdf2::<Sha256>(secret, algorithm.key_len() + MAC_LEN)?)?;
+    let (key, mac_key) = derived
+        .split_at_checked(algorithm.key_len())
+        .ok_or(RSAError::Key)?;
+    let mut key = SecureBox::from_slice(key)?;
+    algorithm.normalize_key(&mut key);
+    let mac = Hmac::<Sha256>::new_from_slice(mac_key).map_err(|_| RSAError::Key)?;
+    Ok((key, mac))
+}
+
+/* What the MAC of an authenticated envelope covers ahead of the
+   ciphertext: the cipher as in EnvelopeHeader, its flags and the IV.
+   Algorithms with the same key length, such as DES-EDE3-CBC and
+   DESX-CBC, cannot then be swapped for each other, nor compression
+   switched on or off.
+*/
+fn mac_prefix(algorithm: EncryptionAlgorithmId, compressed: bool, iv: &[u8]) -> Vec<u8> {
+    let mut prefix = Vec::<u8>::with_capacity(3 + iv.len());
+    prefix.extend((algorithm as u16).to_be_bytes());
+    prefix.push(1 | if compressed { 2 } else { 0 });
+    prefix.extend(iv);
+    prefix
+}
+
+/* Envelope sealing as in R_SealInit/R_SealUpdate/R_SealFinal: content is
+   encrypted under a fresh key, which is RSA-encrypted once per recipient.
+   The final block is padded with PKCS #5 padding.
+*/
+pub struct SealContext {
+    cipher: CipherContext,
+    buffer: Vec<u8>,
+    encrypted_keys: Vec<Vec<u8>>,
+    fingerprints: Vec<[u8; 32]>,
+    iv: Vec<u8>,
+    mac: Option<Hmac<Sha256>>,
+    mac_started: bool,
+    header_pending: bool,
+    #[cfg(feature = "deflate")]
+    compressor: Option<ZlibEncoder<Vec<u8>>>,
+}
+
+impl SealContext {
+    pub fn new(
+        algorithm: EncryptionAlgorithmId,
+        public_keys: &[&RSAPublicKey],
+        random_struct: &mut RandomStruct,
+    ) -> Result<Self, RSAError> {
+        Self::seal(algorithm, public_keys, random_struct, false)
+    }
+
+    /* Encrypt-then-MAC. The recipients get a fresh 32-byte secret rather
+       than the content key; the content key and an HMAC-SHA256 key are
+       derived from it, and finalize appends the MAC of the cipher, the
+       flags, the IV and the ciphertext. Such envelopes are opened only with
+       OpenContext::new_authenticated.
+    */
+    pub fn new_authenticated(
+        algorithm: EncryptionAlgorithmId,
+        public_keys: &[&RSAPublicKey],
+        random_struct: &mut RandomStruct,
+    ) -> Result<Self, RSAError> {
+        Self::seal(algorithm, public_keys, random_struct, true)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(recipients = public_keys.len(), algorithm = ?algorithm, authenticated = authenticated), err(level = "debug"))
+    )]
+    fn seal(
+        algorithm: EncryptionAlgorithmId,
+        public_keys: &[&RSAPublicKey],
+        random_struct: &mut RandomStruct,
+        authenticated: bool,
+    ) -> Result<Self, RSAError> {
+        if public_keys.is_empty() {
+            return Err(RSAError::PublicKey);
+        }
+
+        let secret = SecureBox::from_vec(if authenticated {
+            random_struct.generate_bytes(AUTH_SECRET_LEN)?
+        } else {
+            algorithm.generate_key(random_struct)?
+        })?;
+        let iv = random_struct.generate_bytes(algorithm.iv_len())?;
+
+        let mut encrypted_keys = Vec::<Vec<u8>>::with_capacity(public_keys.len());
+        for public_key in public_keys {
+            encrypted_keys.push(public_key.rsa_public_encrypt(&secret, random_struct)?);
+        }
+        let fingerprints = public_keys
+            .iter()
+            .map(|public_key| key_fingerprint(public_key))
+            .collect();
+
+        let (cipher, mac) = if authenticated {
+            let (key, mac) = authentication_keys(algorithm, &secret)?;
+            (CipherContext::new(algorithm, &key, &iv, true)?, Some(mac))
+        } else {
+            (CipherContext::new(algorithm, &secret, &iv, true)?, None)
+        };
+
+        Ok(Self {
+            cipher,
+            buffer: Vec::<u8>::with_capacity(algorithm.block_len()),
+            encrypted_keys,
+            fingerprints,
+            iv,
+            mac,
+            mac_started: false,
+            header_pending: false,
+            #[cfg(feature = "deflate")]
+            compressor: None,
+        })
+    }
+
+    /* Starts the output with the envelope's EnvelopeHeader, so that it
+       can be opened through EnvelopeHeader::decode and open with no
+       other information. Without it the output is RSAREF's raw
+       ciphertext, and the algorithm, IV and encrypted keys must reach
+       the opener some other way.
+    */
+    pub fn with_header(mut self) -> Self {
+        self.header_pending = true;
+        self
+    }
+
+    /* The header with_header writes, as it stands: call it after
+       with_compression, if at all.
+    */
+    pub fn header(&self) -> Result<Vec<u8>, RSAError> {
+        EnvelopeHeader {
+            algorithm: self.cipher.algorithm(),
+            mac_digest: self.mac.as_ref().map(|_| DigestAlg::Sha256),
+            compressed: self.is_compressed(),
+            iv: self.iv.clone(),
+            recipients: self
+                .fingerprints
+                .iter()
+                .zip(&self.encrypted_keys)
+                .map(|(fingerprint, encrypted_key)| EnvelopeRecipient {
+                    fingerprint: *fingerprint,
+                    encrypted_key: encrypted_key.clone(),
+                })
+                .collect(),
+        }
+        .encode()
+    }
+
+    fn is_compressed(&self) -> bool {
+        #[cfg(feature = "deflate")]
+        let compressed = self.compressor.is_some();
+        #[cfg(not(feature = "deflate"))]
+        let compressed = false;
+        compressed
+    }
+
+    /* Feeds output to the MAC, if any, starting it with mac_prefix the
+       first time: by then with_compression can no longer be called.
+    */
+    fn update_mac(&mut self, output: &[u8]) {
+        let compressed = self.is_compressed();
+        if let Some(mac) = &mut self.mac {
+            if !self.mac_started {
+                mac.update(&mac_prefix(self.cipher.algorithm(), compressed, &self.iv));
+                self.mac_started = true;
+            }
+            mac.update(output);
+        }
+    }
+
+    /* The header, the first time output is produced with with_header. */
+    fn take_header(&mut self) -> Result<Vec<u8>, RSAError> {
+        if !self.header_pending {
+            return Ok(Vec::new());
+        }
+        self.header_pending = false;
+        self.header()
+    }
+
+    /* Compresses the content with zlib before encryption. Whether an
+       envelope is compressed travels with the IV and encrypted keys; the
+       opener must call OpenContext::with_decompression to match.
+    */
+    #[cfg(feature = "deflate")]
+    pub fn with_compression(mut self) -> Self {
+        self.compressor = Some(ZlibEncoder::new(
+            Vec::<u8>::new(),
+            flate2::Compression::default(),
+        ));
+        self
+    }
+
+    #[cfg(feature = "deflate")]
+    pub fn compressed(&self) -> bool {
+        self.compressor.is_some()
+    }
+
+    pub fn algorithm(&self) -> EncryptionAlgorithmId {
+        self.cipher.algorithm()
+    }
+
+    /* The content key encrypted for each public key, in the order given. */
+    pub fn encrypted_keys(&self) -> &[Vec<u8>] {
+        &self.encrypted_keys
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        #[cfg(feature = "deflate")]
+        if let Some(compressor) = &mut self.compressor {
+            compressor.write_all(input).map_err(|_| RSAError::Data)?;
+            self.buffer.append(compressor.get_mut());
+        } else {
+            self.buffer.extend(input);
+        }
+        #[cfg(not(feature = "deflate"))]
+        self.buffer.extend(input);
+
+        let blocks = take_blocks(&mut self.buffer, self.cipher.algorithm().block_len(), false);
+        let output = self.cipher.update(&blocks)?;
+        self.update_mac(&output);
+        if output.is_empty() {
+            return Ok(output);
+        }
+        let mut result = self.take_header()?;
+        result.extend(output);
+        Ok(result)
+    }
+
+    /* The padded final block, then the MAC if the envelope is
+       authenticated. Output starts with the header if with_header was
+       called and update produced none.
+    */
+    pub fn finalize(mut self) -> Result<Vec<u8>, RSAError> {
+        /* Start the MAC while the compressor still shows. */
+        self.update_mac(&[]);
+        #[cfg(feature = "deflate")]
+        if let Some(compressor) = self.compressor.take() {
+            self.buffer
+                .extend(compressor.finish().map_err(|_| RSAError::Data)?);
+        }
+
+        let block_len = self.cipher.algorithm().block_len();
+        let mut blocks = take_blocks(&mut self.buffer, block_len, false);
+        let pad_len = block_len - self.buffer.len();
+        blocks.append(&mut self.buffer);
+        blocks.extend(core::iter::repeat_n(pad_len as u8, pad_len));
+        let output = self.cipher.update(&blocks)?;
+        let mut result = self.take_header()?;
+        result.extend_from_slice(&output);
+        self.update_mac(&output);
+        if let Some(mac) = self.mac.take() {
+            result.extend(mac.finalize().into_bytes());
+        }
+        Ok(result)
+    }
+}
+
+/* Envelope opening as in R_OpenInit/R_OpenUpdate/R_OpenFinal. */
+pub struct OpenContext {
+    cipher: CipherContext,
+    buffer: Vec<u8>,
+    mac: Option<Hmac<Sha256>>,
+    iv: Vec<u8>,
+    #[cfg(feature = "deflate")]
+    decompressor: Option<ZlibDecoder<Vec<u8>>>,
+}
+
+impl OpenContext {
+    pub fn new(
+        algorithm: EncryptionAlgorithmId,
+        encrypted_key: &[u8],
+        iv: &[u8],
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Self, RSAError> {
+        Self::open(algorithm, encrypted_key, iv, private_key, false)
+    }
+
+    /* Opens an envelope from SealContext::new_authenticated. Nothing is
+       decrypted until finalize has checked the MAC, so update returns no
+       output and the whole envelope is held in memory. A MAC that does
+       not verify is reported as RSAError::Data.
+    */
+    pub fn new_authenticated(
+        algorithm: EncryptionAlgorithmId,
+        encrypted_key: &[u8],
+        iv: &[u8],
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Self, RSAError> {
+        Self::open(algorithm, encrypted_key, iv, private_key, true)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(algorithm = ?algorithm, authenticated = authenticated), err(level = "debug"))
+    )]
+    fn open(
+        algorithm: EncryptionAlgorithmId,
+        encrypted_key: &[u8],
+        iv: &[u8],
+        private_key: &dyn PrivateKeyOps,
+        authenticated: bool,
+    ) -> Result<Self, RSAError> {
+        let secret = SecureBox::from_vec(private_key.rsa_private_decrypt(encrypted_key)?)?;
+        let expected_len = if authenticated {
+            AUTH_SECRET_LEN
+        } else {
+            algorithm.key_len()
+        };
+        if secret.len() != expected_len {
+            return Err(RSAError::Key);
+        }
+
+        let (cipher, mac) = if authenticated {
+            let (key, mac) = authentication_keys(algorithm, &secret)?;
+            (CipherContext::new(algorithm, &key, iv, false)?, Some(mac))
+        } else {
+            (CipherContext::new(algorithm, &secret, iv, false)?, None)
+        };
+
+        Ok(Self {
+            cipher,
+            buffer: Vec::<u8>::with_capacity(2 * algorithm.block_len()),
+            mac,
+            iv: iv.to_vec(),
+            #[cfg(feature = "deflate")]
+            decompressor: None,
+        })
+    }
+
+    /* Inflates content sealed with SealContext::with_compression. Corrupt
+       compressed data is reported as RSAError::Data.
+    */
+    #[cfg(feature = "deflate")]
+    pub fn with_decompression(mut self) -> Self {
+        self.decompressor = Some(ZlibDecoder::new(Vec::<u8>::new()));
+        self
+    }
+
+    #[cfg(feature = "deflate")]
+    fn inflate(&mut self, output: Vec<u8>) -> Result<Vec<u8>, RSAError> {
+        let output: SecretVec = Wiped(output);
+        if let Some(decompressor) = &mut self.decompressor {
+            decompressor
+                .write_all(&output)
+                .map_err(|_| RSAError::Data)?;
+            return Ok(core::mem::take(decompressor.get_mut()));
+        }
+        Ok(output.into_vec())
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.buffer.extend(input);
+        if self.mac.is_some() {
+            return Ok(Vec::new());
+        }
+        let blocks = take_blocks(&mut self.buffer, self.cipher.algorithm().block_len(), true);
+        let output = self.cipher.update(&blocks)?;
+        #[cfg(feature = "deflate")]
+        let output = self.inflate(output)?;
+        Ok(output)
+    }
+
+    /* Decrypts the last block and strips its padding. Bad padding is
+       reported as RSAError::Key, since it almost always means the wrong key.
+    */
+    pub fn finalize(mut self) -> Result<Vec<u8>, RSAError> {
+        if let Some(mut mac) = self.mac.take() {
+            let tag_start = self
+                .buffer
+                .len()
+                .checked_sub(MAC_LEN)
+                .ok_or(RSAError::Data)?;
+            let tag = self.buffer.split_off(tag_start);
+            let ciphertext = core::mem::take(&mut self.buffer);
+            #[cfg(feature = "deflate")]
+            let compressed = self.decompressor.is_some();
+            #[cfg(not(feature = "deflate"))]
+            let compressed = false;
+            mac.update(&mac_prefix(self.cipher.algorithm(), compressed, &self.iv));
+            mac.update(&ciphertext);
+            mac.verify_slice(&tag).map_err(|_| RSAError::Data)?;
+
+            let mut output: SecretVec = Wiped(self.update(&ciphertext)?);
+            output.extend(self.finalize()?);
+            return Ok(output.into_vec());
+        }
+
+        let block_len = self.cipher.algorithm().block_len();
+        if self.buffer.len() != block_len {
+            return Err(RSAError::Key);
+        }
+
+        let mut last = self.cipher.update(&self.buffer)?;
+        let pad_len = *last.last().ok_or(RSAError::Key)? as usize;
+        if pad_len == 0
+            || pad_len > last.len()
+            || last
+                .iter()
+                .rev()
+                .take(pad_len)
+                .any(|b| *b as usize != pad_len)
+        {
+            return Err(RSAError::Key);
+        }
+        last.truncate(last.len() - pad_len);
+
+        #[cfg(feature = "deflate")]
+        if self.decompressor.is_some() {
+            let mut output = self.inflate(last)?;
+            if let Some(decompressor) = self.decompressor.take() {
+                output.extend(decompressor.finish().map_err(|_| RSAError::Data)?);
+            }
+            return Ok(output);
+        }
+        Ok(last)
+    }
+
+    /* update and finalize with the plaintext in buffers wiped on drop. */
+    pub fn update_secret(&mut self, input: &[u8]) -> Result<SecretBuffer, RSAError> {
+        self.update(input).map(SecretBuffer::from)
+    }
+
+    pub fn finalize_secret(self) -> Result<SecretBuffer, RSAError> {
+        self.finalize().map(SecretBuffer::from)
+    }
+}
+
+/* Seals everything written to it into the inner writer. finish must be
+   called to write the padded final block; dropping the writer without it
+   leaves the output truncated.
+*/
+#[cfg(feature = "std")]
+pub struct SealWriter<W: Write> {
+    inner: W,
+    context: SealContext,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> SealWriter<W> {
+    pub fn new(inner: W, context: SealContext) -> Self {
+        Self { inner, context }
+    }
+
+    pub fn context(&self) -> &SealContext {
+        &self.context
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let last = self.context.finalize().map_err(io_error)?;
+        self.inner.write_all(&last)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for SealWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let output = self.context.update(buf).map_err(io_error)?;
+        self.inner.write_all(&output)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/* Opens a sealed stream read from the inner reader. Memory use is bounded
+   by the chunk size regardless of the stream length, except for an
+   authenticated envelope, which is read whole before the first byte is
+   returned; a bad final block or MAC surfaces as an InvalidData error
+   from the last read.
+*/
+#[cfg(feature = "std")]
+pub struct OpenReader<R: Read> {
+    inner: R,
+    context: Option<OpenContext>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> OpenReader<R> {
+    pub fn new(inner: R, context: OpenContext) -> Self {
+        Self {
+            inner,
+            context: Some(context),
+            output: Vec::<u8>::new(),
+            position: 0,
+        }
+    }
+
+    /* Reads the EnvelopeHeader at the start of inner and opens the
+       envelope with private_key as it describes.
+    */
+    pub fn with_header(mut inner: R, private_key: &dyn PrivateKeyOps) -> io::Result<Self> {
+        let mut data = Vec::<u8>::new();
+        loop {
+            match EnvelopeHeader::decode(&data) {
+                Ok((header, header_len)) => {
+                    let mut context = header.open(private_key).map_err(io_error)?;
+                    let output = context
+                        .update(data.get(header_len..).unwrap_or_default())
+                        .map_err(io_error)?;
+                    return Ok(Self {
+                        inner,
+                        context: Some(context),
+                        output,
+                        position: 0,
+                    });
+                }
+                Err(RSAError::InputLen { .. }) => {}
+                Err(error) => return Err(io_error(error)),
+            }
+
+            let mut chunk = [0u8; STREAM_CHUNK_LEN];
+            let len = match inner.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => len,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            data.extend(chunk.get(..len).ok_or(io::ErrorKind::InvalidData)?);
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for OpenReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            let Some(context) = self.context.as_mut() else {
+                return Ok(0);
+            };
+
+            let mut chunk = [0u8; STREAM_CHUNK_LEN];
+            let len = match self.inner.read(&mut chunk) {
+                Ok(len) => len,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            self.output = if len == 0 {
+                self.context
+                    .take()
+                    .map_or_else(|| Ok(Vec::new()), OpenContext::finalize)
+            } else {
+                context.update(chunk.get(..len).ok_or(io::ErrorKind::InvalidData)?)
+            }
+            .map_err(io_error)?;
+            self.position = 0;
+        }
+
+        let available = self.output.get(self.position..).unwrap_or_default();
+        let len = buf.len().min(available.len());
+        for (target, byte) in buf.iter_mut().zip(available) {
+            *target = *byte;
+        }
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use crate::rsa::RSAPrivateKey;
+
+    fn generate_keys() -> (RSAPublicKey, RSAPrivateKey) {
+        test_key_pair(512)
+    }
+
+    #[test]
+    fn test_seal_open_multiple_recipients() {
+        let mut random_struct = test_random_struct(0x61);
+        let recipients = [generate_keys(), generate_keys(), generate_keys()];
+        let public_keys = recipients.iter().map(|(p, _)| p).collect::<Vec<_>>();
+        let content = (0u8..=100).collect::<Vec<u8>>();
+
+        for algorithm in [
+            EncryptionAlgorithmId::DesCbc,
+            EncryptionAlgorithmId::DesEde3Cbc,
+            EncryptionAlgorithmId::DesxCbc,
+            EncryptionAlgorithmId::Aes128Cbc,
+        ] {
+            let mut seal = SealContext::new(algorithm, &public_keys, &mut random_struct).unwrap();
+            let encrypted_keys = seal.encrypted_keys().to_vec();
+            let iv = seal.iv().to_vec();
+            assert_eq!(encrypted_keys.len(), 3);
+
+            let mut sealed = Vec::<u8>::new();
+            for chunk in content.chunks(7) {
+                sealed.extend(seal.update(chunk).unwrap());
+            }
+            sealed.extend(seal.finalize().unwrap());
+            assert!(sealed.len().is_multiple_of(algorithm.block_len()));
+            assert!(sealed.len() > content.len());
+
+            for ((_, private_key), encrypted_key) in recipients.iter().zip(&encrypted_keys) {
+                let mut open =
+                    OpenContext::new(algorithm, encrypted_key, &iv, private_key).unwrap();
+                let mut opened = Vec::<u8>::new();
+                for chunk in sealed.chunks(algorithm.block_len()) {
+                    opened.extend(open.update(chunk).unwrap());
+                }
+                opened.extend(open.finalize().unwrap());
+                assert_eq!(opened, content);
+            }
+
+            assert!(
+                OpenContext::new(algorithm, &encrypted_keys[0], &iv, &recipients[1].1)
+                    .and_then(|mut open| {
+                        open.update(&sealed)?;
+                        open.finalize()
+                    })
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_seal_open_identity() {
+        let mut random_struct = test_random_struct(0x65);
+        let (public_key, private_key) = generate_keys();
+        let content = b"Visible through the envelope.";
+
+        let mut seal = SealContext::new(
+            EncryptionAlgorithmId::Identity,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap();
+        let encrypted_key = seal.encrypted_keys()[0].clone();
+        let iv = seal.iv().to_vec();
+        let mut sealed = seal.update(content).unwrap();
+        sealed.extend(seal.finalize().unwrap());
+        assert_eq!(&sealed[..content.len()], content);
+        assert_eq!(sealed.len(), 32);
+
+        let mut open = OpenContext::new(
+            EncryptionAlgorithmId::Identity,
+            &encrypted_key,
+            &iv,
+            &private_key,
+        )
+        .unwrap();
+        let mut opened = open.update(&sealed).unwrap();
+        opened.extend(open.finalize().unwrap());
+        assert_eq!(opened, content);
+
+        let mut open = OpenContext::new(
+            EncryptionAlgorithmId::Identity,
+            &encrypted_key,
+            &iv,
+            &private_key,
+        )
+        .unwrap();
+        let mut opened = open.update_secret(&sealed).unwrap().to_vec();
+        opened.extend_from_slice(&open.finalize_secret().unwrap());
+        assert_eq!(opened, content);
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn test_seal_open_compressed() {
+        let mut random_struct = test_random_struct(0x66);
+        let (public_key, private_key) = generate_keys();
+        let content = b"All work and no play makes Jack a dull boy. ".repeat(500);
+
+        let seal = SealContext::new(
+            EncryptionAlgorithmId::Aes128Cbc,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap()
+        .with_compression();
+        assert!(seal.compressed());
+        let encrypted_key = seal.encrypted_keys()[0].clone();
+        let iv = seal.iv().to_vec();
+
+        let mut writer = SealWriter::new(Vec::<u8>::new(), seal);
+        for chunk in content.chunks(333) {
+            writer.write_all(chunk).unwrap();
+        }
+        let sealed = writer.finish().unwrap();
+        assert!(sealed.len() < content.len() / 10);
+
+        let open = || {
+            OpenContext::new(
+                EncryptionAlgorithmId::Aes128Cbc,
+                &encrypted_key,
+                &iv,
+                &private_key,
+            )
+            .unwrap()
+        };
+        let mut opened = Vec::<u8>::new();
+        OpenReader::new(sealed.as_slice(), open().with_decompression())
+            .read_to_end(&mut opened)
+            .unwrap();
+        assert_eq!(opened, content);
+
+        /* Without decompression the zlib stream comes back as is. */
+        let raw = open().update(&sealed).unwrap();
+        assert_eq!(raw[0], 0x78);
+
+        let mut truncated = open().with_decompression();
+        truncated.update(&sealed[..sealed.len() / 2]).unwrap();
+        assert!(matches!(
+            OpenContext::finalize(truncated),
+            Err(RSAError::Key | RSAError::Data)
+        ));
+    }
+
+    #[test]
+    fn test_seal_open_authenticated() {
+        let mut random_struct = test_random_struct(0x66);
+        let (public_key, private_key) = generate_keys();
+        let content = (0u8..=200).collect::<Vec<u8>>();
+
+        for algorithm in [
+            EncryptionAlgorithmId::DesEde2Cbc,
+            EncryptionAlgorithmId::Aes128Cbc,
+            EncryptionAlgorithmId::Aes256Cbc,
+        ] {
+            let mut seal =
+                SealContext::new_authenticated(algorithm, &[&public_key], &mut random_struct)
+                    .unwrap();
+            let (encrypted_key, iv) = (seal.encrypted_keys()[0].clone(), seal.iv().to_vec());
+            let mut sealed = seal.update(&content).unwrap();
+            sealed.extend(seal.finalize().unwrap());
+            let block_len = algorithm.block_len();
+            assert_eq!(
+                sealed.len(),
+                (content.len() / block_len + 1) * block_len + MAC_LEN
+            );
+
+            let open = |sealed: &[u8]| {
+                let mut open =
+                    OpenContext::new_authenticated(algorithm, &encrypted_key, &iv, &private_key)
+                        .unwrap();
+                assert!(open.update(sealed).unwrap().is_empty());
+                open.finalize()
+            };
+            assert_eq!(open(&sealed).unwrap(), content);
+
+            /* Any change to the ciphertext or the MAC, and any truncation,
+               fails before decryption.
+            */
+            for i in [0, sealed.len() - MAC_LEN - 1, sealed.len() - 1] {
+                let mut tampered = sealed.clone();
+                tampered[i] ^= 1;
+                assert_eq!(open(&tampered), Err(RSAError::Data));
+            }
+            assert_eq!(open(&sealed[..sealed.len() - 16]), Err(RSAError::Data));
+            assert_eq!(open(&sealed[..MAC_LEN - 1]), Err(RSAError::Data));
+            let mut other_iv = iv.clone();
+            other_iv[0] ^= 1;
+            assert!(OpenContext::new_authenticated(
+                algorithm,
+                &encrypted_key,
+                &other_iv,
+                &private_key
+            )
+            .and_then(|mut open| {
+                open.update(&sealed)?;
+                open.finalize()
+            })
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_authenticated_envelope_binds_algorithm() {
+        let mut random_struct = test_random_struct(0x67);
+        let (public_key, private_key) = generate_keys();
+        let content = (0u8..=100).collect::<Vec<u8>>();
+
+        /* Both take a 24-byte key and an 8-byte IV. */
+        let mut seal = SealContext::new_authenticated(
+            EncryptionAlgorithmId::DesxCbc,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap()
+        .with_header();
+        let mut sealed = seal.update(&content).unwrap();
+        sealed.extend(seal.finalize().unwrap());
+
+        let (header, header_len) = EnvelopeHeader::decode(&sealed).unwrap();
+        let open = |algorithm| {
+            let mut open = OpenContext::new_authenticated(
+                algorithm,
+                &header.recipients[0].encrypted_key,
+                &header.iv,
+                &private_key,
+            )?;
+            open.update(&sealed[header_len..])?;
+            open.finalize()
+        };
+        assert_eq!(open(EncryptionAlgorithmId::DesxCbc).unwrap(), content);
+        assert_eq!(open(EncryptionAlgorithmId::DesEde3Cbc), Err(RSAError::Data));
+
+        /* The same through a header whose cipher field was rewritten. */
+        let mut tampered = sealed.clone();
+        tampered[6..8].copy_from_slice(&(EncryptionAlgorithmId::DesEde3Cbc as u16).to_be_bytes());
+        let (tampered_header, _) = EnvelopeHeader::decode(&tampered).unwrap();
+        assert_eq!(tampered_header.algorithm, EncryptionAlgorithmId::DesEde3Cbc);
+        let mut open = tampered_header.open(&private_key).unwrap();
+        open.update(&tampered[header_len..]).unwrap();
+        assert_eq!(open.finalize(), Err(RSAError::Data));
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn test_authenticated_envelope_binds_compression() {
+        let mut random_struct = test_random_struct(0x68);
+        let (public_key, private_key) = generate_keys();
+        let content = b"compressed and authenticated ".repeat(20);
+
+        let mut seal = SealContext::new_authenticated(
+            EncryptionAlgorithmId::Aes128Cbc,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap()
+        .with_compression();
+        let (encrypted_key, iv) = (seal.encrypted_keys()[0].clone(), seal.iv().to_vec());
+        let mut sealed = seal.update(&content).unwrap();
+        sealed.extend(seal.finalize().unwrap());
+
+        let open = || {
+            OpenContext::new_authenticated(
+                EncryptionAlgorithmId::Aes128Cbc,
+                &encrypted_key,
+                &iv,
+                &private_key,
+            )
+            .unwrap()
+        };
+        let mut decompressing = open().with_decompression();
+        decompressing.update(&sealed).unwrap();
+        assert_eq!(decompressing.finalize().unwrap(), content);
+
+        let mut raw = open();
+        raw.update(&sealed).unwrap();
+        assert_eq!(raw.finalize(), Err(RSAError::Data));
+    }
+
+    #[test]
+    fn test_seal_requires_recipient() {
+        let mut random_struct = test_random_struct(0x62);
+        assert!(matches!(
+            SealContext::new(EncryptionAlgorithmId::DesCbc, &[], &mut random_struct),
+            Err(RSAError::PublicKey)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_seal_writer_open_reader() {
+        let mut random_struct = test_random_struct(0x63);
+        let (public_key, private_key) = generate_keys();
+        let content = (0..50000u32).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+
+        let context = SealContext::new(
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap();
+        let encrypted_key = context.encrypted_keys()[0].clone();
+        let iv = context.iv().to_vec();
+
+        let mut writer = SealWriter::new(Vec::<u8>::new(), context);
+        for chunk in content.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let sealed = writer.finish().unwrap();
+
+        let open = OpenContext::new(
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &encrypted_key,
+            &iv,
+            &private_key,
+        )
+        .unwrap();
+        let mut opened = Vec::<u8>::new();
+        OpenReader::new(sealed.as_slice(), open)
+            .read_to_end(&mut opened)
+            .unwrap();
+        assert_eq!(opened, content);
+
+        let open = OpenContext::new(
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &encrypted_key,
+            &iv,
+            &private_key,
+        )
+        .unwrap();
+        let error = OpenReader::new(&sealed[..sealed.len() - 3], open)
+            .read_to_end(&mut Vec::<u8>::new())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.get_ref().unwrap().downcast_ref::<RSAError>(),
+            Some(&RSAError::Key)
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::r_enhanc::{io_error, OpenContext, SealContext, STREAM_CHUNK_LEN};
+use alloc::vec::Vec;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/* Async counterpart of SealWriter. Sealed output that the inner writer does
+   not accept immediately is held until the next write, flush or shutdown;
+   shutdown writes the padded final block before shutting the inner writer
+   down.
+*/
+pub struct AsyncSealWriter<W: AsyncWrite + Unpin> {
+    inner: W,
+    context: Option<SealContext>,
+    pending: Vec<u8>,
+    position: usize,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncSealWriter<W> {
+    pub fn new(inner: W, context: SealContext) -> Self {
+        Self {
+            inner,
+            context: Some(context),
+            pending: Vec::<u8>::new(),
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.position < self.pending.len() {
+            let len = ready!(Pin::new(&mut self.inner)
+                .poll_write(cx, self.pending.get(self.position..).unwrap_or_default()))?;
+            if len == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.position += len;
+        }
+        self.pending.clear();
+        self.position = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncSealWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_pending(cx))?;
+        let Some(context) = self.context.as_mut() else {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "write after shutdown",
+            )));
+        };
+        self.pending = context.update(buf).map_err(io_error)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        if let Some(context) = self.context.take() {
+            self.pending = context.finalize().map_err(io_error)?;
+            ready!(self.poll_pending(cx))?;
+        }
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/* Async counterpart of OpenReader. */
+pub struct AsyncOpenReader<R: AsyncRead + Unpin> {
+    inner: R,
+    context: Option<OpenContext>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncOpenReader<R> {
+    pub fn new(inner: R, context: OpenContext) -> Self {
+        Self {
+            inner,
+            context: Some(context),
+            output: Vec::<u8>::new(),
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncOpenReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        while this.position == this.output.len() {
+            let Some(context) = this.context.as_mut() else {
+                return Poll::Ready(Ok(()));
+            };
+
+            let mut chunk = [0u8; STREAM_CHUNK_LEN];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf))?;
+            let filled = chunk_buf.filled();
+            this.output = if filled.is_empty() {
+                this.context
+                    .take()
+                    .map_or_else(|| Ok(Vec::new()), OpenContext::finalize)
+            } else {
+                context.update(filled)
+            }
+            .map_err(io_error)?;
+            this.position = 0;
+        }
+
+        let available = this.output.get(this.position..).unwrap_or_default();
+        let len = buf.remaining().min(available.len());
+        buf.put_slice(available.split_at(len).0);
+        this.position += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::EncryptionAlgorithmId;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_async_seal_open() {
+        let mut random_struct = test_random_struct(0x64);
+        let (public_key, private_key) = test_key_pair(512);
+        let content = (0..30000u32).map(|i| (i % 253) as u8).collect::<Vec<u8>>();
+
+        let context = SealContext::new(
+            EncryptionAlgorithmId::Aes128Cbc,
+            &[&public_key],
+            &mut random_struct,
+        )
+        .unwrap();
+        let encrypted_key = context.encrypted_keys()[0].clone();
+        let iv = context.iv().to_vec();
+
+        /* A small duplex buffer forces both sides through Pending. */
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut writer = AsyncSealWriter::new(client, context);
+        let (_, sealed) = tokio::join!(
+            async {
+                for chunk in content.chunks(777) {
+                    writer.write_all(chunk).await.unwrap();
+                }
+                writer.shutdown().await.unwrap();
+            },
+            async {
+                let mut sealed = Vec::<u8>::new();
+                server.read_to_end(&mut sealed).await.unwrap();
+                sealed
+            }
+        );
+
+        let open = OpenContext::new(
+            EncryptionAlgorithmId::Aes128Cbc,
+            &encrypted_key,
+            &iv,
+            &private_key,
+        )
+        .unwrap();
+        let mut opened = Vec::<u8>::new();
+        AsyncOpenReader::new(sealed.as_slice(), open)
+            .read_to_end(&mut opened)
+            .await
+            .unwrap();
+        assert_eq!(opened, content);
+    }
+}
@@ -1,36 +1,145 @@
-use std::str::FromStr;
-
+use crate::key_policy::check_key_bits;
+#[cfg(not(feature = "rsa-crate"))]
+use crate::parallel;
+use crate::r_random::RandomStruct;
 use crate::rsa::{
     RSAPrivateKey, RSAProtoKey, RSAPublicKey, MAX_RSA_MODULUS_BITS, MIN_RSA_MODULUS_BITS,
 };
+#[cfg(not(feature = "rsa-crate"))]
+use crate::wipe::Wipe;
 use crate::RSAError;
-use num_integer::Integer;
-use rand::thread_rng;
-use rsa::{BigUint, RsaPrivateKey};
-use std::ops::{Add, Mul, Sub};
+#[cfg(not(feature = "rsa-crate"))]
+use alloc::vec::Vec;
+use core::ops::{Add, Div, Mul, Rem, Sub};
+#[cfg(not(feature = "rsa-crate"))]
+use num_bigint_dig::prime::probably_prime;
+use num_bigint_dig::BigUint;
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "rsa-crate")]
+use rsa::RsaPrivateKey;
+
+#[cfg(not(feature = "rsa-crate"))]
+const PRIME_TEST_ROUNDS: usize = 20;
+#[cfg(not(feature = "rsa-crate"))]
+const CANDIDATE_BATCH_LEN: usize = 32;
 
-fn generate_primes(proto_key: &RSAProtoKey) -> Result<[BigUint; 2], RSAError> {
+#[cfg(feature = "rsa-crate")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bits = proto_key.bits))
+)]
+fn generate_primes<R: RngCore + CryptoRng>(
+    proto_key: &RSAProtoKey,
+    rng: &mut R,
+) -> Result<[BigUint; 2], RSAError> {
     // Use other rsa library to generate primes for us (lol)
-    let key = RsaPrivateKey::new(&mut thread_rng(), proto_key.bits as usize)
-        .map_err(|_| RSAError::Key)?;
-    let mut primes: [BigUint; 2] = Default::default();
-    primes[0] = key.primes()[0].clone();
-    primes[1] = key.primes()[1].clone();
-    Ok(primes)
+    let key = RsaPrivateKey::new(rng, proto_key.bits as usize).map_err(|_| RSAError::Key)?;
+    match key.primes() {
+        [p, q] => Ok([p.clone(), q.clone()]),
+        _ => Err(RSAError::Key),
+    }
+}
+
+#[cfg(not(feature = "rsa-crate"))]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bits = proto_key.bits))
+)]
+fn generate_primes<R: RngCore + CryptoRng>(
+    proto_key: &RSAProtoKey,
+    rng: &mut R,
+) -> Result<[BigUint; 2], RSAError> {
+    let bits = proto_key.bits as usize;
+    let e = public_exponent(proto_key);
+    let p = generate_prime(bits.div_ceil(2), &e, rng);
+    loop {
+        let q = generate_prime(bits / 2, &e, rng);
+        if q != p {
+            return Ok([p, q]);
+        }
+    }
+}
+
+/* Finds a probable prime of exactly bits bits for which p-1 is prime to
+   e, searching upward from a random odd starting point as RSAREF's
+   GeneratePrime does. The top two bits are set so that the product of two
+   such primes has the full modulus length.
+*/
+#[cfg(not(feature = "rsa-crate"))]
+fn generate_prime<R: RngCore + CryptoRng>(bits: usize, e: &BigUint, rng: &mut R) -> BigUint {
+    let one = BigUint::from(1u32);
+    let zero = BigUint::from(0u32);
+    let mut bytes = vec![0u8; bits.div_ceil(8)];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        let mut candidate = BigUint::from_bytes_be(&bytes) >> (bytes.len() * 8 - bits);
+        candidate |= (BigUint::from(3u32) << (bits - 2)) | &one;
+        /* Candidates are tested a batch at a time so that the parallel
+           feature can spread them over threads; the first suitable one in
+           order wins either way, so the result does not depend on it.
+        */
+        while candidate.bits() == bits {
+            let mut batch = Vec::with_capacity(CANDIDATE_BATCH_LEN);
+            while batch.len() < CANDIDATE_BATCH_LEN && candidate.bits() == bits {
+                batch.push(candidate.clone());
+                candidate += 2u32;
+            }
+            let found = parallel::find_first(&batch, |candidate| {
+                candidate.sub(&one).rem(e) != zero && probably_prime(candidate, PRIME_TEST_ROUNDS)
+            });
+            if let Some(prime) = found {
+                bytes.wipe();
+                return prime.clone();
+            }
+        }
+    }
 }
 
+/* Draws prime candidates from a random structure, as R_GeneratePEMKeys
+   does. The structure must be seeded before use.
+*/
+struct RandomStructRng<'a>(&'a mut RandomStruct);
+
+impl RngCore for RandomStructRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let _ = self.0.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for RandomStructRng<'_> {}
+
 fn mod_inv(b: &BigUint, c: &BigUint) -> BigUint {
     /* Apply extended Euclidean algorithm, modified to avoid negative numbers. */
-    let mut u1 = BigUint::from_str("1").unwrap();
-    let mut v1 = BigUint::from_str("0").unwrap();
+    let mut u1 = BigUint::from(1u32);
+    let mut v1 = BigUint::from(0u32);
     let mut u3 = b.clone();
     let mut v3 = c.clone();
 
     let mut u1_sign = 1;
-    let zero = BigUint::from_str("0").unwrap();
+    let zero = BigUint::from(0u32);
 
     while v3 != zero {
-        let (q, t3) = u3.div_rem(&v3);
+        let q = (&u3).div(&v3);
+        let t3 = u3.rem(&v3);
         let w = q.mul(&v1);
         let t1 = u1.add(&w);
         u1 = v1.clone();
@@ -48,21 +157,79 @@ fn mod_inv(b: &BigUint, c: &BigUint) -> BigUint {
     }
 }
 
+/* Generates a key pair with primes from the operating system's random
+   number generator, which the call does not show.
+*/
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+#[deprecated(
+    note = "name the entropy source: generate_pem_keys_with_rng(proto_key, &mut OsRng) or generate_pem_keys_with_random"
+)]
 pub fn generate_pem_keys(
     proto_key: &RSAProtoKey,
 ) -> Result<(RSAPublicKey, RSAPrivateKey), RSAError> {
+    generate_pem_keys_with_rng(proto_key, &mut OsRng)
+}
+
+/* Generates a key pair with primes drawn from rng, which, being a
+   CryptoRng, is seeded by contract; e.g. rand's OsRng, or a StdRng seeded
+   from it. The deterministic build leaves only RandomStruct sources.
+*/
+#[cfg(not(feature = "deterministic"))]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bits = proto_key.bits), err(level = "debug"))
+)]
+pub fn generate_pem_keys_with_rng<R: RngCore + CryptoRng>(
+    proto_key: &RSAProtoKey,
+    rng: &mut R,
+) -> Result<(RSAPublicKey, RSAPrivateKey), RSAError> {
+    check_proto_key(proto_key)?;
+    build_key_pair(proto_key, generate_primes(proto_key, rng)?)
+}
+
+/* Generates a key pair with primes drawn from random_struct, as
+   R_GeneratePEMKeys does, failing with NeedRandom until it is fully
+   seeded. Needs no operating system entropy, so it is the generator
+   available in every build.
+*/
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(bits = proto_key.bits), err(level = "debug"))
+)]
+pub fn generate_pem_keys_with_random(
+    proto_key: &RSAProtoKey,
+    random_struct: &mut RandomStruct,
+) -> Result<(RSAPublicKey, RSAPrivateKey), RSAError> {
+    check_proto_key(proto_key)?;
+    if random_struct.get_random_bytes_needed() != 0 {
+        return Err(RSAError::NeedRandom);
+    }
+    let primes = generate_primes(proto_key, &mut RandomStructRng(random_struct))?;
+    build_key_pair(proto_key, primes)
+}
+
+fn check_proto_key(proto_key: &RSAProtoKey) -> Result<(), RSAError> {
     let bits = proto_key.bits as usize;
     if !(MIN_RSA_MODULUS_BITS..=MAX_RSA_MODULUS_BITS).contains(&bits) {
         return Err(RSAError::ModulusLen);
     }
+    check_key_bits(proto_key.bits)
+}
 
-    let e = if proto_key.use_fermat4 {
-        BigUint::from_str("65537").unwrap()
+fn public_exponent(proto_key: &RSAProtoKey) -> BigUint {
+    if proto_key.use_fermat4 {
+        BigUint::from(65537u32)
     } else {
-        BigUint::from_str("3").unwrap()
-    };
+        BigUint::from(3u32)
+    }
+}
 
-    let primes = generate_primes(proto_key)?;
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+fn build_key_pair(
+    proto_key: &RSAProtoKey,
+    primes: [BigUint; 2],
+) -> Result<(RSAPublicKey, RSAPrivateKey), RSAError> {
+    let e = public_exponent(proto_key);
 
     /* Sort so that p > q. (p = q case is extremely unlikely.) */
     let (p, q) = if primes[0] > primes[1] {
@@ -77,14 +244,14 @@ pub fn generate_pem_keys(
     let n = p.clone().mul(q);
     let q_inv = mod_inv(q, p);
 
-    let t = BigUint::from_str("1").unwrap();
+    let t = BigUint::from(1u32);
     let p_minus_1 = p.clone().sub(&t);
     let q_minus_1 = q.clone().sub(&t);
     let phi_n = p_minus_1.clone().mul(&q_minus_1);
 
     let d = mod_inv(&e, &phi_n);
-    let (_, dp) = d.div_rem(&p_minus_1);
-    let (_, dq) = d.div_rem(&q_minus_1);
+    let dp = (&d).rem(&p_minus_1);
+    let dq = (&d).rem(&q_minus_1);
 
     let private_key = RSAPrivateKey::from_components(
         proto_key.bits,
@@ -99,40 +266,240 @@ pub fn generate_pem_keys(
     Ok((private_key.public_key(), private_key))
 }
 
+/* Keys for unit tests that take no operating-system entropy. Each call on
+   a thread seeds from a per-thread counter, so a test gets distinct keys
+   and the same ones on every run.
+*/
+#[cfg(test)]
+pub(crate) fn test_key_pair(bits: u32) -> (RSAPublicKey, RSAPrivateKey) {
+    std::thread_local! {
+        static CALLS: core::cell::Cell<u32> = const { core::cell::Cell::new(0) };
+    }
+    let call = CALLS.with(|calls| calls.replace(calls.get() + 1));
+    let mut seed = [0u8; 256];
+    seed[..4].copy_from_slice(&call.to_le_bytes());
+    seed[4..8].copy_from_slice(&bits.to_le_bytes());
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(&seed);
+    let proto_key = RSAProtoKey {
+        bits,
+        use_fermat4: true,
+    };
+    generate_pem_keys_with_random(&proto_key, &mut random_struct).unwrap()
+}
+
+/* A RandomStruct seeded with 256 copies of seed, for tests that need one
+   ready to use; distinct seeds keep their outputs apart.
+*/
+#[cfg(test)]
+pub(crate) fn test_random_struct(seed: u8) -> RandomStruct {
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(&[seed; 256]);
+    random_struct
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::r_keygen::test_random_struct;
 
+    #[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
     #[test]
     pub fn test_prime_length() {
-        match generate_primes(&RSAProtoKey {
-            bits: 512,
-            use_fermat4: true,
-        }) {
+        match generate_primes(
+            &RSAProtoKey {
+                bits: 512,
+                use_fermat4: true,
+            },
+            &mut OsRng,
+        ) {
             Ok(primes) => {
                 assert!(primes[0].to_bytes_be().len() == 32);
                 assert!(primes[1].to_bytes_be().len() == 32);
                 assert!(primes[0] != primes[1]);
             }
-            Err(_) => assert!(false, "generate_primes returned an error."),
+            Err(_) => panic!("generate_primes returned an error."),
         }
     }
 
+    #[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
     #[test]
     pub fn test_prime_crypt() {
-        match generate_pem_keys(&RSAProtoKey {
-            bits: 512,
-            use_fermat4: true,
-        }) {
+        match generate_pem_keys_with_rng(
+            &RSAProtoKey {
+                bits: 512,
+                use_fermat4: true,
+            },
+            &mut OsRng,
+        ) {
             Ok((public_key, private_key)) => {
-                let data = (0u8..=255).collect::<Vec<u8>>();
+                let data = (0u8..=255).collect::<alloc::vec::Vec<u8>>();
 
                 let encrypted_data = private_key.encrypt(&data).unwrap();
                 let decrypted_data = public_key.decrypt(&encrypted_data).unwrap();
 
                 assert!(data == decrypted_data);
             }
-            Err(_) => assert!(false, "generate_primes returned an error."),
+            Err(_) => panic!("generate_primes returned an error."),
+        }
+    }
+
+    #[test]
+    pub fn test_generate_with_random_struct() {
+        let proto_key = RSAProtoKey {
+            bits: 512,
+            use_fermat4: true,
+        };
+        assert!(matches!(
+            generate_pem_keys_with_random(&proto_key, &mut RandomStruct::new()),
+            Err(RSAError::NeedRandom)
+        ));
+        let mut partly_seeded = RandomStruct::new();
+        partly_seeded.random_update(&[0x5au8; 100]);
+        assert!(matches!(
+            generate_pem_keys_with_random(&proto_key, &mut partly_seeded),
+            Err(RSAError::NeedRandom)
+        ));
+
+        let seeded = || test_random_struct(0x5a);
+        let (public_key, private_key) =
+            generate_pem_keys_with_random(&proto_key, &mut seeded()).unwrap();
+        let (_, again) = generate_pem_keys_with_random(&proto_key, &mut seeded()).unwrap();
+        assert_eq!(private_key.encode(), again.encode());
+        assert_eq!(public_key.bits(), 512);
+
+        let data = b"keys from a seeded random structure";
+        let encrypted = private_key.rsa_private_encrypt(data).unwrap();
+        assert_eq!(public_key.rsa_public_decrypt(&encrypted).unwrap(), data);
+
+        let data = [0x3cu8; 100];
+        let encrypted = public_key.encrypt(&data, &mut seeded()).unwrap();
+        assert_eq!(&private_key.decrypt_secret(&encrypted).unwrap()[..], data);
+        assert!(private_key.decrypt_secret(&encrypted[1..]).is_err());
+    }
+
+    #[cfg(all(
+        feature = "os-rng",
+        not(feature = "deterministic"),
+        not(feature = "rsa-crate")
+    ))]
+    #[test]
+    pub fn test_native_primes() {
+        for (bits, use_fermat4) in [(512, false), (513, true), (767, false)] {
+            let proto_key = RSAProtoKey { bits, use_fermat4 };
+            let (public_key, private_key) = generate_pem_keys(&proto_key).unwrap();
+            assert_eq!(public_key.modulus().bits(), bits as usize);
+
+            let data = b"primes from the native generator";
+            let encrypted = private_key.rsa_private_encrypt(data).unwrap();
+            assert_eq!(public_key.rsa_public_decrypt(&encrypted).unwrap(), data);
+        }
+    }
+
+    #[test]
+    pub fn test_decode_checks_modulus_length() {
+        let (public_key, private_key) = test_key_pair(512);
+
+        for bits in [0u32, 16, 511, 2048] {
+            let mut encoded = public_key.encode();
+            encoded[..4].copy_from_slice(&bits.to_le_bytes());
+            assert_eq!(
+                RSAPublicKey::decode(&encoded).err(),
+                Some(RSAError::ModulusLen)
+            );
+            let mut encoded = private_key.encode();
+            encoded[..4].copy_from_slice(&bits.to_le_bytes());
+            assert_eq!(
+                RSAPrivateKey::decode(&encoded).err(),
+                Some(RSAError::ModulusLen)
+            );
+        }
+
+        /* Keys built from components are not checked, so the block
+           routines reject moduli too short for PKCS #1 themselves.
+        */
+        let tiny = RSAPublicKey::from_components(16, BigUint::from(0x8001u32), 3u32.into());
+        assert!(matches!(tiny.decrypt(&[1]), Err(RSAError::Len)));
+
+        let mut encoded = private_key.encode();
+        encoded[4 + 3 * 128..4 + 3 * 128 + 64].fill(0);
+        let zero_prime = RSAPrivateKey::decode(&encoded).unwrap();
+        assert!(matches!(
+            zero_prime.rsa_private_block(&[1]),
+            Err(RSAError::PrivateKey)
+        ));
+    }
+
+    #[test]
+    pub fn test_malformed_input_is_an_error() {
+        let (public_key, private_key) = test_key_pair(512);
+
+        let encoded = private_key.encode();
+        for len in 0..encoded.len() {
+            assert!(RSAPrivateKey::decode(&encoded[..len]).is_err());
+            assert!(RSAPublicKey::decode(&encoded[..len.min(259)]).is_err());
+        }
+
+        /* Block type 1 needs eight FF bytes and a separator before the
+           last byte.
+        */
+        let signed = |padding_len: usize, separator: usize| {
+            let mut block = [0u8; 64];
+            block[1] = 1;
+            block[2..2 + padding_len].fill(0xff);
+            block[separator + 1..].fill(0xaa);
+            private_key.rsa_private_block(&block).unwrap()
+        };
+        assert_eq!(
+            public_key.rsa_public_decrypt(&signed(8, 10)).unwrap(),
+            [0xaa; 53]
+        );
+        assert_eq!(
+            public_key.rsa_public_decrypt(&signed(7, 9)),
+            Err(RSAError::Data)
+        );
+        assert_eq!(
+            public_key.rsa_public_decrypt(&signed(61, 63)),
+            Err(RSAError::Data)
+        );
+        let mut block = [0u8; 64];
+        block[1] = 1;
+        block[2..20].fill(0xff);
+        block[11] = 0xfe;
+        let forged = private_key.rsa_private_block(&block).unwrap();
+        assert_eq!(public_key.rsa_public_decrypt(&forged), Err(RSAError::Data));
+        block[1] = 2;
+        block[11] = 0xff;
+        let forged = private_key.rsa_private_block(&block).unwrap();
+        assert_eq!(public_key.rsa_public_decrypt(&forged), Err(RSAError::Data));
+
+        /* Block type 2 needs eight nonzero padding bytes and a separator
+           before the last byte.
+        */
+        let encrypted = |block_type: u8, padding_len: usize, separator: usize| {
+            let mut block = [0x5cu8; 64];
+            block[0] = 0;
+            block[1] = block_type;
+            block[2..2 + padding_len].fill(0x33);
+            block[separator] = 0;
+            public_key.rsa_public_block(&block).unwrap()
+        };
+        assert_eq!(
+            private_key
+                .rsa_private_decrypt(&encrypted(2, 8, 10))
+                .unwrap(),
+            [0x5c; 53]
+        );
+        for (block_type, padding_len, separator) in [(2, 7, 9), (1, 8, 10), (2, 62, 63)] {
+            assert_eq!(
+                private_key.rsa_private_decrypt(&encrypted(block_type, padding_len, separator)),
+                Err(RSAError::Data)
+            );
+        }
+
+        for input in [&[][..], &[0u8; 65][..]] {
+            assert!(public_key.rsa_public_decrypt(input).is_err());
+            assert!(private_key.rsa_private_decrypt(input).is_err());
         }
     }
 }
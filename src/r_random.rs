@@ -1,14 +1,30 @@
+use crate::blob_layout::{BlobLayout, Field};
+use crate::wipe::{Redacted, Wipe, Wiped};
 use crate::RSAError;
+use alloc::vec::Vec;
 use md5::{Digest, Md5};
 
 const RANDOM_BYTES_NEEDED: usize = 256;
+const RANDOM_STRUCT_LEN: usize = 40;
+const RANDOM_STRUCT_FIELDS: [Field; 4] =
+    [Field::Word, Field::Bytes(16), Field::Word, Field::Bytes(16)];
 
-#[derive(Debug)]
 pub struct RandomStruct {
-    bytes_needed: usize,
-    state: [u8; 16],
-    output_available: usize,
-    output: [u8; 16],
+    pub(crate) bytes_needed: usize,
+    pub(crate) state: [u8; 16],
+    pub(crate) output_available: usize,
+    pub(crate) output: [u8; 16],
+}
+
+/* Shows how far seeding has got, never the state or buffered output. */
+impl core::fmt::Debug for RandomStruct {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("RandomStruct")
+            .field("bytes_needed", &self.bytes_needed)
+            .field("state", &Redacted)
+            .field("output", &Redacted)
+            .finish()
+    }
 }
 
 impl RandomStruct {
@@ -27,6 +43,7 @@ impl RandomStruct {
         self.output_available = 0;
     }
 
+    #[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
     pub fn random_update(&mut self, block: &[u8]) {
         let mut context = Md5::new();
         context.update(block);
@@ -47,22 +64,41 @@ impl RandomStruct {
         self.bytes_needed
     }
 
-    pub fn generate_bytes(&mut self, mut block_len: usize) -> Result<Vec<u8>, RSAError> {
+    pub fn generate_bytes(&mut self, block_len: usize) -> Result<Vec<u8>, RSAError> {
         if self.bytes_needed != 0 {
             return Err(RSAError::NeedRandom);
         }
 
-        let mut available: usize = self.output_available;
+        let mut block: Vec<u8> = Vec::new();
+        block
+            .try_reserve_exact(block_len)
+            .map_err(|_| RSAError::Len)?;
+        block.resize(block_len, 0);
+        self.fill_bytes(&mut block)?;
 
-        let mut block: Vec<u8> = Vec::with_capacity(block_len);
+        Ok(block)
+    }
 
-        while block_len > available {
-            block.extend_from_slice(&self.output[(self.output.len() - available)..]);
-            block_len -= available;
+    /* generate_bytes into the caller's buffer, without allocating. */
+    pub fn fill_bytes(&mut self, block: &mut [u8]) -> Result<(), RSAError> {
+        if self.bytes_needed != 0 {
+            return Err(RSAError::NeedRandom);
+        }
+
+        let mut available: usize = self.output_available.min(self.output.len());
+        let mut rest = block;
+
+        while rest.len() > available {
+            let (head, tail) = rest.split_at_mut(available);
+            let buffered = self.output.iter().skip(self.output.len() - available);
+            for (byte, output) in head.iter_mut().zip(buffered) {
+                *byte = *output;
+            }
+            rest = tail;
 
             /* generate new output */
             let mut context = Md5::new();
-            context.update(&self.state);
+            context.update(self.state);
             self.output = context.finalize().into();
             available = self.output.len();
 
@@ -78,13 +114,86 @@ impl RandomStruct {
             }
         }
 
-        let rest_block_start = self.output.len() - available;
-        block.extend_from_slice(&self.output[rest_block_start..(rest_block_start + block_len)]);
-        self.output_available = available - block_len;
+        let buffered = self.output.iter().skip(self.output.len() - available);
+        for (byte, output) in rest.iter_mut().zip(buffered) {
+            *byte = *output;
+        }
+        self.output_available = available - rest.len();
+
+        Ok(())
+    }
 
+    /* N bytes on the stack, for keys, IVs and nonces of a fixed size. */
+    pub fn generate_array<const N: usize>(&mut self) -> Result<[u8; N], RSAError> {
+        let mut block = [0u8; N];
+        self.fill_bytes(&mut block)?;
         Ok(block)
     }
 
+    /* A K-byte key followed by an I-byte IV, drawn in that order. The key
+       is the caller's to wipe.
+    */
+    pub fn generate_key_iv<const K: usize, const I: usize>(
+        &mut self,
+    ) -> Result<([u8; K], [u8; I]), RSAError> {
+        let key = self.generate_array()?;
+        let iv = self.generate_array()?;
+        Ok((key, iv))
+    }
+
+    /* State export in the R_RANDOM_STRUCT field order:
+         bytesNeeded (4, little-endian) || state (16)
+           || outputAvailable (4, little-endian) || output (16)
+    */
+    pub fn encode(&self) -> Vec<u8> {
+        let mut result = Vec::<u8>::with_capacity(RANDOM_STRUCT_LEN);
+        result.extend((self.bytes_needed as u32).to_le_bytes());
+        result.extend(self.state);
+        result.extend((self.output_available as u32).to_le_bytes());
+        result.extend(self.output);
+        result
+    }
+
+    #[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        if data.len() != RANDOM_STRUCT_LEN {
+            return Err(RSAError::Encoding);
+        }
+
+        let (bytes_needed, rest) = data.split_at(4);
+        let (state, rest) = rest.split_at(16);
+        let (output_available, output) = rest.split_at(4);
+        let word = |bytes: &[u8]| {
+            let mut word = [0u8; 4];
+            word.copy_from_slice(bytes);
+            u32::from_le_bytes(word) as usize
+        };
+        let mut random_struct = Self {
+            bytes_needed: word(bytes_needed),
+            state: [0u8; 16],
+            output_available: word(output_available),
+            output: [0u8; 16],
+        };
+        if random_struct.output_available > random_struct.output.len() {
+            return Err(RSAError::Encoding);
+        }
+        random_struct.state.copy_from_slice(state);
+        random_struct.output.copy_from_slice(output);
+        Ok(random_struct)
+    }
+
+    /* encode and decode for states in another layout; see blob_layout. */
+    pub fn encode_with(&self, layout: BlobLayout) -> Vec<u8> {
+        layout.lay_out(&Wiped(self.encode()), &RANDOM_STRUCT_FIELDS)
+    }
+
+    pub fn decode_with(data: &[u8], layout: BlobLayout) -> Result<Self, RSAError> {
+        if data.len() != RANDOM_STRUCT_LEN {
+            return Err(RSAError::Encoding);
+        }
+        Self::decode(&Wiped(layout.to_rsaref(data, &RANDOM_STRUCT_FIELDS)?))
+    }
+
     pub fn random_final(&mut self) {
         self.bytes_needed = 0;
         self.state.fill(0);
@@ -93,6 +202,13 @@ impl RandomStruct {
     }
 }
 
+impl Drop for RandomStruct {
+    fn drop(&mut self) {
+        self.state.wipe();
+        self.output.wipe();
+    }
+}
+
 impl Default for RandomStruct {
     fn default() -> Self {
         Self::new()
@@ -104,7 +220,7 @@ mod tests {
     use super::*;
     #[test]
     fn test_random_bytes1() {
-        use std::cmp::Ordering;
+        use core::cmp::Ordering;
 
         let mut random_struct = RandomStruct::new();
         let random_buf = (0..=255).collect::<Vec<u8>>();
@@ -133,14 +249,14 @@ mod tests {
                 assert_eq!(random_bytes.cmp(&correct_output.to_vec()), Ordering::Equal);
             }
             Err(_) => {
-                assert!(false, "generate_bytes returned an error");
+                panic!("generate_bytes returned an error");
             }
         }
     }
 
     #[test]
     fn test_random_bytes2() {
-        use std::cmp::Ordering;
+        use core::cmp::Ordering;
 
         let mut random_struct = RandomStruct::new();
         let random_buf = (0..=255).rev().collect::<Vec<u8>>();
@@ -169,8 +285,68 @@ mod tests {
                 assert_eq!(random_bytes.cmp(&correct_output.to_vec()), Ordering::Equal);
             }
             Err(_) => {
-                assert!(false, "generate_bytes returned an error");
+                panic!("generate_bytes returned an error");
             }
         }
     }
+
+    #[test]
+    fn test_state_export_round_trip() {
+        let mut random_struct = RandomStruct::new();
+        random_struct.random_update(&[0x17u8; 256]);
+        random_struct.generate_bytes(5).unwrap();
+
+        let encoded = random_struct.encode();
+        assert_eq!(encoded.len(), 40);
+        let mut restored = RandomStruct::decode(&encoded).unwrap();
+        assert_eq!(
+            restored.generate_bytes(40).unwrap(),
+            random_struct.generate_bytes(40).unwrap()
+        );
+
+        let mut bad = encoded.clone();
+        bad[20] = 17;
+        assert!(RandomStruct::decode(&bad).is_err());
+        assert!(RandomStruct::decode(&encoded[1..]).is_err());
+    }
+
+    #[test]
+    fn test_generate_bytes_length_is_checked() {
+        let mut random_struct = RandomStruct::new();
+        random_struct.random_update(&[0x17u8; 256]);
+        assert_eq!(random_struct.generate_bytes(usize::MAX), Err(RSAError::Len));
+        assert!(random_struct.generate_bytes(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fixed_size_output() {
+        let mut random_struct = RandomStruct::new();
+        assert_eq!(
+            random_struct.generate_array::<8>(),
+            Err(RSAError::NeedRandom)
+        );
+        random_struct.random_update(&[0x17u8; 256]);
+        let mut reference = RandomStruct::decode(&random_struct.encode()).unwrap();
+
+        let expected = reference.generate_bytes(3 + 24 + 8 + 40).unwrap();
+        assert_eq!(random_struct.generate_array::<3>().unwrap(), expected[..3]);
+        let (key, iv) = random_struct.generate_key_iv::<24, 8>().unwrap();
+        assert_eq!(key, expected[3..27]);
+        assert_eq!(iv, expected[27..35]);
+        let mut block = [0u8; 40];
+        random_struct.fill_bytes(&mut block).unwrap();
+        assert_eq!(block, expected[35..]);
+        assert_eq!(random_struct.generate_array::<0>().unwrap(), [0u8; 0]);
+    }
+
+    #[test]
+    fn test_debug_is_redacted() {
+        let mut random_struct = RandomStruct::new();
+        random_struct.random_update(&[0x17u8; 256]);
+        random_struct.state = [0xabu8; 16];
+        assert_eq!(
+            format!("{:?}", random_struct),
+            "RandomStruct { bytes_needed: 0, state: <redacted>, output: <redacted> }"
+        );
+    }
 }
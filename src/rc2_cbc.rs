@@ -0,0 +1,159 @@
+use crate::cbc::{cbc_update, BlockCipher};
+use crate::des_cbc::DES_BLOCK_LEN;
+use crate::RSAError;
+use alloc::vec::Vec;
+use rc2::cipher::generic_array::GenericArray;
+use rc2::cipher::{BlockDecrypt, BlockEncrypt};
+use rc2::Rc2;
+
+pub const MAX_RC2_KEY_LEN: usize = 128;
+pub const MAX_RC2_EFFECTIVE_BITS: usize = 1024;
+
+struct RC2Core(Rc2);
+
+impl BlockCipher<DES_BLOCK_LEN> for RC2Core {
+    fn encrypt_block(&self, block: &mut [u8; 8]) {
+        self.0.encrypt_block(GenericArray::from_mut_slice(block));
+    }
+
+    fn decrypt_block(&self, block: &mut [u8; 8]) {
+        self.0.decrypt_block(GenericArray::from_mut_slice(block));
+    }
+}
+
+/* RC2 in CBC mode. The effective key bits are independent of the key
+   length, e.g. 40-bit export-grade RC2 still takes a 5-byte or longer key.
+*/
+pub struct RC2CBCContext {
+    core: RC2Core,
+    iv: [u8; 8],
+    original_iv: [u8; 8],
+    encrypt: bool,
+}
+
+impl RC2CBCContext {
+    pub fn new(
+        key: &[u8],
+        effective_bits: usize,
+        iv: &[u8; 8],
+        encrypt: bool,
+    ) -> Result<Self, RSAError> {
+        if key.is_empty() || key.len() > MAX_RC2_KEY_LEN {
+            return Err(RSAError::Key);
+        }
+        if effective_bits == 0 || effective_bits > MAX_RC2_EFFECTIVE_BITS {
+            return Err(RSAError::Key);
+        }
+
+        Ok(Self {
+            core: RC2Core(Rc2::new_with_eff_key_len(key, effective_bits)),
+            iv: *iv,
+            original_iv: *iv,
+            encrypt,
+        })
+    }
+
+    pub fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        cbc_update(&self.core, &mut self.iv, self.encrypt, input)
+    }
+
+    pub fn restart(&mut self) {
+        self.iv = self.original_iv;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rc2_vectors() {
+        // RFC 2268, section 5; a single block with a zero IV is plain ECB
+        /* (key, effective bits, plaintext, ciphertext) */
+        type Vector<'a> = (&'a [u8], usize, [u8; 8], [u8; 8]);
+        let vectors: [Vector; 4] = [
+            (
+                &[0x00; 8],
+                63,
+                [0x00; 8],
+                [0xeb, 0xb7, 0x73, 0xf9, 0x93, 0x27, 0x8e, 0xff],
+            ),
+            (
+                &[0xff; 8],
+                64,
+                [0xff; 8],
+                [0x27, 0x8b, 0x27, 0xe4, 0x2e, 0x2f, 0x0d, 0x49],
+            ),
+            (
+                &[0x88],
+                64,
+                [0x00; 8],
+                [0x61, 0xa8, 0xa2, 0x44, 0xad, 0xac, 0xcc, 0xf0],
+            ),
+            (
+                &[0x88, 0xbc, 0xa9, 0x0e, 0x90, 0x87, 0x5a],
+                64,
+                [0x00; 8],
+                [0x6c, 0xcf, 0x43, 0x08, 0x97, 0x4c, 0x26, 0x7f],
+            ),
+        ];
+
+        for (key, effective_bits, plaintext, ciphertext) in vectors {
+            let mut context = RC2CBCContext::new(key, effective_bits, &[0u8; 8], true).unwrap();
+            assert_eq!(context.update(&plaintext).unwrap(), ciphertext);
+
+            let mut context = RC2CBCContext::new(key, effective_bits, &[0u8; 8], false).unwrap();
+            assert_eq!(context.update(&ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_rc2_cbc_round_trip() {
+        let key = [0x11u8; 16];
+        let iv = [0x22u8; 8];
+        let data = (0u8..64).collect::<Vec<u8>>();
+
+        let encrypted = RC2CBCContext::new(&key, 40, &iv, true)
+            .unwrap()
+            .update(&data)
+            .unwrap();
+
+        let mut context = RC2CBCContext::new(&key, 40, &iv, false).unwrap();
+        let mut decrypted = context.update(&encrypted[..24]).unwrap();
+        decrypted.extend(context.update(&encrypted[24..]).unwrap());
+        assert_eq!(decrypted, data);
+
+        context.restart();
+        assert_eq!(context.update(&encrypted).unwrap(), data);
+
+        /* The effective key bits change the key schedule. */
+        let encrypted_128 = RC2CBCContext::new(&key, 128, &iv, true)
+            .unwrap()
+            .update(&data)
+            .unwrap();
+        assert_ne!(encrypted, encrypted_128);
+    }
+
+    #[test]
+    fn test_rc2_rejects_invalid_parameters() {
+        let iv = [0u8; 8];
+        assert!(matches!(
+            RC2CBCContext::new(&[], 64, &iv, true),
+            Err(RSAError::Key)
+        ));
+        assert!(matches!(
+            RC2CBCContext::new(&[0u8; 129], 64, &iv, true),
+            Err(RSAError::Key)
+        ));
+        assert!(matches!(
+            RC2CBCContext::new(&[0u8; 8], 0, &iv, true),
+            Err(RSAError::Key)
+        ));
+        assert!(matches!(
+            RC2CBCContext::new(&[0u8; 8], 64, &iv, true)
+                .unwrap()
+                .update(&[0u8; 7]),
+            Err(RSAError::Len)
+        ));
+    }
+}
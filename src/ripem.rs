@@ -0,0 +1,450 @@
+/* RIPEM key files. RIPEM kept keys as records of RFC 822 style fields,
+   separated by blank lines, with base64 bodies on continuation lines:
+
+     User: alice@example.com
+     PublicKeyInfo:
+      MFkwCgYEVQgBAQICAgADSwAwSAJBAL...
+     MD5OfPublicKey: 9C1B6D...
+
+   A public key file holds any number of such records. A private key file
+   holds an EncryptedPrivateKeyInfo field instead, the PKCS #8 key
+   encrypted under a password with pbeWithMD5AndDES-CBC. Fields other than
+   these, and lines outside any field such as armor boundaries, are
+   ignored.
+
+   Public keys are written with the X.509 rsa algorithm identifier and its
+   key size parameter, as RIPEM wrote them; rsaEncryption is read as well.
+   Keys must fit RSAREF's limits and the key size policy.
+*/
+use crate::cms::{pad_content, unpad_content, OID_RSA_ENCRYPTION};
+use crate::der::{
+    bit_string, integer, read_bit_string, read_integer, read_tlv, tlv, TAG_INTEGER, TAG_NULL,
+    TAG_OBJECT_IDENTIFIER, TAG_OCTET_STRING, TAG_SEQUENCE,
+};
+use crate::des_cbc::{DESCBCContext, DES_BLOCK_LEN};
+use crate::pem::{from_hex, hex, parse_fields};
+use crate::pkcs12::encode_private_key_info;
+use crate::r_encode::{decode_pem_block, encode_pem_block};
+use crate::r_random::RandomStruct;
+use crate::rsa::{
+    check_modulus_bits, RSAPrivateKey, RSAPublicKey, MAX_RSA_MODULUS_LEN, MAX_RSA_PRIME_LEN,
+};
+use crate::wipe::{Wipe, Wiped};
+use crate::RSAError;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use md5::{Digest, Md5};
+use num_bigint_dig::BigUint;
+
+const OID_X509_RSA: &[u8] = &[0x55, 0x08, 0x01, 0x01];
+const OID_PBE_MD5_DES_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x03];
+
+const SALT_LEN: usize = 8;
+
+/* A public key record and the users it names. */
+#[derive(Debug)]
+pub struct RipemPublicKey {
+    users: Vec<String>,
+    public_key: RSAPublicKey,
+}
+
+impl RipemPublicKey {
+    pub fn users(&self) -> &[String] {
+        &self.users
+    }
+
+    pub fn public_key(&self) -> &RSAPublicKey {
+        &self.public_key
+    }
+
+    pub fn into_public_key(self) -> RSAPublicKey {
+        self.public_key
+    }
+}
+
+/* A decrypted private key record and the users it names. */
+#[derive(Debug)]
+pub struct RipemPrivateKey {
+    users: Vec<String>,
+    private_key: RSAPrivateKey,
+}
+
+impl RipemPrivateKey {
+    pub fn users(&self) -> &[String] {
+        &self.users
+    }
+
+    pub fn private_key(&self) -> &RSAPrivateKey {
+        &self.private_key
+    }
+
+    pub fn into_private_key(self) -> RSAPrivateKey {
+        self.private_key
+    }
+}
+
+/* A public key record, ending in a newline so records can be appended
+   to a file. Fails with Data if a user name is empty or holds control
+   characters.
+*/
+pub fn encode_ripem_public_key(
+    users: &[&str],
+    public_key: &RSAPublicKey,
+) -> Result<String, RSAError> {
+    let public_key_info = encode_public_key_info(public_key);
+    let mut record = record(users, "PublicKeyInfo", &public_key_info)?;
+    record.push_str("MD5OfPublicKey: ");
+    record.push_str(&hex(&Md5::digest(&public_key_info)));
+    record.push('\n');
+    Ok(record)
+}
+
+/* Every public key record in text, in order. A record whose
+   MD5OfPublicKey does not match its key fails with KeyEncoding.
+*/
+pub fn decode_ripem_public_keys(text: &str) -> Result<Vec<RipemPublicKey>, RSAError> {
+    let mut public_keys = Vec::new();
+    for fields in records(text) {
+        let Some(public_key_info) = field(&fields, "PublicKeyInfo") else {
+            continue;
+        };
+        let public_key_info = decode_pem_block(public_key_info)?;
+        if let Some(md5) = field(&fields, "MD5OfPublicKey") {
+            if from_hex(md5)? != Md5::digest(&public_key_info).as_slice() {
+                return Err(RSAError::KeyEncoding);
+            }
+        }
+        public_keys.push(RipemPublicKey {
+            users: users(&fields),
+            public_key: decode_public_key_info(&public_key_info)?,
+        });
+    }
+    Ok(public_keys)
+}
+
+/* A private key record, the key encrypted under password with a fresh
+   salt and iterations rounds of MD5. Fails with Data if iterations is
+   zero or a user name is unusable.
+*/
+pub fn encode_ripem_private_key(
+    users: &[&str],
+    private_key: &RSAPrivateKey,
+    password: &str,
+    iterations: u32,
+    random_struct: &mut RandomStruct,
+) -> Result<String, RSAError> {
+    if iterations == 0 {
+        return Err(RSAError::Data);
+    }
+    let salt = random_struct.generate_bytes(SALT_LEN)?;
+    let (key, iv) = pbe_key(password, &salt, iterations);
+    let padded = Wiped(pad_content(
+        &Wiped(encode_private_key_info(private_key)),
+        DES_BLOCK_LEN,
+    ));
+    let encrypted = DESCBCContext::des(&key, &iv, true).update(&padded)?;
+
+    let mut parameters = tlv(TAG_OCTET_STRING, &salt);
+    parameters.extend(integer(&iterations.to_be_bytes()));
+    let mut identifier = tlv(TAG_OBJECT_IDENTIFIER, OID_PBE_MD5_DES_CBC);
+    identifier.extend(tlv(TAG_SEQUENCE, &parameters));
+    let mut info = tlv(TAG_SEQUENCE, &identifier);
+    info.extend(tlv(TAG_OCTET_STRING, &encrypted));
+    record(users, "EncryptedPrivateKeyInfo", &tlv(TAG_SEQUENCE, &info))
+}
+
+/* The first private key record in text, decrypted with password. A
+   wrong password fails with Key, and text without a private key record
+   with Encoding.
+*/
+pub fn decode_ripem_private_key(text: &str, password: &str) -> Result<RipemPrivateKey, RSAError> {
+    let fields = records(text)
+        .into_iter()
+        .find(|fields| field(fields, "EncryptedPrivateKeyInfo").is_some())
+        .ok_or(RSAError::Encoding)?;
+    let info = decode_pem_block(field(&fields, "EncryptedPrivateKeyInfo").unwrap_or_default())?;
+    Ok(RipemPrivateKey {
+        users: users(&fields),
+        private_key: decrypt_private_key_info(&info, password)?,
+    })
+}
+
+fn record(users: &[&str], name: &str, body: &[u8]) -> Result<String, RSAError> {
+    let mut record = String::new();
+    for user in users {
+        if user.is_empty() || user.chars().any(char::is_control) {
+            return Err(RSAError::Data);
+        }
+        record.push_str("User: ");
+        record.push_str(user);
+        record.push('\n');
+    }
+    record.push_str(name);
+    record.push_str(":\n");
+    for line in encode_pem_block(body).lines() {
+        record.push(' ');
+        record.push_str(line);
+        record.push('\n');
+    }
+    Ok(record)
+}
+
+fn records(text: &str) -> Vec<Vec<(String, String)>> {
+    let mut lines = text
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .peekable();
+    let mut records = Vec::new();
+    while lines.peek().is_some() {
+        let fields = parse_fields(&mut lines);
+        if !fields.is_empty() {
+            records.push(fields);
+        }
+    }
+    records
+}
+
+/* Field names are matched without regard to case, as in RFC 822. */
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field_name, _)| field_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn users(fields: &[(String, String)]) -> Vec<String> {
+    fields
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("User"))
+        .map(|(_, value)| value.to_string())
+        .collect()
+}
+
+fn encode_public_key_info(public_key: &RSAPublicKey) -> Vec<u8> {
+    let mut rsa_public_key = integer(&public_key.modulus().to_bytes_be());
+    rsa_public_key.extend(integer(&public_key.exponent().to_bytes_be()));
+
+    let mut identifier = tlv(TAG_OBJECT_IDENTIFIER, OID_X509_RSA);
+    identifier.extend(integer(&public_key.bits().to_be_bytes()));
+    let mut info = tlv(TAG_SEQUENCE, &identifier);
+    info.extend(bit_string(&tlv(TAG_SEQUENCE, &rsa_public_key)));
+    tlv(TAG_SEQUENCE, &info)
+}
+
+fn decode_public_key_info(input: &[u8]) -> Result<RSAPublicKey, RSAError> {
+    let (info, rest) = read_tlv(input, TAG_SEQUENCE)?;
+    let (identifier, info_rest) = read_tlv(info, TAG_SEQUENCE)?;
+    read_rsa_algorithm(identifier)?;
+    let (key_bits, info_rest) = read_bit_string(info_rest)?;
+    let (rsa_public_key, key_rest) = read_tlv(key_bits, TAG_SEQUENCE)?;
+    if !rest.is_empty() || !info_rest.is_empty() || !key_rest.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+
+    let (modulus, key_rest) = read_integer(rsa_public_key)?;
+    let (exponent, key_rest) = read_integer(key_rest)?;
+    if !key_rest.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+    let modulus = component(modulus, MAX_RSA_MODULUS_LEN)?;
+    let bits = modulus.bits() as u32;
+    check_modulus_bits(bits, &modulus)?;
+    Ok(RSAPublicKey::from_components(
+        bits,
+        modulus,
+        component(exponent, MAX_RSA_MODULUS_LEN)?,
+    ))
+}
+
+/* The X.509 rsa identifier carries the key size, which is not checked
+   against the key; rsaEncryption carries NULL or nothing.
+*/
+fn read_rsa_algorithm(identifier: &[u8]) -> Result<(), RSAError> {
+    let (oid, parameters) = read_tlv(identifier, TAG_OBJECT_IDENTIFIER)?;
+    let parameters_valid = if oid == OID_X509_RSA {
+        read_tlv(parameters, TAG_INTEGER)?.1.is_empty()
+    } else if oid == OID_RSA_ENCRYPTION {
+        parameters.is_empty() || parameters == [TAG_NULL, 0]
+    } else {
+        return Err(RSAError::KeyEncoding);
+    };
+    if !parameters_valid {
+        return Err(RSAError::Encoding);
+    }
+    Ok(())
+}
+
+/* PBKDF1 with MD5, as PKCS #5 v1.5 defines it for pbeWithMD5AndDES-CBC:
+   the DES key and the IV are the two halves of the last digest.
+*/
+fn pbe_key(password: &str, salt: &[u8], iterations: u32) -> (Wiped<[u8; 8]>, [u8; 8]) {
+    let mut digest = Md5::new()
+        .chain_update(password.as_bytes())
+        .chain_update(salt)
+        .finalize();
+    for _ in 1..iterations {
+        let mut next = Md5::digest(digest);
+        core::mem::swap(&mut digest, &mut next);
+        next.as_mut_slice().wipe();
+    }
+    let (mut key, mut iv) = ([0u8; 8], [0u8; 8]);
+    for (k, d) in key.iter_mut().chain(iv.iter_mut()).zip(digest.iter()) {
+        *k = *d;
+    }
+    digest.as_mut_slice().wipe();
+    (Wiped(key), iv)
+}
+
+fn decrypt_private_key_info(input: &[u8], password: &str) -> Result<RSAPrivateKey, RSAError> {
+    let (info, rest) = read_tlv(input, TAG_SEQUENCE)?;
+    let (identifier, info_rest) = read_tlv(info, TAG_SEQUENCE)?;
+    let (encrypted, info_rest) = read_tlv(info_rest, TAG_OCTET_STRING)?;
+    if !rest.is_empty() || !info_rest.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+    let (oid, parameters) = read_tlv(identifier, TAG_OBJECT_IDENTIFIER)?;
+    if oid != OID_PBE_MD5_DES_CBC {
+        return Err(RSAError::EncryptionAlgorithm);
+    }
+    let (parameters, rest) = read_tlv(parameters, TAG_SEQUENCE)?;
+    let (salt, parameters) = read_tlv(parameters, TAG_OCTET_STRING)?;
+    let (iterations, parameters) = read_integer(parameters)?;
+    if !rest.is_empty() || !parameters.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+    let iterations = match iterations {
+        [] | [0] => return Err(RSAError::Encoding),
+        digits if digits.len() <= 4 => digits
+            .iter()
+            .fold(0u32, |value, digit| (value << 8) | *digit as u32),
+        _ => return Err(RSAError::Encoding),
+    };
+
+    let (key, iv) = pbe_key(password, salt, iterations);
+    let padded = DESCBCContext::des(&key, &iv, false).update(encrypted)?;
+    let private_key_info = Wiped(unpad_content(padded, DES_BLOCK_LEN)?);
+    /* Garbage from a wrong password that happens to unpad is still a
+       wrong password.
+    */
+    decode_private_key_info(&private_key_info).map_err(|error| match error {
+        RSAError::Encoding => RSAError::Key,
+        error => error,
+    })
+}
+
+/* PrivateKeyInfo holding a PKCS #1 RSAPrivateKey; attributes are
+   ignored.
+*/
+fn decode_private_key_info(input: &[u8]) -> Result<RSAPrivateKey, RSAError> {
+    let (info, _) = read_tlv(input, TAG_SEQUENCE)?;
+    let (version, info) = read_integer(info)?;
+    let (identifier, info) = read_tlv(info, TAG_SEQUENCE)?;
+    read_rsa_algorithm(identifier)?;
+    let (key, _) = read_tlv(info, TAG_OCTET_STRING)?;
+    let (key, rest) = read_tlv(key, TAG_SEQUENCE)?;
+    let (key_version, mut key) = read_integer(key)?;
+    if version != [0] || key_version != [0] || !rest.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+
+    let mut components = Vec::with_capacity(8);
+    for max_len in [
+        MAX_RSA_MODULUS_LEN,
+        MAX_RSA_MODULUS_LEN,
+        MAX_RSA_MODULUS_LEN,
+        MAX_RSA_PRIME_LEN,
+        MAX_RSA_PRIME_LEN,
+        MAX_RSA_PRIME_LEN,
+        MAX_RSA_PRIME_LEN,
+        MAX_RSA_PRIME_LEN,
+    ] {
+        let (value, rest) = read_integer(key)?;
+        components.push(component(value, max_len)?);
+        key = rest;
+    }
+    if !key.is_empty() {
+        return Err(RSAError::Encoding);
+    }
+    let mut components = components.into_iter();
+    let mut next = || components.next().unwrap_or_default();
+    let modulus = next();
+    let bits = modulus.bits() as u32;
+    check_modulus_bits(bits, &modulus)?;
+    Ok(RSAPrivateKey::from_components(
+        bits,
+        modulus,
+        next(),
+        next(),
+        [next(), next()],
+        [next(), next()],
+        next(),
+    ))
+}
+
+/* An integer that fits its field in RSAREF's key layout. */
+fn component(value: &[u8], max_len: usize) -> Result<BigUint, RSAError> {
+    if value.len() > max_len {
+        return Err(RSAError::KeyEncoding);
+    }
+    Ok(BigUint::from_bytes_be(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    #[test]
+    fn test_ripem_key_files() {
+        let (public_key, private_key) = test_key_pair(512);
+        let record = encode_ripem_public_key(&["alice@example.com", "alice"], &public_key).unwrap();
+        /* The X.509 rsa identifier with 512 as its parameter. */
+        assert!(record.starts_with(
+            "User: alice@example.com\nUser: alice\nPublicKeyInfo:\n MFkwCgYEVQgBAQICAgADSwAwSAJBA"
+        ));
+
+        let (_, other) = test_key_pair(512);
+        let file = format!(
+            "-----BEGIN PUBLIC KEY-----\r\n{}\r\n\n{}",
+            record,
+            encode_ripem_public_key(&["bob"], &other.public_key()).unwrap()
+        );
+        let public_keys = decode_ripem_public_keys(&file).unwrap();
+        assert_eq!(public_keys.len(), 2);
+        assert_eq!(public_keys[0].users(), ["alice@example.com", "alice"]);
+        assert_eq!(public_keys[0].public_key().encode(), public_key.encode());
+        assert_eq!(public_keys[1].users(), ["bob"]);
+
+        let tampered = format!("{}{}\n", &record[..record.len() - 33], "0".repeat(32));
+        assert_eq!(
+            decode_ripem_public_keys(&tampered).err(),
+            Some(RSAError::KeyEncoding)
+        );
+        assert_eq!(
+            encode_ripem_public_key(&["alice\nPublicKeyInfo: x"], &public_key).err(),
+            Some(RSAError::Data)
+        );
+
+        let mut random_struct = test_random_struct(0x3d);
+        let record = encode_ripem_private_key(
+            &["alice"],
+            &private_key,
+            "passphrase",
+            100,
+            &mut random_struct,
+        )
+        .unwrap();
+        assert!(record.starts_with("User: alice\nEncryptedPrivateKeyInfo:\n "));
+        let decoded = decode_ripem_private_key(&record, "passphrase").unwrap();
+        assert_eq!(decoded.users(), ["alice"]);
+        assert_eq!(decoded.into_private_key().encode(), private_key.encode());
+        assert_eq!(
+            decode_ripem_private_key(&record, "wrong").err(),
+            Some(RSAError::Key)
+        );
+        assert_eq!(
+            decode_ripem_private_key(&file, "passphrase").err(),
+            Some(RSAError::Encoding)
+        );
+    }
+}
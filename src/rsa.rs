@@ -1,52 +1,120 @@
-use std::{
-    io::{BufReader, Read},
-    ops::{Add, Mul, Sub},
-};
-
+#[cfg(feature = "full")]
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(feature = "full")]
+use core::ops::{Mul, Rem};
+
+use crate::blob_layout::{BlobLayout, Field};
+use crate::constant_time::ct_eq;
+#[cfg(feature = "full")]
+use crate::csr::key_fingerprint;
+use crate::der::Located;
+use crate::digest_info::{encode_digest_info, DigestAlg};
+#[cfg(feature = "full")]
+use crate::key_audit::{KeyAudit, PrivateKeyAudit, PrivateKeyOperation, PrivateKeyUsage};
+use crate::key_policy::check_key_bits;
+#[cfg(feature = "full")]
+use crate::montgomery::{crt_combine_into, to_be_bytes, to_limbs, Modulus};
+use crate::parallel::map_chunks;
+#[cfg(feature = "full")]
+use crate::pem::hex;
+use crate::pkcs1::{pad_type1, unpad_type1};
+#[cfg(feature = "full")]
+use crate::pkcs1::{pad_type2_into, unpad_type2};
+#[cfg(feature = "full")]
 use crate::r_random::RandomStruct;
-use num_integer::Integer;
-use rsa::BigUint;
+use crate::wipe::Wiped;
+#[cfg(feature = "full")]
+use crate::wipe::{Redacted, SecretArray, SecretBuffer, SecretVec, Wipe};
+use crate::RSAError;
+use num_bigint_dig::BigUint;
+#[cfg(feature = "full")]
+use num_bigint_dig::ModInverse;
+#[cfg(feature = "full")]
+use once_cell::race::OnceBox;
+#[cfg(feature = "full")]
+use rand::{CryptoRng, RngCore};
+
+/* Decoded keys come from untrusted input, so their bit count must be one
+   this crate can produce, must cover the modulus and must meet the key
+   size policy.
+*/
+pub(crate) fn check_modulus_bits(bits: u32, modulus: &BigUint) -> Result<(), RSAError> {
+    let bits = bits as usize;
+    if !(MIN_RSA_MODULUS_BITS..=MAX_RSA_MODULUS_BITS).contains(&bits) || modulus.bits() > bits {
+        return Err(RSAError::ModulusLen);
+    }
+    check_key_bits(bits as u32)
+}
 
-trait RSASerialize {
+pub(crate) trait RSASerialize {
     fn to_be(&self, bytes: usize) -> Vec<u8>;
 }
 
 impl RSASerialize for BigUint {
     fn to_be(&self, bytes: usize) -> Vec<u8> {
-        let le = self.to_bytes_le();
-        let result = if le.len() > bytes {
-            let mut result = le[0..bytes].to_vec();
-            result.reverse();
-            result
-        } else {
-            let mut result = Vec::<u8>::with_capacity(bytes);
-            result.extend(le);
-            let bytes_needed = bytes - result.len();
-            if bytes_needed > 0 {
-                result.extend(vec![0u8; bytes_needed]);
-            }
-            result.reverse();
-            result
-        };
-        assert_eq!(result.len(), bytes);
+        let mut result = self.to_bytes_le();
+        result.resize(bytes, 0);
+        result.reverse();
         result
     }
 }
 
-use crate::RSAError;
+/* Takes buf.len() bytes off the front of reader. Callers check the total
+   length up front, so a short reader is only an encoding error.
+*/
+#[cfg_attr(all(feature = "no-panic", not(debug_assertions)), no_panic::no_panic)]
+pub(crate) fn read_exact(reader: &mut &[u8], buf: &mut [u8]) -> Result<(), RSAError> {
+    let Some((head, rest)) = reader.split_at_checked(buf.len()) else {
+        return Err(RSAError::Encoding);
+    };
+    buf.copy_from_slice(head);
+    *reader = rest;
+    Ok(())
+}
 
 pub const MIN_RSA_MODULUS_BITS: usize = 508;
 pub const MAX_RSA_MODULUS_BITS: usize = 1024;
-pub const MAX_RSA_MODULUS_LEN: usize = (MAX_RSA_MODULUS_BITS + 7) / 8;
-
-#[derive(Debug)]
+pub const MAX_RSA_MODULUS_LEN: usize = MAX_RSA_MODULUS_BITS.div_ceil(8);
+#[cfg(feature = "full")]
+pub const MAX_RSA_PRIME_BITS: usize = MAX_RSA_MODULUS_BITS.div_ceil(2);
+#[cfg(feature = "full")]
+pub const MAX_RSA_PRIME_LEN: usize = MAX_RSA_PRIME_BITS.div_ceil(8);
+
+/* The fields of the raw key blobs, in the order encode writes them. */
+const PUBLIC_KEY_FIELDS: [Field; 3] = [
+    Field::Word,
+    Field::Bytes(MAX_RSA_MODULUS_LEN),
+    Field::Bytes(MAX_RSA_MODULUS_LEN),
+];
+#[cfg(feature = "full")]
+const PRIVATE_KEY_FIELDS: [Field; 9] = [
+    Field::Word,
+    Field::Bytes(MAX_RSA_MODULUS_LEN),
+    Field::Bytes(MAX_RSA_MODULUS_LEN),
+    Field::Bytes(MAX_RSA_MODULUS_LEN),
+    Field::Bytes(MAX_RSA_PRIME_LEN),
+    Field::Bytes(MAX_RSA_PRIME_LEN),
+    Field::Bytes(MAX_RSA_PRIME_LEN),
+    Field::Bytes(MAX_RSA_PRIME_LEN),
+    Field::Bytes(MAX_RSA_PRIME_LEN),
+];
+
+/* The plaintext each block of encrypt carries; padded, it fits the
+   smallest modulus.
+*/
+#[cfg(feature = "full")]
+pub(crate) const ENCRYPT_CHUNK_LEN: usize = 48;
+
+#[derive(Debug, Clone)]
 pub struct RSAPublicKey {
     bits: u32,
     modulus: BigUint,
     exponent: BigUint,
 }
 
-#[derive(Debug)]
+#[cfg(feature = "full")]
 pub struct RSAPrivateKey {
     bits: u32,
     modulus: BigUint,
@@ -55,8 +123,119 @@ pub struct RSAPrivateKey {
     prime: [BigUint; 2],
     prime_exponent: [BigUint; 2],
     coefficient: BigUint,
+    audit: KeyAudit,
+    crt: CrtCache,
+}
+
+/* Identifies the key by size and the SHA-256 fingerprint of its public
+   half, as key_fingerprint computes it; the secret fields are redacted.
+*/
+#[cfg(feature = "full")]
+impl core::fmt::Debug for RSAPrivateKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let fingerprint = key_fingerprint(&self.public_key());
+        f.debug_struct("RSAPrivateKey")
+            .field("bits", &self.bits)
+            .field("fingerprint", &format_args!("{}", hex(&fingerprint)))
+            .field("exponent", &Redacted)
+            .field("prime", &Redacted)
+            .field("prime_exponent", &Redacted)
+            .field("coefficient", &Redacted)
+            .field("audit", &self.audit)
+            .field("crt", &self.crt)
+            .finish()
+    }
+}
+
+/* A clone is a key of its own: it keeps the audit hook, but counts from
+   zero and builds its own CRT context. To share one key between threads,
+   counters and all, use a SharedPrivateKey.
+*/
+#[cfg(feature = "full")]
+impl Clone for RSAPrivateKey {
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits,
+            modulus: self.modulus.clone(),
+            public_exponent: self.public_exponent.clone(),
+            exponent: self.exponent.clone(),
+            prime: self.prime.clone(),
+            prime_exponent: self.prime_exponent.clone(),
+            coefficient: self.coefficient.clone(),
+            audit: self.audit.clone(),
+            crt: CrtCache::new(),
+        }
+    }
+}
+
+/* The conversions are decode and encode; a private key's encoding comes
+   as a SecretBuffer, so that it is wiped when dropped.
+*/
+impl TryFrom<&[u8]> for RSAPublicKey {
+    type Error = RSAError;
+
+    fn try_from(data: &[u8]) -> Result<Self, RSAError> {
+        Self::decode(data)
+    }
+}
+
+#[cfg(feature = "full")]
+impl TryFrom<&[u8]> for RSAPrivateKey {
+    type Error = RSAError;
+
+    fn try_from(data: &[u8]) -> Result<Self, RSAError> {
+        Self::decode(data)
+    }
+}
+
+#[cfg(feature = "full")]
+impl From<&RSAPrivateKey> for RSAPublicKey {
+    fn from(private_key: &RSAPrivateKey) -> Self {
+        private_key.public_key()
+    }
+}
+
+impl From<&RSAPublicKey> for Vec<u8> {
+    fn from(public_key: &RSAPublicKey) -> Self {
+        public_key.encode()
+    }
+}
+
+#[cfg(feature = "full")]
+impl From<&RSAPrivateKey> for SecretBuffer {
+    fn from(private_key: &RSAPrivateKey) -> Self {
+        SecretBuffer::from(private_key.encode())
+    }
 }
 
+/* Cheap handles to one key for worker threads. Clones of a
+   SharedPrivateKey share its counters, audit hook and CRT context.
+*/
+pub type SharedPublicKey = Arc<RSAPublicKey>;
+#[cfg(feature = "full")]
+pub type SharedPrivateKey = Arc<RSAPrivateKey>;
+
+/* Keys are handed between threads; this stops compiling if a field ever
+   makes them other than Send and Sync.
+*/
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RSAPublicKey>();
+    #[cfg(feature = "full")]
+    assert_send_sync::<RSAPrivateKey>();
+};
+
+#[cfg(all(feature = "zeroize", feature = "full"))]
+impl Drop for RSAPrivateKey {
+    fn drop(&mut self) {
+        self.exponent.wipe();
+        self.prime.wipe();
+        self.prime_exponent.wipe();
+        self.coefficient.wipe();
+    }
+}
+
+#[cfg(feature = "full")]
 pub struct RSAProtoKey {
     pub bits: u32,
     pub use_fermat4: bool,
@@ -71,35 +250,53 @@ impl RSAPublicKey {
         }
     }
 
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub fn exponent(&self) -> &BigUint {
+        &self.exponent
+    }
+
     pub fn encode(&self) -> Vec<u8> {
         let mut result = Vec::<u8>::with_capacity(260);
 
         result.extend(self.bits.to_le_bytes());
-        result.extend(self.modulus.to_be(1024 / 8));
-        result.extend(self.exponent.to_be(1024 / 8));
-
-        assert_eq!(result.len(), 260);
+        result.extend(self.modulus.to_be(MAX_RSA_MODULUS_LEN));
+        result.extend(self.exponent.to_be(MAX_RSA_MODULUS_LEN));
 
         result
     }
 
-    pub fn decode(data: &[u8]) -> Result<Self, String> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+    )]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
         if data.len() < 260 {
-            return Err("Input data is not large enough".to_string());
+            return Err(RSAError::InputLen {
+                expected: 260,
+                actual: data.len(),
+            });
         }
 
-        let mut reader = BufReader::new(data);
+        let mut reader = data;
 
         let mut bits_buf = [0u8; 4];
-        reader.read_exact(&mut bits_buf).unwrap();
+        read_exact(&mut reader, &mut bits_buf)?;
         let bits = u32::from_le_bytes(bits_buf);
 
-        let mut modulus_buf = [0u8; 1024 / 8];
-        reader.read_exact(&mut modulus_buf).unwrap();
+        let mut modulus_buf = [0u8; MAX_RSA_MODULUS_LEN];
+        read_exact(&mut reader, &mut modulus_buf)?;
         let modulus = BigUint::from_bytes_be(&modulus_buf);
+        check_modulus_bits(bits, &modulus).at(data, data, "bits")?;
 
-        let mut exponent_buf = [0u8; 1024 / 8];
-        reader.read_exact(&mut exponent_buf).unwrap();
+        let mut exponent_buf = [0u8; MAX_RSA_MODULUS_LEN];
+        read_exact(&mut reader, &mut exponent_buf)?;
         let exponent = BigUint::from_bytes_be(&exponent_buf);
 
         Ok(Self {
@@ -109,8 +306,22 @@ impl RSAPublicKey {
         })
     }
 
-    fn rsa_public_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let m = BigUint::from_bytes_be(input);
+    /* encode and decode for blobs in another layout; see blob_layout. */
+    pub fn encode_with(&self, layout: BlobLayout) -> Vec<u8> {
+        layout.lay_out(&self.encode(), &PUBLIC_KEY_FIELDS)
+    }
+
+    pub fn decode_with(data: &[u8], layout: BlobLayout) -> Result<Self, RSAError> {
+        Self::decode(&layout.to_rsaref(data, &PUBLIC_KEY_FIELDS)?)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(bits = self.bits, input_len = input.len()), err(level = "debug"))
+    )]
+    pub(crate) fn rsa_public_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        check_key_bits(self.bits)?;
+        let m = Wiped(BigUint::from_bytes_be(input));
         let n = &self.modulus;
         let e = &self.exponent;
 
@@ -121,65 +332,78 @@ impl RSAPublicKey {
         // Perform operation
         let c = m.modpow(e, n);
 
-        let output_len = ((self.bits + 7) / 8) as usize;
+        let output_len = self.bits.div_ceil(8) as usize;
         let output = c.to_be(output_len);
 
         Ok(output)
     }
 
-    fn rsa_public_encrypt(
+    #[cfg(feature = "full")]
+    pub(crate) fn rsa_public_encrypt(
         &self,
         input: &[u8],
         random_struct: &mut RandomStruct,
     ) -> Result<Vec<u8>, RSAError> {
-        let modulus_len = ((self.bits + 7) / 8) as usize;
-        if input.len() + 11 > modulus_len {
-            return Err(RSAError::Len);
-        }
+        let mut pkcs_block: SecretVec = Wiped(Vec::new());
+        pad_type2_into(
+            input,
+            self.bits.div_ceil(8) as usize,
+            random_struct,
+            &mut pkcs_block,
+        )?;
+        self.rsa_public_block(&pkcs_block)
+    }
 
-        let mut pkcs_block = [0u8; MAX_RSA_MODULUS_LEN];
-        /* block type 2 */
-        pkcs_block[1] = 2;
-
-        for e in pkcs_block[2..(modulus_len - input.len() - 1)].iter_mut() {
-            loop {
-                let random_byte = random_struct.generate_bytes(1)?[0];
-                if random_byte != 0 {
-                    *e = random_byte;
-                    break;
-                }
+    /* rsa_public_encrypt of each input in turn, each a whole message of
+       at most 11 bytes less than the modulus, with the modulus-length
+       ciphertexts appended to out after clearing it. One padding buffer
+       serves the whole batch. On an error out is left empty.
+    */
+    #[cfg(feature = "full")]
+    pub fn encrypt_many(
+        &self,
+        inputs: &[&[u8]],
+        random_struct: &mut RandomStruct,
+        out: &mut Vec<u8>,
+    ) -> Result<(), RSAError> {
+        out.clear();
+        let modulus_len = self.bits.div_ceil(8) as usize;
+        out.reserve(inputs.len().saturating_mul(modulus_len));
+        let mut pkcs_block: SecretVec = Wiped(Vec::with_capacity(modulus_len));
+        let mut result = Ok(());
+        for input in inputs {
+            result = pad_type2_into(input, modulus_len, random_struct, &mut pkcs_block)
+                .and_then(|()| self.rsa_public_block(&pkcs_block))
+                .map(|block| out.extend(block));
+            if result.is_err() {
+                out.clear();
+                break;
             }
         }
-
-        let mut i = modulus_len - input.len() - 1;
-
-        /* separator */
-        pkcs_block[i] = 0;
-        i += 1;
-
-        for (target, src) in pkcs_block[i..].iter_mut().zip(input) {
-            *target = *src;
-        }
-
-        self.rsa_public_block(&pkcs_block[..modulus_len])
+        result
     }
 
+    #[cfg(feature = "full")]
     pub fn encrypt(
         &self,
         input: &[u8],
         random_struct: &mut RandomStruct,
     ) -> Result<Vec<u8>, RSAError> {
         let mut result = Vec::<u8>::with_capacity(input.len());
-        for chunk in input.chunks(48) {
+        for chunk in input.chunks(ENCRYPT_CHUNK_LEN) {
             let encrypted_chunk = self.rsa_public_encrypt(chunk, random_struct)?;
             result.extend(&encrypted_chunk);
         }
         Ok(result)
     }
 
-    fn rsa_public_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let modulus_len = ((self.bits + 7) / 8) as usize;
-        if input.len() > modulus_len {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(bits = self.bits, input_len = input.len()), err(level = "debug"))
+    )]
+    pub(crate) fn rsa_public_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        let modulus_len = self.bits.div_ceil(8) as usize;
+        if input.len() > modulus_len || modulus_len < 11 {
             return Err(RSAError::Len);
         }
 
@@ -189,47 +413,160 @@ impl RSAPublicKey {
             return Err(RSAError::Len);
         }
 
-        /* Require block type 1. */
-        if pkcs_block[0] != 0 || pkcs_block[1] != 1 {
-            return Err(RSAError::Data);
-        }
+        unpad_type1(&pkcs_block)
+    }
 
-        let mut separator_start: usize = 0;
-        for (i, e) in pkcs_block[2..pkcs_block.len() - 1].iter().enumerate() {
-            separator_start = i + 2;
-            if *e != 0xFF {
-                break;
-            }
-        }
+    pub fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        map_chunks(input, 64, |chunk| self.rsa_public_decrypt(chunk))
+    }
 
-        /* separator */
-        if pkcs_block[separator_start] != 0 {
-            return Err(RSAError::Data);
+    /* Checks a PKCS #1 v1.5 signature on a digest computed by the caller.
+       The expected block is rebuilt from alg and digest and compared
+       whole, so a missing NULL parameter, a different length encoding,
+       short padding or trailing bytes all fail. The signature must be
+       exactly the modulus length. Any failure is Signature, except a
+       digest of the wrong length for alg, which is Len.
+    */
+    pub fn verify_prehashed(
+        &self,
+        alg: DigestAlg,
+        digest: &[u8],
+        signature: &[u8],
+    ) -> Result<(), RSAError> {
+        let modulus_len = self.bits.div_ceil(8) as usize;
+        let expected = pad_type1(&encode_digest_info(alg, digest)?, modulus_len)
+            .map_err(|_| RSAError::Signature)?;
+        if signature.len() != modulus_len {
+            return Err(RSAError::Signature);
         }
+        let block = self
+            .rsa_public_block(signature)
+            .map_err(|_| RSAError::Signature)?;
+        if !ct_eq(&block, &expected) {
+            return Err(RSAError::Signature);
+        }
+        Ok(())
+    }
+}
 
-        let i = separator_start + 1;
+/* The private-key side of RSA as the signing, opening and envelope code
+   uses it. RSAPrivateKey implements it in memory; an implementation backed
+   by an HSM, a PKCS #11 token or a remote service need only supply the raw
+   exponentiation, or may override the padded operations when the device
+   performs PKCS #1 itself, and the message formats work unchanged.
+*/
+#[cfg(feature = "full")]
+pub trait PrivateKeyOps {
+    fn bits(&self) -> u32;
 
-        let output_len = modulus_len - i;
+    fn public_key(&self) -> RSAPublicKey;
 
-        if output_len + 11 > modulus_len {
-            return Err(RSAError::Data);
-        }
+    /* input^d mod n, as a modulus-length big-endian block. */
+    fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError>;
 
-        let output = pkcs_block[i..].to_vec();
+    /* PKCS #1 v1.5 block type 1 signing of input, normally a DigestInfo. */
+    fn rsa_private_encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        pkcs1_sign(self.bits(), input, |block| self.rsa_private_block(block))
+    }
 
-        Ok(output)
+    /* PKCS #1 v1.5 block type 2 decryption. */
+    fn rsa_private_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        pkcs1_decrypt(self.bits(), input, |block| self.rsa_private_block(block))
     }
+}
 
-    pub fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let mut result = Vec::<u8>::with_capacity(input.len());
-        for chunk in input.chunks(64) {
-            let decrypted_chunk = self.rsa_public_decrypt(chunk)?;
-            result.extend(&decrypted_chunk);
-        }
-        Ok(result)
+/* A key behind an Arc, such as a SharedPrivateKey, works wherever the
+   key itself does.
+*/
+#[cfg(feature = "full")]
+impl<T: PrivateKeyOps + ?Sized> PrivateKeyOps for Arc<T> {
+    fn bits(&self) -> u32 {
+        (**self).bits()
+    }
+
+    fn public_key(&self) -> RSAPublicKey {
+        (**self).public_key()
+    }
+
+    fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        (**self).rsa_private_block(input)
+    }
+
+    fn rsa_private_encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        (**self).rsa_private_encrypt(input)
+    }
+
+    fn rsa_private_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        (**self).rsa_private_decrypt(input)
+    }
+}
+
+/* The padded operations over a private_block that stands for input^d mod
+   n, shared by the PrivateKeyOps defaults and the implementations that
+   override them.
+*/
+#[cfg(feature = "full")]
+pub(crate) fn pkcs1_sign(
+    bits: u32,
+    input: &[u8],
+    private_block: impl FnOnce(&[u8]) -> Result<Vec<u8>, RSAError>,
+) -> Result<Vec<u8>, RSAError> {
+    let pkcs_block = pad_type1(input, bits.div_ceil(8) as usize)?;
+    private_block(&pkcs_block)
+}
+
+#[cfg(feature = "full")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(bits = bits, input_len = input.len()), err(level = "debug"))
+)]
+pub(crate) fn pkcs1_decrypt(
+    bits: u32,
+    input: &[u8],
+    private_block: impl FnOnce(&[u8]) -> Result<Vec<u8>, RSAError>,
+) -> Result<Vec<u8>, RSAError> {
+    let modulus_len = bits.div_ceil(8) as usize;
+    if input.len() > modulus_len || modulus_len < 11 {
+        return Err(RSAError::Len);
     }
+
+    let pkcs_block = Wiped(private_block(input)?);
+
+    if pkcs_block.len() != modulus_len {
+        return Err(RSAError::Len);
+    }
+
+    unpad_type2(&pkcs_block)
 }
 
+#[cfg(feature = "full")]
+impl PrivateKeyOps for RSAPrivateKey {
+    fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    fn public_key(&self) -> RSAPublicKey {
+        RSAPrivateKey::public_key(self)
+    }
+
+    fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        RSAPrivateKey::rsa_private_block(self, input)
+    }
+
+    fn rsa_private_encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.audited(PrivateKeyOperation::Sign, input, || {
+            pkcs1_sign(self.bits, input, |block| self.private_block(block))
+        })
+    }
+
+    fn rsa_private_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.audited(PrivateKeyOperation::Decrypt, input, || {
+            pkcs1_decrypt(self.bits, input, |block| self.private_block(block))
+        })
+    }
+}
+
+#[cfg(feature = "full")]
 impl RSAPrivateKey {
     pub fn from_components(
         bits: u32,
@@ -248,72 +585,82 @@ impl RSAPrivateKey {
             prime,
             prime_exponent,
             coefficient,
+            audit: KeyAudit::default(),
+            crt: CrtCache::new(),
         }
     }
     pub fn encode(&self) -> Vec<u8> {
         let mut result = Vec::<u8>::with_capacity(708);
 
         result.extend(self.bits.to_le_bytes());
-        result.extend(self.modulus.to_be(1024 / 8));
-        result.extend(self.public_exponent.to_be(1024 / 8));
-        result.extend(self.exponent.to_be(1024 / 8));
-        result.extend(self.prime[0].to_be(512 / 8));
-        result.extend(self.prime[1].to_be(512 / 8));
-        result.extend(self.prime_exponent[0].to_be(512 / 8));
-        result.extend(self.prime_exponent[1].to_be(512 / 8));
-        result.extend(self.coefficient.to_be(512 / 8));
-
-        assert_eq!(result.len(), 708);
+        result.extend(self.modulus.to_be(MAX_RSA_MODULUS_LEN));
+        result.extend(self.public_exponent.to_be(MAX_RSA_MODULUS_LEN));
+        result.extend(self.exponent.to_be(MAX_RSA_MODULUS_LEN));
+        result.extend(self.prime[0].to_be(MAX_RSA_PRIME_LEN));
+        result.extend(self.prime[1].to_be(MAX_RSA_PRIME_LEN));
+        result.extend(self.prime_exponent[0].to_be(MAX_RSA_PRIME_LEN));
+        result.extend(self.prime_exponent[1].to_be(MAX_RSA_PRIME_LEN));
+        result.extend(self.coefficient.to_be(MAX_RSA_PRIME_LEN));
 
         result
     }
 
-    pub fn decode(data: &[u8]) -> Result<Self, String> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+    )]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
         if data.len() < 708 {
-            return Err("Input data is not large enough".to_string());
+            return Err(RSAError::InputLen {
+                expected: 708,
+                actual: data.len(),
+            });
         }
 
-        let mut reader = BufReader::new(data);
+        let mut reader = data;
 
         let mut bits_buf = [0u8; 4];
-        reader.read_exact(&mut bits_buf).unwrap();
+        read_exact(&mut reader, &mut bits_buf)?;
         let bits = u32::from_le_bytes(bits_buf);
 
-        let mut modulus_buf = [0u8; 1024 / 8];
-        reader.read_exact(&mut modulus_buf).unwrap();
+        let mut modulus_buf = [0u8; MAX_RSA_MODULUS_LEN];
+        read_exact(&mut reader, &mut modulus_buf)?;
         let modulus = BigUint::from_bytes_be(&modulus_buf);
+        check_modulus_bits(bits, &modulus).at(data, data, "bits")?;
 
-        let mut public_exponent_buf = [0u8; 1024 / 8];
-        reader.read_exact(&mut public_exponent_buf).unwrap();
+        let mut public_exponent_buf = [0u8; MAX_RSA_MODULUS_LEN];
+        read_exact(&mut reader, &mut public_exponent_buf)?;
         let public_exponent = BigUint::from_bytes_be(&public_exponent_buf);
 
-        let mut exponent_buf = [0u8; 1024 / 8];
-        reader.read_exact(&mut exponent_buf).unwrap();
-        let exponent = BigUint::from_bytes_be(&exponent_buf);
+        let mut exponent_buf: SecretArray<MAX_RSA_MODULUS_LEN> = Wiped([0u8; MAX_RSA_MODULUS_LEN]);
+        read_exact(&mut reader, exponent_buf.as_mut_slice())?;
+        let exponent = BigUint::from_bytes_be(exponent_buf.as_slice());
 
-        let mut prime0_buf = [0u8; 512 / 8];
-        reader.read_exact(&mut prime0_buf).unwrap();
-        let prime0 = BigUint::from_bytes_be(&prime0_buf);
+        let mut prime0_buf: SecretArray<MAX_RSA_PRIME_LEN> = Wiped([0u8; MAX_RSA_PRIME_LEN]);
+        read_exact(&mut reader, prime0_buf.as_mut_slice())?;
+        let prime0 = BigUint::from_bytes_be(prime0_buf.as_slice());
 
-        let mut prime1_buf = [0u8; 512 / 8];
-        reader.read_exact(&mut prime1_buf).unwrap();
-        let prime1 = BigUint::from_bytes_be(&prime1_buf);
+        let mut prime1_buf: SecretArray<MAX_RSA_PRIME_LEN> = Wiped([0u8; MAX_RSA_PRIME_LEN]);
+        read_exact(&mut reader, prime1_buf.as_mut_slice())?;
+        let prime1 = BigUint::from_bytes_be(prime1_buf.as_slice());
 
         let prime = [prime0, prime1];
 
-        let mut prime_exponent0_buf = [0u8; 512 / 8];
-        reader.read_exact(&mut prime_exponent0_buf).unwrap();
-        let prime_exponent0 = BigUint::from_bytes_be(&prime_exponent0_buf);
+        let mut prime_exponent0_buf: SecretArray<MAX_RSA_PRIME_LEN> =
+            Wiped([0u8; MAX_RSA_PRIME_LEN]);
+        read_exact(&mut reader, prime_exponent0_buf.as_mut_slice())?;
+        let prime_exponent0 = BigUint::from_bytes_be(prime_exponent0_buf.as_slice());
 
-        let mut prime_exponent1_buf = [0u8; 512 / 8];
-        reader.read_exact(&mut prime_exponent1_buf).unwrap();
-        let prime_exponent1 = BigUint::from_bytes_be(&prime_exponent1_buf);
+        let mut prime_exponent1_buf: SecretArray<MAX_RSA_PRIME_LEN> =
+            Wiped([0u8; MAX_RSA_PRIME_LEN]);
+        read_exact(&mut reader, prime_exponent1_buf.as_mut_slice())?;
+        let prime_exponent1 = BigUint::from_bytes_be(prime_exponent1_buf.as_slice());
 
         let prime_exponent = [prime_exponent0, prime_exponent1];
 
-        let mut coefficient_buf = [0u8; 512 / 8];
-        reader.read_exact(&mut coefficient_buf).unwrap();
-        let coefficient = BigUint::from_bytes_be(&coefficient_buf);
+        let mut coefficient_buf: SecretArray<MAX_RSA_PRIME_LEN> = Wiped([0u8; MAX_RSA_PRIME_LEN]);
+        read_exact(&mut reader, coefficient_buf.as_mut_slice())?;
+        let coefficient = BigUint::from_bytes_be(coefficient_buf.as_slice());
 
         Ok(Self {
             bits,
@@ -323,9 +670,20 @@ impl RSAPrivateKey {
             prime,
             prime_exponent,
             coefficient,
+            audit: KeyAudit::default(),
+            crt: CrtCache::new(),
         })
     }
 
+    pub fn encode_with(&self, layout: BlobLayout) -> Vec<u8> {
+        layout.lay_out(&Wiped(self.encode()), &PRIVATE_KEY_FIELDS)
+    }
+
+    pub fn decode_with(data: &[u8], layout: BlobLayout) -> Result<Self, RSAError> {
+        let data: SecretVec = Wiped(layout.to_rsaref(data, &PRIVATE_KEY_FIELDS)?);
+        Self::decode(&data)
+    }
+
     pub fn public_key(&self) -> RSAPublicKey {
         RSAPublicKey {
             bits: self.bits,
@@ -334,134 +692,397 @@ impl RSAPrivateKey {
         }
     }
 
-    pub fn rsa_private_encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let modulus_len = ((self.bits + 7) / 8) as usize;
-        if input.len() + 11 > modulus_len {
-            return Err(RSAError::Len);
-        }
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
 
-        let mut pkcs_block = [0u8; MAX_RSA_MODULUS_LEN];
-        /* block type 1 */
-        pkcs_block[1] = 1;
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
 
-        for e in pkcs_block
-            .iter_mut()
-            .take(modulus_len - input.len() - 1)
-            .skip(2)
-        {
-            *e = 0xFF;
-        }
+    pub fn public_exponent(&self) -> &BigUint {
+        &self.public_exponent
+    }
 
-        let mut i = modulus_len - input.len() - 1;
+    pub fn exponent(&self) -> &BigUint {
+        &self.exponent
+    }
 
-        /* separator */
-        pkcs_block[i] = 0;
-        i += 1;
+    pub fn prime(&self) -> &[BigUint; 2] {
+        &self.prime
+    }
 
-        for (target, src) in pkcs_block[i..].iter_mut().zip(input) {
-            *target = *src;
-        }
+    pub fn prime_exponent(&self) -> &[BigUint; 2] {
+        &self.prime_exponent
+    }
 
-        self.rsa_private_block(&pkcs_block[0..modulus_len])
+    pub fn coefficient(&self) -> &BigUint {
+        &self.coefficient
+    }
+
+    /* Calls hook after each private-key operation from now on; None
+       removes it. The counters run either way.
+    */
+    pub fn set_audit(&mut self, hook: Option<Arc<dyn PrivateKeyAudit>>) {
+        self.audit.set_hook(hook);
+    }
+
+    pub fn usage(&self) -> PrivateKeyUsage {
+        self.audit.usage()
+    }
+
+    pub(crate) fn audited(
+        &self,
+        operation: PrivateKeyOperation,
+        input: &[u8],
+        f: impl FnOnce() -> Result<Vec<u8>, RSAError>,
+    ) -> Result<Vec<u8>, RSAError> {
+        self.audit.audited(operation, self.bits, input.len(), f)
+    }
+
+    pub fn rsa_private_encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        PrivateKeyOps::rsa_private_encrypt(self, input)
     }
 
     pub fn encrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let mut result = Vec::<u8>::with_capacity(input.len());
-        for chunk in input.chunks(48) {
-            let encrypted_chunk = self.rsa_private_encrypt(chunk)?;
-            result.extend(&encrypted_chunk);
-        }
-        Ok(result)
+        map_chunks(input, ENCRYPT_CHUNK_LEN, |chunk| {
+            self.rsa_private_encrypt(chunk)
+        })
     }
 
     pub fn rsa_private_decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let modulus_len = ((self.bits + 7) / 8) as usize;
-        if input.len() > modulus_len {
-            return Err(RSAError::Len);
-        }
+        PrivateKeyOps::rsa_private_decrypt(self, input)
+    }
 
-        let pkcs_block = self.rsa_private_block(input)?;
+    pub fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        map_chunks(input, 64, |chunk| self.rsa_private_decrypt(chunk))
+    }
 
-        if pkcs_block.len() != modulus_len {
-            return Err(RSAError::Len);
+    /* decrypt into a buffer that is wiped when dropped. Blocks are
+       decrypted in turn and each wiped once copied out.
+    */
+    pub fn decrypt_secret(&self, input: &[u8]) -> Result<SecretBuffer, RSAError> {
+        let mut output = SecretBuffer::with_capacity(input.len());
+        for chunk in input.chunks(64) {
+            output.push_within_capacity(&Wiped(self.rsa_private_decrypt(chunk)?))?;
         }
+        Ok(output)
+    }
 
-        /* Require block type 2. */
-        if pkcs_block[0] != 0 || pkcs_block[1] != 2 {
-            return Err(RSAError::Data);
-        }
+    pub fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.audited(PrivateKeyOperation::Block, input, || {
+            self.private_block(input)
+        })
+    }
 
-        let mut separator_start: usize = 0;
-        for (i, e) in pkcs_block[2..pkcs_block.len() - 1].iter().enumerate() {
-            /* separator */
-            separator_start = i + 2;
-            if *e == 0 {
-                break;
-            }
-        }
+    /* rsa_private_block without the audit, for the operations that record
+       themselves.
+    */
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(bits = self.bits, input_len = input.len()), err(level = "debug"))
+    )]
+    pub(crate) fn private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        check_key_bits(self.bits)?;
+        self.crt
+            .get_or_try_init(|| CrtContext::new(self).map(Box::new))?
+            .block(self, input)
+    }
 
-        let i = separator_start + 1;
-        if i > modulus_len {
-            return Err(RSAError::Data);
+    /* rsa_private_decrypt of each input in turn, with the plaintexts
+       appended to out and their lengths to lens, both cleared first. out
+       is reserved up front so it never reallocates while it holds
+       plaintext. Each message is audited as a decryption. On an
+       error both are left empty, out wiped.
+    */
+    pub fn decrypt_many(
+        &self,
+        inputs: &[&[u8]],
+        out: &mut Vec<u8>,
+        lens: &mut Vec<usize>,
+    ) -> Result<(), RSAError> {
+        out.wipe();
+        out.clear();
+        lens.clear();
+        let result = self.decrypt_many_into(inputs, out, lens);
+        if result.is_err() {
+            out.wipe();
+            out.clear();
+            lens.clear();
         }
+        result
+    }
 
-        let output_len = modulus_len - i;
+    fn decrypt_many_into(
+        &self,
+        inputs: &[&[u8]],
+        out: &mut Vec<u8>,
+        lens: &mut Vec<usize>,
+    ) -> Result<(), RSAError> {
+        let modulus_len = self.bits.div_ceil(8) as usize;
+        out.reserve(inputs.len().saturating_mul(modulus_len));
+        lens.reserve(inputs.len());
+        for input in inputs {
+            let output = Wiped(self.audited(PrivateKeyOperation::Decrypt, input, || {
+                pkcs1_decrypt(self.bits, input, |block| self.private_block(block))
+            })?);
+            out.extend_from_slice(&output);
+            lens.push(output.len());
+        }
+        Ok(())
+    }
 
-        if output_len + 11 > modulus_len {
+    /* rsa_private_block on input * r^e for a fresh random r, with the
+       result multiplied by r^-1, so the exponentiation never sees the
+       caller's input.
+    */
+    #[cfg_attr(
+        not(all(feature = "os-rng", not(feature = "deterministic"))),
+        allow(dead_code)
+    )]
+    pub(crate) fn rsa_private_block_blinded<R: RngCore + CryptoRng>(
+        &self,
+        input: &[u8],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, RSAError> {
+        let n = &self.modulus;
+        let c = BigUint::from_bytes_be(input);
+        if c.cmp(n).is_ge() {
             return Err(RSAError::Data);
         }
 
-        let output = pkcs_block[i..].to_vec();
+        let mut random: SecretVec = Wiped(vec![0u8; self.bits.div_ceil(8) as usize + 8]);
+        let (r, r_inv) = loop {
+            rng.fill_bytes(&mut random);
+            let r = BigUint::from_bytes_be(&random).rem(n);
+            if let Some(r_inv) = (&r).mod_inverse(n).and_then(|r_inv| r_inv.to_biguint()) {
+                break (Wiped(r), Wiped(r_inv));
+            }
+        };
 
-        Ok(output)
+        let output_len = self.bits.div_ceil(8) as usize;
+        let blinded = c.mul(r.modpow(&self.public_exponent, n)).rem(n);
+        let block: SecretVec = Wiped(self.private_block(&blinded.to_be(output_len))?);
+        let m = Wiped(BigUint::from_bytes_be(&block).mul(&*r_inv).rem(n));
+        Ok(m.to_be(output_len))
     }
+}
 
-    pub fn decrypt(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
-        let mut result = Vec::<u8>::with_capacity(input.len());
-        for chunk in input.chunks(64) {
-            let decrypted_chunk = self.rsa_private_decrypt(chunk)?;
-            result.extend(&decrypted_chunk);
+/* What the private-key block needs from a key, in the form montgomery
+   works on. Each RSAPrivateKey builds it on first use and keeps it, so
+   later operations skip converting the CRT values and computing the
+   Montgomery constants for p and q. Wiped on drop.
+*/
+#[cfg(feature = "full")]
+struct CrtContext {
+    p: Modulus,
+    q: Modulus,
+    dp: Vec<u64>,
+    dq: Vec<u64>,
+    /* qInv reduced mod p */
+    qinv: Vec<u64>,
+    q_limbs: Vec<u64>,
+}
+
+#[cfg(feature = "full")]
+impl CrtContext {
+    fn new(key: &RSAPrivateKey) -> Result<Self, RSAError> {
+        let [p, q] = &key.prime;
+        /* A decoded key may carry zero primes, which would divide by zero. */
+        if p.bits() == 0 || q.bits() == 0 {
+            return Err(RSAError::PrivateKey);
         }
-        Ok(result)
+        let (p_modulus, q_modulus) = (Modulus::new(p), Modulus::new(q));
+        let [dp, dq] = &key.prime_exponent;
+        let mut qinv = to_limbs(&key.coefficient, p_modulus.len());
+        let context = Self {
+            dp: to_limbs(dp, p_modulus.len()),
+            dq: to_limbs(dq, q_modulus.len()),
+            qinv: p_modulus.reduce(&qinv),
+            q_limbs: to_limbs(q, q_modulus.len()),
+            p: p_modulus,
+            q: q_modulus,
+        };
+        qinv.wipe();
+        Ok(context)
     }
 
-    pub fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+    fn block(&self, key: &RSAPrivateKey, input: &[u8]) -> Result<Vec<u8>, RSAError> {
         let c = BigUint::from_bytes_be(input);
-        let n = &self.modulus;
-        let p = &self.prime[0];
-        let q = &self.prime[1];
-        let dp = &self.prime_exponent[0];
-        let dq = &self.prime_exponent[1];
-        let qinv = &self.coefficient;
-
-        if c.cmp(n).is_ge() {
+        if c.cmp(&key.modulus).is_ge() {
             return Err(RSAError::Data);
         }
 
-        /* Compute mP = cP^dP mod p  and  mQ = cQ^dQ mod q. */
-
-        let cp = c.mod_floor(p);
-        let cq = c.mod_floor(q);
-        let mp = cp.modpow(dp, p);
-        let mq = cq.modpow(dq, q);
+        /* Compute mP = cP^dP mod p  and  mQ = cQ^dQ mod q, in constant
+           time; see montgomery.
+        */
+        let c = to_limbs(&c, c.bits().div_ceil(64));
+        let cp = Wiped(self.p.reduce(&c));
+        let cq = Wiped(self.q.reduce(&c));
+        let mp = Wiped(self.p.pow(&cp, &self.dp));
+        let mq = Wiped(self.q.pow(&cq, &self.dq));
 
         /* Chinese Remainder Theorem:
         m = ((((mP - mQ) mod p) * qInv) mod p) * q + mQ.
         */
-        let mut t;
-        if mp.cmp(&mq).is_ge() {
-            t = mp.sub(&mq);
-        } else {
-            t = mq.clone().sub(&mp);
-            t = p.sub(t);
+        let mut m = Wiped(vec![0; self.p.len() + self.q.len()]);
+        crt_combine_into(&self.p, &self.qinv, &self.q_limbs, &mp, &mq, &mut m);
+
+        let output_len = key.bits.div_ceil(8) as usize;
+        let output = to_be_bytes(&m, output_len);
+        Ok(output)
+    }
+}
+
+#[cfg(feature = "full")]
+impl Drop for CrtContext {
+    fn drop(&mut self) {
+        for limbs in [
+            &mut self.dp,
+            &mut self.dq,
+            &mut self.qinv,
+            &mut self.q_limbs,
+        ] {
+            limbs.wipe();
         }
-        t = t.mul(qinv).mod_floor(p);
-        t = t.mul(q);
-        t = t.add(mq);
+    }
+}
 
-        let output_len = ((self.bits + 7) / 8) as usize;
-        let output = t.to_be(output_len);
+/* RSAPrivateKey's CrtContext. Threads racing to build it may each do so;
+   one result is kept and the others are dropped.
+*/
+#[cfg(feature = "full")]
+struct CrtCache(OnceBox<CrtContext>);
 
-        Ok(output)
+#[cfg(feature = "full")]
+impl CrtCache {
+    fn new() -> Self {
+        Self(OnceBox::new())
+    }
+
+    fn get_or_try_init(
+        &self,
+        f: impl FnOnce() -> Result<Box<CrtContext>, RSAError>,
+    ) -> Result<&CrtContext, RSAError> {
+        self.0.get_or_try_init(f)
+    }
+}
+
+/* Shows whether the context has been built, not the context. */
+#[cfg(feature = "full")]
+impl core::fmt::Debug for CrtCache {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_tuple("CrtCache")
+            .field(&self.0.get().is_some())
+            .finish()
+    }
+}
+
+/* Every key test generates keys, which needs full. */
+#[cfg(all(test, feature = "full"))]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    #[test]
+    fn test_private_key_debug_is_redacted() {
+        let (public_key, private_key) = test_key_pair(512);
+        assert!(format!("{:?}", public_key).starts_with("RSAPublicKey { bits: 512, modulus: "));
+
+        let debug = format!("{:?}", private_key);
+        assert!(debug.starts_with("RSAPrivateKey { bits: 512, fingerprint: "));
+        assert!(debug.contains("exponent: <redacted>, prime: <redacted>"));
+        for secret in [
+            private_key.exponent(),
+            &private_key.prime()[0],
+            private_key.coefficient(),
+        ] {
+            assert!(!debug.contains(&format!("{:?}", secret)));
+            assert!(!debug.contains(&secret.to_str_radix(16)));
+        }
+    }
+
+    #[test]
+    fn test_clone_and_share_keys() {
+        let (public_key, private_key) = test_key_pair(512);
+        let signature = private_key.rsa_private_encrypt(b"clone").unwrap();
+
+        let copy = private_key.clone();
+        assert_eq!(copy.encode(), private_key.encode());
+        assert_eq!(copy.rsa_private_encrypt(b"clone").unwrap(), signature);
+        assert_eq!(copy.usage().signatures, 1);
+        assert_eq!(private_key.usage().signatures, 1);
+        assert!(format!("{:?}", private_key.clone()).contains("CrtCache(false)"));
+        assert_eq!(public_key.clone().encode(), public_key.encode());
+
+        let shared: SharedPrivateKey = alloc::sync::Arc::new(private_key);
+        let workers = (0..4)
+            .map(|_| {
+                let key = shared.clone();
+                std::thread::spawn(move || key.rsa_private_encrypt(b"clone").unwrap())
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), signature);
+        }
+        assert_eq!(shared.usage().signatures, 5);
+        assert_eq!(PrivateKeyOps::bits(&shared), 512);
+    }
+
+    #[test]
+    fn test_key_conversions() {
+        let (public_key, private_key) = test_key_pair(512);
+        let public_encoding = Vec::from(&public_key);
+        assert_eq!(public_encoding, public_key.encode());
+        let private_encoding = SecretBuffer::from(&private_key);
+        assert_eq!(&private_encoding[..], &private_key.encode()[..]);
+
+        let decoded = RSAPublicKey::try_from(&public_encoding[..]).unwrap();
+        assert_eq!(decoded.encode(), public_encoding);
+        let decoded: RSAPrivateKey = private_encoding[..].try_into().unwrap();
+        assert_eq!(RSAPublicKey::from(&decoded).encode(), public_encoding);
+        assert!(RSAPrivateKey::try_from(&public_encoding[..]).is_err());
+    }
+
+    #[test]
+    fn test_batch_encrypt_decrypt() {
+        let (public_key, private_key) = test_key_pair(512);
+        let mut random_struct = test_random_struct(0x2e);
+
+        let messages: [&[u8]; 4] = [b"k", b"one key", &[0x11; 32], &[0x22; 53]];
+        let mut ciphertexts = Vec::new();
+        public_key
+            .encrypt_many(&messages, &mut random_struct, &mut ciphertexts)
+            .unwrap();
+        assert_eq!(ciphertexts.len(), 4 * 64);
+
+        let inputs: Vec<&[u8]> = ciphertexts.chunks(64).collect();
+        let (mut out, mut lens) = (vec![0xee; 3], vec![9]);
+        private_key
+            .decrypt_many(&inputs, &mut out, &mut lens)
+            .unwrap();
+        assert_eq!(lens, [1, 7, 32, 53]);
+        assert_eq!(out, messages.concat());
+        for (input, message) in inputs.iter().zip(messages) {
+            assert_eq!(private_key.rsa_private_decrypt(input).unwrap(), message);
+        }
+        assert_eq!(private_key.usage().decryptions, 8);
+
+        /* A key builds its CRT context on first use and keeps it. */
+        let decoded = RSAPrivateKey::decode(&private_key.encode()).unwrap();
+        assert!(format!("{:?}", decoded).contains("CrtCache(false)"));
+        assert_eq!(decoded.rsa_private_decrypt(inputs[1]).unwrap(), b"one key");
+        assert!(format!("{:?}", decoded).contains("CrtCache(true)"));
+
+        /* One bad message fails the batch and leaves nothing behind. */
+        let bad: [&[u8]; 2] = [inputs[0], &[1; 64]];
+        assert!(private_key.decrypt_many(&bad, &mut out, &mut lens).is_err());
+        assert!(out.is_empty() && lens.is_empty());
+        let too_long: [&[u8]; 2] = [b"fits", &[0; 54]];
+        assert_eq!(
+            public_key.encrypt_many(&too_long, &mut random_struct, &mut ciphertexts),
+            Err(RSAError::Len)
+        );
+        assert!(ciphertexts.is_empty());
     }
 }
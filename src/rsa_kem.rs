@@ -0,0 +1,116 @@
+/* RSA-KEM (ISO 18033-2, 10.2): the sender picks z uniformly below n,
+   sends z^e mod n and keeps KDF(z) as the shared key. The ciphertext
+   stands for a random number, not a padded message, so there is no
+   structure for the recipient to check and no padding oracle to expose.
+
+   The KDF is any function from the secret z and a key length to that
+   many bytes; kdf2 over a digest is the standard choice, e.g.
+   kdf2::<Sha256>.
+*/
+use crate::r_random::RandomStruct;
+use crate::rsa::{PrivateKeyOps, RSAPublicKey};
+use crate::wipe::{SecretBuffer, Wipe, Wiped};
+use crate::RSAError;
+use alloc::vec::Vec;
+use num_bigint_dig::BigUint;
+
+/* A ciphertext of the modulus length and a key_len-byte key. */
+pub fn rsa_kem_encapsulate(
+    public_key: &RSAPublicKey,
+    kdf: impl Fn(&[u8], usize) -> Result<Vec<u8>, RSAError>,
+    key_len: usize,
+    random_struct: &mut RandomStruct,
+) -> Result<(Vec<u8>, SecretBuffer), RSAError> {
+    let modulus_len = public_key.bits().div_ceil(8) as usize;
+    let n_bits = public_key.modulus().bits();
+    /* No z is below a zero modulus. */
+    if n_bits == 0 {
+        return Err(RSAError::PublicKey);
+    }
+    let top_mask = match n_bits % 8 {
+        0 => 0xff,
+        top_bits => (1u8 << top_bits) - 1,
+    };
+
+    /* Candidates are masked to the bit length of n and drawn again until
+       one falls below it, so z is uniform.
+    */
+    let z = loop {
+        let mut candidate = random_struct.generate_bytes(n_bits.div_ceil(8))?;
+        if let Some(first) = candidate.first_mut() {
+            *first &= top_mask;
+        }
+        let mut value = BigUint::from_bytes_be(&candidate);
+        let below = value < *public_key.modulus();
+        value.wipe();
+        if below {
+            let mut z = vec![0u8; modulus_len.saturating_sub(candidate.len())];
+            z.extend_from_slice(&candidate);
+            candidate.wipe();
+            break Wiped(z);
+        }
+        candidate.wipe();
+    };
+
+    let ciphertext = public_key.rsa_public_block(&z)?;
+    let key = kdf(&z, key_len)?;
+    Ok((ciphertext, SecretBuffer::from(key)))
+}
+
+/* The key_len-byte key for ciphertext, which must be exactly the modulus
+   length. kdf and key_len must be those the sender used; a ciphertext
+   for another key gives a different key, not an error.
+*/
+pub fn rsa_kem_decapsulate(
+    private_key: &dyn PrivateKeyOps,
+    ciphertext: &[u8],
+    kdf: impl Fn(&[u8], usize) -> Result<Vec<u8>, RSAError>,
+    key_len: usize,
+) -> Result<SecretBuffer, RSAError> {
+    if ciphertext.len() != private_key.bits().div_ceil(8) as usize {
+        return Err(RSAError::Len);
+    }
+    let z = Wiped(private_key.rsa_private_block(ciphertext)?);
+    Ok(SecretBuffer::from(kdf(&z, key_len)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padding::kdf2;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use sha2::Sha256;
+
+    #[test]
+    fn test_rsa_kem() {
+        let (public_key, private_key) = test_key_pair(512);
+        let mut random_struct = test_random_struct(0x4e);
+
+        let (ciphertext, key) =
+            rsa_kem_encapsulate(&public_key, kdf2::<Sha256>, 32, &mut random_struct).unwrap();
+        assert_eq!(ciphertext.len(), 64);
+        assert_eq!(key.len(), 32);
+        let decapsulated =
+            rsa_kem_decapsulate(&private_key, &ciphertext, kdf2::<Sha256>, 32).unwrap();
+        assert_eq!(&decapsulated[..], &key[..]);
+
+        let (again, other_key) =
+            rsa_kem_encapsulate(&public_key, kdf2::<Sha256>, 32, &mut random_struct).unwrap();
+        assert_ne!(again, ciphertext);
+        assert_ne!(&other_key[..], &key[..]);
+
+        let (_, other) = test_key_pair(512);
+        /* Another key yields another key, or Data if the ciphertext is
+           not below its modulus.
+        */
+        assert!(rsa_kem_decapsulate(&other, &ciphertext, kdf2::<Sha256>, 32)
+            .map_or(true, |wrong| wrong[..] != key[..]));
+        assert_eq!(
+            rsa_kem_decapsulate(&private_key, &ciphertext[1..], kdf2::<Sha256>, 32).err(),
+            Some(RSAError::Len)
+        );
+        assert!(
+            rsa_kem_encapsulate(&public_key, kdf2::<Sha256>, 32, &mut RandomStruct::new()).is_err()
+        );
+    }
+}
@@ -0,0 +1,316 @@
+/* The block framing of RSAPublicKey::encrypt and RSAPrivateKey::decrypt
+   over streams: plaintext goes in ENCRYPT_CHUNK_LEN-byte chunks, the last
+   possibly shorter, and each becomes one PKCS #1 block of the modulus
+   length. Data written through an RSAChunkWriter and read back through an
+   RSAChunkReader comes out as it went in, whatever sizes the writes and
+   reads had.
+*/
+use crate::r_enhanc::io_error;
+use crate::r_random::RandomStruct;
+use crate::rsa::{PrivateKeyOps, RSAPublicKey, ENCRYPT_CHUNK_LEN, MAX_RSA_MODULUS_LEN};
+use crate::wipe::{SecretVec, Wiped};
+use alloc::vec::Vec;
+use std::io::{self, Read, Write};
+
+/* Encrypts everything written to it into the inner writer, one block per
+   full chunk. finish must be called to write the final partial chunk;
+   dropping the writer without it leaves the output truncated.
+
+   A block the inner writer fails on is held back and written first by the
+   next write or by finish, so a write that returns an error has consumed
+   nothing and can be retried.
+*/
+pub struct RSAChunkWriter<'a, W: Write> {
+    inner: W,
+    public_key: &'a RSAPublicKey,
+    random_struct: &'a mut RandomStruct,
+    buffer: SecretVec,
+    block: Vec<u8>,
+}
+
+impl<'a, W: Write> RSAChunkWriter<'a, W> {
+    pub fn new(
+        inner: W,
+        public_key: &'a RSAPublicKey,
+        random_struct: &'a mut RandomStruct,
+    ) -> Self {
+        Self {
+            inner,
+            public_key,
+            random_struct,
+            buffer: Wiped(Vec::with_capacity(ENCRYPT_CHUNK_LEN)),
+            block: Vec::new(),
+        }
+    }
+
+    /* Encrypts the buffer unless a block is still held back, and writes
+       the block out. What the inner writer takes is dropped from the
+       block, so a retry resumes where it stopped.
+    */
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.block.is_empty() {
+            self.block = self
+                .public_key
+                .rsa_public_encrypt(&self.buffer, self.random_struct)
+                .map_err(io_error)?;
+            self.buffer.0.clear();
+        }
+        while !self.block.is_empty() {
+            match self.inner.write(&self.block) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.block.drain(..len);
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.block.is_empty() || !self.buffer.is_empty() {
+            self.write_chunk()?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for RSAChunkWriter<'_, W> {
+    /* Once part of buf is in the buffer it counts as written, and a
+       failure to write the chunk it completed is reported by the next
+       call instead.
+    */
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.block.is_empty() || self.buffer.len() == ENCRYPT_CHUNK_LEN {
+            self.write_chunk()?;
+        }
+        let mut rest = buf;
+        while !rest.is_empty() {
+            let take = rest.len().min(ENCRYPT_CHUNK_LEN - self.buffer.len());
+            let (head, tail) = rest.split_at(take);
+            self.buffer.0.extend_from_slice(head);
+            rest = tail;
+            if self.buffer.len() == ENCRYPT_CHUNK_LEN && self.write_chunk().is_err() {
+                break;
+            }
+        }
+        Ok(buf.len() - rest.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/* Decrypts a stream of blocks from the inner reader. A stream that ends
+   part way through a block gives an UnexpectedEof error, and a block that
+   does not decrypt an InvalidData error carrying the RSAError.
+*/
+pub struct RSAChunkReader<'a, R: Read> {
+    inner: R,
+    private_key: &'a dyn PrivateKeyOps,
+    output: SecretVec,
+    position: usize,
+}
+
+impl<'a, R: Read> RSAChunkReader<'a, R> {
+    pub fn new(inner: R, private_key: &'a dyn PrivateKeyOps) -> Self {
+        Self {
+            inner,
+            private_key,
+            output: Wiped(Vec::new()),
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /* Fills block from the inner reader, returning how much it got before
+       the end of the stream.
+    */
+    fn read_block(&mut self, block: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while let Some(rest) = block.get_mut(filled..).filter(|rest| !rest.is_empty()) {
+            match self.inner.read(rest) {
+                Ok(0) => break,
+                Ok(len) => filled += len,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(filled)
+    }
+}
+
+impl<R: Read> Read for RSAChunkReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            let mut block = [0u8; MAX_RSA_MODULUS_LEN];
+            let block_len = self.private_key.bits().div_ceil(8) as usize;
+            let block = block
+                .get_mut(..block_len)
+                .ok_or(io::ErrorKind::InvalidData)?;
+            match self.read_block(block)? {
+                0 => return Ok(0),
+                len if len < block_len => return Err(io::ErrorKind::UnexpectedEof.into()),
+                _ => {}
+            }
+            self.output = Wiped(
+                self.private_key
+                    .rsa_private_decrypt(block)
+                    .map_err(io_error)?,
+            );
+            self.position = 0;
+        }
+
+        let available = self.output.get(self.position..).unwrap_or_default();
+        let len = buf.len().min(available.len());
+        for (target, byte) in buf.iter_mut().zip(available) {
+            *target = *byte;
+        }
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use crate::RSAError;
+
+    /* Hands out at most step bytes per read, as a socket might. */
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.step).min(self.data.len());
+            let (head, rest) = self.data.split_at(len);
+            buf[..len].copy_from_slice(head);
+            self.data = rest;
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_chunk_stream_round_trip() {
+        let (public_key, private_key) = test_key_pair(512);
+        let mut random_struct = test_random_struct(0x3c);
+        let message: Vec<u8> = (0..=200u8).collect();
+
+        let mut writer = RSAChunkWriter::new(Vec::new(), &public_key, &mut random_struct);
+        for piece in message.chunks(7) {
+            writer.write_all(piece).unwrap();
+        }
+        let ciphertext = writer.finish().unwrap();
+        assert_eq!(ciphertext.len(), 5 * 64);
+        assert_eq!(private_key.decrypt(&ciphertext).unwrap(), message);
+
+        for step in [1, 5, 64, 1000] {
+            let mut reader = RSAChunkReader::new(
+                Trickle {
+                    data: &ciphertext,
+                    step,
+                },
+                &private_key,
+            );
+            let mut opened = Vec::new();
+            reader.read_to_end(&mut opened).unwrap();
+            assert_eq!(opened, message);
+        }
+
+        let empty = RSAChunkWriter::new(Vec::new(), &public_key, &mut random_struct)
+            .finish()
+            .unwrap();
+        assert!(empty.is_empty());
+        let mut opened = Vec::new();
+        RSAChunkReader::new(empty.as_slice(), &private_key)
+            .read_to_end(&mut opened)
+            .unwrap();
+        assert!(opened.is_empty());
+    }
+
+    /* Takes at most step bytes per write and fails the calls listed. */
+    struct Flaky<'a> {
+        output: Vec<u8>,
+        step: usize,
+        calls: usize,
+        failing: &'a [usize],
+    }
+
+    impl Write for Flaky<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.failing.contains(&(self.calls - 1)) {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            let len = buf.len().min(self.step);
+            self.output.extend(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_chunk_writer_inner_errors() {
+        let (public_key, private_key) = test_key_pair(512);
+        let mut random_struct = test_random_struct(0x3e);
+        let message: Vec<u8> = (0..=200u8).collect();
+        let inner = Flaky {
+            output: Vec::new(),
+            step: 10,
+            calls: 0,
+            failing: &[0, 1, 3],
+        };
+
+        /* The first chunk is taken even though its block is not written. */
+        let mut writer = RSAChunkWriter::new(inner, &public_key, &mut random_struct);
+        assert_eq!(writer.write(&message[..100]).unwrap(), 48);
+        assert_eq!(
+            writer.write(&message[48..]).unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+        /* The held block is resumed after ten bytes went out. */
+        assert!(writer.write_all(&message[48..]).is_err());
+        writer.write_all(&message[48..]).unwrap();
+        let ciphertext = writer.finish().unwrap().output;
+        assert_eq!(ciphertext.len(), 5 * 64);
+        assert_eq!(private_key.decrypt(&ciphertext).unwrap(), message);
+    }
+
+    #[test]
+    fn test_chunk_reader_errors() {
+        let (public_key, private_key) = test_key_pair(512);
+        let mut random_struct = test_random_struct(0x3d);
+        let ciphertext = public_key
+            .encrypt(&[0x11u8; 100], &mut random_struct)
+            .unwrap();
+
+        let mut opened = Vec::new();
+        let error = RSAChunkReader::new(&ciphertext[..100], &private_key)
+            .read_to_end(&mut opened)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(opened, [0x11u8; 48]);
+
+        let (_, other) = test_key_pair(512);
+        let error = RSAChunkReader::new(ciphertext.as_slice(), &other)
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<RSAError>())
+            .is_some());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::constant_time::ct_eq;
+use crate::r_random::RandomStruct;
+use crate::rsa::RSAPrivateKey;
+use crate::wipe::Redacted;
+use crate::wipe::{SecretBuffer, SecretVec, Wipe, Wiped};
+use crate::RSAError;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+const CHECKSUM_LEN: usize = 4;
+const SECRET_DIGEST_LEN: usize = 32;
+
+/* Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1. */
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/* Inverse of a nonzero element, as a^254. */
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent != 0 {
+        if exponent & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn share_checksum(threshold: u8, index: u8, value: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let digest = Sha256::new()
+        .chain_update([threshold, index])
+        .chain_update(value)
+        .finalize();
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    for (byte, digest_byte) in checksum.iter_mut().zip(digest) {
+        *byte = digest_byte;
+    }
+    checksum
+}
+
+/* One of n Shamir shares of a secret, any threshold of which rebuild it.
+   The encoding is
+     threshold || index || value || checksum
+   where checksum is the first four bytes of SHA-256 over the rest, so a
+   damaged share is caught before it is combined.
+*/
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretShare {
+    threshold: u8,
+    index: u8,
+    value: Vec<u8>,
+}
+
+impl Drop for SecretShare {
+    fn drop(&mut self) {
+        self.value.wipe();
+    }
+}
+
+impl core::fmt::Debug for SecretShare {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("SecretShare")
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .field("value", &Redacted)
+            .finish()
+    }
+}
+
+impl SecretShare {
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /* The nonzero x coordinate of this share, 1 through n. */
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.value.len() + 2 + CHECKSUM_LEN);
+        result.push(self.threshold);
+        result.push(self.index);
+        result.extend(&self.value);
+        result.extend(share_checksum(self.threshold, self.index, &self.value));
+        result
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        if data.len() < 2 + CHECKSUM_LEN {
+            return Err(RSAError::Encoding);
+        }
+        let (body, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+        let [threshold, index, value @ ..] = body else {
+            return Err(RSAError::Encoding);
+        };
+        let (threshold, index) = (*threshold, *index);
+        if threshold == 0
+            || index == 0
+            || !ct_eq(checksum, &share_checksum(threshold, index, value))
+        {
+            return Err(RSAError::Encoding);
+        }
+
+        Ok(Self {
+            threshold,
+            index,
+            value: value.to_vec(),
+        })
+    }
+}
+
+/* Splits secret into count shares, any threshold of which recover it. A
+   SHA-256 of the secret is shared along with it so that combining the
+   wrong or too few shares is detected.
+*/
+pub fn split_secret(
+    secret: &[u8],
+    threshold: u8,
+    count: u8,
+    random_struct: &mut RandomStruct,
+) -> Result<Vec<SecretShare>, RSAError> {
+    if threshold == 0 || threshold > count {
+        return Err(RSAError::Data);
+    }
+
+    let mut payload: SecretVec = Wiped(Vec::with_capacity(secret.len() + SECRET_DIGEST_LEN));
+    payload.extend(secret);
+    payload.extend(Sha256::digest(secret));
+
+    /* Row i holds the coefficient of x^(i+1) for every byte. */
+    let coefficients: SecretVec =
+        Wiped(random_struct.generate_bytes(payload.len() * (threshold as usize - 1))?);
+    let coefficients: Vec<&[u8]> = coefficients.chunks(payload.len()).collect();
+
+    Ok((1..=count)
+        .map(|index| {
+            let value = payload
+                .iter()
+                .enumerate()
+                .map(|(position, byte)| {
+                    /* Horner's rule, highest coefficient first. */
+                    coefficients.iter().rev().fold(0u8, |acc, row| {
+                        gf_mul(acc ^ row.get(position).copied().unwrap_or_default(), index)
+                    }) ^ byte
+                })
+                .collect();
+            SecretShare {
+                threshold,
+                index,
+                value,
+            }
+        })
+        .collect())
+}
+
+/* Rebuilds the secret from at least threshold distinct shares. */
+pub fn combine_shares(shares: &[SecretShare]) -> Result<SecretBuffer, RSAError> {
+    let first = shares.first().ok_or(RSAError::Data)?;
+    let shares = shares
+        .get(..first.threshold as usize)
+        .ok_or(RSAError::Data)?;
+    for (i, share) in shares.iter().enumerate() {
+        if share.threshold != first.threshold
+            || share.value.len() != first.value.len()
+            || shares
+                .iter()
+                .take(i)
+                .any(|other| other.index == share.index)
+        {
+            return Err(RSAError::Data);
+        }
+    }
+    if first.value.len() < SECRET_DIGEST_LEN {
+        return Err(RSAError::Data);
+    }
+
+    /* Lagrange interpolation at x = 0. In GF(2^8) subtraction is XOR. */
+    let weights: Vec<u8> = shares
+        .iter()
+        .map(|share| {
+            shares
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold(1u8, |weight, other| {
+                    gf_mul(
+                        weight,
+                        gf_mul(other.index, gf_inv(other.index ^ share.index)),
+                    )
+                })
+        })
+        .collect();
+
+    let mut payload: SecretVec = Wiped(
+        (0..first.value.len())
+            .map(|position| {
+                shares
+                    .iter()
+                    .zip(&weights)
+                    .fold(0u8, |acc, (share, weight)| {
+                        acc ^ gf_mul(
+                            share.value.get(position).copied().unwrap_or_default(),
+                            *weight,
+                        )
+                    })
+            })
+            .collect(),
+    );
+
+    let secret_len = payload.len() - SECRET_DIGEST_LEN;
+    let (secret, digest) = payload.split_at(secret_len);
+    if !ct_eq(&Sha256::digest(secret), digest) {
+        return Err(RSAError::Data);
+    }
+    payload.truncate(secret_len);
+    Ok(SecretBuffer::from(payload.into_vec()))
+}
+
+/* Shares of the RSAREF encoding of private_key, for backup or escrow. */
+pub fn split_private_key_shares(
+    private_key: &RSAPrivateKey,
+    threshold: u8,
+    count: u8,
+    random_struct: &mut RandomStruct,
+) -> Result<Vec<SecretShare>, RSAError> {
+    let encoded: SecretVec = Wiped(private_key.encode());
+    split_secret(&encoded, threshold, count, random_struct)
+}
+
+pub fn combine_private_key_shares(shares: &[SecretShare]) -> Result<RSAPrivateKey, RSAError> {
+    RSAPrivateKey::decode(&combine_shares(shares)?).map_err(|_| RSAError::KeyEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    #[test]
+    fn test_gf_arithmetic() {
+        /* FIPS-197 section 4.2 example. */
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_and_combine_secret() {
+        let secret = b"long-lived legacy key material";
+        let shares = split_secret(secret, 3, 5, &mut test_random_struct(0x5e)).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let chosen: Vec<SecretShare> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(&combine_shares(&chosen).unwrap()[..], secret);
+        }
+        assert!(matches!(combine_shares(&shares[..2]), Err(RSAError::Data)));
+        assert!(matches!(
+            combine_shares(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]),
+            Err(RSAError::Data)
+        ));
+
+        let mut other_random = test_random_struct(0x5e);
+        other_random.random_update(b"another seed");
+        let other = split_secret(secret, 3, 5, &mut other_random).unwrap();
+        assert!(matches!(
+            combine_shares(&[shares[0].clone(), shares[1].clone(), other[2].clone()]),
+            Err(RSAError::Data)
+        ));
+
+        assert!(split_secret(secret, 0, 5, &mut test_random_struct(0x5e)).is_err());
+        assert!(split_secret(secret, 6, 5, &mut test_random_struct(0x5e)).is_err());
+    }
+
+    #[test]
+    fn test_private_key_shares() {
+        let (_, private_key) = test_key_pair(512);
+
+        let shares =
+            split_private_key_shares(&private_key, 2, 3, &mut test_random_struct(0x5e)).unwrap();
+        let encoded: Vec<Vec<u8>> = shares.iter().map(SecretShare::encode).collect();
+
+        let decoded = [
+            SecretShare::decode(&encoded[2]).unwrap(),
+            SecretShare::decode(&encoded[0]).unwrap(),
+        ];
+        assert_eq!(decoded[0].index(), 3);
+        assert_eq!(decoded[0].threshold(), 2);
+        let restored = combine_private_key_shares(&decoded).unwrap();
+        assert_eq!(restored.encode(), private_key.encode());
+
+        let mut damaged = encoded[1].clone();
+        damaged[10] ^= 1;
+        assert!(matches!(
+            SecretShare::decode(&damaged),
+            Err(RSAError::Encoding)
+        ));
+    }
+}
@@ -0,0 +1,287 @@
+/* Heap storage for secrets that should stay out of swap and core dumps.
+   With the mlock feature a SecureBox occupies whole pages of its own,
+   locked with mlock (VirtualLock on Windows) and, on Linux, excluded from
+   core dumps. Without the feature it is an ordinary allocation. Either
+   way the contents are wiped on drop as described in wipe.
+
+   Locking can fail, e.g. past RLIMIT_MEMLOCK; the box is still usable and
+   is_locked reports it.
+
+   Envelope content keys are held in one while they are in use. The limbs
+   of an RSAPrivateKey live in BigUint allocations the crate does not
+   control, so a key to be kept in locked memory for a long time is held
+   as a LockedPrivateKey instead, which decodes it for each operation.
+*/
+use crate::rsa::{PrivateKeyOps, RSAPrivateKey, RSAPublicKey};
+use crate::wipe::Wipe;
+use crate::RSAError;
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+pub struct SecureBox {
+    ptr: NonNull<u8>,
+    len: usize,
+    layout: Layout,
+    locked: bool,
+}
+
+/* The allocation is owned and only reachable through the box. */
+unsafe impl Send for SecureBox {}
+unsafe impl Sync for SecureBox {}
+
+impl SecureBox {
+    /* len zero bytes. Fails with Len if no allocation that large can be
+       described.
+    */
+    pub fn new(len: usize) -> Result<Self, RSAError> {
+        let align = page_len();
+        let size = len
+            .max(1)
+            .checked_next_multiple_of(align)
+            .ok_or(RSAError::Len)?;
+        let layout = Layout::from_size_align(size, align).map_err(|_| RSAError::Len)?;
+        /* SAFETY: layout has a nonzero size. */
+        let ptr = NonNull::new(unsafe { alloc_zeroed(layout) })
+            .unwrap_or_else(|| handle_alloc_error(layout));
+        let locked = lock(ptr, size);
+        Ok(Self {
+            ptr,
+            len,
+            layout,
+            locked,
+        })
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self, RSAError> {
+        let mut secure_box = Self::new(data.len())?;
+        secure_box.copy_from_slice(data);
+        Ok(secure_box)
+    }
+
+    /* Moves data into the box, wiping the vector it came in. */
+    pub fn from_vec(mut data: Vec<u8>) -> Result<Self, RSAError> {
+        let secure_box = Self::from_slice(&data);
+        data.wipe();
+        secure_box
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+impl Deref for SecureBox {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        /* SAFETY: ptr is valid for layout.size() >= len initialized bytes. */
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for SecureBox {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        /* SAFETY: as for deref, and the box is borrowed mutably. */
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for SecureBox {
+    fn drop(&mut self) {
+        self.deref_mut().wipe();
+        if self.locked {
+            unlock(self.ptr, self.layout.size());
+        }
+        /* SAFETY: ptr came from alloc_zeroed with this layout. */
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+/* Never shows the contents. */
+impl core::fmt::Debug for SecureBox {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("SecureBox")
+            .field("len", &self.len)
+            .field("locked", &self.locked)
+            .finish()
+    }
+}
+
+/* Boxes are page-aligned and page-sized when locked, so that unlocking
+   one never unlocks a neighbour sharing its page.
+*/
+#[cfg(all(feature = "mlock", unix))]
+fn page_len() -> usize {
+    /* SAFETY: sysconf has no preconditions. */
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        len if len > 0 => len as usize,
+        _ => 4096,
+    }
+}
+
+#[cfg(all(feature = "mlock", windows))]
+fn page_len() -> usize {
+    4096
+}
+
+#[cfg(not(all(feature = "mlock", any(unix, windows))))]
+fn page_len() -> usize {
+    1
+}
+
+#[cfg(all(feature = "mlock", unix))]
+fn lock(ptr: NonNull<u8>, len: usize) -> bool {
+    let addr = ptr.as_ptr().cast::<libc::c_void>();
+    /* SAFETY: the range is one live allocation of whole pages. */
+    let locked = unsafe { libc::mlock(addr, len) } == 0;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    /* SAFETY: as above; a failure only leaves the pages dumpable. */
+    unsafe {
+        libc::madvise(addr, len, libc::MADV_DONTDUMP);
+    }
+    locked
+}
+
+#[cfg(all(feature = "mlock", unix))]
+fn unlock(ptr: NonNull<u8>, len: usize) {
+    /* SAFETY: the range was locked by lock and is still allocated. */
+    unsafe {
+        libc::munlock(ptr.as_ptr().cast::<libc::c_void>(), len);
+    }
+}
+
+#[cfg(all(feature = "mlock", windows))]
+fn lock(ptr: NonNull<u8>, len: usize) -> bool {
+    use windows_sys::Win32::System::Memory::VirtualLock;
+    /* SAFETY: the range is one live allocation of whole pages. */
+    unsafe { VirtualLock(ptr.as_ptr().cast(), len) != 0 }
+}
+
+#[cfg(all(feature = "mlock", windows))]
+fn unlock(ptr: NonNull<u8>, len: usize) {
+    use windows_sys::Win32::System::Memory::VirtualUnlock;
+    /* SAFETY: the range was locked by lock and is still allocated. */
+    unsafe {
+        VirtualUnlock(ptr.as_ptr().cast(), len);
+    }
+}
+
+#[cfg(not(all(feature = "mlock", any(unix, windows))))]
+fn lock(_ptr: NonNull<u8>, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(all(feature = "mlock", any(unix, windows))))]
+fn unlock(_ptr: NonNull<u8>, _len: usize) {}
+
+/* A private key held in its RSAREF encoding inside a SecureBox. Each
+   operation decodes a temporary RSAPrivateKey, which the zeroize feature
+   wipes when the operation ends.
+*/
+pub struct LockedPrivateKey {
+    public_key: RSAPublicKey,
+    encoded: SecureBox,
+}
+
+impl LockedPrivateKey {
+    pub fn new(private_key: &RSAPrivateKey) -> Result<Self, RSAError> {
+        Ok(Self {
+            public_key: private_key.public_key(),
+            encoded: SecureBox::from_vec(private_key.encode())?,
+        })
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.encoded.is_locked()
+    }
+
+    /* A copy of the key in ordinary memory. */
+    pub fn unlock(&self) -> Result<RSAPrivateKey, RSAError> {
+        RSAPrivateKey::decode(&self.encoded)
+    }
+}
+
+impl core::fmt::Debug for LockedPrivateKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("LockedPrivateKey")
+            .field("public_key", &self.public_key)
+            .field("encoded", &self.encoded)
+            .finish()
+    }
+}
+
+impl PrivateKeyOps for LockedPrivateKey {
+    fn bits(&self) -> u32 {
+        self.public_key.bits()
+    }
+
+    fn public_key(&self) -> RSAPublicKey {
+        RSAPublicKey::from_components(
+            self.public_key.bits(),
+            self.public_key.modulus().clone(),
+            self.public_key.exponent().clone(),
+        )
+    }
+
+    fn rsa_private_block(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        self.unlock()?.rsa_private_block(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::generate_pem_keys_with_random;
+    use crate::r_keygen::test_random_struct;
+    use crate::rsa::RSAProtoKey;
+
+    #[test]
+    fn test_secure_box() {
+        let mut secure_box = SecureBox::new(24).unwrap();
+        assert_eq!(&secure_box[..], [0u8; 24]);
+        secure_box.copy_from_slice(&[0x5a; 24]);
+        assert_eq!(&secure_box[..], [0x5a; 24]);
+        assert_eq!(
+            format!("{:?}", secure_box),
+            format!(
+                "SecureBox {{ len: 24, locked: {} }}",
+                secure_box.is_locked()
+            )
+        );
+        if !cfg!(feature = "mlock") {
+            assert!(!secure_box.is_locked());
+        }
+
+        assert!(SecureBox::new(0).unwrap().is_empty());
+        assert_eq!(&SecureBox::from_vec(vec![1, 2, 3]).unwrap()[..], [1, 2, 3]);
+        assert_eq!(SecureBox::new(usize::MAX).err(), Some(RSAError::Len));
+    }
+
+    #[test]
+    fn test_locked_private_key() {
+        let mut random_struct = test_random_struct(0x2b);
+        let proto_key = RSAProtoKey {
+            bits: 512,
+            use_fermat4: true,
+        };
+        let (public_key, private_key) =
+            generate_pem_keys_with_random(&proto_key, &mut random_struct).unwrap();
+
+        let locked = LockedPrivateKey::new(&private_key).unwrap();
+        assert_eq!(locked.bits(), 512);
+        assert_eq!(locked.unlock().unwrap().encode(), private_key.encode());
+        assert_eq!(locked.public_key().encode(), public_key.encode());
+
+        let signature = locked.rsa_private_encrypt(b"locked").unwrap();
+        assert_eq!(
+            signature,
+            private_key.rsa_private_encrypt(b"locked").unwrap()
+        );
+        let encrypted = public_key
+            .rsa_public_encrypt(b"locked", &mut random_struct)
+            .unwrap();
+        assert_eq!(locked.rsa_private_decrypt(&encrypted).unwrap(), b"locked");
+    }
+}
@@ -0,0 +1,247 @@
+/* Known-answer power-up tests, callable at run time rather than only from
+   cargo test, over the vectors in test_vectors.
+*/
+use crate::constant_time::ct_eq;
+use crate::des_cbc::DESCBCContext;
+use crate::digest_info::DigestAlg;
+use crate::r_enhanc::SealContext;
+use crate::r_keygen::generate_pem_keys_with_random;
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAProtoKey, RSAPublicKey};
+use crate::test_vectors::{
+    PKCS1Vector, DES_CBC_VECTORS, DIGEST_VECTORS, PKCS1_VECTORS, RANDOM_SEED, RANDOM_VECTORS,
+    RSA_KEY, SEAL_VECTORS,
+};
+use crate::RSAError;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestError {
+    test: &'static str,
+    error: Option<RSAError>,
+}
+
+impl SelfTestError {
+    /* Name of the test that failed, as listed in Report::tests. */
+    pub fn test(&self) -> &'static str {
+        self.test
+    }
+
+    /* The error the operation returned, or None if it returned the wrong
+       answer.
+    */
+    pub fn error(&self) -> Option<&RSAError> {
+        self.error.as_ref()
+    }
+}
+
+impl core::fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match &self.error {
+            Some(error) => write!(f, "self-test {} failed: {}", self.test, error),
+            None => write!(f, "self-test {} gave the wrong answer", self.test),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SelfTestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error
+            .as_ref()
+            .map(|error| error as &(dyn std::error::Error + 'static))
+    }
+}
+
+/* The tests that ran, in order. All of them passed. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    tests: Vec<&'static str>,
+}
+
+impl Report {
+    pub fn tests(&self) -> &[&'static str] {
+        &self.tests
+    }
+}
+
+fn check(test: &'static str, result: Result<bool, RSAError>) -> Result<(), SelfTestError> {
+    match result {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(SelfTestError { test, error: None }),
+        Err(error) => Err(SelfTestError {
+            test,
+            error: Some(error),
+        }),
+    }
+}
+
+fn seeded(seed: &[u8]) -> RandomStruct {
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(seed);
+    random_struct
+}
+
+fn pkcs1_vectors(block_type: u8) -> impl Iterator<Item = &'static PKCS1Vector> {
+    PKCS1_VECTORS
+        .iter()
+        .filter(move |vector| vector.block_type == block_type)
+}
+
+fn test_digest(algorithm: DigestAlg) -> Result<bool, RSAError> {
+    Ok(DIGEST_VECTORS
+        .iter()
+        .filter(|vector| vector.algorithm == algorithm)
+        .all(|vector| algorithm.digest(vector.input) == vector.digest))
+}
+
+fn test_prng() -> Result<bool, RSAError> {
+    for vector in RANDOM_VECTORS {
+        if seeded(vector.seed).generate_bytes(vector.output.len())? != vector.output {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn test_des_cbc() -> Result<bool, RSAError> {
+    for vector in DES_CBC_VECTORS {
+        let encrypted =
+            DESCBCContext::des(&vector.key, &vector.iv, true).update(vector.plaintext)?;
+        let decrypted =
+            DESCBCContext::des(&vector.key, &vector.iv, false).update(vector.ciphertext)?;
+        if encrypted != vector.ciphertext || decrypted != vector.plaintext {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn test_rsa_sign(private_key: &RSAPrivateKey) -> Result<bool, RSAError> {
+    for vector in pkcs1_vectors(1) {
+        if private_key.rsa_private_encrypt(vector.input)? != vector.output {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn test_rsa_verify(public_key: &RSAPublicKey) -> Result<bool, RSAError> {
+    for vector in pkcs1_vectors(1) {
+        let recovered = public_key.rsa_public_decrypt(vector.output)?;
+        if !ct_eq(&recovered, vector.input) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn test_rsa_encrypt(public_key: &RSAPublicKey) -> Result<bool, RSAError> {
+    for vector in pkcs1_vectors(2) {
+        let mut random_struct = seeded(vector.seed.unwrap_or_default());
+        if public_key.rsa_public_encrypt(vector.input, &mut random_struct)? != vector.output {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn test_rsa_decrypt(private_key: &RSAPrivateKey) -> Result<bool, RSAError> {
+    for vector in pkcs1_vectors(2) {
+        if private_key.rsa_private_decrypt(vector.output)? != vector.input {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn test_seal(public_key: &RSAPublicKey) -> Result<bool, RSAError> {
+    for vector in SEAL_VECTORS {
+        let mut random_struct = seeded(vector.seed);
+        let mut context = SealContext::new(vector.algorithm, &[public_key], &mut random_struct)?;
+        let mut ciphertext = context.update(vector.content)?;
+        if context.encrypted_keys() != [vector.encrypted_key] || context.iv() != vector.iv {
+            return Ok(false);
+        }
+        ciphertext.extend(context.finalize()?);
+        if ciphertext != vector.ciphertext {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/* A fresh key must have the requested length and its halves must agree. */
+fn test_keygen() -> Result<bool, RSAError> {
+    let proto_key = RSAProtoKey {
+        bits: 512,
+        use_fermat4: true,
+    };
+    let (public_key, private_key) =
+        generate_pem_keys_with_random(&proto_key, &mut seeded(&RANDOM_SEED))?;
+    let data = b"RSAREF self-test";
+    let signature = private_key.rsa_private_encrypt(data)?;
+    Ok(public_key.modulus().bits() == 512 && public_key.rsa_public_decrypt(&signature)? == data)
+}
+
+type SelfTest<'a> = (&'static str, &'a dyn Fn() -> Result<bool, RSAError>);
+
+/* Runs every known-answer test, stopping at the first failure. */
+pub fn run_all() -> Result<Report, SelfTestError> {
+    let private_key = RSA_KEY.private_key();
+    let public_key = private_key.public_key();
+    let tests: [SelfTest; 10] = [
+        ("md2", &|| test_digest(DigestAlg::Md2)),
+        ("md5", &|| test_digest(DigestAlg::Md5)),
+        ("prng", &test_prng),
+        ("des-cbc", &test_des_cbc),
+        ("rsa-sign", &|| test_rsa_sign(&private_key)),
+        ("rsa-verify", &|| test_rsa_verify(&public_key)),
+        ("rsa-decrypt", &|| test_rsa_decrypt(&private_key)),
+        ("rsa-encrypt", &|| test_rsa_encrypt(&public_key)),
+        ("seal", &|| test_seal(&public_key)),
+        ("keygen", &test_keygen),
+    ];
+
+    let mut report = Report { tests: Vec::new() };
+    for (test, run) in tests {
+        check(test, run())?;
+        report.tests.push(test);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_all() {
+        let report = run_all().unwrap();
+        assert_eq!(
+            report.tests(),
+            [
+                "md2",
+                "md5",
+                "prng",
+                "des-cbc",
+                "rsa-sign",
+                "rsa-verify",
+                "rsa-decrypt",
+                "rsa-encrypt",
+                "seal",
+                "keygen"
+            ]
+        );
+
+        assert_eq!(check("md5", Ok(true)), Ok(()));
+        let wrong = check("md5", Ok(false)).unwrap_err();
+        assert_eq!((wrong.test(), wrong.error()), ("md5", None));
+        let failed = check("rsa-sign", Err(RSAError::Len)).unwrap_err();
+        assert_eq!(failed.error(), Some(&RSAError::Len));
+        assert_eq!(
+            alloc::string::ToString::to_string(&failed),
+            "self-test rsa-sign failed: data length is out of range"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+/* Serialize and Deserialize as each type's canonical byte encoding, so
+   every serde format persists the same bytes the type's own encode and
+   decode use:
+     RSAPublicKey, RSAPrivateKey  RSAREF R_RSA_PUBLIC_KEY/R_RSA_PRIVATE_KEY
+     DHParams                     length-prefixed prime, generator and
+                                  subprime
+     RandomStruct                 R_RANDOM_STRUCT state export
+     EnvelopedData, SignedData    DER ContentInfo
+*/
+use crate::cms::{EnvelopedData, SignedData};
+use crate::r_dh::DHParams;
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAPublicKey};
+use alloc::vec::Vec;
+use core::fmt;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(value)
+    }
+
+    /* Formats without a byte string type, such as JSON, write a sequence
+       of integers.
+    */
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut value = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            value.push(byte);
+        }
+        Ok(value)
+    }
+}
+
+macro_rules! impl_serde_bytes {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(&self.encode())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = deserializer.deserialize_byte_buf(BytesVisitor)?;
+                <$type>::decode(&bytes).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_serde_bytes!(RSAPublicKey);
+impl_serde_bytes!(RSAPrivateKey);
+impl_serde_bytes!(DHParams);
+impl_serde_bytes!(RandomStruct);
+impl_serde_bytes!(EnvelopedData);
+impl_serde_bytes!(SignedData);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::EncryptionAlgorithmId;
+    use crate::cms::RecipientIdentifier;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+    use num_bigint_dig::BigUint;
+
+    #[test]
+    fn test_serde_uses_canonical_encodings() {
+        let (public_key, private_key) = test_key_pair(512);
+
+        let json = serde_json::to_string(&public_key).unwrap();
+        assert_eq!(json, serde_json::to_string(&public_key.encode()).unwrap());
+        let decoded: RSAPublicKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.encode(), public_key.encode());
+
+        let json = serde_json::to_string(&private_key).unwrap();
+        let decoded: RSAPrivateKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.encode(), private_key.encode());
+
+        let params = DHParams::from_components(BigUint::from(23u32), BigUint::from(5u32)).unwrap();
+        let json = serde_json::to_string(&params).unwrap();
+        let decoded: DHParams = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.encode(), params.encode());
+
+        let mut random_struct = test_random_struct(0x29);
+        let json = serde_json::to_string(&random_struct).unwrap();
+        let mut decoded: RandomStruct = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            decoded.generate_bytes(16).unwrap(),
+            random_struct.generate_bytes(16).unwrap()
+        );
+
+        let enveloped = EnvelopedData::seal(
+            b"persisted envelope",
+            &[(
+                RecipientIdentifier::SubjectKeyIdentifier(b"key".to_vec()),
+                &public_key,
+            )],
+            EncryptionAlgorithmId::DesEde3Cbc,
+            &mut random_struct,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&enveloped).unwrap();
+        let decoded: EnvelopedData = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            decoded.open(None, &private_key).unwrap(),
+            b"persisted envelope"
+        );
+
+        assert!(serde_json::from_str::<RSAPublicKey>("[1, 2, 3]").is_err());
+        assert!(serde_json::from_str::<RandomStruct>("\"state\"").is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use crate::constant_time::ct_eq;
+use crate::der::{
+    algorithm_identifier, read_algorithm_identifier, read_tlv, tlv, Located, TAG_OCTET_STRING,
+    TAG_SEQUENCE,
+};
+use crate::digest_registry::{
+    encode_digest_info_with, find_digest_algorithm_by_oid, DigestAlgorithm,
+};
+use crate::parallel;
+use crate::r_encode::{decode_pem_block, encode_pem_block};
+use crate::rsa::{PrivateKeyOps, RSAPublicKey};
+use crate::RSAError;
+use alloc::{string::String, sync::Arc, vec::Vec};
+#[cfg(feature = "std")]
+use std::fs::{self, File};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+const PEM_HEADER: &str = "-----BEGIN SIGNATURE-----";
+const PEM_FOOTER: &str = "-----END SIGNATURE-----";
+
+#[cfg(feature = "std")]
+const READ_CHUNK_LEN: usize = 65536;
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum SignatureFileError {
+    Io(io::Error),
+    Signature(RSAError),
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for SignatureFileError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            SignatureFileError::Io(error) => write!(f, "signature file I/O failed: {}", error),
+            SignatureFileError::Signature(error) => write!(f, "signature check failed: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SignatureFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SignatureFileError::Io(error) => Some(error),
+            SignatureFileError::Signature(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for SignatureFileError {
+    fn from(error: io::Error) -> Self {
+        SignatureFileError::Io(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<RSAError> for SignatureFileError {
+    fn from(error: RSAError) -> Self {
+        SignatureFileError::Signature(error)
+    }
+}
+
+#[cfg(feature = "std")]
+fn digest_reader<R: Read>(algorithm: &dyn DigestAlgorithm, mut reader: R) -> io::Result<Vec<u8>> {
+    let mut context = algorithm.new_context();
+    let mut chunk = vec![0u8; READ_CHUNK_LEN];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => return Ok(context.finalize()),
+            Ok(len) => context.update(chunk.get(..len).ok_or(io::ErrorKind::InvalidData)?),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/* A PKCS #1 v1.5 RSA signature kept apart from the data it signs, paired
+   with its digest algorithm:
+     DetachedSignature ::= SEQUENCE {
+       digestAlgorithm AlgorithmIdentifier,
+       signature OCTET STRING }
+   Files hold this DER encoding either raw or PEM-wrapped as SIGNATURE.
+*/
+#[derive(Debug, Clone)]
+pub struct DetachedSignature {
+    digest_algorithm: Arc<dyn DigestAlgorithm>,
+    signature: Vec<u8>,
+}
+
+impl DetachedSignature {
+    pub fn sign(
+        data: &[u8],
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Self, RSAError> {
+        let digest = digest_algorithm.digest(data);
+        Self::sign_digest(&digest, digest_algorithm, private_key)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn sign_reader<R: Read>(
+        reader: R,
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Self, SignatureFileError> {
+        let digest = digest_reader(digest_algorithm.as_ref(), reader)?;
+        Ok(Self::sign_digest(&digest, digest_algorithm, private_key)?)
+    }
+
+    fn sign_digest(
+        digest: &[u8],
+        digest_algorithm: Arc<dyn DigestAlgorithm>,
+        private_key: &dyn PrivateKeyOps,
+    ) -> Result<Self, RSAError> {
+        let digest_info = encode_digest_info_with(digest_algorithm.as_ref(), digest)?;
+        Ok(Self {
+            signature: private_key.rsa_private_encrypt(&digest_info)?,
+            digest_algorithm,
+        })
+    }
+
+    pub fn digest_algorithm(&self) -> &dyn DigestAlgorithm {
+        self.digest_algorithm.as_ref()
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    pub fn verify(&self, data: &[u8], public_key: &RSAPublicKey) -> Result<(), RSAError> {
+        self.verify_digest(&self.digest_algorithm.digest(data), public_key)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn verify_reader<R: Read>(
+        &self,
+        reader: R,
+        public_key: &RSAPublicKey,
+    ) -> Result<(), SignatureFileError> {
+        let digest = digest_reader(self.digest_algorithm.as_ref(), reader)?;
+        Ok(self.verify_digest(&digest, public_key)?)
+    }
+
+    fn verify_digest(&self, digest: &[u8], public_key: &RSAPublicKey) -> Result<(), RSAError> {
+        let digest_info = public_key
+            .rsa_public_decrypt(&self.signature)
+            .map_err(|_| RSAError::Signature)?;
+        let expected = encode_digest_info_with(self.digest_algorithm.as_ref(), digest)?;
+        if !ct_eq(&digest_info, &expected) {
+            return Err(RSAError::Signature);
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut content = algorithm_identifier(self.digest_algorithm.oid());
+        content.extend(tlv(TAG_OCTET_STRING, &self.signature));
+        tlv(TAG_SEQUENCE, &content)
+    }
+
+    pub fn encode_pem(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n",
+            PEM_HEADER,
+            encode_pem_block(&self.encode()),
+            PEM_FOOTER
+        )
+    }
+
+    /* The digest algorithm must be in the digest registry. */
+    #[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = data.len()), err(level = "debug"))
+)]
+    pub fn decode(data: &[u8]) -> Result<Self, RSAError> {
+        let (content, rest) = read_tlv(data, TAG_SEQUENCE).at(data, data, "SignatureFile")?;
+        if !rest.is_empty() {
+            return Err(RSAError::Encoding).at(data, rest, "trailing data");
+        }
+        let (oid, rest) =
+            read_algorithm_identifier(content).at(data, content, "digestAlgorithm")?;
+        let digest_algorithm = find_digest_algorithm_by_oid(oid)
+            .ok_or(RSAError::DigestAlgorithm)
+            .at(data, content, "digestAlgorithm")?;
+        let (signature, after) = read_tlv(rest, TAG_OCTET_STRING).at(data, rest, "signature")?;
+        if !after.is_empty() {
+            return Err(RSAError::Encoding).at(data, after, "trailing data");
+        }
+
+        Ok(Self {
+            digest_algorithm,
+            signature: signature.to_vec(),
+        })
+    }
+
+    pub fn decode_pem(encoded: &str) -> Result<Self, RSAError> {
+        let body = encoded
+            .trim()
+            .strip_prefix(PEM_HEADER)
+            .and_then(|body| body.strip_suffix(PEM_FOOTER))
+            .ok_or(RSAError::Encoding)?;
+        Self::decode(&decode_pem_block(body)?)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_file<P: AsRef<Path>>(&self, path: P, pem: bool) -> io::Result<()> {
+        if pem {
+            fs::write(path, self.encode_pem())
+        } else {
+            fs::write(path, self.encode())
+        }
+    }
+
+    /* Reads either form, telling PEM from DER by the leading header. */
+    #[cfg(feature = "std")]
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self, SignatureFileError> {
+        let data = fs::read(path)?;
+        let pem = core::str::from_utf8(&data)
+            .ok()
+            .filter(|text| text.trim_start().starts_with(PEM_HEADER));
+        Ok(match pem {
+            Some(text) => Self::decode_pem(text)?,
+            None => Self::decode(&data)?,
+        })
+    }
+}
+
+/* Checks each signature against its data and public key, with the checks
+   spread over threads under the parallel feature. Results are in input
+   order.
+*/
+pub fn verify_batch(
+    items: &[(&DetachedSignature, &[u8], &RSAPublicKey)],
+) -> Vec<Result<(), RSAError>> {
+    parallel::map(items, |(signature, data, public_key)| {
+        signature.verify(data, public_key)
+    })
+}
+
+/* Signs the file at path and writes the signature to sig_path. */
+#[cfg(feature = "std")]
+pub fn sign_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    sig_path: Q,
+    digest_algorithm: Arc<dyn DigestAlgorithm>,
+    private_key: &dyn PrivateKeyOps,
+    pem: bool,
+) -> Result<(), SignatureFileError> {
+    let signature =
+        DetachedSignature::sign_reader(File::open(path)?, digest_algorithm, private_key)?;
+    Ok(signature.write_file(sig_path, pem)?)
+}
+
+/* Checks the file at path against the signature file at sig_path, raw or
+   PEM. A mismatch is SignatureFileError::Signature(RSAError::Signature).
+*/
+#[cfg(feature = "std")]
+pub fn verify_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    sig_path: Q,
+    public_key: &RSAPublicKey,
+) -> Result<(), SignatureFileError> {
+    let signature = DetachedSignature::read_file(sig_path)?;
+    signature.verify_reader(File::open(path)?, public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest_info::DigestAlg;
+    use crate::r_keygen::test_key_pair;
+    use crate::rsa::RSAPrivateKey;
+
+    fn generate_keys() -> (RSAPublicKey, RSAPrivateKey) {
+        test_key_pair(512)
+    }
+
+    fn sha256() -> Arc<dyn DigestAlgorithm> {
+        find_digest_algorithm_by_oid(DigestAlg::Sha256.oid()).unwrap()
+    }
+
+    #[test]
+    fn test_detached_signature_round_trip() {
+        let (public_key, private_key) = generate_keys();
+        let data = b"Release tarball contents.";
+
+        let signature = DetachedSignature::sign(data, sha256(), &private_key).unwrap();
+        assert!(signature.verify(data, &public_key).is_ok());
+        assert!(matches!(
+            signature.verify(b"Tampered contents.", &public_key),
+            Err(RSAError::Signature)
+        ));
+
+        for decoded in [
+            DetachedSignature::decode(&signature.encode()).unwrap(),
+            DetachedSignature::decode_pem(&signature.encode_pem()).unwrap(),
+        ] {
+            assert_eq!(decoded.signature(), signature.signature());
+            assert_eq!(decoded.digest_algorithm().oid(), DigestAlg::Sha256.oid());
+            assert!(decoded.verify(data, &public_key).is_ok());
+        }
+
+        let mut trailing = signature.encode();
+        trailing.push(0);
+        assert!(DetachedSignature::decode(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let (public_key, private_key) = generate_keys();
+        let (other_public_key, _) = generate_keys();
+        let messages = [b"first".as_slice(), b"second", b"third"];
+        let signatures = messages
+            .iter()
+            .map(|data| DetachedSignature::sign(data, sha256(), &private_key).unwrap())
+            .collect::<Vec<_>>();
+
+        let results = verify_batch(&[
+            (&signatures[0], messages[0], &public_key),
+            (&signatures[1], messages[2], &public_key),
+            (&signatures[2], messages[2], &other_public_key),
+            (&signatures[2], messages[2], &public_key),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                Ok(()),
+                Err(RSAError::Signature),
+                Err(RSAError::Signature),
+                Ok(())
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_sign_and_verify_file() {
+        let (public_key, private_key) = generate_keys();
+        let (other_public_key, _) = generate_keys();
+        let dir = std::env::temp_dir().join(format!("rsaref-sigfile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        fs::write(&path, (0..200000u32).map(|i| i as u8).collect::<Vec<u8>>()).unwrap();
+
+        for (name, pem) in [("data.sig", false), ("data.sig.pem", true)] {
+            let sig_path = dir.join(name);
+            sign_file(&path, &sig_path, sha256(), &private_key, pem).unwrap();
+            assert_eq!(
+                fs::read(&sig_path)
+                    .unwrap()
+                    .starts_with(PEM_HEADER.as_bytes()),
+                pem
+            );
+
+            verify_file(&path, &sig_path, &public_key).unwrap();
+            assert!(matches!(
+                verify_file(&path, &sig_path, &other_public_key),
+                Err(SignatureFileError::Signature(RSAError::Signature))
+            ));
+            assert!(matches!(
+                verify_file(dir.join("missing"), &sig_path, &public_key),
+                Err(SignatureFileError::Io(_))
+            ));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
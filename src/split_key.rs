@@ -0,0 +1,182 @@
+use alloc::vec::Vec;
+use core::ops::{Mul, Rem, Sub};
+
+use crate::pkcs1::pad_type1;
+use crate::r_random::RandomStruct;
+use crate::rsa::{RSAPrivateKey, RSAPublicKey, RSASerialize};
+use crate::wipe::Redacted;
+#[cfg(feature = "zeroize")]
+use crate::wipe::Wipe;
+use crate::RSAError;
+use num_bigint_dig::BigUint;
+
+/* One half of a private exponent split additively, d = d1 + d2 mod phi(n).
+   Each share raises the padded block to its own exponent; neither share
+   alone can produce a signature, and neither reveals the factors of n.
+*/
+#[derive(Clone)]
+pub struct RSAKeyShare {
+    bits: u32,
+    modulus: BigUint,
+    public_exponent: BigUint,
+    exponent: BigUint,
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for RSAKeyShare {
+    fn drop(&mut self) {
+        self.exponent.wipe();
+    }
+}
+
+impl core::fmt::Debug for RSAKeyShare {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("RSAKeyShare")
+            .field("bits", &self.bits)
+            .field("modulus", &self.modulus)
+            .field("public_exponent", &self.public_exponent)
+            .field("exponent", &Redacted)
+            .finish()
+    }
+}
+
+impl RSAKeyShare {
+    pub fn from_components(
+        bits: u32,
+        modulus: BigUint,
+        public_exponent: BigUint,
+        exponent: BigUint,
+    ) -> Self {
+        Self {
+            bits,
+            modulus,
+            public_exponent,
+            exponent,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub fn public_exponent(&self) -> &BigUint {
+        &self.public_exponent
+    }
+
+    pub fn exponent(&self) -> &BigUint {
+        &self.exponent
+    }
+
+    pub fn public_key(&self) -> RSAPublicKey {
+        RSAPublicKey::from_components(
+            self.bits,
+            self.modulus.clone(),
+            self.public_exponent.clone(),
+        )
+    }
+
+    /* This share's part of rsa_private_encrypt(input): the PKCS #1 block
+       type 1 encoding of input raised to the share exponent mod n.
+    */
+    pub fn partial_sign(&self, input: &[u8]) -> Result<Vec<u8>, RSAError> {
+        let modulus_len = self.bits.div_ceil(8) as usize;
+        let pkcs_block = pad_type1(input, modulus_len)?;
+        let m = BigUint::from_bytes_be(&pkcs_block);
+        Ok(m.modpow(&self.exponent, &self.modulus).to_be(modulus_len))
+    }
+}
+
+/* Splits the private exponent of private_key into two shares, one drawn
+   at random below phi(n) and the other its complement.
+*/
+pub fn split_private_key(
+    private_key: &RSAPrivateKey,
+    random_struct: &mut RandomStruct,
+) -> Result<(RSAKeyShare, RSAKeyShare), RSAError> {
+    let one = BigUint::from(1u32);
+    let [p, q] = private_key.prime();
+    let phi = p.clone().sub(&one).mul(q.clone().sub(&one));
+
+    let random = random_struct.generate_bytes(phi.bits().div_ceil(8) + 8)?;
+    let first = BigUint::from_bytes_be(&random).rem(&phi);
+    let second = (private_key.exponent().rem(&phi) + &phi - &first).rem(&phi);
+
+    let share = |exponent| {
+        RSAKeyShare::from_components(
+            private_key.bits(),
+            private_key.modulus().clone(),
+            private_key.public_exponent().clone(),
+            exponent,
+        )
+    };
+    Ok((share(first), share(second)))
+}
+
+/* Multiplies the two partial signatures into the full signature and checks
+   it against public_key, so a wrong or mismatched share gives Signature.
+*/
+pub fn combine_partial_signatures(
+    public_key: &RSAPublicKey,
+    first: &[u8],
+    second: &[u8],
+) -> Result<Vec<u8>, RSAError> {
+    let modulus_len = public_key.bits().div_ceil(8) as usize;
+    if first.len() != modulus_len || second.len() != modulus_len {
+        return Err(RSAError::Len);
+    }
+
+    let n = public_key.modulus();
+    let s = BigUint::from_bytes_be(first)
+        .mul(BigUint::from_bytes_be(second))
+        .rem(n);
+    let signature = s.to_be(modulus_len);
+
+    public_key
+        .rsa_public_decrypt(&signature)
+        .map_err(|_| RSAError::Signature)?;
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r_keygen::{test_key_pair, test_random_struct};
+
+    fn generate_keys() -> (RSAPublicKey, RSAPrivateKey) {
+        test_key_pair(512)
+    }
+
+    #[test]
+    fn test_split_key_signature_matches_private_key() {
+        let (public_key, private_key) = generate_keys();
+        let mut random_struct = test_random_struct(0x5b);
+
+        let (online, offline) = split_private_key(&private_key, &mut random_struct).unwrap();
+        assert_ne!(online.exponent(), private_key.exponent());
+        assert_eq!(online.public_key().modulus(), public_key.modulus());
+
+        let input = b"digest info to be signed";
+        let first = online.partial_sign(input).unwrap();
+        let second = offline.partial_sign(input).unwrap();
+        assert_ne!(first, private_key.rsa_private_encrypt(input).unwrap());
+
+        let signature = combine_partial_signatures(&public_key, &first, &second).unwrap();
+        assert_eq!(signature, private_key.rsa_private_encrypt(input).unwrap());
+        assert_eq!(public_key.rsa_public_decrypt(&signature).unwrap(), input);
+
+        let (_, other_offline) = split_private_key(&private_key, &mut random_struct).unwrap();
+        let mismatched = other_offline.partial_sign(input).unwrap();
+        assert!(matches!(
+            combine_partial_signatures(&public_key, &first, &mismatched),
+            Err(RSAError::Signature)
+        ));
+        assert!(matches!(
+            combine_partial_signatures(&public_key, &first, &second[1..]),
+            Err(RSAError::Len)
+        ));
+    }
+}
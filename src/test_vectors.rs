@@ -0,0 +1,367 @@
+/* Known-answer vectors for integrators checking their own wiring against
+   RSAREF behavior, the same data the selftest module runs. Sources:
+     - PRNG outputs: the reference C implementation, seeded with one
+       256-byte R_RandomUpdate call.
+     - MD2 and MD5: the RFC 1319 and RFC 1321 test suites, which the C
+       mddriver program reproduces.
+     - DES-CBC: FIPS 81, appendix C.
+     - RSA: a fixed 512-bit key. The block type 1 signature matches
+       OpenSSL's. The block type 2 ciphertext and the sealed envelope draw
+       their padding, key and IV from a seeded random structure the way
+       RSAPublicEncrypt and R_SealInit do, and OpenSSL decrypts both.
+   All byte strings are big-endian where that matters.
+*/
+use crate::algorithm::EncryptionAlgorithmId;
+use crate::digest_info::DigestAlg;
+use crate::rsa::RSAPrivateKey;
+use num_bigint_dig::BigUint;
+
+/* Only ever evaluated in constants, so a bad literal fails the build. */
+#[allow(clippy::indexing_slicing, clippy::panic)]
+const fn hex<const N: usize>(value: &str) -> [u8; N] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            _ => panic!("not a lowercase hex digit"),
+        }
+    }
+    let value = value.as_bytes();
+    assert!(value.len() == 2 * N);
+    let mut result = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        result[i] = nibble(value[2 * i]) << 4 | nibble(value[2 * i + 1]);
+        i += 1;
+    }
+    result
+}
+
+#[allow(clippy::indexing_slicing)]
+const fn ascending() -> [u8; 256] {
+    let mut result = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        result[i] = i as u8;
+        i += 1;
+    }
+    result
+}
+
+#[allow(clippy::indexing_slicing)]
+const fn descending() -> [u8; 256] {
+    let mut result = ascending();
+    let mut i = 0;
+    while i < 256 {
+        result[i] = 255 - result[i];
+        i += 1;
+    }
+    result
+}
+
+/* The seed every vector below that needs randomness starts from: one
+   R_RandomUpdate call with the bytes 0, 1, ..., 255.
+*/
+pub const RANDOM_SEED: [u8; 256] = ascending();
+
+/* R_GenerateBytes output straight after seeding with seed. */
+#[derive(Debug, Clone, Copy)]
+pub struct RandomVector {
+    pub seed: &'static [u8],
+    pub output: &'static [u8],
+}
+
+pub const RANDOM_VECTORS: &[RandomVector] = &[
+    RandomVector {
+        seed: &RANDOM_SEED,
+        output: &hex::<256>(
+            "e4afdfd62981009baaa67923a22b2180a0f3720797efe28821d31bc60643513a\
+            90996b6652c557f943c10f88498514969e0af09da386a229dc71eaf1892176f5\
+            e2bac2b4609622fad39f032546f42e05ca2456b26c7eaa5cedc5628692019d6d\
+            fe08a232151253190c3bd43fdb42e4233c3160b045082201c50fdb68f5d1edd4\
+            468658add36499930e9352e46dd590b9f2082b6b2b5aaabe004a9d7523330f57\
+            e92f4b9cbe716cd7b00bcfa68bf3e2cbc87063c858df72b26b211d003500aba0\
+            c4e75ee73eeee6684ca3c2a21c956d3cb21b688ef61b3ada8efa7ed6f8e447fd\
+            9fe44d93d4a8147ffcee9076b3a9b11fa8324bb12bb0ac7d0f78995825038da8",
+        ),
+    },
+    RandomVector {
+        seed: &descending(),
+        output: &hex::<256>(
+            "e8b917e8ed7db790b14107b4e475c3e8f2d6edc8212cd777abe26a6e996fa7ac\
+            7715cf631b2acf4d1821e5ee07bdc7b411ebe09efc73efb469d9b28153b6afed\
+            3e281f5524dc5ca7454db4db57468ec0482e2f60a9da93072514b3fd77d0867f\
+            fcae891cafb0b70d107a73b3a640839af04dccd19b3d15aeea0e9374912996d6\
+            0e663e09e983d30a87e7cff89f23ff6350c4206358bf8366c84306b35cc82793\
+            f83e23871cf23f4f2c791b14a097ee50f6558397ffe9c1177d190ab826591acc\
+            402991001734699ba234905cd21b3ea86d5301735e0949581447180ddc35444c\
+            e8c6f06f36e1e80591c8d91950fae4183083dc3854999c3c5dfa46af86c152fc",
+        ),
+    },
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct DigestVector {
+    pub algorithm: DigestAlg,
+    pub input: &'static [u8],
+    pub digest: &'static [u8],
+}
+
+pub const DIGEST_VECTORS: &[DigestVector] = &[
+    DigestVector {
+        algorithm: DigestAlg::Md2,
+        input: b"",
+        digest: &hex::<16>("8350e5a3e24c153df2275c9f80692773"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md2,
+        input: b"a",
+        digest: &hex::<16>("32ec01ec4a6dac72c0ab96fb34c0b5d1"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md2,
+        input: b"abc",
+        digest: &hex::<16>("da853b0d3f88d99b30283a69e6ded6bb"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md2,
+        input: b"message digest",
+        digest: &hex::<16>("ab4f496bfb2a530b219ff33031fe06b0"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md2,
+        input: b"abcdefghijklmnopqrstuvwxyz",
+        digest: &hex::<16>("4e8ddff3650292ab5a4108c3aa47940b"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md5,
+        input: b"",
+        digest: &hex::<16>("d41d8cd98f00b204e9800998ecf8427e"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md5,
+        input: b"a",
+        digest: &hex::<16>("0cc175b9c0f1b6a831c399e269772661"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md5,
+        input: b"abc",
+        digest: &hex::<16>("900150983cd24fb0d6963f7d28e17f72"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md5,
+        input: b"message digest",
+        digest: &hex::<16>("f96b697d7cb7938d525a2f31aaf161d0"),
+    },
+    DigestVector {
+        algorithm: DigestAlg::Md5,
+        input: b"abcdefghijklmnopqrstuvwxyz",
+        digest: &hex::<16>("c3fcd3d76192e4007dfb496cca67e13b"),
+    },
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct DESCBCVector {
+    pub key: [u8; 8],
+    pub iv: [u8; 8],
+    pub plaintext: &'static [u8],
+    pub ciphertext: &'static [u8],
+}
+
+pub const DES_CBC_VECTORS: &[DESCBCVector] = &[DESCBCVector {
+    key: hex("0123456789abcdef"),
+    iv: hex("1234567890abcdef"),
+    plaintext: b"Now is the time for all ",
+    ciphertext: &hex::<24>("e5c7cdde872bf27c43e934008c389c0f683788499a7c05f6"),
+}];
+
+/* The components of R_RSA_PRIVATE_KEY, without its zero padding. */
+#[derive(Debug, Clone, Copy)]
+pub struct RSAKeyVector {
+    pub bits: u32,
+    pub modulus: &'static [u8],
+    pub public_exponent: &'static [u8],
+    pub exponent: &'static [u8],
+    pub prime: [&'static [u8]; 2],
+    pub prime_exponent: [&'static [u8]; 2],
+    pub coefficient: &'static [u8],
+}
+
+impl RSAKeyVector {
+    pub fn private_key(&self) -> RSAPrivateKey {
+        RSAPrivateKey::from_components(
+            self.bits,
+            BigUint::from_bytes_be(self.modulus),
+            BigUint::from_bytes_be(self.public_exponent),
+            BigUint::from_bytes_be(self.exponent),
+            self.prime.map(BigUint::from_bytes_be),
+            self.prime_exponent.map(BigUint::from_bytes_be),
+            BigUint::from_bytes_be(self.coefficient),
+        )
+    }
+}
+
+pub const RSA_KEY: RSAKeyVector = RSAKeyVector {
+    bits: 512,
+    modulus: &hex::<64>(
+        "a3164dc1ff32b503b8243fa392adb47bdbbdd8546a261fadb3c61cd9369f3b94\
+            f21968b85389468e29fe2e137a56a6c5cf9a6d204217cbefb5efae92758ba8b1",
+    ),
+    public_exponent: &hex::<3>("010001"),
+    exponent: &hex::<64>(
+        "9ddf0c5c9c3ea8cdb5e699182df82afbaf26e2902cc544eb3058ba3a66ced3ab\
+            fa72fa81e0d3d5c16b5351c08952990791c04b32455b1b222aa0042e559dc2a5",
+    ),
+    prime: [
+        &hex::<32>("d6918e33df11a5e4b7a60f53d300e2f6e616429a72ce0e414ae612cebddc1a8f"),
+        &hex::<32>("c293f17faaf4d4b0faea039bb0f562ba46dd3963ea5be6cfbcf4d0cc5fbaa8bf"),
+    ],
+    prime_exponent: [
+        &hex::<32>("76437fd78349dcdff58127cd91ed592bb0a1f04fe49b422c26b62d039a8de86d"),
+        &hex::<32>("183279b7dcb4b1b6889d8d71512252f2c3594387cf5f3a66df22bacc8fa310d5"),
+    ],
+    coefficient: &hex::<32>("601f8a10beb249f2b52fc6a285dbdfee9abb26defa4143769c734f31139b03a8"),
+};
+
+/* A PKCS #1 v1.5 operation under RSA_KEY. Block type 1 is
+   RSAPrivateEncrypt and needs no randomness; block type 2 is
+   RSAPublicEncrypt with its padding drawn from a structure seeded with
+   seed. block is the padded input before exponentiation.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct PKCS1Vector {
+    pub block_type: u8,
+    pub seed: Option<&'static [u8]>,
+    pub input: &'static [u8],
+    pub block: &'static [u8],
+    pub output: &'static [u8],
+}
+
+pub const PKCS1_VECTORS: &[PKCS1Vector] = &[
+    /* The MD5 DigestInfo of "abc". */
+    PKCS1Vector {
+        block_type: 1,
+        seed: None,
+        input: &hex::<34>(
+            "3020300c06082a864886f70d020505000410900150983cd24fb0d6963f7d28e1\
+            7f72",
+        ),
+        block: &hex::<64>(
+            "0001ffffffffffffffffffffffffffffffffffffffffffffffffffffff003020\
+            300c06082a864886f70d020505000410900150983cd24fb0d6963f7d28e17f72",
+        ),
+        output: &hex::<64>(
+            "481be0edc11ed626864fcf0accb9e588426ebe00fccdb9f659086ad5e0854c58\
+            d122bb8dc16e1aff7e499e1805e1a9034d9741121f5c6dad8dd37a714c386436",
+        ),
+    },
+    PKCS1Vector {
+        block_type: 2,
+        seed: Some(&RANDOM_SEED),
+        input: b"RSAREF self-test",
+        block: &hex::<64>(
+            "0002e4afdfd629819baaa67923a22b2180a0f3720797efe28821d31bc6064351\
+            3a90996b6652c557f943c10f884985005253415245462073656c662d74657374",
+        ),
+        output: &hex::<64>(
+            "3565325cc87089a33341fdcc6652fcc7fcc7ac596bb087c09ebd4d9a3f253f09\
+            fe0669d752c7ca07f21d55d742624c1cbd28b1f465125b26636a809beb3cf4f9",
+        ),
+    },
+];
+
+/* R_SealInit, R_SealUpdate and R_SealFinal to one recipient, RSA_KEY,
+   with the content key, IV and key padding drawn in that order from a
+   structure seeded with seed.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct SealVector {
+    pub algorithm: EncryptionAlgorithmId,
+    pub seed: &'static [u8],
+    pub content: &'static [u8],
+    pub encrypted_key: &'static [u8],
+    pub iv: &'static [u8],
+    pub ciphertext: &'static [u8],
+}
+
+pub const SEAL_VECTORS: &[SealVector] = &[SealVector {
+    algorithm: EncryptionAlgorithmId::DesCbc,
+    seed: &RANDOM_SEED,
+    content: b"Sealed with RSAREF.",
+    encrypted_key: &hex::<64>(
+        "25c597e5e49eff033927435e6e7e3b1d4234b1371e342479615e870fd8496d1e\
+            e1deaa1913587e216a883085db134adfc4b6e4eca399319ecb4f6888415f79f4",
+    ),
+    iv: &hex::<8>("aaa67923a22b2180"),
+    ciphertext: &hex::<24>("603c5437da603ea8fe1b2d2bae9947e045384458dff4cc19"),
+}];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::des_cbc::DESCBCContext;
+    use crate::r_enhanc::{OpenContext, SealContext};
+    use crate::r_random::RandomStruct;
+
+    fn seeded(seed: &[u8]) -> RandomStruct {
+        let mut random_struct = RandomStruct::new();
+        random_struct.random_update(seed);
+        random_struct
+    }
+
+    #[test]
+    fn test_vectors() {
+        for vector in RANDOM_VECTORS {
+            let output = seeded(vector.seed).generate_bytes(vector.output.len());
+            assert_eq!(output.unwrap(), vector.output);
+        }
+
+        for vector in DIGEST_VECTORS {
+            assert_eq!(vector.algorithm.digest(vector.input), vector.digest);
+        }
+
+        for vector in DES_CBC_VECTORS {
+            let mut context = DESCBCContext::des(&vector.key, &vector.iv, true);
+            assert_eq!(context.update(vector.plaintext).unwrap(), vector.ciphertext);
+        }
+
+        let private_key = RSA_KEY.private_key();
+        let public_key = private_key.public_key();
+        for vector in PKCS1_VECTORS {
+            let output = match vector.seed {
+                None => private_key.rsa_private_encrypt(vector.input),
+                Some(seed) => public_key.rsa_public_encrypt(vector.input, &mut seeded(seed)),
+            };
+            assert_eq!(output.unwrap(), vector.output);
+            assert_eq!(vector.block[1], vector.block_type);
+
+            let block = match vector.block_type {
+                1 => public_key.rsa_public_block(vector.output),
+                _ => private_key.rsa_private_block(vector.output),
+            };
+            assert_eq!(block.unwrap(), vector.block);
+        }
+
+        for vector in SEAL_VECTORS {
+            let mut random_struct = seeded(vector.seed);
+            let mut context =
+                SealContext::new(vector.algorithm, &[&public_key], &mut random_struct).unwrap();
+            assert_eq!(context.encrypted_keys(), [vector.encrypted_key]);
+            assert_eq!(context.iv(), vector.iv);
+            let mut ciphertext = context.update(vector.content).unwrap();
+            ciphertext.extend(context.finalize().unwrap());
+            assert_eq!(ciphertext, vector.ciphertext);
+
+            let mut context = OpenContext::new(
+                vector.algorithm,
+                vector.encrypted_key,
+                vector.iv,
+                &private_key,
+            )
+            .unwrap();
+            let mut content = context.update(vector.ciphertext).unwrap();
+            content.extend(context.finalize().unwrap());
+            assert_eq!(content, vector.content);
+        }
+    }
+}
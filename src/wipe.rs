@@ -0,0 +1,280 @@
+/* Wiping of secret material: keys, PRNG state, content-encryption keys
+   and the padded blocks and intermediates of the RSA block routines.
+   Byte and limb buffers are always wiped, through zeroize with the
+   feature and through volatile writes without it. BigUint values can
+   only be reached through num-bigint-dig's zeroize support, so they are
+   wiped only with the feature; call sites need no cfg of their own.
+*/
+#[cfg(feature = "full")]
+use crate::RSAError;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+#[cfg(not(feature = "zeroize"))]
+use core::sync::atomic::{compiler_fence, Ordering};
+use num_bigint_dig::BigUint;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+pub(crate) trait Wipe {
+    fn wipe(&mut self);
+}
+
+/* Zeroes values with writes the compiler may not elide, and keeps them
+   from being moved past the free that usually follows.
+*/
+#[cfg(not(feature = "zeroize"))]
+fn volatile_zero<T: Copy + Default>(values: &mut [T]) {
+    for value in values.iter_mut() {
+        /* SAFETY: value is a valid, aligned, exclusive reference. */
+        unsafe { core::ptr::write_volatile(value, T::default()) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/* Zeroes the whole allocation, spare capacity included, and leaves the
+   vector empty. Filling to capacity never reallocates.
+*/
+#[cfg(not(feature = "zeroize"))]
+fn volatile_zero_vec<T: Copy + Default>(values: &mut Vec<T>) {
+    values.resize(values.capacity(), T::default());
+    volatile_zero(values);
+    values.clear();
+}
+
+impl Wipe for [u8] {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+        #[cfg(not(feature = "zeroize"))]
+        volatile_zero(self);
+    }
+}
+
+impl Wipe for [u64] {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+        #[cfg(not(feature = "zeroize"))]
+        volatile_zero(self);
+    }
+}
+
+/* Wipes the whole allocation, spare capacity included, and leaves the
+   vector empty.
+*/
+impl Wipe for Vec<u8> {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+        #[cfg(not(feature = "zeroize"))]
+        volatile_zero_vec(self);
+    }
+}
+
+impl Wipe for Vec<u64> {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+        #[cfg(not(feature = "zeroize"))]
+        volatile_zero_vec(self);
+    }
+}
+
+/* Leaves zero behind; a zeroized BigUint is not normalized and must not
+   be used in arithmetic. Without the zeroize feature this does nothing.
+*/
+impl Wipe for BigUint {
+    fn wipe(&mut self) {
+        #[cfg(feature = "zeroize")]
+        {
+            self.zeroize();
+            *self = BigUint::default();
+        }
+    }
+}
+
+impl<const N: usize> Wipe for [BigUint; N] {
+    fn wipe(&mut self) {
+        for value in self.iter_mut() {
+            value.wipe();
+        }
+    }
+}
+
+impl<const N: usize> Wipe for [u8; N] {
+    fn wipe(&mut self) {
+        self.as_mut_slice().wipe();
+    }
+}
+
+impl<const N: usize> Wipe for [u64; N] {
+    fn wipe(&mut self) {
+        self.as_mut_slice().wipe();
+    }
+}
+
+/* Holds a secret temporary and wipes it when dropped, so that early
+   returns through ? wipe it too.
+*/
+pub(crate) struct Wiped<T: Wipe>(pub(crate) T);
+
+impl<T: Wipe> Deref for Wiped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe> DerefMut for Wiped<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe> Drop for Wiped<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe> core::fmt::Debug for Wiped<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        Redacted.fmt(f)
+    }
+}
+
+/* A padded block, recovered plaintext or content key being worked on. It
+   may grow, unlike SecretBuffer, so reserve its full length up front:
+   reallocation leaves an unwiped copy behind.
+*/
+#[cfg(feature = "full")]
+pub(crate) type SecretVec = Wiped<Vec<u8>>;
+
+#[cfg(feature = "full")]
+impl SecretVec {
+    /* Hands the contents on unwiped, as a successful result. */
+    pub(crate) fn into_vec(mut self) -> Vec<u8> {
+        core::mem::take(&mut self.0)
+    }
+}
+
+/* A fixed-size secret on the stack, such as a key field being decoded. */
+#[cfg(feature = "full")]
+pub(crate) type SecretArray<const N: usize> = Wiped<[u8; N]>;
+
+/* Recovered plaintext and session keys handed back to the caller, wiped
+   when dropped. Reallocation would leave an
+   unwiped copy behind, so it derefs to a fixed-length slice only.
+*/
+#[derive(Default)]
+pub struct SecretBuffer(Vec<u8>);
+
+impl SecretBuffer {
+    #[cfg(feature = "full")]
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self(Vec::with_capacity(capacity))
+    }
+
+    /* Appends data unless that would outgrow the allocation, in which
+       case it fails with Len.
+    */
+    #[cfg(feature = "full")]
+    pub(crate) fn push_within_capacity(&mut self, data: &[u8]) -> Result<(), RSAError> {
+        if data.len() > self.0.capacity() - self.0.len() {
+            return Err(RSAError::Len);
+        }
+        self.0.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+impl From<Vec<u8>> for SecretBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Deref for SecretBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerefMut for SecretBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl Drop for SecretBuffer {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+/* Stands in for a secret field in Debug output. */
+pub(crate) struct Redacted;
+
+impl core::fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/* Never shows the contents. */
+impl core::fmt::Debug for SecretBuffer {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("SecretBuffer")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wipe() {
+        let mut block = [0x5au8; 16];
+        let mut limbs = [0x5a5a_5a5a_5a5a_5a5au64; 2];
+        let mut value = BigUint::from_bytes_be(&block);
+        let mut buffer = Vec::with_capacity(32);
+        buffer.extend(block);
+        block.wipe();
+        limbs.wipe();
+        value.wipe();
+        buffer.wipe();
+
+        assert_eq!(block, [0u8; 16]);
+        assert_eq!(limbs, [0u64; 2]);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.capacity(), 32);
+        if cfg!(feature = "zeroize") {
+            assert_eq!(value, BigUint::from(0u32));
+        } else {
+            assert_eq!(value.to_bytes_be(), [0x5au8; 16]);
+        }
+
+        let mut secret = SecretBuffer::from(vec![1u8, 2, 3]);
+        secret[0] = 4;
+        assert_eq!(&secret[..], [4, 2, 3]);
+        assert_eq!(format!("{:?}", secret), "SecretBuffer { len: 3 }");
+    }
+
+    #[cfg(feature = "full")]
+    #[test]
+    fn test_wiped_guards() {
+        let mut guarded: SecretVec = Wiped(vec![1u8, 2, 3]);
+        guarded.push(4);
+        assert_eq!(guarded.as_slice(), [1, 2, 3, 4]);
+        assert_eq!(format!("{:?}", guarded), "<redacted>");
+        assert_eq!(guarded.into_vec(), [1, 2, 3, 4]);
+        let mut array: SecretArray<2> = Wiped([1u8, 2]);
+        array[1] = 3;
+        assert_eq!(*array, [1, 3]);
+    }
+}
@@ -0,0 +1,203 @@
+/* Helpers shared by the integration tests, most of them for checking the
+   crate against the openssl command. Files are exchanged through a
+   scratch directory, one per test, removed when the test ends. Keys go to OpenSSL as PKCS #1 RSAPrivateKey DER, written and
+   read here so that the crate's own encoders are not their own oracle.
+*/
+#![allow(dead_code)]
+
+use num_bigint_dig::BigUint;
+use rsaref_rs::{generate_pem_keys_with_random, RSAPrivateKey, RSAProtoKey, RandomStruct};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::{env, fs, process};
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_SEQUENCE: u8 = 0x30;
+
+pub struct OpenSSL {
+    dir: PathBuf,
+}
+
+impl OpenSSL {
+    pub fn new(test: &str) -> Self {
+        let dir = env::temp_dir().join(format!("rsaref-openssl-{}-{}", test, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    pub fn write(&self, file: &str, data: &[u8]) {
+        fs::write(self.dir.join(file), data).unwrap();
+    }
+
+    pub fn read(&self, file: &str) -> Vec<u8> {
+        fs::read(self.dir.join(file)).unwrap()
+    }
+
+    /* Runs openssl in the scratch directory and returns its standard
+       output. Panics with the standard error if the command fails.
+    */
+    pub fn run(&self, args: &[&str]) -> Vec<u8> {
+        let output = Command::new(env::var("OPENSSL").unwrap_or_else(|_| "openssl".into()))
+            .args(args)
+            .current_dir(&self.dir)
+            .output()
+            .expect("openssl is not installed");
+        assert!(
+            output.status.success(),
+            "openssl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output.stdout
+    }
+
+    pub fn write_private_key(&self, file: &str, private_key: &RSAPrivateKey) {
+        self.write(file, &encode_rsa_private_key(private_key));
+    }
+
+    pub fn read_private_key(&self, file: &str) -> RSAPrivateKey {
+        decode_rsa_private_key(&self.read(file))
+    }
+
+    /* A self-signed certificate for the PKCS #1 DER key in key_file, with
+       OpenSSL's default subject key identifier extension.
+    */
+    pub fn self_signed_certificate(&self, key_file: &str, cert_file: &str) {
+        self.run(&[
+            "req",
+            "-new",
+            "-x509",
+            "-key",
+            key_file,
+            "-keyform",
+            "DER",
+            "-subj",
+            "/CN=rsaref",
+            "-days",
+            "1",
+            "-out",
+            cert_file,
+        ]);
+    }
+
+    pub fn subject_key_identifier(&self, cert_file: &str) -> Vec<u8> {
+        let text = self.run(&[
+            "x509",
+            "-in",
+            cert_file,
+            "-noout",
+            "-ext",
+            "subjectKeyIdentifier",
+        ]);
+        let text = String::from_utf8(text).unwrap();
+        let hex = text.lines().last().unwrap().trim();
+        hex.split(':')
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+}
+
+impl Drop for OpenSSL {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/* One 512-bit key, generated from a fixed seed, shared by every test. */
+pub fn test_key() -> &'static RSAPrivateKey {
+    static KEY: OnceLock<RSAPrivateKey> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let proto_key = RSAProtoKey {
+            bits: 512,
+            use_fermat4: true,
+        };
+        generate_pem_keys_with_random(&proto_key, &mut seeded_random_struct(0x5a))
+            .unwrap()
+            .1
+    })
+}
+
+/* A RandomStruct seeded with 256 copies of seed. */
+pub fn seeded_random_struct(seed: u8) -> RandomStruct {
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(&[seed; 256]);
+    random_struct
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut result = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        result.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let skip = len_bytes.iter().take_while(|b| **b == 0).count();
+        result.push(0x80 | (len_bytes.len() - skip) as u8);
+        result.extend(&len_bytes[skip..]);
+    }
+    result.extend(content);
+    result
+}
+
+fn integer(value: &BigUint) -> Vec<u8> {
+    let mut content = value.to_bytes_be();
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+    tlv(TAG_INTEGER, &content)
+}
+
+/* Splits one TLV with a definite length off the front of input. */
+fn read_tlv(input: &[u8], tag: u8) -> (&[u8], &[u8]) {
+    assert_eq!(input[0], tag);
+    let (len, header_len) = match input[1] {
+        len if len < 0x80 => (len as usize, 2),
+        len_len => {
+            let len_len = (len_len & 0x7f) as usize;
+            let len = input[2..2 + len_len]
+                .iter()
+                .fold(0usize, |len, b| len << 8 | *b as usize);
+            (len, 2 + len_len)
+        }
+    };
+    input[header_len..].split_at(len)
+}
+
+pub fn encode_rsa_private_key(private_key: &RSAPrivateKey) -> Vec<u8> {
+    let mut content = integer(&BigUint::from(0u32));
+    for value in [
+        private_key.modulus(),
+        private_key.public_exponent(),
+        private_key.exponent(),
+        &private_key.prime()[0],
+        &private_key.prime()[1],
+        &private_key.prime_exponent()[0],
+        &private_key.prime_exponent()[1],
+        private_key.coefficient(),
+    ] {
+        content.extend(integer(value));
+    }
+    tlv(TAG_SEQUENCE, &content)
+}
+
+pub fn decode_rsa_private_key(data: &[u8]) -> RSAPrivateKey {
+    let (mut content, _) = read_tlv(data, TAG_SEQUENCE);
+    let mut values = Vec::<BigUint>::new();
+    while !content.is_empty() {
+        let (value, rest) = read_tlv(content, TAG_INTEGER);
+        values.push(BigUint::from_bytes_be(value));
+        content = rest;
+    }
+    let [_version, modulus, public_exponent, exponent, p, q, dp, dq, coefficient] =
+        <[BigUint; 9]>::try_from(values).unwrap();
+    RSAPrivateKey::from_components(
+        modulus.bits() as u32,
+        modulus,
+        public_exponent,
+        exponent,
+        [p, q],
+        [dp, dq],
+        coefficient,
+    )
+}
@@ -0,0 +1,75 @@
+/* The key size floor is process-wide, so it is exercised in a binary of
+   its own with a single test, where changing it cannot disturb others.
+   It generates keys, so it needs full.
+*/
+#![cfg(feature = "full")]
+
+mod common;
+
+use common::seeded_random_struct;
+use rsaref_rs::{
+    allow_legacy_key_sizes, generate_pem_keys_with_random, minimum_key_bits, set_minimum_key_bits,
+    RSAError, RSAPrivateKey, RSAProtoKey, RSAPublicKey,
+};
+
+fn generate(bits: u32) -> Result<(RSAPublicKey, RSAPrivateKey), RSAError> {
+    generate_pem_keys_with_random(
+        &RSAProtoKey {
+            bits,
+            use_fermat4: true,
+        },
+        &mut seeded_random_struct(0x19),
+    )
+}
+
+#[test]
+fn test_minimum_key_bits() {
+    assert_eq!(minimum_key_bits(), 508);
+    let (public_key, private_key) = generate(512).unwrap();
+    let (long_public, long_private) = generate(1024).unwrap();
+
+    set_minimum_key_bits(1024).unwrap();
+    assert_eq!(minimum_key_bits(), 1024);
+    assert_eq!(generate(512).err(), Some(RSAError::ModulusLen));
+    assert_eq!(
+        RSAPrivateKey::decode(&private_key.encode()).err(),
+        Some(RSAError::ModulusLen)
+    );
+    assert_eq!(
+        RSAPublicKey::decode(&public_key.encode()).err(),
+        Some(RSAError::ModulusLen)
+    );
+
+    /* Keys already in hand are refused when used. */
+    assert_eq!(
+        private_key.rsa_private_encrypt(b"short").err(),
+        Some(RSAError::ModulusLen)
+    );
+    assert_eq!(
+        public_key
+            .encrypt(b"short", &mut seeded_random_struct(0x1a))
+            .err(),
+        Some(RSAError::ModulusLen)
+    );
+
+    assert_eq!(
+        long_private.rsa_private_encrypt(b"long").unwrap().len(),
+        128
+    );
+    assert!(RSAPublicKey::decode(&long_public.encode()).is_ok());
+    assert!(RSAPrivateKey::decode(&long_private.encode()).is_ok());
+
+    /* Both bounds are refused just past them. */
+    for bits in [256, 507, 1025, 2048] {
+        assert_eq!(set_minimum_key_bits(bits), Err(RSAError::ModulusLen));
+        assert_eq!(minimum_key_bits(), 1024);
+    }
+    set_minimum_key_bits(508).unwrap();
+    assert_eq!(minimum_key_bits(), 508);
+    set_minimum_key_bits(1024).unwrap();
+
+    allow_legacy_key_sizes();
+    assert_eq!(minimum_key_bits(), 508);
+    let signature = private_key.rsa_private_encrypt(b"short").unwrap();
+    assert_eq!(public_key.decrypt(&signature).unwrap(), b"short");
+}
@@ -0,0 +1,309 @@
+/* Interoperability with OpenSSL, run through the openssl command (or the
+   one named by OPENSSL):
+     cargo test --features openssl-interop --test openssl
+   Every format is checked in both directions where OpenSSL can produce
+   it. The crate has no OAEP encryption, so only PKCS #1 v1.5 padding is
+   covered. Single DES and RC2 are left out: OpenSSL 3 keeps them in its
+   legacy provider.
+*/
+#![cfg(feature = "openssl-interop")]
+
+mod common;
+
+use common::{test_key, OpenSSL};
+use rsaref_rs::{
+    encode_digest_info, export_pkcs12, CertificationRequest, DigestAlg, EncryptionAlgorithmId,
+    EnvelopedData, NameAttribute, PKCS12Encryption, RecipientIdentifier, SignedData,
+};
+use std::sync::Arc;
+
+const CONTENT: &[u8] = b"RSAREF and OpenSSL agree on this message.\n";
+
+#[test]
+fn test_private_key() {
+    let openssl = OpenSSL::new("private-key");
+    openssl.write_private_key("key.der", test_key());
+    let check = openssl.run(&[
+        "rsa", "-in", "key.der", "-inform", "DER", "-check", "-noout",
+    ]);
+    assert!(String::from_utf8_lossy(&check).contains("RSA key ok"));
+
+    /* A key from OpenSSL works with the crate. */
+    openssl.run(&["genrsa", "-out", "openssl.pem", "512"]);
+    openssl.run(&[
+        "rsa",
+        "-in",
+        "openssl.pem",
+        "-outform",
+        "DER",
+        "-traditional",
+        "-out",
+        "openssl.der",
+    ]);
+    let private_key = openssl.read_private_key("openssl.der");
+    let signature = private_key.rsa_private_encrypt(CONTENT).unwrap();
+    assert_eq!(
+        private_key.public_key().decrypt(&signature).unwrap(),
+        CONTENT
+    );
+}
+
+#[test]
+fn test_pkcs1_signatures() {
+    let openssl = OpenSSL::new("signatures");
+    let private_key = test_key();
+    openssl.write_private_key("key.der", private_key);
+    openssl.write("content", CONTENT);
+
+    for (alg, name) in [(DigestAlg::Md5, "-md5"), (DigestAlg::Sha256, "-sha256")] {
+        let digest_info = encode_digest_info(alg, &alg.digest(CONTENT)).unwrap();
+        openssl.write(
+            "ours.sig",
+            &private_key.rsa_private_encrypt(&digest_info).unwrap(),
+        );
+        let verified = openssl.run(&[
+            "dgst",
+            name,
+            "-prverify",
+            "key.der",
+            "-keyform",
+            "DER",
+            "-signature",
+            "ours.sig",
+            "content",
+        ]);
+        assert_eq!(verified, b"Verified OK\n");
+
+        let signature = openssl.run(&[
+            "dgst", name, "-sign", "key.der", "-keyform", "DER", "content",
+        ]);
+        assert_eq!(
+            private_key.public_key().decrypt(&signature).unwrap(),
+            digest_info
+        );
+    }
+}
+
+#[test]
+fn test_pkcs1_encryption() {
+    let openssl = OpenSSL::new("encryption");
+    let private_key = test_key();
+    openssl.write_private_key("key.der", private_key);
+    openssl.write("content", CONTENT);
+
+    let encrypted = private_key
+        .public_key()
+        .encrypt(CONTENT, &mut common::seeded_random_struct(0xa5))
+        .unwrap();
+    openssl.write("ours.bin", &encrypted);
+    let decrypted = openssl.run(&[
+        "pkeyutl", "-decrypt", "-inkey", "key.der", "-keyform", "DER", "-in", "ours.bin",
+    ]);
+    assert_eq!(decrypted, CONTENT);
+
+    let encrypted = openssl.run(&[
+        "pkeyutl", "-encrypt", "-inkey", "key.der", "-keyform", "DER", "-in", "content",
+    ]);
+    assert_eq!(
+        private_key.rsa_private_decrypt(&encrypted).unwrap(),
+        CONTENT
+    );
+}
+
+#[test]
+fn test_certification_request() {
+    let openssl = OpenSSL::new("csr");
+    let private_key = test_key();
+    let public_key = private_key.public_key();
+
+    for alg in [DigestAlg::Md5, DigestAlg::Sha1, DigestAlg::Sha256] {
+        let request = CertificationRequest::new(
+            &[
+                (NameAttribute::Country, "US"),
+                (NameAttribute::CommonName, "rsaref"),
+            ],
+            &public_key,
+            private_key,
+            alg,
+        )
+        .unwrap();
+        openssl.write("request.pem", request.encode_pem().as_bytes());
+        openssl.run(&["req", "-in", "request.pem", "-verify", "-noout"]);
+
+        let modulus = openssl.run(&["req", "-in", "request.pem", "-noout", "-modulus"]);
+        assert_eq!(
+            String::from_utf8(modulus).unwrap().trim(),
+            format!(
+                "Modulus={}",
+                public_key.modulus().to_str_radix(16).to_uppercase()
+            )
+        );
+    }
+}
+
+#[test]
+fn test_pkcs12() {
+    let openssl = OpenSSL::new("pkcs12");
+    let private_key = test_key();
+    openssl.write_private_key("key.der", private_key);
+    openssl.self_signed_certificate("key.der", "cert.pem");
+    openssl.run(&[
+        "x509", "-in", "cert.pem", "-outform", "DER", "-out", "cert.der",
+    ]);
+    let certificate = openssl.read("cert.der");
+
+    for encryption in [
+        PKCS12Encryption::Sha1DesEde3Cbc,
+        PKCS12Encryption::Pbes2Aes256Cbc,
+    ] {
+        let pfx = export_pkcs12(
+            private_key,
+            &certificate,
+            Some("rsaref"),
+            "pass phrase",
+            encryption,
+            2048,
+            &mut common::seeded_random_struct(0xa5),
+        )
+        .unwrap();
+        openssl.write("key.p12", &pfx);
+
+        let pass = ["-passin", "pass:pass phrase"];
+        let mut args = vec![
+            "pkcs12", "-in", "key.p12", "-nocerts", "-nodes", "-out", "out.pem",
+        ];
+        args.extend(pass);
+        openssl.run(&args);
+        openssl.run(&[
+            "rsa",
+            "-in",
+            "out.pem",
+            "-outform",
+            "DER",
+            "-traditional",
+            "-out",
+            "out.der",
+        ]);
+        assert_eq!(
+            openssl.read_private_key("out.der").encode(),
+            private_key.encode()
+        );
+
+        let mut args = vec!["pkcs12", "-in", "key.p12", "-nokeys", "-out", "out.crt"];
+        args.extend(pass);
+        openssl.run(&args);
+        openssl.run(&[
+            "x509", "-in", "out.crt", "-outform", "DER", "-out", "out.cer",
+        ]);
+        assert_eq!(openssl.read("out.cer"), certificate);
+    }
+}
+
+#[test]
+fn test_enveloped_data() {
+    let openssl = OpenSSL::new("enveloped-data");
+    let private_key = test_key();
+    let public_key = private_key.public_key();
+    openssl.write_private_key("key.der", private_key);
+    openssl.self_signed_certificate("key.der", "cert.pem");
+    openssl.write("content", CONTENT);
+    let rid = RecipientIdentifier::SubjectKeyIdentifier(openssl.subject_key_identifier("cert.pem"));
+
+    for (algorithm, name) in [
+        (EncryptionAlgorithmId::DesEde3Cbc, "-des3"),
+        (EncryptionAlgorithmId::Aes128Cbc, "-aes128"),
+        (EncryptionAlgorithmId::Aes256Cbc, "-aes256"),
+    ] {
+        let enveloped = EnvelopedData::seal(
+            CONTENT,
+            &[(rid.clone(), &public_key)],
+            algorithm,
+            &mut common::seeded_random_struct(0xa5),
+        )
+        .unwrap();
+        openssl.write("ours.der", &enveloped.encode());
+        let opened = openssl.run(&[
+            "cms", "-decrypt", "-binary", "-inform", "DER", "-in", "ours.der", "-recip",
+            "cert.pem", "-inkey", "key.der", "-keyform", "DER",
+        ]);
+        assert_eq!(opened, CONTENT);
+
+        /* By subject key identifier and by issuer and serial number. */
+        for keyid in [true, false] {
+            let mut args = vec![
+                "cms", "-encrypt", "-binary", name, "-outform", "DER", "-in", "content",
+            ];
+            if keyid {
+                args.push("-keyid");
+            }
+            args.push("cert.pem");
+            let enveloped = EnvelopedData::decode(&openssl.run(&args)).unwrap();
+            assert_eq!(enveloped.content_encryption_algorithm(), algorithm);
+            let rid = keyid.then_some(&rid);
+            assert_eq!(enveloped.open(rid, private_key).unwrap(), CONTENT);
+        }
+    }
+}
+
+#[test]
+fn test_signed_data() {
+    let openssl = OpenSSL::new("signed-data");
+    let private_key = test_key();
+    let public_key = private_key.public_key();
+    openssl.write_private_key("key.der", private_key);
+    openssl.self_signed_certificate("key.der", "cert.pem");
+    openssl.write("content", CONTENT);
+    let sid = RecipientIdentifier::SubjectKeyIdentifier(openssl.subject_key_identifier("cert.pem"));
+    let signers = [(sid.clone(), private_key as _)];
+
+    for with_attributes in [false, true] {
+        let digest = Arc::new(DigestAlg::Sha256);
+        let signed = if with_attributes {
+            SignedData::sign_with_attributes(CONTENT, false, &signers, digest, None)
+        } else {
+            SignedData::sign_with_digest(CONTENT, false, &signers, digest)
+        }
+        .unwrap();
+        openssl.write("ours.der", &signed.encode());
+        let verified = openssl.run(&[
+            "cms",
+            "-verify",
+            "-binary",
+            "-inform",
+            "DER",
+            "-in",
+            "ours.der",
+            "-certfile",
+            "cert.pem",
+            "-noverify",
+        ]);
+        assert_eq!(verified, CONTENT);
+
+        let mut args = vec![
+            "cms",
+            "-sign",
+            "-binary",
+            "-nodetach",
+            "-md",
+            "sha256",
+            "-outform",
+            "DER",
+            "-keyid",
+            "-signer",
+            "cert.pem",
+            "-inkey",
+            "key.der",
+            "-keyform",
+            "DER",
+            "-in",
+            "content",
+        ];
+        if !with_attributes {
+            args.push("-noattr");
+        }
+        let signed = SignedData::decode(&openssl.run(&args)).unwrap();
+        assert_eq!(signed.content(), Some(CONTENT));
+        assert_eq!(signed.signers()[0].has_signed_attributes(), with_attributes);
+        signed.verify(None, Some(&sid), &public_key).unwrap();
+    }
+}
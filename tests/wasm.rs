@@ -0,0 +1,58 @@
+#![cfg(target_arch = "wasm32")]
+
+use rsaref_rs::{
+    generate_pem_keys_with_random, EncryptionAlgorithmId, OpenContext, RSAProtoKey, RandomStruct,
+    SealContext,
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn seeded_random_struct() -> RandomStruct {
+    let mut random_struct = RandomStruct::new();
+    random_struct.random_update(&[0x42u8; 256]);
+    random_struct
+}
+
+#[wasm_bindgen_test]
+fn test_keygen_and_sign_without_os_entropy() {
+    let (public_key, private_key) = generate_pem_keys_with_random(
+        &RSAProtoKey {
+            bits: 512,
+            use_fermat4: true,
+        },
+        &mut seeded_random_struct(),
+    )
+    .unwrap();
+
+    let signature = private_key.rsa_private_encrypt(b"wasm smoke test").unwrap();
+    assert_eq!(public_key.decrypt(&signature).unwrap(), b"wasm smoke test");
+
+    let algorithm = EncryptionAlgorithmId::DesEde3Cbc;
+    let mut seal =
+        SealContext::new(algorithm, &[&public_key], &mut seeded_random_struct()).unwrap();
+    let mut open = OpenContext::new(
+        algorithm,
+        &seal.encrypted_keys()[0],
+        seal.iv(),
+        &private_key,
+    )
+    .unwrap();
+    let mut sealed = seal.update(b"sealed in the browser").unwrap();
+    sealed.extend(seal.finalize().unwrap());
+    let mut opened = open.update(&sealed).unwrap();
+    opened.extend(open.finalize().unwrap());
+    assert_eq!(opened, b"sealed in the browser");
+}
+
+#[cfg(all(feature = "os-rng", not(feature = "deterministic")))]
+#[wasm_bindgen_test]
+fn test_keygen_with_os_entropy() {
+    let (public_key, _) = rsaref_rs::generate_pem_keys_with_rng(
+        &RSAProtoKey {
+            bits: 512,
+            use_fermat4: true,
+        },
+        &mut rand::rngs::OsRng,
+    )
+    .unwrap();
+    assert_eq!(public_key.bits(), 512);
+}